@@ -44,6 +44,7 @@ use iron::modifiers::Header;
 
 use sigchain_core::db::Connection;
 use sigchain_core::protocol::SignatureResult;
+use sigchain_core::ssh_session::SSHSessionData;
 
 extern crate includedir;
 extern crate phf;
@@ -342,6 +343,9 @@ impl StaticServer {
             "api/promote" => {
                 promote_handler(&mut req, client)
             }
+            "api/remove_device" => {
+                remove_device_handler(&mut req, client)
+            }
             "api/team_info" => {
                 team_info_handler(&mut req, client)
             }
@@ -364,6 +368,36 @@ impl StaticServer {
                 };
                 return Ok(Response::with((status::Ok, response)));
             }
+            "api/logs/search" => {
+                let response = match log_search_handler(&mut req, client) {
+                    Ok(response) => response,
+                    Err(e) => {
+                        println!("{:?}", e);
+                        return Ok(Response::with((status::BadRequest, format!("{}", e))))
+                    },
+                };
+                return Ok(Response::with((status::Ok, response)));
+            }
+            "api/anomalies" => {
+                let response = match anomalies_handler(client) {
+                    Ok(response) => response,
+                    Err(e) => {
+                        println!("{:?}", e);
+                        return Ok(Response::with(status::InternalServerError))
+                    },
+                };
+                return Ok(Response::with((status::Ok, response)));
+            }
+            "api/devices" => {
+                let response = match client.get_device_inventory().and_then(|d| Ok(serde_json::to_vec(&d)?)) {
+                    Ok(response) => response,
+                    Err(e) => {
+                        println!("{:?}", e);
+                        return Ok(Response::with(status::InternalServerError))
+                    },
+                };
+                return Ok(Response::with((status::Ok, response)));
+            }
             "api/team" => {
                 let response = match team_handler(client) {
                     Ok(response) => response,
@@ -404,6 +438,8 @@ pub fn to_dashboard_identity(identity: protocol::team::Identity) -> Identity {
         public_key: identity.public_key.clone(),
         pgp_public_key: pgp::pgp_public_key_ascii_armor_string(&identity.pgp_public_key).unwrap_or("invalid PGP public key".to_string()),
         ssh_public_key: format!("{} {}", ssh::ssh_public_key_wire_string(&identity.ssh_public_key).unwrap_or("invalid SSH public key".to_string()), identity.email.clone()),
+        pgp_fingerprint: pgp::public_key::PGPPublicKey::parse(&identity.pgp_public_key).ok().map(|k| k.fingerprint_string()),
+        ssh_fingerprint: ssh::ssh_public_key_fingerprint(&identity.ssh_public_key).ok(),
     }
 }
 
@@ -431,12 +467,13 @@ fn team_handler(client: &DelegatedNetworkClient) -> Result<Vec<u8>> {
     team_db_conn.conn.transaction::<_, sigchain_core::errors::Error, _>(|| {
         let admin_public_keys = db::TeamMembership::filter_admin_public_keys(team_db_conn)?;
 
-        let me = to_dashboard_identity(db::Identity::find(team_db_conn, client.identity_pk())?.into_identity());
+        let me = to_dashboard_identity(db::Identity::find_for_signer(team_db_conn, client.identity_pk())?.into_identity());
 
         let members = db::Identity::find_all_for_team(team_db_conn)?;
         let mut member_rows:Vec<TeamMember> = members.into_iter().map(
             |m| -> Result<_> {
-                let logs: Vec<logs::Log> = db::Log::for_member(team_db_conn, &m.public_key)?
+                let db_logs = db::Log::for_member(team_db_conn, &m.public_key)?;
+                let logs: Vec<logs::Log> = db_logs
                     .iter().filter_map(|db_log| serde_json::from_str::<logs::Log>(&db_log.log_json).ok()).collect();
                 let is_admin = admin_public_keys.contains(&m.public_key);
                 let mut sorted_logs = logs.clone();
@@ -445,10 +482,14 @@ fn team_handler(client: &DelegatedNetworkClient) -> Result<Vec<u8>> {
 
                 use std::collections::HashMap;
                 let mut logs_by_host = HashMap::new();
-                for log in logs.clone() {
+                for db_log in db_logs.iter() {
+                    let log = match serde_json::from_str::<logs::Log>(&db_log.log_json) {
+                        Ok(log) => log,
+                        Err(_) => continue,
+                    };
                     if let logs::LogBody::Ssh(ssh_log) = log.clone().body {
                         if let Some(host_auth) = ssh_log.host_authorization {
-                            logs_by_host.entry(host_auth.host.clone()).or_insert(vec![]).push(log);
+                            logs_by_host.entry(host_auth.host.clone()).or_insert(vec![]).push((log, db_log.host_key_verification()));
                         }
                     }
                 }
@@ -457,7 +498,12 @@ fn team_handler(client: &DelegatedNetworkClient) -> Result<Vec<u8>> {
                     hosts.push(HostAccess{
                         host,
                         accesses: logs.len() as i64,
-                        last_access_unix_seconds: logs.clone().iter().max_by_key(|log| log.unix_seconds).map(|log| log.unix_seconds).unwrap_or(0u64) as i64,
+                        last_access_unix_seconds: logs.iter().map(|&(ref log, _)| log.unix_seconds).max().unwrap_or(0u64) as i64,
+                        pinned_accesses: logs.iter().filter(|&&(_, v)| v == Some(HostKeyVerification::PinnedMatch)).count() as i64,
+                        mismatched_accesses: logs.iter().filter(|&&(_, v)| match v {
+                            Some(HostKeyVerification::Mismatch) | Some(HostKeyVerification::InvalidSignature) => true,
+                            _ => false,
+                        }).count() as i64,
                     })
                 }
                 hosts.sort_by_key(|h| h.host.clone());
@@ -470,14 +516,38 @@ fn team_handler(client: &DelegatedNetworkClient) -> Result<Vec<u8>> {
                 ).cloned();
 
                 use sigchain_core::diesel::OptionalExtension;
+                let is_removed = db::TeamMembership::find(team_db_conn, &m.public_key).optional()?.is_none();
+                let devices = devices_from_logs(&logs, !is_removed, sigchain_core::chrono::Utc::now().timestamp());
+
+                let ssh_sessions = sorted_logs.iter().filter_map(|log| match log.body {
+                    logs::LogBody::Ssh(ref ssh) => SSHSessionData::from_log(log)
+                        .map(|session| (sigchain_core::base64::encode(&ssh.session_data), session)),
+                    _ => None,
+                }).collect();
+
+                let verified_by = client.get_current_member_verifications(&m.public_key)?.into_iter()
+                    .filter_map(|v| db::Identity::find(team_db_conn, &v.verifier_public_key).ok())
+                    .map(|verifier| verifier.email)
+                    .collect();
+
+                let signing_devices = db::MemberDevice::filter_by_member(team_db_conn, &m.public_key)?.into_iter()
+                    .map(|d| SigningDevice { name: d.name, public_key: d.public_key })
+                    .collect();
+
                 Ok(TeamMember {
                     identity: to_dashboard_identity(m.clone().into_identity()),
                     is_admin,
-                    is_removed: db::TeamMembership::find(team_db_conn, &m.public_key).optional()?.is_none(),
+                    is_removed,
                     last_access,
                     logins_today: logs.len() as i64,
                     last_24_hours_accesses: sorted_logs,
                     hosts,
+                    log_integrity: client.get_member_log_integrity(&m.public_key)?,
+                    devices,
+                    ssh_sessions,
+                    safety_number: client.get_safety_number(&m.public_key).ok(),
+                    verified_by,
+                    signing_devices,
                 })
             }
         ).filter_map(|result| result.map_err(|e| {println!("{:?}", e); e}).ok()).collect();
@@ -532,6 +602,31 @@ fn team_handler(client: &DelegatedNetworkClient) -> Result<Vec<u8>> {
     })
 }
 
+fn log_search_handler(req: &mut Request, client: &DelegatedNetworkClient) -> Result<Vec<u8>> {
+    let search_request: LogSearchRequest = serde_json::from_reader(&mut req.body)?;
+    let team_db_conn = &client.team_db_conn();
+
+    let mut results = vec![];
+    for db_log in client.search_logs(&search_request.query, search_request.limit.or(Some(1000)))? {
+        let log = match serde_json::from_str::<logs::Log>(&db_log.log_json) {
+            Ok(log) => log,
+            Err(_) => continue,
+        };
+        results.push(LogSearchResult {
+            email: db::Identity::find(team_db_conn, &db_log.member_public_key)?.email,
+            member_public_key: db_log.member_public_key,
+            log,
+        });
+    }
+
+    Ok(serde_json::to_vec(&LogSearchResponse { results })?)
+}
+
+fn anomalies_handler(client: &DelegatedNetworkClient) -> Result<Vec<u8>> {
+    let anomalies = client.get_access_anomalies(Some(200))?;
+    Ok(serde_json::to_vec(&AnomaliesResponse { anomalies })?)
+}
+
 fn demote_handler(req: &mut Request, client: &DelegatedNetworkClient) -> Result<()> {
     let demote_request: PublicKeyRequest = serde_json::from_reader(&mut req.body)?;
     client.remove_admin_pk(&demote_request.public_key)
@@ -547,6 +642,11 @@ fn promote_handler(req: &mut Request, client: &DelegatedNetworkClient) -> Result
     client.add_admin_pk(&demote_request.public_key)
 }
 
+fn remove_device_handler(req: &mut Request, client: &DelegatedNetworkClient) -> Result<()> {
+    let remove_device_request: PublicKeyRequest = serde_json::from_reader(&mut req.body)?;
+    client.remove_device(&remove_device_request.public_key)
+}
+
 fn invite_handler(req: &mut Request, client: &DelegatedNetworkClient) -> Result<Vec<u8>> {
     let indirect_invite_restriction: protocol::team::IndirectInvitationRestriction = serde_json::from_reader(&mut req.body)?;
     let link = client.create_invite(indirect_invite_restriction)?;
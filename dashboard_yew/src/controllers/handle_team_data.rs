@@ -51,7 +51,7 @@ pub fn handle_team_data(response:DashboardResponse, is_recurring: bool, model: &
     let mut all_logs:Vec<LogByUser> = Vec::new();
     for member in &response.team_members {
         for log in &member.last_24_hours_accesses {
-            all_logs.push(LogByUser { log: log.clone(), member_email: member.identity.email.clone() });
+            all_logs.push(LogByUser { log: log.clone(), member_email: member.identity.email.clone(), ssh_session: member.ssh_session(log) });
         }
     }
     all_logs.sort_by(|a, b| {
@@ -75,7 +75,7 @@ pub fn handle_team_data(response:DashboardResponse, is_recurring: bool, model: &
                     match signature.host_authorization {
                         Some(ref host_auth) => {
                             host_log_map.entry(host_auth.host.clone()).or_insert(vec![]).push(
-                                LogByUser { log: log.clone(), member_email: member_email.clone() }
+                                LogByUser { log: log.clone(), member_email: member_email.clone(), ssh_session: member.ssh_session(log) }
                             );
 
                             host_people_map.entry(host_auth.host.clone()).or_insert(vec![]).push(member);
@@ -169,6 +169,9 @@ impl Component<Context> for Model {
             Event::Remove(member) => {
                 return handle_remove_member(member, self, context);
             }
+            Event::RemoveDevice(device) => {
+                return handle_remove_device(device, self, context);
+            }
             Event::Demote(member) | Event::Promote(member) => {
                 return change_member_role(member, self, context);
             }
@@ -9,6 +9,14 @@ use context::*;
 use models::*;
 
 pub fn handle_remove_member(member:TeamMember, model: &mut Model, context: &mut Env<Context, Model>) -> ShouldRender {
+    post_public_key("/api/remove", member.identity.public_key, model, context)
+}
+
+pub fn handle_remove_device(device:SigningDevice, model: &mut Model, context: &mut Env<Context, Model>) -> ShouldRender {
+    post_public_key("/api/remove_device", device.public_key, model, context)
+}
+
+fn post_public_key(path: &str, public_key: Vec<u8>, model: &mut Model, context: &mut Env<Context, Model>) -> ShouldRender {
     model.cancel_task_if_present();
     model.fetching = true;
 
@@ -33,10 +41,10 @@ pub fn handle_remove_member(member:TeamMember, model: &mut Model, context: &mut
         }
     });
 
-    let public_key_request = PublicKeyRequest { public_key: member.identity.public_key };
+    let public_key_request = PublicKeyRequest { public_key };
     match serde_json::to_string(&public_key_request) {
         Ok(json_string) => {
-            let request = model.build_post("/api/remove").body(json_string).unwrap();
+            let request = model.build_post(path).body(json_string).unwrap();
             context.web.fetch(request, callback);
         }
         _ => {
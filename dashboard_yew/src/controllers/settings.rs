@@ -26,7 +26,7 @@ pub fn edit_approval_window(seconds:Option<i64>, model: &mut Model, context: &mu
         return false;
     }
 
-    do_post_request(&Policy { temporary_approval_seconds: seconds }, "policy".into(), model, context);
+    do_post_request(&Policy { temporary_approval_seconds: seconds, log_redaction: None }, "policy".into(), model, context);
     return true;
 }
 
@@ -16,6 +16,7 @@ pub enum Event {
     Remove(TeamMember),
     Promote(TeamMember),
     Demote(TeamMember),
+    RemoveDevice(SigningDevice),
 
     // searching
     SearchChanged(String),
@@ -1,6 +1,7 @@
 pub use sigchain_core::dashboard_protocol::*;
 use sigchain_core::protocol::billing::*;
 pub use sigchain_core::protocol::logs;
+pub use sigchain_core::ssh_session::SSHSessionData;
 use yew::services::Task;
 
 mod event;
@@ -107,6 +108,7 @@ pub struct TeamMemberForHost {
 pub struct LogByUser {
     pub log:logs::Log,
     pub member_email:String,
+    pub ssh_session:Option<SSHSessionData>,
 }
 
 use http;
@@ -3,6 +3,7 @@ use yew::prelude::*;
 use context::*;
 use sigchain_core::time_util::*;
 use sigchain_core::protocol::logs::*;
+use sigchain_core::protocol::SignatureResult;
 use sigchain_core::git_hash::*;
 
 fn view_for_commit_link(commit:&GitCommitSignature) -> Html<Context, Model> {
@@ -30,6 +31,37 @@ fn view_for_commit_link(commit:&GitCommitSignature) -> Html<Context, Model> {
     };
 }
 
+fn view_for_log_row(badge: (&str, &str), success: bool, info: String, user: Option<&str>, domain: Option<&str>, unix_seconds: u64) -> Html<Context, Model> {
+    let (badge_class, badge_text) = badge;
+    let (result_string, result_class) = match success {
+        true => ("✔", "audit-log-result-success"),
+        false => ("✘", "audit-log-result-fail"),
+    };
+
+    html! {
+        <tr>
+            <td>
+                <span class=(badge_class, "badge"),>
+                    { badge_text }
+                </span>
+            </td>
+            <td class={ result_class },>
+                { result_string }
+            </td>
+            <td class="log-body-info",>
+                { info }
+            </td>
+            <td>
+                <span class="blue",>
+                    { user.unwrap_or("--") }
+                </span>
+                {"@"}{ domain.unwrap_or("--") }
+            </td>
+            <td>{ unix_seconds.time_ago() }</td>
+        </tr>
+    }
+}
+
 pub fn view_for_logs_log_item(log:&LogByUser) -> Html<Context, Model> {
     let mut tokens = log.member_email.split("@");
     let (user, domain) = (tokens.next(), tokens.next());
@@ -57,6 +89,12 @@ pub fn view_for_logs_log_item(log:&LogByUser) -> Html<Context, Model> {
                     </td>
                     <td class="log-body-info",>
                         { &signature.user }{" @ "}<span class="blue",> {host}</span>
+                        {
+                            match log.ssh_session {
+                                Some(ref session) => format!(" ({}, session {})", session.algorithm, session.short_session_id()),
+                                None => "".into(),
+                            }
+                        }
                     </td>
                     <td>
                         <span class="blue",>
@@ -136,6 +174,34 @@ pub fn view_for_logs_log_item(log:&LogByUser) -> Html<Context, Model> {
                         <td>{log.log.unix_seconds.time_ago()}</td>
                     </tr>
             }
+        },
+        LogBody::Sudo(ref sudo) => {
+            view_for_log_row(("sudo", "Sudo"),
+                             sudo.result.is_success(),
+                             format!("{} as {} @ {}: {}", sudo.user, sudo.target_user, sudo.host,
+                                     sudo.command.clone().unwrap_or("unknown command".into())),
+                             user, domain, log.log.unix_seconds)
+        },
+        LogBody::PamLogin(ref pam_login) => {
+            view_for_log_row(("login", "Login"),
+                             pam_login.result.is_success(),
+                             format!("{} @ {} ({})", pam_login.user, pam_login.host, pam_login.service),
+                             user, domain, log.log.unix_seconds)
+        },
+        LogBody::SshAgentForwarding(ref agent_forwarding) => {
+            let host = agent_forwarding.host_authorization.as_ref().map(|h| h.host.clone()).unwrap_or("unknown host".into());
+            let destination = agent_forwarding.destination_host.clone().unwrap_or("unknown host".into());
+            view_for_log_row(("ssh", "SSH Agent"),
+                             agent_forwarding.result.is_success(),
+                             format!("{} @ {} → {}", agent_forwarding.user, host, destination),
+                             user, domain, log.log.unix_seconds)
+        },
+        LogBody::GitPush(ref push) => {
+            let refs = push.ref_updates.iter().map(|r| r.name.clone()).collect::<Vec<_>>().join(" ");
+            view_for_log_row(("sign", "Git Push"),
+                             push.result.is_success(),
+                             format!("[{}] {}", push.remote, refs),
+                             user, domain, log.log.unix_seconds)
         }
     }
 }
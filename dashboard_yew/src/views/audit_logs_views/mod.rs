@@ -33,6 +33,32 @@ fn log_body_matches(log_body:&LogBody, search_query: &String) -> bool {
                 || tag.type_.contains(search_query)
                 || Into::<String>::into("git tag").contains(search_query)
         }
+        &LogBody::Sudo(ref sudo) => {
+            sudo.user.contains(search_query)
+                || sudo.target_user.contains(search_query)
+                || sudo.host.contains(search_query)
+                || sudo.command.clone().unwrap_or("".into()).contains(search_query)
+                || Into::<String>::into("sudo").contains(search_query)
+        }
+        &LogBody::PamLogin(ref pam_login) => {
+            pam_login.user.contains(search_query)
+                || pam_login.host.contains(search_query)
+                || pam_login.service.contains(search_query)
+                || Into::<String>::into("login").contains(search_query)
+        }
+        &LogBody::SshAgentForwarding(ref agent_forwarding) => {
+            let host:String = agent_forwarding.clone().host_authorization.map(|h| h.host).unwrap_or("unknown host".into());
+            host.contains(search_query)
+                || agent_forwarding.user.contains(search_query)
+                || agent_forwarding.destination_host.clone().unwrap_or("".into()).contains(search_query)
+                || Into::<String>::into("ssh agent forwarding").contains(search_query)
+        }
+        &LogBody::GitPush(ref push) => {
+            push.remote.contains(search_query)
+                || push.pusher.contains(search_query)
+                || push.ref_updates.iter().any(|r| r.name.contains(search_query) || r.new_object.contains(search_query))
+                || Into::<String>::into("git push").contains(search_query)
+        }
     };
 }
 impl Model {
@@ -29,6 +29,75 @@ pub fn view_for_last_active(member:&TeamMember) -> Html<Context, Model> {
     }
 }
 
+pub fn view_for_log_integrity(member:&TeamMember) -> Html<Context, Model> {
+    let integrity = &member.log_integrity;
+    if !integrity.has_gaps() {
+        return view_for_signature_verification(member);
+    }
+
+    let mut reasons = vec![];
+    if integrity.missing_key > 0 {
+        reasons.push(format!("{} without a key", integrity.missing_key));
+    }
+    if integrity.bad_ciphertext > 0 {
+        reasons.push(format!("{} with bad ciphertext", integrity.bad_ciphertext));
+    }
+    if integrity.malformed_log > 0 {
+        reasons.push(format!("{} malformed", integrity.malformed_log));
+    }
+    if integrity.bad_wrapped_key > 0 {
+        reasons.push(format!("{} bad wrapped keys", integrity.bad_wrapped_key));
+    }
+
+    html! {
+        <div class=("last-active", "audit-log-result-fail"),>
+            { format!("{} of {} logs unreadable ({})",
+                      integrity.missing_logs(),
+                      integrity.missing_logs() + integrity.decrypted_logs,
+                      reasons.join(", ")) }
+            {
+                match integrity.last_failure_unix_seconds {
+                    Some(t) => format!(", last {}", t.time_ago()),
+                    None => "".into(),
+                }
+            }
+            { view_for_signature_verification(member) }
+        </div>
+    }
+}
+
+fn view_for_signature_verification(member:&TeamMember) -> Html<Context, Model> {
+    let integrity = &member.log_integrity;
+    let mut warnings = vec![];
+    if integrity.invalid_signatures > 0 {
+        warnings.push(format!("{} of {} signed logs have an invalid signature",
+                              integrity.invalid_signatures,
+                              integrity.invalid_signatures + integrity.valid_signatures + integrity.unverified_signatures
+                                  + integrity.redacted_signatures));
+    }
+    if integrity.revoked_device_logs > 0 {
+        warnings.push(format!("{} logs from revoked devices", integrity.revoked_device_logs));
+    }
+    if warnings.is_empty() {
+        return html! { <div></div> };
+    }
+    html! {
+        <div class=("last-active", "audit-log-result-fail"),>
+            { warnings.join(", ") }
+        </div>
+    }
+}
+
+/// The member's own key plus their authorized devices, when they have more than one.
+pub fn view_for_device_count(member:&TeamMember) -> Html<Context, Model> {
+    if member.signing_devices.is_empty() {
+        return html! { <div></div> };
+    }
+    html! {
+        <div class="last-active",>{ format!("{} devices", member.signing_devices.len() + 1) }</div>
+    }
+}
+
 pub fn view_for_last_access(member:&TeamMember) -> Html<Context, Model> {
     match member.last_access {
         Some(ref access) => {
@@ -79,6 +148,44 @@ pub fn view_for_remove_button(member:&TeamMember, is_me:bool) -> Html<Context, M
     }
 }
 
+pub fn view_for_key_fingerprints(member:&TeamMember) -> Html<Context, Model> {
+    html! {
+        <div>
+            { view_for_fingerprint("SSH", &member.identity.ssh_fingerprint) }
+            { view_for_fingerprint("PGP", &member.identity.pgp_fingerprint) }
+        </div>
+    }
+}
+
+pub fn view_for_safety_number(member:&TeamMember) -> Html<Context, Model> {
+    let verified = match member.verified_by.is_empty() {
+        true => "Not verified in person".to_string(),
+        false => format!("Verified by {}", member.verified_by.join(", ")),
+    };
+    match member.safety_number {
+        Some(ref safety_number) => html! {
+            <div>
+                <div class="last-active",>{ format!("Safety number {}", safety_number) }</div>
+                <div class="last-active",>{ verified }</div>
+            </div>
+        },
+        None => html! {
+            <div></div>
+        },
+    }
+}
+
+fn view_for_fingerprint(key_type: &str, fingerprint: &Option<String>) -> Html<Context, Model> {
+    match fingerprint {
+        &Some(ref fingerprint) => html! {
+            <div class="last-active",>{ format!("{} {}", key_type, fingerprint) }</div>
+        },
+        &None => html! {
+            <div class=("last-active", "audit-log-result-fail"),>{ format!("Invalid {} public key", key_type) }</div>
+        },
+    }
+}
+
 pub fn view_for_copy_buttons(member:&TeamMember) -> Html<Context, Model> {
     let ssh = member.identity.ssh_public_key.clone();
     let pgp = member.identity.pgp_public_key.clone();
@@ -144,6 +251,7 @@ pub fn view_for_team_list_item(member:&TeamMember, me: Option<Identity>) -> Html
                     </span>
                     { view_for_is_admin(member) }
                     { view_for_last_active(member) }
+                    { view_for_device_count(member) }
                 </div>
 
                 <div class="list-item-last-access",>
@@ -28,6 +28,14 @@ pub fn view_host_access_item(host_access:&HostAccess) -> Html<Context, Model> {
                     { host_access.host.clone() }
                 </div>
                 <div class="meta",>{"Last access: "} { host_access.last_access_unix_seconds.time_ago() }</div>
+                <div class="meta",>{ format!("{} of {} with a pinned host key", host_access.pinned_accesses, host_access.accesses) }</div>
+                {
+                    if host_access.mismatched_accesses > 0 {
+                        html! { <div class=("meta", "audit-log-result-fail"),>{ format!("{} with an unexpected host key", host_access.mismatched_accesses) }</div> }
+                    } else {
+                        html! { <div></div> }
+                    }
+                }
             </div>
             <div class="right",>
                 <div><span class="data",> { host_access.accesses } </span> {" Accesses"} </div>
@@ -37,6 +45,49 @@ pub fn view_host_access_item(host_access:&HostAccess) -> Html<Context, Model> {
     }
 }
 
+pub fn view_device_item(device:&Device) -> Html<Context, Model> {
+    html! {
+        <div class="item",>
+            <div>
+                <div class=("data", "host"),>
+                    { device.device_name.clone() }
+                </div>
+                <div class="meta",>{"First seen: "} { device.first_seen_unix_seconds.time_ago() }</div>
+                <div class="meta",>{"Last seen: "} { device.last_seen_unix_seconds.time_ago() }</div>
+            </div>
+            <div class="right",>
+                <div><span class="data",> { device.ssh_operations } </span> {" SSH"} </div>
+                <div><span class="data",> { device.git_operations } </span> {" Git"} </div>
+                <div>{ if device.is_active { "Active" } else { "Inactive" } }</div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_signing_device_item(device:&SigningDevice, can_remove:bool) -> Html<Context, Model> {
+    let the_device = device.clone();
+    html! {
+        <div class="item",>
+            <div>
+                <div class=("data", "host"),>
+                    { device.name.clone() }
+                </div>
+                <div class="meta",>{"Authorized device"}</div>
+            </div>
+            <div class="right",>
+                {
+                    if can_remove {
+                        html! {
+                            <button class=("list-action-button", "remove"), onclick=move |_| { Event::RemoveDevice(the_device.clone()) },>{"REMOVE"}</button>
+                        }
+                    } else {
+                        html! { <div></div> }
+                    }
+                }
+            </div>
+        </div>
+    }
+}
 
 impl Model {
     pub fn view_member_sidebar(&self) -> Html<Context, Model> {
@@ -71,6 +122,10 @@ impl Model {
                                     { view_for_is_admin(member) }
                                 </div>
                                 { view_for_last_active(member) }
+                                { view_for_device_count(member) }
+                                { view_for_log_integrity(member) }
+                                { view_for_key_fingerprints(member) }
+                                { view_for_safety_number(member) }
 
                                 <div>
                                     <div class="list-item-public-keys",>
@@ -90,6 +145,9 @@ impl Model {
                                     <li id="side_logs-title", class="side-menu", onclick=|_| {js!{showSideTab("side_logs");}; Event::ViewNextLogsPage(1)},>
                                         {"Audit Logs"}
                                     </li>
+                                    <li id="side_devices-title", class="side-menu", onclick=|_| {js!{showSideTab("side_devices");}; Event::Ignore},>
+                                        {"Devices"}
+                                    </li>
                                 </ul>
                                 <div id="side_hosts", class=("tab", "show"),>
                                     <div class="side-table",>
@@ -146,6 +204,26 @@ impl Model {
                                     </div> //side-table
 
                                 </div> //side_logs
+
+                                <div id="side_devices", class="tab",>
+                                    <div class="side-table",>
+                                        <div class="item",>
+                                            <span>
+                                                <span id="devices-num", class="data",> { member.devices.len() } </span>
+                                                {" devices"}
+                                            </span>
+                                        </div>
+                                        <div class="table-data",>
+                                            {
+                                                for member.signing_devices.iter().map(|d| view_signing_device_item(d, !member.is_removed) )
+                                            }
+                                            {
+                                                for member.devices.iter().map(|d| view_device_item(d) )
+                                            }
+                                        </div> //table-data
+                                    </div> //side-table
+
+                                </div> //side_devices
                          </div> //sidebar
                 };
             }
@@ -19,6 +19,8 @@ pub mod c_api {
     extern crate dashboard_middleware;
 
     use sigchain_core::errors::{Result, Error};
+    use sigchain_core::dashboard_protocol::{SignatureVerification, HostKeyVerification};
+    use sigchain_core::ssh_session::SSHSessionData;
 
     use sigchain_client::*;
     use std::str::from_utf8;
@@ -108,6 +110,78 @@ pub mod c_api {
         });
     }
 
+    #[no_mangle]
+    pub extern "C" fn verify_member(email_ptr: *const u8, email_len: usize) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            let email = unsafe{ from_utf8(from_raw_parts(email_ptr, email_len))? };
+            let member = client.get_active_member_by_email(email)?;
+            eprintln!("Safety number for {}:", email.yellow());
+            eprintln!("{}", client.get_safety_number(&member.public_key)?.green());
+            eprintln!("Approve the request on your phone only if this matches the number on {}'s phone.", email);
+            client.verify_member(email)?;
+            Ok(())
+        });
+    }
+
+    /// Revoke the device named `name` of the member with email `email`.
+    #[no_mangle]
+    pub extern "C" fn remove_device(
+        email_ptr: *const u8, email_len: usize,
+        name_ptr: *const u8, name_len: usize,
+    ) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            let email = from_utf8(unsafe{ from_raw_parts(email_ptr, email_len) })?;
+            let name = from_utf8(unsafe{ from_raw_parts(name_ptr, name_len) })?;
+            let member = client.get_active_member_by_email(email)?;
+            let device = match client.get_signing_devices(&member.public_key)?.into_iter().find(|d| d.name == name) {
+                Some(device) => device,
+                None => bail!("{} has no device named \"{}\"", email, name),
+            };
+            client.remove_device(&device.public_key)?;
+            eprintln!("{}", format!("Removed {}'s device \"{}\"", email, name).green());
+            Ok(())
+        });
+    }
+
+    /// Replace the current member's SSH key, given as an authorized_keys line, and/or PGP key,
+    /// given ASCII armored. Pass a null pointer to keep a key.
+    #[no_mangle]
+    pub extern "C" fn update_keys(
+        ssh_ptr: *const u8, ssh_len: usize,
+        pgp_ptr: *const u8, pgp_len: usize,
+    ) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            use sigchain_core::base64;
+            use sigchain_core::pgp;
+
+            let ssh_public_key = match ssh_ptr.is_null() {
+                true => None,
+                false => {
+                    let line = from_utf8(unsafe{ from_raw_parts(ssh_ptr, ssh_len) })?;
+                    let encoded = match line.split_whitespace().nth(1) {
+                        Some(encoded) => encoded,
+                        None => bail!("expected an SSH public key of the form \"<type> <base64> [comment]\""),
+                    };
+                    Some(base64::decode(encoded)?)
+                }
+            };
+            let pgp_public_key = match pgp_ptr.is_null() {
+                true => None,
+                false => {
+                    let input = from_utf8(unsafe{ from_raw_parts(pgp_ptr, pgp_len) })?;
+                    Some(pgp::packets_from_input(input, pgp::LABEL_PUBLIC_KEY)?)
+                }
+            };
+            if ssh_public_key.is_none() && pgp_public_key.is_none() {
+                bail!("no keys to update");
+            }
+
+            client.update_keys(team::KeyUpdate { ssh_public_key, pgp_public_key })?;
+            eprintln!("{}", "Keys updated".green());
+            Ok(())
+        });
+    }
+
     #[no_mangle]
     pub extern "C" fn get_policy() {
         do_with_delegated_network_cli(|client| -> Result<()> {
@@ -122,6 +196,11 @@ pub mod c_api {
                 },
                 None => eprintln!("{}", "Auto-approval window is unrestricted".green()),
             }
+            let log_redaction_rules = client.get_log_redaction_rules()?;
+            if !log_redaction_rules.is_empty() {
+                eprintln!("{}", "Audit logs are redacted with:".green());
+                eprintln!("{}", serde_json::to_string_pretty(&log_redaction_rules)?);
+            }
             Ok(())
         });
     }
@@ -132,10 +211,12 @@ pub mod c_api {
             let policy = if approval_window.is_null() {
                 Policy {
                     temporary_approval_seconds: None,
+                    log_redaction: None,
                 }
             } else {
                 Policy {
                     temporary_approval_seconds: Some(unsafe{ *approval_window }),
+                    log_redaction: None,
                 }
             };
 
@@ -160,6 +241,26 @@ pub mod c_api {
         });
     }
 
+    /// Set the team's audit log redaction rules from a JSON encoded `LogRedactionRules`.
+    #[no_mangle]
+    pub extern "C" fn set_log_redaction_rules(rules_ptr: *const u8, rules_len: usize) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            let rules: team::LogRedactionRules = serde_json::from_slice(unsafe{ from_raw_parts(rules_ptr, rules_len) })?;
+            client.set_policy(Policy {
+                temporary_approval_seconds: client.get_policy()?.temporary_approval_seconds,
+                log_redaction: Some(rules.clone()),
+            })?;
+
+            if rules.is_empty() {
+                eprintln!("{}", "Success! Audit logs are no longer redacted ✔".green());
+            } else {
+                eprintln!("{}", "Success! Team’s audit log redaction rules are now:".green());
+                eprintln!("{}", serde_json::to_string_pretty(&rules)?);
+            }
+            Ok(())
+        });
+    }
+
     #[no_mangle]
     pub extern "C" fn set_team_name(name_ptr: *const u8, name_len: usize) {
         do_with_delegated_network_cli(|client| -> Result<()> {
@@ -175,6 +276,8 @@ pub mod c_api {
     pub extern "C" fn get_members(email_ptr: *const u8, email_len: usize,
                                   print_ssh_pubkey: bool, print_pgp_pubkey: bool, admin: bool) {
         do_with_delegated_network_cli(|client| -> Result<()> {
+            use sigchain_core::pgp::public_key::PGPPublicKey;
+
             let email: Option<&str> = match email_ptr.is_null() {
                 true => None,
                 false => Some(from_utf8(
@@ -215,13 +318,44 @@ pub mod c_api {
             }
             eprintln!();
 
+            let all_members = client.get_active_and_removed_members()?;
             for (idx, identity) in members.iter().enumerate() {
                 let header = format!("{}. {}", idx + 1, identity.email).green();
                 eprintln!("{}", header);
+                if let Ok(fingerprint) = ssh_public_key_fingerprint(&identity.ssh_public_key) {
+                    eprintln!("   SSH {}", fingerprint);
+                }
+                if let Ok(public_key) = PGPPublicKey::parse(&identity.pgp_public_key) {
+                    eprintln!("   PGP {}", public_key.fingerprint_string());
+                }
+                let devices = client.get_signing_devices(&identity.public_key)?;
+                for device in devices.iter() {
+                    match ssh_public_key_fingerprint(&device.ssh_public_key) {
+                        Ok(fingerprint) => eprintln!("   Device \"{}\" SSH {}", device.name, fingerprint),
+                        Err(_) => eprintln!("   Device \"{}\"", device.name),
+                    }
+                }
+                if let Ok(safety_number) = client.get_safety_number(&identity.public_key) {
+                    eprintln!("   Safety number {}", safety_number);
+                    let verifiers = client.get_current_member_verifications(&identity.public_key)?.into_iter()
+                        .filter_map(|v| all_members.iter().find(|m| m.public_key == v.verifier_public_key))
+                        .map(|verifier| verifier.email.clone())
+                        .collect::<Vec<_>>();
+                    if verifiers.is_empty() {
+                        eprintln!("   {}", "Not verified in person".yellow());
+                    } else {
+                        eprintln!("   {}", format!("Verified in person by {}", verifiers.join(", ")).green());
+                    }
+                }
                 if print_ssh_pubkey {
                     if let Ok(ssh_pubkey) = ssh_public_key_wire_string(&identity.ssh_public_key) {
                         println!("{} {}", ssh_pubkey, identity.email);
                     }
+                    for device in devices.iter() {
+                        if let Ok(ssh_pubkey) = ssh_public_key_wire_string(&device.ssh_public_key) {
+                            println!("{} {}", ssh_pubkey, identity.email);
+                        }
+                    }
                 }
                 if print_pgp_pubkey {
                     if let Ok(pgp_pubkey) = pgp_public_key_ascii_armor_string(&identity.pgp_public_key) {
@@ -266,7 +400,8 @@ pub mod c_api {
                     |e| bail!("Error updating known hosts: {}", e))?;
                 eprintln!("Updated known hosts for {}", host_port);
             }
-            let known_host_keys = local_host_keys(&host_port)?;
+            let known_host_keys = local_host_keys(&host_port)?
+                .into_iter().map(canonical_ssh_public_key_or_raw).collect::<Vec<_>>();
 
             let pinned_keys = client.get_pinned_host_keys(&host_port, false)?
                 .into_iter().map(|pinned_key| pinned_key.public_key).collect::<Vec<_>>();
@@ -317,9 +452,10 @@ pub mod c_api {
             let mut pinned_keys = client.get_pinned_host_keys(&host_port, search)?;
             pinned_keys.sort_by_key(|pinned_key| pinned_key.host.clone());
             for pinned_key in pinned_keys.into_iter() {
-                eprintln!("{} {}",
+                eprintln!("{} {} {}",
                           pinned_key.host,
-                          ssh_public_key_wire_string(&pinned_key.public_key).unwrap_or("invalid key format".into()));
+                          ssh_public_key_wire_string(&pinned_key.public_key).unwrap_or("invalid key format".into()),
+                          ssh_public_key_fingerprint(&pinned_key.public_key).unwrap_or_default());
             }
             Ok(())
         });
@@ -331,9 +467,10 @@ pub mod c_api {
             let mut all_pinned_keys = client.get_all_pinned_host_keys()?;
             all_pinned_keys.sort_by_key(|pinned_key| pinned_key.host.clone());
             for pinned_key in all_pinned_keys.into_iter() {
-                eprintln!("{} {}",
+                eprintln!("{} {} {}",
                           pinned_key.host,
-                          ssh_public_key_wire_string(&pinned_key.public_key).unwrap_or("invalid key format".into()));
+                          ssh_public_key_wire_string(&pinned_key.public_key).unwrap_or("invalid key format".into()),
+                          ssh_public_key_fingerprint(&pinned_key.public_key).unwrap_or_default());
             }
             Ok(())
         });
@@ -368,6 +505,90 @@ pub mod c_api {
         });
     }
 
+    fn print_log(email: &str, log: &logs::Log, db_log: &db::LogWithId) {
+        use sigchain_core::time_util::TimeAgo;
+        use sigchain_core::git_hash::*;
+
+        let mut log_type:String;
+        let mut log_body_string:String;
+
+        match log.body {
+            LogBody::Ssh(ref signature) => {
+                let host:String = signature.clone().host_authorization.map(|h| h.host).unwrap_or("unknown host".into());
+
+                log_type = "SSH".into();
+                log_body_string = format!("{} @ {}", signature.user, host).yellow().to_string();
+                if let Some(session) = SSHSessionData::from_log(log) {
+                    log_body_string = format!("{} ({}, session {})", log_body_string, session.algorithm, session.short_session_id());
+                }
+
+            },
+            LogBody::GitCommit(ref commit) => {
+                let message:String = commit.clone().message_string.unwrap_or("unknown".into());
+
+                log_type = "Git".into();
+                log_body_string = format!("[{}] {}", commit.git_hash_short_hex_string().unwrap_or("?".into()), message.trim()).into();
+            },
+            logs::LogBody::GitTag(ref tag) => {
+                let cloned_tag = tag.clone();
+                let message:String = cloned_tag.message_string.unwrap_or("unknown".into());
+
+                log_type = "Git".into();
+                log_body_string = format!("Tag {}: {}", cloned_tag.tag, message).into();
+            },
+            LogBody::Sudo(ref sudo) => {
+                let command:String = sudo.command.clone().unwrap_or("unknown command".into());
+
+                log_type = "Sudo".into();
+                log_body_string = format!("{} as {} @ {}: {}", sudo.user, sudo.target_user, sudo.host, command).into();
+            },
+            LogBody::PamLogin(ref pam_login) => {
+                log_type = "PAM".into();
+                log_body_string = format!("{} @ {} ({})", pam_login.user, pam_login.host, pam_login.service).yellow().to_string();
+            },
+            LogBody::SshAgentForwarding(ref agent_forwarding) => {
+                let host:String = agent_forwarding.clone().host_authorization.map(|h| h.host).unwrap_or("unknown host".into());
+                let destination:String = agent_forwarding.clone().destination_host.unwrap_or("unknown host".into());
+
+                log_type = "SSH -A".into();
+                log_body_string = format!("{} @ {} -> {}", agent_forwarding.user, host, destination).yellow().to_string();
+            },
+            LogBody::GitPush(ref push) => {
+                let refs:Vec<String> = push.ref_updates.iter().map(|r| r.name.clone()).collect();
+
+                log_type = "Git".into();
+                log_body_string = format!("Push {}: {}", push.remote, refs.join(" ")).into();
+            }
+        };
+
+        let log_string = format!("{:18}    {:30}    Device: {:20}    {}",
+                                 log.unix_seconds.full_timestamp(),
+                                 email,
+                                 log.session.device_name,
+                                 log_body_string,
+                                 );
+
+        let warning = if db_log.signature_verification() == Some(SignatureVerification::Invalid) {
+            Some("INVALID SIGNATURE")
+        } else if db_log.signature_verification() == Some(SignatureVerification::RevokedDevice) {
+            Some("FROM REVOKED DEVICE")
+        } else {
+            match db_log.host_key_verification() {
+                Some(HostKeyVerification::Mismatch) => Some("HOST KEY NOT PINNED FOR HOST"),
+                Some(HostKeyVerification::InvalidSignature) => Some("INVALID HOST SIGNATURE"),
+                _ => None,
+            }
+        };
+
+        if let Some(warning) = warning {
+            println!("{}", format!("[{}]\t⚠\t{}    {}", log_type, log_string, warning).red().bold());
+        } else if log.body.is_success() {
+            println!("{}", format!("[{}]\t✔\t{}", log_type, log_string).green());
+        } else {
+            println!("{}", format!("[{}]\t✘\t{}", log_type, log_string).red());
+        }
+    }
+
     #[no_mangle]
     pub extern "C" fn view_logs() {
         do_with_delegated_network_cli(|client| -> Result<()> {
@@ -397,7 +618,7 @@ pub mod c_api {
                     _ => {}
                 };
 
-                let logs: Vec<(String, logs::Log)> = log_result.iter().filter_map(|db_log| {
+                let logs: Vec<(String, logs::Log, db::LogWithId)> = log_result.iter().filter_map(|db_log| {
                     let log_object = match serde_json::from_str::<logs::Log>(&db_log.log_json) {
                         Ok(object) => { object }
                         Err(_) => { return None; }
@@ -408,66 +629,420 @@ pub mod c_api {
                         Err(_) => { return None; }
                     };
 
-                    Some((email, log_object))
+                    Some((email, log_object, db_log.clone()))
                 }).collect();
 
                 let mut sorted_logs = logs.clone();
                 sorted_logs.sort_by_key(|log| log.1.unix_seconds);
 
-                for email_log in sorted_logs {
-                    let (email, log) = email_log;
+                for (email, log, db_log) in sorted_logs {
+                    print_log(&email, &log, &db_log);
 
-                    use sigchain_core::time_util::TimeAgo;
-                    use sigchain_core::git_hash::*;
+                    if !skip_slow_print {
+                        thread::sleep(Duration::from_millis(200));
+                    }
+                }
 
-                    let mut log_type:String;
-                    let mut log_body_string:String;
+                skip_slow_print = false;
+                client.update_team_log_blocks()?;
+                thread::sleep(Duration::from_secs(5));
+            }
+        });
+    }
 
-                    match log.body {
-                        LogBody::Ssh(ref signature) => {
-                            let host:String = signature.clone().host_authorization.map(|h| h.host).unwrap_or("unknown host".into());
+    /// Print logs matching a search query such as `member:alice host:db1 result:rejected since:7d "text"`.
+    #[no_mangle]
+    pub extern "C" fn search_logs(query_ptr: *const u8, query_len: usize) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            let query = from_utf8(unsafe{ from_raw_parts(query_ptr, query_len) })?;
 
-                            log_type = "SSH".into();
-                            log_body_string = format!("{} @ {}", signature.user, host).yellow().to_string();
+            eprintln!("Fetching logs...");
+            client.update_team_log_blocks()?;
+            eprintln!("{}", format!("Done fetching logs ✔").green());
 
-                        },
-                        LogBody::GitCommit(ref commit) => {
-                            let message:String = commit.clone().message_string.unwrap_or("unknown".into());
+            let conn = &client.team_db_conn();
+            let mut matching_logs = client.search_logs(query, Some(100))?;
+            if matching_logs.is_empty() {
+                eprintln!("{}", "No matching logs".yellow());
+                return Ok(());
+            }
+            eprintln!("Showing {} most recent matching logs:", matching_logs.len());
+            eprintln!();
 
-                            log_type = "Git".into();
-                            log_body_string = format!("[{}] {}", commit.git_hash_short_hex_string().unwrap_or("?".into()), message.trim()).into();
-                        },
-                        logs::LogBody::GitTag(ref tag) => {
-                            let cloned_tag = tag.clone();
-                            let message:String = cloned_tag.message_string.unwrap_or("unknown".into());
+            matching_logs.reverse();
+            for db_log in matching_logs {
+                let log = match serde_json::from_str::<logs::Log>(&db_log.log_json) {
+                    Ok(log) => log,
+                    Err(_) => continue,
+                };
+                let email = db::Identity::find(conn, &db_log.member_public_key)?.email;
+                print_log(&email, &log, &db_log);
+            }
+            Ok(())
+        });
+    }
 
-                            log_type = "Git".into();
-                            log_body_string = format!("Tag {}: {}", cloned_tag.tag, message).into();
-                        }
-                    };
+    /// Write logs to stdout as `jsonl`, `syslog` or `cef` for a SIEM, starting after the named
+    /// cursor. With `follow`, keep fetching and exporting new logs.
+    #[no_mangle]
+    pub extern "C" fn export_logs(format_ptr: *const u8, format_len: usize, cursor_ptr: *const u8, cursor_len: usize, follow: bool) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            use sigchain_core::log_export::ExportFormat;
+            use std::io::stdout;
+            use std::thread;
+            use std::time::Duration;
+
+            let format = ExportFormat::from_str(from_utf8(unsafe{ from_raw_parts(format_ptr, format_len) })?)?;
+            let cursor = from_utf8(unsafe{ from_raw_parts(cursor_ptr, cursor_len) })?;
+
+            let stdout = stdout();
+            loop {
+                client.update_team_log_blocks()?;
+                let exported = client.export_logs(cursor, format, &mut stdout.lock())?;
+                if !follow {
+                    eprintln!("{}", format!("Exported {} logs ✔", exported).green());
+                    return Ok(());
+                }
+                thread::sleep(Duration::from_secs(5));
+            }
+        });
+    }
+
+    /// Add or replace a JSON encoded `alerts::AlertRule`.
+    #[no_mangle]
+    pub extern "C" fn add_alert_rule(rule_ptr: *const u8, rule_len: usize) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            let rule: client::alerts::AlertRule = serde_json::from_slice(unsafe{ from_raw_parts(rule_ptr, rule_len) })?;
+            client.set_alert_rule(&rule)?;
+            eprintln!("{}", format!("Success! Alert rule {} added ✔", rule.name).green());
+            Ok(())
+        });
+    }
 
-                    let log_string = format!("{:18}    {:30}    Device: {:20}    {}",
-                                             log.unix_seconds.full_timestamp(),
-                                             email,
-                                             log.session.device_name,
-                                             log_body_string,
-                                             );
+    #[no_mangle]
+    pub extern "C" fn remove_alert_rule(name_ptr: *const u8, name_len: usize) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            let name = from_utf8(unsafe{ from_raw_parts(name_ptr, name_len) })?;
+            client.remove_alert_rule(name)?;
+            eprintln!("{}", format!("Success! Alert rule {} removed ✔", name).green());
+            Ok(())
+        });
+    }
+
+    #[no_mangle]
+    pub extern "C" fn view_alerts() {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            use sigchain_core::time_util::TimeAgo;
 
-                    if log.body.is_success() {
-                        println!("{}", format!("[{}]\t✔\t{}", log_type, log_string).green());
+            let rules = client.get_alert_rules()?;
+            if rules.is_empty() {
+                eprintln!("{}", "No alert rules configured".yellow());
+            }
+            for rule in &rules {
+                eprintln!("{} {}", rule.name.green(), serde_json::to_string(&rule.condition)?);
+            }
+            eprintln!();
+
+            client.update_team_log_blocks()?;
+            let mut alerts = client.get_alerts(Some(100))?;
+            alerts.reverse();
+            for alert in alerts {
+                println!("{}    {}", alert.unix_seconds.full_timestamp(), alert.message.red());
+            }
+            Ok(())
+        });
+    }
+
+    #[no_mangle]
+    pub extern "C" fn view_anomalies() {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            use sigchain_core::time_util::TimeAgo;
+
+            eprintln!("Fetching logs...");
+            client.update_team_log_blocks()?;
+            eprintln!("{}", format!("Done fetching logs ✔").green());
+
+            let mut anomalies = client.get_access_anomalies(Some(100))?;
+            if anomalies.is_empty() {
+                eprintln!("{}", "No unusual access found".green());
+                return Ok(());
+            }
+            anomalies.reverse();
+            for anomaly in anomalies {
+                let description = anomaly.log.as_ref().map(|l| l.body.log_description()).unwrap_or("unknown".into());
+                println!("{:18}    {:30}    score {:3}    {}",
+                         anomaly.unix_seconds.full_timestamp(),
+                         anomaly.email,
+                         anomaly.score,
+                         description.yellow());
+                for reason in &anomaly.reasons {
+                    println!("    - {}", reason.explanation().red());
+                }
+            }
+            Ok(())
+        });
+    }
+
+    #[no_mangle]
+    pub extern "C" fn view_devices() {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            use sigchain_core::time_util::TimeAgo;
+
+            eprintln!("Fetching logs...");
+            client.update_team_log_blocks()?;
+            eprintln!("{}", format!("Done fetching logs ✔").green());
+            eprintln!();
+
+            for member in client.get_device_inventory()? {
+                if member.is_member_active {
+                    println!("{}", member.email.green());
+                } else {
+                    println!("{} {}", member.email.red(), "(removed)".red());
+                }
+                for device in member.devices {
+                    let device_line = format!("    {:24}    {}    SSH: {:5}    Git: {:5}    Other: {:5}    first seen {}, last seen {}",
+                                              device.device_name,
+                                              base64::encode(&device.workstation_public_key_double_hash),
+                                              device.ssh_operations,
+                                              device.git_operations,
+                                              device.other_operations,
+                                              device.first_seen_unix_seconds.time_ago(),
+                                              device.last_seen_unix_seconds.time_ago());
+                    if device.is_active {
+                        println!("{}", device_line);
                     } else {
-                        println!("{}", format!("[{}]\t✘\t{}", log_type, log_string).red());
+                        println!("{}", device_line.yellow());
+                    }
+                    if !device.hosts.is_empty() {
+                        println!("        hosts: {}", device.hosts.join(", "));
                     }
+                }
+            }
+            Ok(())
+        });
+    }
 
-                    if !skip_slow_print {
-                        thread::sleep(Duration::from_millis(200));
+    /// Report signed commits and tags in the git repository at `repo_ptr` that have no matching
+    /// log, logged commits that never landed, and objects signed by members after their removal.
+    #[no_mangle]
+    pub extern "C" fn audit_git_repository(repo_ptr: *const u8, repo_len: usize) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            use sigchain_core::time_util::TimeAgo;
+            use sigchain_client::client::git_audit::GitAuditFinding;
+
+            let repo = from_utf8(unsafe{ from_raw_parts(repo_ptr, repo_len) })?;
+
+            eprintln!("Fetching logs...");
+            client.update_team_log_blocks()?;
+            eprintln!("{}", format!("Done fetching logs ✔").green());
+
+            let report = client.audit_git_repository(::std::path::Path::new(repo))?;
+            eprintln!("Scanned {} commits and {} tags", report.commits_scanned, report.tags_scanned);
+            eprintln!();
+
+            let print_findings = |title: &str, findings: &[GitAuditFinding]| {
+                if findings.is_empty() {
+                    println!("{}", format!("{}: none ✔", title).green());
+                    return;
+                }
+                println!("{}", format!("{}: {}", title, findings.len()).red());
+                for finding in findings {
+                    println!("    {} {:8}    {:30}    {:18}    {}",
+                             finding.object_type,
+                             &finding.object_id[..finding.object_id.len().min(8)],
+                             finding.member_email,
+                             finding.unix_seconds.time_ago(),
+                             finding.summary);
+                }
+            };
+            print_findings("Signed by team keys without a Krypton log", &report.unlogged);
+            print_findings("Logged but never landed", &report.never_landed);
+            print_findings("Signed by removed members after removal", &report.signed_after_removal);
+            Ok(())
+        });
+    }
+
+    /// Verify a raw git commit or tag object (`git cat-file <type> <id>`) against the locally
+    /// synced team chain without contacting the server. Returns true only if the object was signed
+    /// by someone who was a member of the team at signing time.
+    #[no_mangle]
+    pub extern "C" fn verify_git_object(object_ptr: *const u8, object_len: usize) -> bool {
+        try_with_delegated_network_cli(false, |client| -> Result<bool> {
+            use sigchain_core::time_util::TimeAgo;
+
+            let object = unsafe{ from_raw_parts(object_ptr, object_len) };
+            let report = client.verify_git_object(object)?;
+            let object_id = &report.object_id[..report.object_id.len().min(8)];
+
+            let signer = match report.signer {
+                Some(ref signer) => signer,
+                None => {
+                    println!("{}", format!("✘\t{} {} is not signed by a team member's key", report.object_type, object_id).red());
+                    return Ok(false);
+                }
+            };
+            let role = match (signer.status.is_member, signer.status.is_admin) {
+                (true, true) => "admin",
+                (true, false) => "member",
+                (false, _) => "not a member",
+            };
+            let summary = format!("{} {} signed by {} ({:?} key) {}, {} at signing time",
+                                  report.object_type, object_id, signer.email, signer.signing_key,
+                                  report.unix_seconds.time_ago(), role);
+            if report.signed_by_member() {
+                println!("{}", format!("✔\t{}", summary).green());
+            } else {
+                println!("{}", format!("✘\t{}", summary).red());
+                if let Some(ref removal) = signer.removal {
+                    println!("\tremoved from the team {}", removal.unix_seconds.time_ago());
+                }
+            }
+            Ok(report.signed_by_member())
+        }).unwrap_or(false)
+    }
+
+    /// Write member PGP keys as an armored keyring to `pgp_keyring_path_ptr` and SSH keys as a git
+    /// `allowed_signers` file to `allowed_signers_path_ptr`. Either is printed if its path is null.
+    #[no_mangle]
+    pub extern "C" fn export_team_keyrings(
+        pgp_keyring_path_ptr: *const u8, pgp_keyring_path_len: usize,
+        allowed_signers_path_ptr: *const u8, allowed_signers_path_len: usize,
+        include_removed: bool,
+    ) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            let export = client.export_team_keyrings(include_removed)?;
+
+            let outputs = [
+                (pgp_keyring_path_ptr, pgp_keyring_path_len, &export.pgp_keyring, "PGP keyring"),
+                (allowed_signers_path_ptr, allowed_signers_path_len, &export.allowed_signers, "allowed signers"),
+            ];
+            for &(path_ptr, path_len, contents, name) in outputs.iter() {
+                if path_ptr.is_null() {
+                    println!("{}", contents);
+                    continue;
+                }
+                let path = from_utf8(unsafe{ from_raw_parts(path_ptr, path_len) })?;
+                ::std::fs::File::create(path)?.write_all(contents.as_bytes())?;
+                eprintln!("{}", format!("Wrote {} to {} ✔", name, path).green());
+            }
+            eprintln!("Exported keys of {} members", export.members_exported);
+            Ok(())
+        });
+    }
+
+    /// Print the fingerprint and user IDs of each key in an ASCII armored or base64 public key
+    /// (or keyring) and the team member it belongs to, if any.
+    #[no_mangle]
+    pub extern "C" fn identify_pgp_public_key(key_ptr: *const u8, key_len: usize) {
+        let _ = try_with_delegated_network_cli(false, |client| -> Result<()> {
+            use sigchain_core::pgp;
+            use sigchain_core::pgp::public_key::PGPPublicKey;
+            use sigchain_core::time_util::TimeAgo;
+
+            let input = from_utf8(unsafe{ from_raw_parts(key_ptr, key_len) })?;
+            let packets = pgp::packets_from_input(input, pgp::LABEL_PUBLIC_KEY)?;
+            let identities = db::Identity::find_all_for_team(&client.team_db_conn())?;
+
+            for key in PGPPublicKey::parse_keyring(&packets)? {
+                println!("{}", key.fingerprint_string());
+                println!("\t{:?} key created {}", key.algorithm(), (key.created_unix_seconds() as i64).time_ago());
+                for user_id in key.user_ids.iter() {
+                    println!("\t{}", user_id.user_id);
+                }
+                let member = identities.iter().find(|i| {
+                    PGPPublicKey::parse(&i.pgp_public_key).map(|k| k.fingerprint() == key.fingerprint()).unwrap_or(false)
+                });
+                match member {
+                    Some(member) => println!("{}", format!("\tteam member {}", member.email).green()),
+                    None => println!("{}", format!("\tnot a team member's key").red()),
+                }
+            }
+            Ok(())
+        });
+    }
+
+    /// `heads_ptr` optionally points to a JSON array of member-signed log chain heads to cross-check.
+    #[no_mangle]
+    pub extern "C" fn verify_log_chains(heads_ptr: *const u8, heads_len: usize) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            use sigchain_core::protocol::SignedMessage;
+            use sigchain_core::base64;
+
+            let published_heads: Vec<SignedMessage> = match heads_ptr.is_null() {
+                true => vec![],
+                false => serde_json::from_slice(unsafe{ from_raw_parts(heads_ptr, heads_len) })?,
+            };
+
+            eprintln!("Fetching logs...");
+            client.update_team_log_blocks()?;
+            eprintln!("{}", format!("Done fetching logs ✔").green());
+            eprintln!();
+
+            let conn = &client.team_db_conn();
+            let reports = client.verify_log_chains(&published_heads)?;
+
+            for report in reports.iter() {
+                let email = db::Identity::find_for_signer(conn, &report.member_public_key)
+                    .map(|i| i.email)
+                    .unwrap_or(base64::encode(&report.member_public_key));
+                let summary = format!("{:30}    {} blocks, {} published heads checked",
+                                      email, report.block_count, report.published_heads_checked);
+                if report.is_intact() {
+                    println!("{}", format!("✔\t{}", summary).green());
+                } else {
+                    println!("{}", format!("✘\t{}", summary).red());
+                    for issue in report.issues.iter() {
+                        println!("\t{}", serde_json::to_string(issue)?);
                     }
                 }
+            }
 
-                skip_slow_print = false;
-                client.update_team_log_blocks()?;
-                thread::sleep(Duration::from_secs(5));
+            let broken = reports.iter().filter(|r| !r.is_intact()).count();
+            eprintln!();
+            if broken == 0 {
+                eprintln!("{}", format!("All {} log chains intact", reports.len()).green());
+            } else {
+                eprintln!("{}", format!("{} of {} log chains have integrity issues", broken, reports.len()).red());
+            }
+            Ok(())
+        });
+    }
+
+    #[no_mangle]
+    pub extern "C" fn view_log_queue(requeue_dead_lettered: bool) {
+        do_with_delegated_network_cli(|client| -> Result<()> {
+            use sigchain_core::time_util::TimeAgo;
+
+            if requeue_dead_lettered {
+                let requeued = client.requeue_dead_lettered_logs()?;
+                eprintln!("{}", format!("Requeued {} dead-lettered logs ✔", requeued).green());
+                eprintln!();
             }
+
+            let status = client.get_log_queue_status()?;
+
+            eprintln!("Queued logs: {}", format!("{}", status.queued).yellow());
+            if let Some(oldest) = status.oldest_queued_unix_seconds {
+                eprintln!("Oldest queued log: {}", oldest.time_ago().yellow());
+                eprintln!("Most attempts on a queued log: {}", format!("{}", status.max_attempts).yellow());
+            }
+            eprintln!("Dead-lettered logs: {}", format!("{}", status.dead_lettered.len()).yellow());
+            eprintln!();
+
+            for dead_lettered in status.dead_lettered.iter() {
+                let description = dead_lettered.log.as_ref()
+                    .map(|l| l.body.log_description())
+                    .unwrap_or("unreadable log".into());
+                println!("{}", format!("[{}]\t{}\t(queued {}, {} attempts)",
+                                       dead_lettered.dead_lettered_unix_seconds.full_timestamp(),
+                                       description,
+                                       dead_lettered.created_unix_seconds.time_ago(),
+                                       dead_lettered.attempts,
+                ).red());
+                println!("\t{}", dead_lettered.reason);
+            }
+
+            Ok(())
         });
     }
 
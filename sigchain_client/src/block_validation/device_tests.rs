@@ -0,0 +1,349 @@
+use super::*;
+
+gen_test!(member_add_device,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+});
+
+gen_test!(member_add_device_for_other_member,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Try to add a device authorized for the admin.
+    let (device_user, device) = generate_device(&users[0], "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+});
+
+gen_test!(member_add_device_bad_encryption_key_proof,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Try to add a device with an encryption key it did not prove possession of.
+    let (device_user, mut device) = generate_device(&user, "laptop");
+    device.encryption_public_key = gen_box_key_pair().unwrap().public_key_bytes().into();
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+});
+
+gen_test!(member_add_member_key_as_device,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Try to add the admin's keys as a device for user.
+    let device = authorized_device(&user, &users[0], "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+});
+
+gen_test!(member_add_device_twice,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user, then try to add it again.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+    let device_add_again_block = add_device_block(
+        &user, &device, &device_add_block.hash(), false);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_add_again_block);
+});
+
+gen_test!(non_member_add_device,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Try to add a device for the non-member.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.push(device_add_block);
+});
+
+gen_test!(device_update_keys,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+
+    // Replace the user's SSH key from the device.
+    let new_key_pair = gen_sign_key_pair().unwrap();
+    let device_update_block = update_keys_block(&device_user, KeyUpdate {
+        ssh_public_key: Some(ssh_ed25519_public_key(&new_key_pair)),
+        pgp_public_key: None,
+    }, &device_add_block.hash(), true);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_update_block);
+});
+
+gen_test!(member_device_pin_host,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+
+    // Try to pin host from the member's device.
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
+    let device_pin_host_block = pin_host_block(
+        "test.krypt.co", &host_public_key, &device_user, &device_add_block.hash(), false);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_pin_host_block);
+});
+
+gen_test!(admin_device_pin_host,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Add a device for admin.
+    let (device_user, device) = generate_device(&users[0], "laptop");
+    let device_add_block = add_device_block(
+        &users[0], &device, &blocks.last().unwrap().hash(), true);
+
+    // Pin host from the admin's device.
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
+    let device_pin_host_block = pin_host_block(
+        "test.krypt.co", &host_public_key, &device_user, &device_add_block.hash(), true);
+
+    users.push(device_user);
+    blocks.push(device_add_block);
+    blocks.push(device_pin_host_block);
+});
+
+gen_test!(member_remove_own_device,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user, then remove it.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+    let device_remove_block = remove_device_block(
+        &user, &device_user, &device_add_block.hash(), true);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_remove_block);
+});
+
+gen_test!(device_create_log_chain,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user, which starts its own log chain.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+    let device_log_chain_block = create_log_chain_block(&device_user, true);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_log_chain_block);
+});
+
+gen_test!(removed_device_create_log_chain,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user, then remove it.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+    let device_remove_block = remove_device_block(
+        &user, &device_user, &device_add_block.hash(), true);
+
+    // The server rejects a new log chain from the removed device. Clients read log chains after
+    // the main chain, so they accept it as written before the removal and mark its logs.
+    let mut device_log_chain_block = create_log_chain_block(&device_user, false);
+    device_log_chain_block.expected.valid_on_replay = Some(true);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_remove_block);
+    blocks.push(device_log_chain_block);
+});
+
+gen_test!(removed_device_leave,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user, then remove it.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+    let device_remove_block = remove_device_block(
+        &user, &device_user, &device_add_block.hash(), true);
+
+    // Try to leave the team from the removed device.
+    let device_leave_block = leave_team_block(&device_user, &device_remove_block.hash(), false);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_remove_block);
+    blocks.push(device_leave_block);
+});
+
+gen_test!(admin_remove_member_device,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+
+    // Remove the user's device.
+    let device_remove_block = remove_device_block(
+        &users[0], &device_user, &device_add_block.hash(), true);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_remove_block);
+});
+
+gen_test!(member_remove_other_member_device,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) users to team.
+    let user_1 = generate_user(&users[0].client.team_public_key, 1);
+    let user_1_add_blocks = add_user_blocks(&users[0], &user_1, &blocks.last().unwrap().hash());
+    let user_2 = generate_user(&users[0].client.team_public_key, 2);
+    let user_2_add_blocks = add_user_blocks(&users[0], &user_2, &user_1_add_blocks.last().unwrap().hash());
+
+    // Add a device for user_1.
+    let (device_user, device) = generate_device(&user_1, "laptop");
+    let device_add_block = add_device_block(
+        &user_1, &device, &user_2_add_blocks.last().unwrap().hash(), true);
+
+    // Try to remove user_1's device as user_2.
+    let device_remove_block = remove_device_block(
+        &user_2, &device_user, &device_add_block.hash(), false);
+
+    users.push(user_1);
+    users.push(user_2);
+    users.push(device_user);
+    blocks.extend(user_1_add_blocks);
+    blocks.extend(user_2_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_remove_block);
+});
+
+gen_test!(removed_member_device_update_keys,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Add a device for user, then remove user.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+    let user_remove_block = remove_user_block(&users[0], &user, &device_add_block.hash(), true);
+
+    // Try to replace the removed user's SSH key from the device.
+    let new_key_pair = gen_sign_key_pair().unwrap();
+    let device_update_block = update_keys_block(&device_user, KeyUpdate {
+        ssh_public_key: Some(ssh_ed25519_public_key(&new_key_pair)),
+        pgp_public_key: None,
+    }, &user_remove_block.hash(), false);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(user_remove_block);
+    blocks.push(device_update_block);
+});
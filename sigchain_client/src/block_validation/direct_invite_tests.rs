@@ -291,7 +291,7 @@ gen_test!(dir_invite_user_hijack,
     // Generate user 2 and 3.
     let mut user_2 = generate_user(&users[0].client.team_public_key, 2);
     user_2.client.sign_key_pair_seed = user_1.client.sign_key_pair_seed.clone();
-    user_2.sign_key_pair = user_1.sign_key_pair.clone();
+    user_2.sign_key_pair = user_1.sign_key_pair.duplicate();
     let mut user_3 = generate_user(&users[0].client.team_public_key, 3);
     user_3.email = user_1.email.clone();
 
@@ -351,6 +351,7 @@ gen_test!(dir_invite_accept_id_sig_mismatch_key,
             body: Main(Append(Block {
                 last_block_hash: user_invite_block.hash(),
                 operation: AcceptInvite(generate_identity(
+                    &user.client.team_public_key,
                     &gen_sign_key_pair().unwrap(),
                     &user.client.box_key_pair,
                     &user.email,
@@ -365,6 +366,7 @@ gen_test!(dir_invite_accept_id_sig_mismatch_key,
         expected: ExpectedResult {
             valid: false,
             team_public_key: user_invite_block.expected.team_public_key.clone(),
+            valid_on_replay: None,
         },
     };
 
@@ -392,6 +394,282 @@ gen_test!(dir_invite_accept_pk_sig_mismatch_key,
     blocks.extend(user_add_blocks);
 });
 
+gen_test!(dir_invite_accept_malformed_pgp_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Invite (direct) user to team.
+    let user_invite_block = dir_invite_user_block(
+        &users[0], &user, &blocks.last().unwrap().hash(), true);
+
+    // Accept with raw key bytes in place of OpenPGP packets.
+    let mut identity = generate_identity(&user.client.team_public_key, &user.sign_key_pair, &user.client.box_key_pair, &user.email);
+    identity.pgp_public_key = user.sign_key_pair.public_key_bytes().into();
+
+    let user_accept_inv_msg = SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Main(Append(Block {
+                last_block_hash: user_invite_block.hash(),
+                operation: AcceptInvite(identity),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap();
+
+    let user_accept_inv_block = TestBlock {
+        signed_message: user_accept_inv_msg,
+        expected: ExpectedResult {
+            valid: false,
+            team_public_key: user_invite_block.expected.team_public_key.clone(),
+            valid_on_replay: None,
+        },
+    };
+
+    users.push(user);
+    blocks.push(user_invite_block);
+    blocks.push(user_accept_inv_block);
+});
+
+gen_test!(dir_invite_accept_legacy_raw_pgp_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Invite (direct) user to team.
+    let user_invite_block = dir_invite_user_block(
+        &users[0], &user, &blocks.last().unwrap().hash(), true);
+
+    // Accept with raw key bytes, as older clients did, before PGP keys were validated.
+    let mut identity = generate_identity(&user.client.team_public_key, &user.sign_key_pair, &user.client.box_key_pair, &user.email);
+    identity.pgp_public_key = user.sign_key_pair.public_key_bytes().into();
+
+    let user_accept_inv_msg = SignedMessage::from_message(
+        Message {
+            header: legacy_header(),
+            body: Main(Append(Block {
+                last_block_hash: user_invite_block.hash(),
+                operation: AcceptInvite(identity),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap();
+
+    let user_accept_inv_block = TestBlock {
+        signed_message: user_accept_inv_msg,
+        expected: ExpectedResult {
+            valid: true,
+            team_public_key: user_invite_block.expected.team_public_key.clone(),
+            valid_on_replay: None,
+        },
+    };
+
+    users.push(user);
+    blocks.push(user_invite_block);
+    blocks.push(user_accept_inv_block);
+});
+
+gen_test!(dir_invite_accept_pgp_user_id_mismatch,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Invite (direct) user to team.
+    let user_invite_block = dir_invite_user_block(
+        &users[0], &user, &blocks.last().unwrap().hash(), true);
+
+    // Accept with a PGP key whose user ID is for another email.
+    let mut identity = generate_identity(&user.client.team_public_key, &user.sign_key_pair, &user.client.box_key_pair, &user.email);
+    identity.pgp_public_key = generate_pgp_public_key(&user.sign_key_pair, &users[0].email);
+
+    let user_accept_inv_msg = SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Main(Append(Block {
+                last_block_hash: user_invite_block.hash(),
+                operation: AcceptInvite(identity),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap();
+
+    let user_accept_inv_block = TestBlock {
+        signed_message: user_accept_inv_msg,
+        expected: ExpectedResult {
+            valid: false,
+            team_public_key: user_invite_block.expected.team_public_key.clone(),
+            valid_on_replay: None,
+        },
+    };
+
+    users.push(user);
+    blocks.push(user_invite_block);
+    blocks.push(user_accept_inv_block);
+});
+
+gen_test!(dir_invite_accept_malformed_ssh_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Invite (direct) user to team.
+    let user_invite_block = dir_invite_user_block(
+        &users[0], &user, &blocks.last().unwrap().hash(), true);
+
+    // Accept with a truncated SSH public key.
+    let mut identity = generate_identity(&user.client.team_public_key, &user.sign_key_pair, &user.client.box_key_pair, &user.email);
+    identity.ssh_public_key.truncate(20);
+
+    let user_accept_inv_msg = SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Main(Append(Block {
+                last_block_hash: user_invite_block.hash(),
+                operation: AcceptInvite(identity),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap();
+
+    let user_accept_inv_block = TestBlock {
+        signed_message: user_accept_inv_msg,
+        expected: ExpectedResult {
+            valid: false,
+            team_public_key: user_invite_block.expected.team_public_key.clone(),
+            valid_on_replay: None,
+        },
+    };
+
+    users.push(user);
+    blocks.push(user_invite_block);
+    blocks.push(user_accept_inv_block);
+});
+
+gen_test!(dir_invite_accept_legacy_malformed_ssh_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Invite (direct) user to team.
+    let user_invite_block = dir_invite_user_block(
+        &users[0], &user, &blocks.last().unwrap().hash(), true);
+
+    // Accept with a truncated SSH public key, as older clients could, before SSH keys were validated.
+    let mut identity = generate_identity(&user.client.team_public_key, &user.sign_key_pair, &user.client.box_key_pair, &user.email);
+    identity.ssh_public_key.truncate(20);
+
+    let user_accept_inv_msg = SignedMessage::from_message(
+        Message {
+            header: legacy_header(),
+            body: Main(Append(Block {
+                last_block_hash: user_invite_block.hash(),
+                operation: AcceptInvite(identity),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap();
+
+    let user_accept_inv_block = TestBlock {
+        signed_message: user_accept_inv_msg,
+        expected: ExpectedResult {
+            valid: true,
+            team_public_key: user_invite_block.expected.team_public_key.clone(),
+            valid_on_replay: None,
+        },
+    };
+
+    users.push(user);
+    blocks.push(user_invite_block);
+    blocks.push(user_accept_inv_block);
+});
+
+gen_test!(dir_invite_accept_foreign_encryption_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Invite (direct) user to team.
+    let user_invite_block = dir_invite_user_block(
+        &users[0], &user, &blocks.last().unwrap().hash(), true);
+
+    // Accept claiming the admin's encryption key, copying the admin's proof for it.
+    let admin_identity = generate_identity(
+        &users[0].client.team_public_key, &users[0].sign_key_pair, &users[0].client.box_key_pair, &users[0].email);
+    let mut identity = generate_identity(&user.client.team_public_key, &user.sign_key_pair, &user.client.box_key_pair, &user.email);
+    identity.encryption_public_key = admin_identity.encryption_public_key;
+    identity.encryption_key_proof = admin_identity.encryption_key_proof;
+
+    let user_accept_inv_msg = SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Main(Append(Block {
+                last_block_hash: user_invite_block.hash(),
+                operation: AcceptInvite(identity),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap();
+
+    let user_accept_inv_block = TestBlock {
+        signed_message: user_accept_inv_msg,
+        expected: ExpectedResult {
+            valid: false,
+            team_public_key: user_invite_block.expected.team_public_key.clone(),
+            valid_on_replay: None,
+        },
+    };
+
+    users.push(user);
+    blocks.push(user_invite_block);
+    blocks.push(user_accept_inv_block);
+});
+
+gen_test!(dir_invite_accept_missing_encryption_key_proof,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Invite (direct) user to team.
+    let user_invite_block = dir_invite_user_block(
+        &users[0], &user, &blocks.last().unwrap().hash(), true);
+
+    // Accept without proving possession of the encryption key.
+    let mut identity = generate_identity(&user.client.team_public_key, &user.sign_key_pair, &user.client.box_key_pair, &user.email);
+    identity.encryption_key_proof = None;
+
+    let user_accept_inv_msg = SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Main(Append(Block {
+                last_block_hash: user_invite_block.hash(),
+                operation: AcceptInvite(identity),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap();
+
+    let user_accept_inv_block = TestBlock {
+        signed_message: user_accept_inv_msg,
+        expected: ExpectedResult {
+            valid: false,
+            team_public_key: user_invite_block.expected.team_public_key.clone(),
+            valid_on_replay: None,
+        },
+    };
+
+    users.push(user);
+    blocks.push(user_invite_block);
+    blocks.push(user_accept_inv_block);
+});
+
 gen_test!(dir_invite_duplicate,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
@@ -0,0 +1,189 @@
+use super::*;
+
+/// The X25519 public key for the secret key 0x20, 0x21, ..., 0x3f.
+const KNOWN_ANSWER_ENCRYPTION_PUBLIC_KEY: [u8; 32] = [
+    0x35, 0x80, 0x72, 0xd6, 0x36, 0x58, 0x80, 0xd1, 0xae, 0xea, 0x32, 0x9a, 0xdf, 0x91, 0x21, 0x38,
+    0x38, 0x51, 0xed, 0x21, 0xa2, 0x8e, 0x3b, 0x75, 0xe9, 0x65, 0xd0, 0xd2, 0xcd, 0x16, 0x62, 0x54,
+];
+
+/// An XEdDSA signature by that key over the creator's `encryption_key_proof_data`, the signing
+/// key being derived from the seed 0x00, 0x01, ..., 0x1f, made by an independent implementation
+/// of the XEdDSA specification with the random input 0x40, 0x41, ..., 0x7f.
+const KNOWN_ANSWER_ENCRYPTION_KEY_PROOF: [u8; 64] = [
+    0xc6, 0x26, 0xbc, 0xe7, 0xe3, 0xf9, 0xa4, 0x01, 0xa4, 0x49, 0xa4, 0x48, 0x69, 0xda, 0x38, 0x72,
+    0xd7, 0x61, 0xdd, 0xa3, 0xb9, 0x62, 0x24, 0xc0, 0xfa, 0xa8, 0x6a, 0xd8, 0x40, 0x22, 0x41, 0x79,
+    0x68, 0x01, 0xdd, 0x4f, 0xec, 0x5b, 0x5b, 0xe8, 0x39, 0x1d, 0x45, 0xe0, 0x12, 0xf1, 0x79, 0x96,
+    0xf3, 0x4f, 0x5e, 0x16, 0xf4, 0x7e, 0x72, 0x3e, 0x0b, 0x7d, 0xcd, 0x6e, 0x7c, 0x3d, 0xfe, 0x02,
+];
+
+/// A team created by a fixed creator whose identity carries `encryption_key_proof`.
+fn known_answer_team_block(encryption_key_proof: Option<Vec<u8>>, valid: bool) -> (User, TestBlock) {
+    use crypto::ed25519_box;
+
+    let sign_key_pair_seed: Vec<u8> = (0x00..0x20).collect();
+    let sign_key_pair = sign_keypair_from_seed(&sign_key_pair_seed).unwrap();
+    let mut box_secret_key = [0u8; 32];
+    for (i, b) in box_secret_key.iter_mut().enumerate() {
+        *b = 0x20 + i as u8;
+    }
+    let team_public_key: Vec<u8> = sign_key_pair.public_key_bytes().into();
+    let creator = User {
+        client: Client {
+            sign_key_pair_seed,
+            box_key_pair: BoxKeyPair {
+                public_key: ed25519_box::PublicKey(KNOWN_ANSWER_ENCRYPTION_PUBLIC_KEY),
+                secret_key: ed25519_box::SecretKey(box_secret_key),
+            },
+            team_public_key: team_public_key.clone(),
+        },
+        sign_key_pair,
+        email: format!("alex@{}", TEST_EMAIL_DOMAIN),
+    };
+
+    let mut creator_identity = generate_identity(
+        &team_public_key,
+        &creator.sign_key_pair,
+        &creator.client.box_key_pair,
+        &creator.email,
+    );
+    creator_identity.encryption_key_proof = encryption_key_proof;
+
+    let team_creation_msg = SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Main(Create(GenesisBlock {
+                team_info: TeamInfo {
+                    name: String::from("Acme Engineering"),
+                },
+                creator_identity,
+            }))
+        },
+        &creator.sign_key_pair,
+    ).unwrap();
+
+    let expected = ExpectedResult {
+        valid,
+        team_public_key,
+        valid_on_replay: None,
+    };
+    let block = block_from_signed_message(&team_creation_msg, &expected);
+    (creator, block)
+}
+
+/// Direct invite a new user and accept with the identity `modify` makes of their own.
+fn invite_and_accept_blocks<F>(users: &mut Vec<User>, blocks: &mut Vec<TestBlock>, header: Header, modify: F, valid: bool)
+    where F: FnOnce(&mut Identity) {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Invite (direct) user to team.
+    let user_invite_block = dir_invite_user_block(
+        &users[0], &user, &blocks.last().unwrap().hash(), true);
+
+    let mut identity = generate_identity(&user.client.team_public_key, &user.sign_key_pair, &user.client.box_key_pair, &user.email);
+    modify(&mut identity);
+
+    let user_accept_inv_msg = SignedMessage::from_message(
+        Message {
+            header,
+            body: Main(Append(Block {
+                last_block_hash: user_invite_block.hash(),
+                operation: AcceptInvite(identity),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap();
+
+    let user_accept_inv_block = TestBlock {
+        signed_message: user_accept_inv_msg,
+        expected: ExpectedResult {
+            valid,
+            team_public_key: user_invite_block.expected.team_public_key.clone(),
+            valid_on_replay: None,
+        },
+    };
+
+    users.push(user);
+    blocks.push(user_invite_block);
+    blocks.push(user_accept_inv_block);
+}
+
+gen_test!(encryption_key_proof_known_answer,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Create the team with a proof made by another implementation.
+    let (creator, team_creation_block) = known_answer_team_block(
+        Some(KNOWN_ANSWER_ENCRYPTION_KEY_PROOF.to_vec()), true);
+    users[0] = creator;
+    blocks[0] = team_creation_block;
+});
+
+gen_test!(encryption_key_proof_known_answer_tampered,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Flip a bit of the known good proof's scalar.
+    let mut proof = KNOWN_ANSWER_ENCRYPTION_KEY_PROOF.to_vec();
+    proof[40] ^= 0x01;
+    let (creator, team_creation_block) = known_answer_team_block(Some(proof), false);
+    users[0] = creator;
+    blocks[0] = team_creation_block;
+});
+
+gen_test!(encryption_key_proof_known_answer_truncated,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Drop the last byte of the known good proof.
+    let proof = KNOWN_ANSWER_ENCRYPTION_KEY_PROOF[..63].to_vec();
+    let (creator, team_creation_block) = known_answer_team_block(Some(proof), false);
+    users[0] = creator;
+    blocks[0] = team_creation_block;
+});
+
+gen_test!(create_team_missing_encryption_key_proof,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Create the team without proving possession of the creator's encryption key.
+    let (creator, team_creation_block) = known_answer_team_block(None, false);
+    users[0] = creator;
+    blocks[0] = team_creation_block;
+});
+
+gen_test!(dir_invite_accept_encryption_key_proof,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Accept with a freshly made proof.
+    invite_and_accept_blocks(users, blocks, Header::new(), |_| {}, true);
+});
+
+gen_test!(dir_invite_accept_tampered_encryption_key_proof,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Accept with a proof whose commitment was altered.
+    invite_and_accept_blocks(users, blocks, Header::new(), |identity| {
+        if let Some(ref mut proof) = identity.encryption_key_proof {
+            proof[0] ^= 0x01;
+        }
+    }, false);
+});
+
+gen_test!(dir_invite_accept_encryption_key_proof_for_other_team,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Accept with a proof made for another team.
+    let other_team_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    invite_and_accept_blocks(users, blocks, Header::new(), |identity| {
+        let box_key_pair = gen_box_key_pair().unwrap();
+        identity.encryption_public_key = box_key_pair.public_key_bytes().into();
+        identity.prove_encryption_key(&other_team_public_key, &box_key_pair.secret_key.0).unwrap();
+    }, false);
+});
+
+gen_test!(dir_invite_accept_legacy_missing_encryption_key_proof,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Blocks from before encryption keys were proven are replayed without a proof.
+    invite_and_accept_blocks(users, blocks, legacy_header(), |identity| {
+        identity.encryption_key_proof = None;
+    }, true);
+});
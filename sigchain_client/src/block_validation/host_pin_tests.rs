@@ -4,7 +4,7 @@ gen_test!(admin_pin_host,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
     // Pin host.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_pin_host_block = pin_host_block(
         "test.krypt.co", &host_public_key, &users[0], &blocks.last().unwrap().hash(), true);
 
@@ -19,7 +19,7 @@ gen_test!(member_pin_host,
     let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
 
     // Pin host.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let user_pin_host_block = pin_host_block(
         "test.krypt.co", &host_public_key, &user, &user_add_blocks.last().unwrap().hash(), false);
 
@@ -35,7 +35,7 @@ gen_test!(non_member_pin_host,
     let user = generate_user(&users[0].client.team_public_key, 1);
 
     // Pin host.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let user_pin_host_block = pin_host_block(
         "test.krypt.co", &host_public_key, &user, &blocks.last().unwrap().hash(), false);
 
@@ -47,7 +47,7 @@ gen_test!(admin_unpin_host,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
     // Pin host.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_pin_host_block = pin_host_block(
         "test.krypt.co", &host_public_key, &users[0], &blocks.last().unwrap().hash(), true);
 
@@ -67,7 +67,7 @@ gen_test!(member_unpin_host,
     let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
 
     // Pin host.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_pin_host_block = pin_host_block(
         "test.krypt.co",
         &host_public_key,
@@ -93,7 +93,7 @@ gen_test!(non_member_unpin_host,
     let user = generate_user(&users[0].client.team_public_key, 1);
 
     // Pin host.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_pin_host_block = pin_host_block(
         "test.krypt.co", &host_public_key, &users[0], &blocks.last().unwrap().hash(), true);
 
@@ -110,12 +110,12 @@ gen_test!(admin_unpin_host_wrong_public_key,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
     // Pin host.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_pin_host_block = pin_host_block(
         "test.krypt.co", &host_public_key, &users[0], &blocks.last().unwrap().hash(), true);
 
     // Try to unpin different public key from host name.
-    let new_host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let new_host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_unpin_host_block = unpin_host_block(
         "test.krypt.co", &new_host_public_key, &users[0], &admin_pin_host_block.hash(), false);
 
@@ -127,7 +127,7 @@ gen_test!(admin_unpin_host_wrong_host_name,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
     // Pin host.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_pin_host_block = pin_host_block(
         "test.krypt.co", &host_public_key, &users[0], &blocks.last().unwrap().hash(), true);
 
@@ -143,7 +143,7 @@ gen_test!(admin_duplicate_pin_host,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
     // Pin host and try to pin it again.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_pin_host_block = pin_host_block(
         "test.krypt.co", &host_public_key, &users[0], &blocks.last().unwrap().hash(), true);
     let admin_pin_host_again_block = pin_host_block(
@@ -157,8 +157,8 @@ gen_test!(admin_pin_many_keys_for_host,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
     // Pin many keys to a host.
-    let host_public_key_1: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
-    let host_public_key_2: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key_1: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
+    let host_public_key_2: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_pin_host_1_block = pin_host_block(
         "test.krypt.co", &host_public_key_1, &users[0], &blocks.last().unwrap().hash(), true);
     let admin_pin_host_2_block = pin_host_block(
@@ -172,7 +172,7 @@ gen_test!(admin_pin_same_key_for_hosts,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
     // Pin same key to many hosts.
-    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let host_public_key: Vec<u8> = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
     let admin_pin_host_1_block = pin_host_block(
         "test.krypt.co", &host_public_key, &users[0], &blocks.last().unwrap().hash(), true);
     let admin_pin_host_2_block = pin_host_block(
@@ -181,3 +181,58 @@ gen_test!(admin_pin_same_key_for_hosts,
     blocks.push(admin_pin_host_1_block);
     blocks.push(admin_pin_host_2_block);
 });
+
+gen_test!(admin_pin_malformed_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Try to pin a raw ed25519 key that is not in SSH wire format.
+    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let admin_pin_host_block = pin_host_block(
+        "test.krypt.co", &host_public_key, &users[0], &blocks.last().unwrap().hash(), false);
+
+    blocks.push(admin_pin_host_block);
+});
+
+gen_test!(admin_pin_unknown_key_type,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Try to pin a key of an unsupported type.
+    let mut host_public_key = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
+    host_public_key[4..15].copy_from_slice(b"ssh-ed99999");
+    let admin_pin_host_block = pin_host_block(
+        "test.krypt.co", &host_public_key, &users[0], &blocks.last().unwrap().hash(), false);
+
+    blocks.push(admin_pin_host_block);
+});
+
+gen_test!(admin_pin_legacy_malformed_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Blocks from before host keys were validated pinned keys as they were written.
+    let host_public_key: Vec<u8> = gen_sign_key_pair().unwrap().public_key_bytes().into();
+    let admin_pin_host_msg = SignedMessage::from_message(
+        Message {
+            header: legacy_header(),
+            body: Main(Append(Block {
+                last_block_hash: blocks.last().unwrap().hash(),
+                operation: PinHostKey(SSHHostKey {
+                    host: String::from("test.krypt.co"),
+                    public_key: host_public_key.clone(),
+                }),
+            })),
+        },
+        &users[0].sign_key_pair,
+    ).unwrap();
+    let admin_pin_host_block = block_from_signed_message(&admin_pin_host_msg, &ExpectedResult {
+        valid: true,
+        team_public_key: users[0].client.team_public_key.clone(),
+        valid_on_replay: None,
+    });
+
+    // The key can be unpinned as it was written.
+    let admin_unpin_host_block = unpin_host_block(
+        "test.krypt.co", &host_public_key, &users[0], &admin_pin_host_block.hash(), true);
+
+    blocks.push(admin_pin_host_block);
+    blocks.push(admin_unpin_host_block);
+});
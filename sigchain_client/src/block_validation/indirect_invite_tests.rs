@@ -348,7 +348,7 @@ gen_test!(indir_invite_user_hijack,
     // Generate user 2 and 3.
     let mut user_2 = generate_user(&users[0].client.team_public_key, 2);
     user_2.client.sign_key_pair_seed = user_1.client.sign_key_pair_seed.clone();
-    user_2.sign_key_pair = user_1.sign_key_pair.clone();
+    user_2.sign_key_pair = user_1.sign_key_pair.duplicate();
     let mut user_3 = generate_user(&users[0].client.team_public_key, 3);
     user_3.email = user_1.email.clone();
 
@@ -0,0 +1,81 @@
+use super::*;
+
+gen_test!(member_publish_log_chain_head,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+    let main_chain_hash = user_add_blocks.last().unwrap().hash();
+
+    // The user logs, then publishes the head of their log chain on the main chain.
+    let log_chain_block = create_log_chain_block(&user, true);
+    let log_block = append_log_block(&user, &log_chain_block.hash(), true);
+    let first_head_block = publish_log_chain_head_block(
+        &user, &log_chain_block.hash(), &main_chain_hash, true);
+    let second_head_block = publish_log_chain_head_block(
+        &user, &log_block.hash(), &first_head_block.hash(), true);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(log_chain_block);
+    blocks.push(first_head_block);
+    blocks.push(log_block);
+    blocks.push(second_head_block);
+});
+
+gen_test!(device_publish_log_chain_head,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // A device publishes the head of its own log chain.
+    let (device_user, device) = generate_device(&user, "laptop");
+    let device_add_block = add_device_block(
+        &user, &device, &user_add_blocks.last().unwrap().hash(), true);
+    let device_log_chain_block = create_log_chain_block(&device_user, true);
+    let head_block = publish_log_chain_head_block(
+        &device_user, &device_log_chain_block.hash(), &device_add_block.hash(), true);
+
+    users.push(user);
+    users.push(device_user);
+    blocks.extend(user_add_blocks);
+    blocks.push(device_add_block);
+    blocks.push(device_log_chain_block);
+    blocks.push(head_block);
+});
+
+gen_test!(removed_member_publish_log_chain_head,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // The user starts a log chain and is then removed.
+    let log_chain_block = create_log_chain_block(&user, true);
+    let remove_block = remove_user_block(
+        &users[0], &user, &user_add_blocks.last().unwrap().hash(), true);
+    let head_block = publish_log_chain_head_block(
+        &user, &log_chain_block.hash(), &remove_block.hash(), false);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(log_chain_block);
+    blocks.push(remove_block);
+    blocks.push(head_block);
+});
+
+gen_test!(non_member_publish_log_chain_head,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // A user who never joined the team cannot publish on its main chain.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let head_block = publish_log_chain_head_block(
+        &user, &[0u8; 32], &blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    blocks.push(head_block);
+});
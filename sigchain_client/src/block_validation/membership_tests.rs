@@ -640,3 +640,185 @@ gen_test!(non_member_demote_self,
     users.push(user);
     blocks.push(user_demote_block);
 });
+
+gen_test!(admin_verify_member,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Attest to the user's safety number.
+    let user_verify_block = verify_member_block(
+        &users[0], &user, &user_safety_number(&user), &user_add_blocks.last().unwrap().hash(), true);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(user_verify_block);
+});
+
+gen_test!(admin_verify_member_wrong_safety_number,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Attest to the admin's safety number for the user.
+    let user_verify_block = verify_member_block(
+        &users[0], &user, &user_safety_number(&users[0]), &user_add_blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(user_verify_block);
+});
+
+gen_test!(admin_verify_non_member,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Try to attest to the user's safety number.
+    let user_verify_block = verify_member_block(
+        &users[0], &user, &user_safety_number(&user), &blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    blocks.push(user_verify_block);
+});
+
+gen_test!(member_verify_member,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) two users to team.
+    let user_1 = generate_user(&users[0].client.team_public_key, 1);
+    let user_1_add_blocks = add_user_blocks(&users[0], &user_1, &blocks.last().unwrap().hash());
+    let user_2 = generate_user(&users[0].client.team_public_key, 2);
+    let user_2_add_blocks = add_user_blocks(
+        &users[0], &user_2, &user_1_add_blocks.last().unwrap().hash());
+
+    // Try to attest to the other member's safety number.
+    let user_verify_block = verify_member_block(
+        &user_1, &user_2, &user_safety_number(&user_2), &user_2_add_blocks.last().unwrap().hash(), false);
+
+    users.push(user_1);
+    users.push(user_2);
+    blocks.extend(user_1_add_blocks);
+    blocks.extend(user_2_add_blocks);
+    blocks.push(user_verify_block);
+});
+
+gen_test!(member_update_keys,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Replace the user's SSH and PGP keys.
+    let new_key_pair = gen_sign_key_pair().unwrap();
+    let user_update_block = update_keys_block(&user, KeyUpdate {
+        ssh_public_key: Some(ssh_ed25519_public_key(&new_key_pair)),
+        pgp_public_key: Some(generate_pgp_public_key(&new_key_pair, &user.email)),
+    }, &user_add_blocks.last().unwrap().hash(), true);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(user_update_block);
+});
+
+gen_test!(member_update_keys_twice,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Replace the user's SSH key, then its PGP key.
+    let new_key_pair = gen_sign_key_pair().unwrap();
+    let user_update_ssh_block = update_keys_block(&user, KeyUpdate {
+        ssh_public_key: Some(ssh_ed25519_public_key(&new_key_pair)),
+        pgp_public_key: None,
+    }, &user_add_blocks.last().unwrap().hash(), true);
+    let user_update_pgp_block = update_keys_block(&user, KeyUpdate {
+        ssh_public_key: None,
+        pgp_public_key: Some(generate_pgp_public_key(&new_key_pair, &user.email)),
+    }, &user_update_ssh_block.hash(), true);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(user_update_ssh_block);
+    blocks.push(user_update_pgp_block);
+});
+
+gen_test!(member_update_keys_empty,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Try to update no keys.
+    let user_update_block = update_keys_block(&user, KeyUpdate {
+        ssh_public_key: None,
+        pgp_public_key: None,
+    }, &user_add_blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(user_update_block);
+});
+
+gen_test!(member_update_keys_malformed_ssh_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Try to replace the user's SSH key with a malformed one.
+    let user_update_block = update_keys_block(&user, KeyUpdate {
+        ssh_public_key: Some(b"not an ssh key".to_vec()),
+        pgp_public_key: None,
+    }, &user_add_blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(user_update_block);
+});
+
+gen_test!(member_update_keys_foreign_pgp_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate and add (direct invite and accept) user to team.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+    let user_add_blocks = add_user_blocks(&users[0], &user, &blocks.last().unwrap().hash());
+
+    // Try to replace the user's PGP key with one for the admin's email.
+    let new_key_pair = gen_sign_key_pair().unwrap();
+    let user_update_block = update_keys_block(&user, KeyUpdate {
+        ssh_public_key: None,
+        pgp_public_key: Some(generate_pgp_public_key(&new_key_pair, &users[0].email)),
+    }, &user_add_blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    blocks.extend(user_add_blocks);
+    blocks.push(user_update_block);
+});
+
+gen_test!(non_member_update_keys,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Generate user.
+    let user = generate_user(&users[0].client.team_public_key, 1);
+
+    // Try to update the non-member's keys.
+    let new_key_pair = gen_sign_key_pair().unwrap();
+    let user_update_block = update_keys_block(&user, KeyUpdate {
+        ssh_public_key: Some(ssh_ed25519_public_key(&new_key_pair)),
+        pgp_public_key: None,
+    }, &blocks.last().unwrap().hash(), false);
+
+    users.push(user);
+    blocks.push(user_update_block);
+});
@@ -18,7 +18,7 @@ use crypto::{
 };
 use errors::Result;
 use protocol::*;
-use team::Identity;
+use team::{Identity, Device};
 use Body::*;
 use Invitation::*;
 use MainChain::*;
@@ -56,9 +56,12 @@ pub struct ExpectedResult {
     pub valid: bool,
     #[serde(with = "b64data")]
     pub team_public_key: Vec<u8>,
+    // Whether clients replaying the block accept it, when that differs from `valid`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_on_replay: Option<bool>,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Client {
     #[serde(with = "b64data")]
     pub sign_key_pair_seed: Vec<u8>,
@@ -67,7 +70,6 @@ pub struct Client {
     pub team_public_key: Vec<u8>,
 }
 
-#[derive(Clone)]
 pub struct User {
     pub client: Client,
     pub sign_key_pair: SignKeyPair,
@@ -116,6 +118,12 @@ mod host_pin_tests;
 use self::host_pin_tests::*;
 mod other_tests;
 use self::other_tests::*;
+mod device_tests;
+use self::device_tests::*;
+mod encryption_key_proof_tests;
+use self::encryption_key_proof_tests::*;
+mod log_chain_tests;
+use self::log_chain_tests::*;
 
 fn block_from_signed_message(signed_message: &SignedMessage, expected: &ExpectedResult) -> TestBlock {
     TestBlock {
@@ -143,14 +151,15 @@ fn run_protocol_test(test: BlockValidationTest) {
 
         // Create client with local db and create transaction that cannot commit for testing.
         let client_sign_key_pair = sign_keypair_from_seed(&client.sign_key_pair_seed).unwrap();
+        let client_public_key = client_sign_key_pair.public_key;
         let test_client = TestClient::from_key_pair_temp_db(
-            client_sign_key_pair.clone(),
-            client.box_key_pair.clone(),
-            client.team_public_key.clone(),
+            client_sign_key_pair,
+            client.box_key_pair,
+            client.team_public_key,
             &server_conn,
         ).unwrap();
         test_client.db_connection.begin_test_transaction().unwrap();
-        key_client_map.insert(client_sign_key_pair.public_key, test_client);
+        key_client_map.insert(client_public_key, test_client);
     }
     println!("done");
 
@@ -175,6 +184,7 @@ fn run_protocol_test(test: BlockValidationTest) {
             i, block.expected.valid, server_response.is_ok(), server_response);
 
         // Update all of the client chains and validate.
+        let valid_on_replay = block.expected.valid_on_replay.unwrap_or(block.expected.valid);
         for client in key_client_map.values() {
             let client_response = client.verified_payload_with_db_txn(&block.signed_message);
 
@@ -183,9 +193,9 @@ fn run_protocol_test(test: BlockValidationTest) {
             } else {
                 // TODO: Match response error/types
                 assert_eq!(
-                    block.expected.valid && block.expected.team_public_key == client.team_public_key, client_response.is_ok(),
+                    valid_on_replay && block.expected.team_public_key == client.team_public_key, client_response.is_ok(),
                     "Assertion failed by client for block {}: expected {}, received {}. Response: {:?}",
-                    i, block.expected.valid && block.expected.team_public_key == client.team_public_key, client_response.is_ok(), client_response);
+                    i, valid_on_replay && block.expected.team_public_key == client.team_public_key, client_response.is_ok(), client_response);
             };
         }
 
@@ -196,13 +206,106 @@ fn run_protocol_test(test: BlockValidationTest) {
     println!("Test {:?} complete", test.name);
 }
 
-pub fn generate_identity(sign_key_pair: &SignKeyPair, box_key_pair: &BoxKeyPair, email: &str) -> Identity {
-    Identity {
+pub fn generate_identity(team_public_key: &[u8], sign_key_pair: &SignKeyPair, box_key_pair: &BoxKeyPair, email: &str) -> Identity {
+    let mut identity = Identity {
         public_key: sign_key_pair.public_key_bytes().into(),
         encryption_public_key: box_key_pair.public_key_bytes().into(),
-        ssh_public_key: Vec::new(),
-        pgp_public_key: sign_key_pair.public_key_bytes().into(),
+        ssh_public_key: ssh_ed25519_public_key(sign_key_pair),
+        pgp_public_key: generate_pgp_public_key(sign_key_pair, email),
         email: email.into(),
+        encryption_key_proof: None,
+    };
+    identity.prove_encryption_key(team_public_key, &box_key_pair.secret_key.0).unwrap();
+    identity
+}
+
+/// `sign_key_pair`'s public key in SSH wire format.
+pub fn ssh_ed25519_public_key(sign_key_pair: &SignKeyPair) -> Vec<u8> {
+    let mut public_key = be_bytes(11, 4);
+    public_key.extend_from_slice(b"ssh-ed25519");
+    public_key.extend(be_bytes(32, 4));
+    public_key.extend_from_slice(sign_key_pair.public_key_bytes());
+    public_key
+}
+
+/// An EdDSA OpenPGP public key for `sign_key_pair` with a self-signed user ID for `email`.
+pub fn generate_pgp_public_key(sign_key_pair: &SignKeyPair, email: &str) -> Vec<u8> {
+    use sigchain_core::pgp::packets::*;
+    use sigchain_core::pgp::public_key::PGPUserId;
+
+    let created = Utc::now().timestamp() as u32;
+    let mut point = vec![0x40];
+    point.extend_from_slice(sign_key_pair.public_key_bytes());
+    let mut key_body = vec![4];
+    key_body.extend_from_slice(&be_bytes(created as u64, 4));
+    key_body.extend_from_slice(&[22, OID_ED25519.len() as u8]);
+    key_body.extend_from_slice(OID_ED25519);
+    key_body.extend(pgp_mpi(&point));
+    let primary_key = PublicKeyPacket::parse(&key_body).unwrap();
+    let user_id = PGPUserId { user_id: format!("<{}>", email), signatures: vec![] };
+
+    // Positive certification hashed with SHA-256, with creation time and issuer fingerprint subpackets.
+    let mut subpackets = vec![5, SUBPACKET_CREATION_TIME];
+    subpackets.extend_from_slice(&be_bytes(created as u64, 4));
+    subpackets.extend_from_slice(&[22, SUBPACKET_ISSUER_FINGERPRINT, 4]);
+    subpackets.extend_from_slice(&primary_key.fingerprint());
+    let mut signature_body = vec![4, 0x13, 22, 8];
+    signature_body.extend_from_slice(&be_bytes(subpackets.len() as u64, 2));
+    signature_body.extend(subpackets);
+
+    let mut signed_data = user_id.certification_data(&primary_key);
+    signed_data.extend_from_slice(&signature_body);
+    signed_data.extend_from_slice(&[4, 0xff]);
+    signed_data.extend_from_slice(&be_bytes(signature_body.len() as u64, 4));
+    let digest = sha256::hash(&signed_data);
+    let signature = crypto::ed25519::sign_detached(&digest.0, &sign_key_pair.secret_key);
+
+    let mut unhashed = vec![9, SUBPACKET_ISSUER];
+    unhashed.extend(primary_key.key_id());
+    signature_body.extend_from_slice(&be_bytes(unhashed.len() as u64, 2));
+    signature_body.extend(unhashed);
+    signature_body.extend_from_slice(&digest.0[..2]);
+    signature_body.extend(pgp_mpi(&signature.0[..32]));
+    signature_body.extend(pgp_mpi(&signature.0[32..]));
+
+    let mut packets = pgp_packet(TAG_PUBLIC_KEY, &key_body);
+    packets.extend(pgp_packet(TAG_USER_ID, user_id.user_id.as_bytes()));
+    packets.extend(pgp_packet(TAG_SIGNATURE, &signature_body));
+    packets
+}
+
+fn pgp_packet(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0xc0 | tag, 0xff];
+    packet.extend_from_slice(&be_bytes(body.len() as u64, 4));
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn pgp_mpi(bytes: &[u8]) -> Vec<u8> {
+    let bytes = &bytes[bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len())..];
+    let bits = bytes.first().map_or(0, |b| (bytes.len() - 1) * 8 + (8 - b.leading_zeros() as usize));
+    let mut mpi = be_bytes(bits as u64, 2);
+    mpi.extend_from_slice(bytes);
+    mpi
+}
+
+fn be_bytes(n: u64, length: usize) -> Vec<u8> {
+    (0..length).rev().map(|i| (n >> (8 * i)) as u8).collect()
+}
+
+/// A header from before `KEY_VALIDATION_VERSION`, as written by older clients.
+pub fn legacy_header() -> Header {
+    Header {
+        utc_time: Utc::now().timestamp(),
+        protocol_version: Version::new(1, 0, 0),
+    }
+}
+
+/// A header at `CURRENT_VERSION`, which operations added in version 2 must be written at.
+pub fn current_header() -> Header {
+    Header {
+        utc_time: Utc::now().timestamp(),
+        protocol_version: CURRENT_VERSION.clone(),
     }
 }
 
@@ -212,6 +315,7 @@ pub fn setup_team_block(domain: Option<&str>) -> (User, TestBlock) {
     let expected = ExpectedResult {
         valid: true,
         team_public_key: creator.sign_key_pair.public_key_bytes().into(),
+        valid_on_replay: None,
     };
 
     (creator, block_from_signed_message(&team_creation_msg, &expected))
@@ -226,10 +330,10 @@ pub fn setup_team(domain: Option<&str>) -> Result<(User, SignedMessage)> {
     let creator = User {
         client: Client {
             sign_key_pair_seed: creator_sign_key_pair_seed,
-            box_key_pair: creator_box_key_pair.clone(),
+            box_key_pair: creator_box_key_pair,
             team_public_key: team_public_key.clone(),
         },
-        sign_key_pair: creator_sign_key_pair.clone(),
+        sign_key_pair: creator_sign_key_pair,
         email: format!("alex@{}", email_domain),
     };
 
@@ -241,6 +345,7 @@ pub fn setup_team(domain: Option<&str>) -> Result<(User, SignedMessage)> {
                     name: String::from("Acme Engineering"),
                 },
                 creator_identity: generate_identity(
+                    &team_public_key,
                     &creator.sign_key_pair,
                     &creator.client.box_key_pair,
                     &creator.email,
@@ -269,10 +374,10 @@ pub fn generate_user_with_email(team_public_key: &[u8], email: &str) -> Result<U
     Ok(User {
         client: Client {
             sign_key_pair_seed: user_sign_key_pair_seed,
-            box_key_pair: user_box_key_pair.clone(),
+            box_key_pair: user_box_key_pair,
             team_public_key: team_public_key.into(),
         },
-        sign_key_pair: user_sign_key_pair.clone(),
+        sign_key_pair: user_sign_key_pair,
         email: email.into(),
     })
 }
@@ -281,6 +386,7 @@ pub fn dir_invite_user_block(admin: &User, user: &User, last_block_hash: &[u8],
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&dir_invite_user(admin, user, last_block_hash), &expected)
@@ -310,6 +416,7 @@ pub fn indir_invite_restriction_block(admin: &User, restriction: IndirectInvitat
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     (nonce_key_pair_seed, block_from_signed_message(&admin_inv_users_msg, &expected))
@@ -326,7 +433,7 @@ pub fn indir_invite_restriction(admin: &User, restriction: IndirectInvitationRes
         restriction: restriction.clone(),
     };
 
-    let invite_encryption = crypto::secretbox::ephemeral_encrypt(serde_json::to_vec(
+    let invite_encryption = crypto::secretbox::ephemeral_encrypt(&serde_json::to_vec(
         &admin_inv_users_secret,
     ).unwrap()).unwrap();
 
@@ -396,6 +503,7 @@ pub fn accept_dir_invite_block(user: &User, last_block_hash: &[u8], valid: bool)
     let expected = ExpectedResult {
         valid,
         team_public_key: user.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&accept_dir_invite(user, last_block_hash), &expected)
@@ -408,6 +516,7 @@ pub fn accept_dir_invite(user: &User, last_block_hash: &[u8]) -> SignedMessage {
             body: Main(Append(Block {
                 last_block_hash: last_block_hash.into(),
                 operation: AcceptInvite(generate_identity(
+                    &user.client.team_public_key,
                     &user.sign_key_pair,
                     &user.client.box_key_pair,
                     &user.email,
@@ -422,6 +531,7 @@ pub fn accept_indir_invite_block(nonce_key_pair_seed: &[u8], user: &User, last_b
     let expected = ExpectedResult {
         valid,
         team_public_key: user.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&accept_indir_invite(nonce_key_pair_seed, user, last_block_hash), &expected)
@@ -434,6 +544,7 @@ pub fn accept_indir_invite(nonce_key_pair_seed: &[u8], user: &User, last_block_h
             body: Main(Append(Block {
                 last_block_hash: last_block_hash.into(),
                 operation: AcceptInvite(generate_identity(
+                    &user.client.team_public_key,
                     &user.sign_key_pair,
                     &user.client.box_key_pair,
                     &user.email,
@@ -448,6 +559,7 @@ pub fn close_invites_block(admin: &User, last_block_hash: &[u8], valid: bool) ->
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&close_invites(admin, last_block_hash), &expected)
@@ -470,6 +582,7 @@ pub fn add_user_blocks(admin: &User, user: &User, last_block_hash: &[u8]) -> Vec
     let expected = ExpectedResult {
         valid: true,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     add_user(admin, user, last_block_hash)
@@ -494,6 +607,7 @@ pub fn add_user_indir_blocks(admin: &User, user: &User, last_block_hash: &[u8])
     let expected = ExpectedResult {
         valid: true,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     let add_user_blocks = add_user_msgs
@@ -535,6 +649,7 @@ pub fn add_domain_indir_blocks(admin: &User, user: &User, domain: &str, last_blo
     let expected = ExpectedResult {
         valid: true,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     let add_user_blocks = add_user_msgs
@@ -574,6 +689,7 @@ pub fn promote_user_block(admin: &User, user: &User, last_block_hash: &[u8], val
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&promote_user(admin, user, last_block_hash), &expected)
@@ -596,6 +712,7 @@ pub fn remove_user_block(admin: &User, user: &User, last_block_hash: &[u8], vali
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&remove_user(admin, user, last_block_hash), &expected)
@@ -618,6 +735,7 @@ pub fn demote_user_block(admin: &User, user: &User, last_block_hash: &[u8], vali
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&demote_user(admin, user, last_block_hash), &expected)
@@ -636,10 +754,214 @@ pub fn demote_user(admin: &User, user: &User, last_block_hash: &[u8]) -> SignedM
     ).unwrap()
 }
 
+/// `user`'s safety number as a verifier computes it from the chain.
+pub fn user_safety_number(user: &User) -> String {
+    crypto::safety_number::safety_number(
+        &user.client.team_public_key,
+        &generate_identity(&user.client.team_public_key, &user.sign_key_pair, &user.client.box_key_pair, &user.email),
+    )
+}
+
+pub fn verify_member_block(admin: &User, user: &User, safety_number: &str, last_block_hash: &[u8], valid: bool) -> TestBlock {
+    let expected = ExpectedResult {
+        valid,
+        team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
+    };
+
+    block_from_signed_message(&verify_member(admin, user, safety_number, last_block_hash), &expected)
+}
+
+pub fn verify_member(admin: &User, user: &User, safety_number: &str, last_block_hash: &[u8]) -> SignedMessage {
+    SignedMessage::from_message(
+        Message {
+            header: current_header(),
+            body: Main(Append(Block {
+                last_block_hash: last_block_hash.into(),
+                operation: VerifyMember(MemberVerification {
+                    public_key: user.sign_key_pair.public_key_bytes().into(),
+                    safety_number: safety_number.into(),
+                }),
+            })),
+        },
+        &admin.sign_key_pair,
+    ).unwrap()
+}
+
+pub fn update_keys_block(user: &User, key_update: KeyUpdate, last_block_hash: &[u8], valid: bool) -> TestBlock {
+    let expected = ExpectedResult {
+        valid,
+        team_public_key: user.client.team_public_key.clone(),
+        valid_on_replay: None,
+    };
+
+    block_from_signed_message(&update_keys(user, key_update, last_block_hash), &expected)
+}
+
+pub fn update_keys(user: &User, key_update: KeyUpdate, last_block_hash: &[u8]) -> SignedMessage {
+    SignedMessage::from_message(
+        Message {
+            header: current_header(),
+            body: Main(Append(Block {
+                last_block_hash: last_block_hash.into(),
+                operation: UpdateKeys(key_update),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap()
+}
+
+/// A new device for `user`, authorized to sign for `user` on its team, along with a `User` that
+/// signs with the device's keys.
+pub fn generate_device(user: &User, name: &str) -> (User, Device) {
+    let device_user = generate_user_with_email(&user.client.team_public_key, &user.email).unwrap();
+    let device = authorized_device(user, &device_user, name);
+    (device_user, device)
+}
+
+/// `device_user`'s keys as a device authorized to sign for `user`.
+pub fn authorized_device(user: &User, device_user: &User, name: &str) -> Device {
+    let mut device = Device {
+        public_key: device_user.sign_key_pair.public_key_bytes().into(),
+        encryption_public_key: device_user.client.box_key_pair.public_key_bytes().into(),
+        ssh_public_key: ssh_ed25519_public_key(&device_user.sign_key_pair),
+        name: name.into(),
+        device_signature: vec![],
+        encryption_key_proof: vec![],
+    };
+    device.authorize(
+        &user.client.team_public_key,
+        user.sign_key_pair.public_key_bytes(),
+        &device_user.sign_key_pair,
+        &device_user.client.box_key_pair.secret_key.0,
+    ).unwrap();
+    device
+}
+
+pub fn add_device_block(user: &User, device: &Device, last_block_hash: &[u8], valid: bool) -> TestBlock {
+    let expected = ExpectedResult {
+        valid,
+        team_public_key: user.client.team_public_key.clone(),
+        valid_on_replay: None,
+    };
+
+    block_from_signed_message(&add_device(user, device, last_block_hash), &expected)
+}
+
+pub fn add_device(user: &User, device: &Device, last_block_hash: &[u8]) -> SignedMessage {
+    SignedMessage::from_message(
+        Message {
+            header: current_header(),
+            body: Main(Append(Block {
+                last_block_hash: last_block_hash.into(),
+                operation: AddDevice(device.clone()),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap()
+}
+
+pub fn remove_device_block(user: &User, device: &User, last_block_hash: &[u8], valid: bool) -> TestBlock {
+    let expected = ExpectedResult {
+        valid,
+        team_public_key: user.client.team_public_key.clone(),
+        valid_on_replay: None,
+    };
+
+    block_from_signed_message(&remove_device(user, device, last_block_hash), &expected)
+}
+
+pub fn remove_device(user: &User, device: &User, last_block_hash: &[u8]) -> SignedMessage {
+    SignedMessage::from_message(
+        Message {
+            header: current_header(),
+            body: Main(Append(Block {
+                last_block_hash: last_block_hash.into(),
+                operation: RemoveDevice(device.sign_key_pair.public_key_bytes().into()),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap()
+}
+
+pub fn create_log_chain_block(logger: &User, valid: bool) -> TestBlock {
+    let expected = ExpectedResult {
+        valid,
+        team_public_key: logger.client.team_public_key.clone(),
+        valid_on_replay: None,
+    };
+
+    block_from_signed_message(&create_log_chain(logger), &expected)
+}
+
+pub fn create_log_chain(logger: &User) -> SignedMessage {
+    SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Body::Log(LogChain::Create(GenesisLogBlock {
+                team_pointer: TeamPointer::PublicKey(logger.client.team_public_key.clone()),
+                wrapped_keys: vec![],
+            })),
+        },
+        &logger.sign_key_pair,
+    ).unwrap()
+}
+
+pub fn append_log_block(logger: &User, last_block_hash: &[u8], valid: bool) -> TestBlock {
+    let expected = ExpectedResult {
+        valid,
+        team_public_key: logger.client.team_public_key.clone(),
+        valid_on_replay: None,
+    };
+
+    block_from_signed_message(&append_log(logger, last_block_hash), &expected)
+}
+
+pub fn append_log(logger: &User, last_block_hash: &[u8]) -> SignedMessage {
+    SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Body::Log(LogChain::Append(LogBlock {
+                last_block_hash: last_block_hash.into(),
+                operation: LogOperation::EncryptLog(EncryptedLog {
+                    ciphertext: vec![0u8; 64],
+                }),
+            })),
+        },
+        &logger.sign_key_pair,
+    ).unwrap()
+}
+
+pub fn publish_log_chain_head_block(user: &User, log_block_hash: &[u8], last_block_hash: &[u8], valid: bool) -> TestBlock {
+    let expected = ExpectedResult {
+        valid,
+        team_public_key: user.client.team_public_key.clone(),
+        valid_on_replay: None,
+    };
+
+    block_from_signed_message(&publish_log_chain_head(user, log_block_hash, last_block_hash), &expected)
+}
+
+pub fn publish_log_chain_head(user: &User, log_block_hash: &[u8], last_block_hash: &[u8]) -> SignedMessage {
+    SignedMessage::from_message(
+        Message {
+            header: current_header(),
+            body: Main(Append(Block {
+                last_block_hash: last_block_hash.into(),
+                operation: PublishLogChainHead(PublishedLogChainHead {
+                    last_block_hash: log_block_hash.into(),
+                }),
+            })),
+        },
+        &user.sign_key_pair,
+    ).unwrap()
+}
+
 pub fn leave_team_block(user: &User, last_block_hash: &[u8], valid: bool) -> TestBlock {
     let expected = ExpectedResult {
         valid,
         team_public_key: user.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&leave_team(user, last_block_hash), &expected)
@@ -662,6 +984,7 @@ pub fn add_logging_block(admin: &User, last_block_hash: &[u8], valid: bool) -> T
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&add_logging(admin, last_block_hash), &expected)
@@ -684,6 +1007,7 @@ pub fn add_logging_with_version_block(version: Version, admin: &User, last_block
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&add_logging_with_version(version, admin, last_block_hash), &expected)
@@ -709,6 +1033,7 @@ pub fn pin_host_block(host: &str, host_public_key: &[u8], admin: &User, last_blo
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&pin_host(host, host_public_key, admin, last_block_hash), &expected)
@@ -734,6 +1059,7 @@ pub fn unpin_host_block(host: &str, host_public_key: &[u8], admin: &User, last_b
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&unpin_host(host, host_public_key, admin, last_block_hash), &expected)
@@ -759,6 +1085,7 @@ pub fn set_policy_block(approval_window: i64, admin: &User, last_block_hash: &[u
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&set_policy(approval_window, admin, last_block_hash), &expected)
@@ -772,6 +1099,7 @@ pub fn set_policy(approval_window: i64, admin: &User, last_block_hash: &[u8]) ->
                 last_block_hash: last_block_hash.into(),
                 operation: SetPolicy(Policy {
                     temporary_approval_seconds: Some(approval_window),
+                    log_redaction: None,
                 }),
             })),
         },
@@ -783,6 +1111,7 @@ pub fn set_team_info_block(team_name: &str, admin: &User, last_block_hash: &[u8]
     let expected = ExpectedResult {
         valid,
         team_public_key: admin.client.team_public_key.clone(),
+        valid_on_replay: None,
     };
 
     block_from_signed_message(&set_team_info(team_name, admin, last_block_hash), &expected)
@@ -845,6 +1174,36 @@ pub fn gather_data() -> Vec<BlockValidationTest> {
         non_member_demote_member::data(),
         non_member_demote_non_member::data(),
         non_member_demote_self::data(),
+        admin_verify_member::data(),
+        admin_verify_member_wrong_safety_number::data(),
+        admin_verify_non_member::data(),
+        member_verify_member::data(),
+        member_update_keys::data(),
+        member_update_keys_twice::data(),
+        member_update_keys_empty::data(),
+        member_update_keys_malformed_ssh_key::data(),
+        member_update_keys_foreign_pgp_key::data(),
+        non_member_update_keys::data(),
+        member_add_device::data(),
+        member_add_device_for_other_member::data(),
+        member_add_device_bad_encryption_key_proof::data(),
+        member_add_member_key_as_device::data(),
+        member_add_device_twice::data(),
+        non_member_add_device::data(),
+        device_update_keys::data(),
+        member_device_pin_host::data(),
+        admin_device_pin_host::data(),
+        member_remove_own_device::data(),
+        device_create_log_chain::data(),
+        member_publish_log_chain_head::data(),
+        device_publish_log_chain_head::data(),
+        removed_member_publish_log_chain_head::data(),
+        non_member_publish_log_chain_head::data(),
+        removed_device_create_log_chain::data(),
+        removed_device_leave::data(),
+        admin_remove_member_device::data(),
+        member_remove_other_member_device::data(),
+        removed_member_device_update_keys::data(),
         admin_dir_invite_admin::data(),
         admin_dir_invite_self::data(),
         admin_dir_invite_member::data(),
@@ -866,6 +1225,21 @@ pub fn gather_data() -> Vec<BlockValidationTest> {
         dir_invite_team_hijack::data(),
         dir_invite_accept_id_sig_mismatch_key::data(),
         dir_invite_accept_pk_sig_mismatch_key::data(),
+        dir_invite_accept_malformed_pgp_key::data(),
+        dir_invite_accept_legacy_raw_pgp_key::data(),
+        dir_invite_accept_pgp_user_id_mismatch::data(),
+        dir_invite_accept_malformed_ssh_key::data(),
+        dir_invite_accept_legacy_malformed_ssh_key::data(),
+        dir_invite_accept_foreign_encryption_key::data(),
+        dir_invite_accept_missing_encryption_key_proof::data(),
+        dir_invite_accept_encryption_key_proof::data(),
+        dir_invite_accept_tampered_encryption_key_proof::data(),
+        dir_invite_accept_encryption_key_proof_for_other_team::data(),
+        dir_invite_accept_legacy_missing_encryption_key_proof::data(),
+        encryption_key_proof_known_answer::data(),
+        encryption_key_proof_known_answer_tampered::data(),
+        encryption_key_proof_known_answer_truncated::data(),
+        create_team_missing_encryption_key_proof::data(),
         dir_invite_duplicate::data(),
         admin_indir_invite_admin::data(),
         admin_indir_invite_self::data(),
@@ -907,10 +1281,16 @@ pub fn gather_data() -> Vec<BlockValidationTest> {
         admin_duplicate_pin_host::data(),
         admin_pin_many_keys_for_host::data(),
         admin_pin_same_key_for_hosts::data(),
+        admin_pin_malformed_key::data(),
+        admin_pin_unknown_key_type::data(),
+        admin_pin_legacy_malformed_key::data(),
         semver_reject::data(),
+        version_2_operation_at_compatible_version::data(),
         consume_dir_invite::data(),
         create_team_id_sig_mismatch_key::data(),
         create_team_pk_sig_mismatch_key::data(),
+        create_team_malformed_pgp_key::data(),
+        create_team_malformed_ssh_key::data(),
         admin_set_policy::data(),
         member_set_policy::data(),
         non_member_set_policy::data(),
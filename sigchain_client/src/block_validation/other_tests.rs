@@ -15,6 +15,29 @@ gen_test!(semver_reject,
     blocks.push(admin_add_logging_block);
 });
 
+gen_test!(version_2_operation_at_compatible_version,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Operations that 1.x clients cannot parse must be written at the current major version.
+    let new_key_pair = gen_sign_key_pair().unwrap();
+    let key_update = KeyUpdate {
+        ssh_public_key: Some(ssh_ed25519_public_key(&new_key_pair)),
+        pgp_public_key: None,
+    };
+    let mut update_keys_message: Message = serde_json::from_str(
+        &update_keys(&users[0], key_update, &blocks.last().unwrap().hash()).message).unwrap();
+    update_keys_message.header.protocol_version = COMPATIBLE_VERSION.clone();
+    let update_keys_block = block_from_signed_message(
+        &SignedMessage::from_message(update_keys_message, &users[0].sign_key_pair).unwrap(),
+        &ExpectedResult {
+            valid: false,
+            team_public_key: users[0].client.team_public_key.clone(),
+            valid_on_replay: None,
+        });
+
+    blocks.push(update_keys_block);
+});
+
 gen_test!(consume_dir_invite,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
@@ -55,6 +78,7 @@ gen_test!(create_team_id_sig_mismatch_key,
                     name: String::from("Acme Engineering"),
                 },
                 creator_identity: generate_identity(
+                    &users[0].client.team_public_key,
                     &gen_sign_key_pair().unwrap(),
                     &users[0].client.box_key_pair,
                     &users[0].email,
@@ -69,6 +93,7 @@ gen_test!(create_team_id_sig_mismatch_key,
         expected: ExpectedResult {
             valid: false,
             team_public_key: users[0].client.team_public_key.clone(),
+            valid_on_replay: None,
         },
     };
 
@@ -90,6 +115,74 @@ gen_test!(create_team_pk_sig_mismatch_key,
     users.push(user);
 });
 
+gen_test!(create_team_malformed_pgp_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Re-create the team with raw key bytes in place of the creator's PGP public key.
+    let creator = &users[0];
+    let mut creator_identity = generate_identity(
+        &creator.client.team_public_key,
+        &creator.sign_key_pair,
+        &creator.client.box_key_pair,
+        &creator.email,
+    );
+    creator_identity.pgp_public_key = creator.sign_key_pair.public_key_bytes().into();
+
+    let team_creation_msg = SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Main(Create(GenesisBlock {
+                team_info: TeamInfo {
+                    name: String::from("Acme Engineering"),
+                },
+                creator_identity,
+            }))
+        },
+        &creator.sign_key_pair,
+    ).unwrap();
+
+    let expected = ExpectedResult {
+        valid: false,
+        team_public_key: creator.client.team_public_key.clone(),
+        valid_on_replay: None,
+    };
+    blocks[0] = block_from_signed_message(&team_creation_msg, &expected);
+});
+
+gen_test!(create_team_malformed_ssh_key,
+|users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
+
+    // Re-create the team with a truncated creator SSH public key.
+    let creator = &users[0];
+    let mut creator_identity = generate_identity(
+        &creator.client.team_public_key,
+        &creator.sign_key_pair,
+        &creator.client.box_key_pair,
+        &creator.email,
+    );
+    creator_identity.ssh_public_key.truncate(20);
+
+    let team_creation_msg = SignedMessage::from_message(
+        Message {
+            header: Header::new(),
+            body: Main(Create(GenesisBlock {
+                team_info: TeamInfo {
+                    name: String::from("Acme Engineering"),
+                },
+                creator_identity,
+            }))
+        },
+        &creator.sign_key_pair,
+    ).unwrap();
+
+    let expected = ExpectedResult {
+        valid: false,
+        team_public_key: creator.client.team_public_key.clone(),
+        valid_on_replay: None,
+    };
+    blocks[0] = block_from_signed_message(&team_creation_msg, &expected);
+});
+
 gen_test!(admin_set_policy,
 |users: &mut Vec<User>, blocks: &mut Vec<TestBlock>| {
 
@@ -202,7 +295,7 @@ gen_test!(duplicate_encryption_public_key_on_team,
     // Generate and add two users to team with the same encryption public key.
     let user_1 = generate_user(&users[0].client.team_public_key, 1);
     let mut user_2 = generate_user(&users[0].client.team_public_key, 2);
-    user_2.client.box_key_pair = user_1.client.box_key_pair.clone();
+    user_2.client.box_key_pair = user_1.client.box_key_pair.duplicate();
 
     let user_1_add_blocks = add_user_blocks(&users[0], &user_1, &blocks.last().unwrap().hash());
     let user_2_add_blocks = add_user_blocks(
@@ -0,0 +1,414 @@
+/// Declarative alert rules evaluated against each decrypted log as it is stored.
+///
+/// Matches are recorded in the `alerts` table inside the log's transaction and delivered to the
+/// rule's sinks afterwards, so a failing sink never blocks ingesting logs and is retried on the
+/// next update.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::collections::HashMap;
+use serde_json;
+use chrono::prelude::*;
+use url::Url;
+
+use Result;
+use db::{self, TeamDBConnection};
+use logs::Log;
+use sigchain_core::log_search::{LogQuery, LogSearchFields};
+
+use b64data;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AlertRule {
+    pub name: String,
+    pub condition: AlertCondition,
+    /// Where to deliver matches. With no sinks, alerts are only stored.
+    #[serde(default)]
+    pub sinks: Vec<AlertSink>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Every log matching `query`, e.g. `result:host_mismatch`.
+    Match {
+        query: String,
+    },
+    /// More than `count` logs from one member matching `query` received within `window_seconds`.
+    /// Windows are measured by when this client stored the logs rather than the times set by the
+    /// member's devices, and the rule alerts at most once per member per window.
+    Threshold {
+        query: String,
+        count: u64,
+        window_seconds: i64,
+    },
+    /// Logs matching `query` outside of `start_hour`..`end_hour` in the given UTC offset.
+    OutsideHours {
+        query: String,
+        start_hour: u32,
+        end_hour: u32,
+        #[serde(default)]
+        utc_offset_seconds: i32,
+        /// Also alert on any matching log on a Saturday or Sunday.
+        #[serde(default)]
+        weekdays_only: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSink {
+    Stderr,
+    /// Append one JSON encoded `AlertNotification` per line.
+    File {
+        path: String,
+    },
+    /// POST a JSON encoded `AlertNotification`. Only loopback URLs are allowed so that decrypted
+    /// log contents never leave the machine.
+    Webhook {
+        url: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AlertNotification {
+    pub rule_name: String,
+    #[serde(with = "b64data")]
+    pub member_public_key: Vec<u8>,
+    pub message: String,
+    pub unix_seconds: i64,
+    pub log: Option<Log>,
+}
+
+impl AlertCondition {
+    fn query(&self) -> &str {
+        match self {
+            &AlertCondition::Match { ref query } => query,
+            &AlertCondition::Threshold { ref query, .. } => query,
+            &AlertCondition::OutsideHours { ref query, .. } => query,
+        }
+    }
+}
+
+impl AlertRule {
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            bail!("alert rule name is empty");
+        }
+        LogQuery::parse(self.condition.query(), 0)?;
+        if let AlertCondition::OutsideHours { start_hour, end_hour, utc_offset_seconds, .. } = self.condition {
+            if start_hour > 24 || end_hour > 24 || start_hour >= end_hour {
+                bail!("business hours must satisfy start_hour < end_hour <= 24");
+            }
+            if utc_offset_seconds <= -86400 || utc_offset_seconds >= 86400 {
+                bail!("utc_offset_seconds must be less than a day");
+            }
+        }
+        for sink in &self.sinks {
+            if let &AlertSink::Webhook { ref url } = sink {
+                let url = Url::parse(url).map_err(|e| format!("invalid webhook url: {}", e))?;
+                match url.host_str() {
+                    Some("localhost") | Some("127.0.0.1") | Some("[::1]") => {}
+                    _ => bail!("webhook url must point to localhost"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The alert message if `log`, stored at `received_unix_seconds`, triggers this rule. `log` must
+    /// already be stored.
+    fn evaluate(&self, conn: &TeamDBConnection, member_public_key: &[u8], member_email: &str, log: &Log, received_unix_seconds: i64) -> Result<Option<String>> {
+        let unix_seconds = log.unix_seconds as i64;
+        let query = LogQuery::parse(self.condition.query(), unix_seconds)?;
+        if !query.matches(&LogSearchFields::from_log(log), member_email, unix_seconds) {
+            return Ok(None);
+        }
+
+        Ok(match self.condition {
+            AlertCondition::Match { .. } => {
+                Some(format!("{}: {} by {}", self.name, log.body.log_description(), member_email))
+            }
+            AlertCondition::Threshold { count, window_seconds, .. } => {
+                let window_start = received_unix_seconds - window_seconds;
+                // Alert once per window, not for every log after the threshold is crossed.
+                let alerted_in_window = db::Alert::latest_received_unix_seconds(conn, &self.name, member_public_key)?
+                    .map_or(false, |alerted| alerted > window_start);
+                if alerted_in_window {
+                    return Ok(None);
+                }
+                // `since` terms are relative to the triggering log, the window replaces them here.
+                let query = LogQuery { since_unix_seconds: None, ..query };
+                let matching = db::Log::for_member_received_since(conn, member_public_key, window_start + 1)?
+                    .into_iter()
+                    .filter(|l| l.received_unix_seconds <= received_unix_seconds)
+                    .filter_map(|l| serde_json::from_str::<Log>(&l.log_json).ok())
+                    .filter(|l| query.matches(&LogSearchFields::from_log(l), member_email, l.unix_seconds as i64))
+                    .count() as u64;
+                if matching > count {
+                    Some(format!("{}: {} matching logs by {} within {} minutes",
+                                 self.name, matching, member_email, window_seconds / 60))
+                } else {
+                    None
+                }
+            }
+            AlertCondition::OutsideHours { start_hour, end_hour, utc_offset_seconds, weekdays_only, .. } => {
+                // Log times are set by the member's device and can be out of range.
+                let local_time = match FixedOffset::east_opt(utc_offset_seconds)
+                    .and_then(|offset| offset.timestamp_opt(unix_seconds, 0).single()) {
+                    Some(local_time) => local_time,
+                    None => return Ok(None),
+                };
+                let is_weekend = match local_time.weekday() {
+                    Weekday::Sat | Weekday::Sun => true,
+                    _ => false,
+                };
+                let in_hours = local_time.hour() >= start_hour && local_time.hour() < end_hour;
+                if in_hours && !(weekdays_only && is_weekend) {
+                    None
+                } else {
+                    Some(format!("{}: {} by {} at {}",
+                                 self.name, log.body.log_description(), member_email, local_time.format("%a %H:%M")))
+                }
+            }
+        })
+    }
+}
+
+pub fn get_alert_rules(conn: &TeamDBConnection) -> Result<Vec<AlertRule>> {
+    Ok(db::AlertRule::all(conn)?.into_iter()
+        .filter_map(|r| serde_json::from_str(&r.rule_json)
+            .map_err(|e| error!("invalid alert rule {}: {}", r.name, e)).ok())
+        .collect())
+}
+
+/// Evaluate every alert rule against a newly stored log and record the matches.
+pub fn evaluate_alert_rules(conn: &TeamDBConnection, member_public_key: &[u8], log: &Log, log_json: &str, received_unix_seconds: i64) -> Result<()> {
+    let rules = get_alert_rules(conn)?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let member_email = db::Identity::find(conn, member_public_key)?.email;
+
+    for rule in rules {
+        let message = match rule.evaluate(conn, member_public_key, &member_email, log, received_unix_seconds) {
+            Ok(Some(message)) => message,
+            Ok(None) => continue,
+            Err(e) => {
+                error!("error evaluating alert rule {}: {}", rule.name, e);
+                continue;
+            }
+        };
+        db::Alert::insert(conn.conn, &db::NewAlert {
+            team_public_key: conn.team.into(),
+            rule_name: rule.name.clone(),
+            member_public_key: member_public_key.into(),
+            log_json: log_json.into(),
+            message,
+            unix_seconds: log.unix_seconds as i64,
+            delivered: false,
+            received_unix_seconds,
+        })?;
+    }
+    Ok(())
+}
+
+/// Deliver stored alerts to their rules' sinks. Alerts stay pending until every sink succeeds.
+pub fn deliver_pending_alerts(conn: &TeamDBConnection) -> Result<()> {
+    let sinks_by_rule = get_alert_rules(conn)?.into_iter()
+        .map(|r| (r.name, r.sinks))
+        .collect::<HashMap<_, _>>();
+
+    for alert in db::Alert::undelivered(conn)? {
+        let notification = AlertNotification {
+            rule_name: alert.rule_name.clone(),
+            member_public_key: alert.member_public_key.clone(),
+            message: alert.message.clone(),
+            unix_seconds: alert.unix_seconds,
+            log: serde_json::from_str(&alert.log_json).ok(),
+        };
+        let mut delivered = true;
+        for sink in sinks_by_rule.get(&alert.rule_name).into_iter().flat_map(|s| s.iter()) {
+            if let Err(e) = deliver(sink, &notification) {
+                warn!("failed to deliver alert {} to {:?}: {}", alert.id, sink, e);
+                delivered = false;
+            }
+        }
+        if delivered {
+            alert.mark_delivered(conn.conn)?;
+        }
+    }
+    Ok(())
+}
+
+fn deliver(sink: &AlertSink, notification: &AlertNotification) -> Result<()> {
+    match sink {
+        &AlertSink::Stderr => {
+            eprintln!("[alert] {}", notification.message);
+        }
+        &AlertSink::File { ref path } => {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", serde_json::to_string(notification)?)?;
+        }
+        &AlertSink::Webhook { ref url } => {
+            deliver_webhook(url, notification)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "reqwest")]
+fn deliver_webhook(url: &str, notification: &AlertNotification) -> Result<()> {
+    use reqwest;
+    use std::time::Duration;
+
+    // Don't follow redirects away from localhost.
+    let client = reqwest::ClientBuilder::new()?
+        .timeout(Duration::from_secs(5))
+        .redirect(reqwest::RedirectPolicy::none())
+        .build()?;
+    let response = client.post(url)?.body(serde_json::to_vec(notification)?).send()?;
+    if !response.status().is_success() {
+        bail!(format!("webhook responded with {}", response.status()));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "reqwest"))]
+fn deliver_webhook(_: &str, _: &AlertNotification) -> Result<()> {
+    bail!("webhook alerts are not supported in this build")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::{Connection, DBConnection};
+    use logs::{ApprovalResult, LogBody, Session, SudoApproval};
+    use protocol::E;
+
+    struct Fixture {
+        conn: DBConnection,
+        team_public_key: Vec<u8>,
+        member_public_key: Vec<u8>,
+    }
+
+    impl Fixture {
+        fn new(query: &str, count: u64, window_seconds: i64) -> Fixture {
+            let conn = DBConnection::establish("").unwrap();
+            db::run_migrations(&conn).unwrap();
+            db::column_encryption::load_key_for_database(&conn, "").unwrap();
+            let fixture = Fixture { conn, team_public_key: vec![1; 32], member_public_key: vec![2; 32] };
+
+            db::Identity {
+                team_public_key: fixture.team_public_key.clone(),
+                public_key: fixture.member_public_key.clone(),
+                encryption_public_key: vec![3; 32],
+                ssh_public_key: vec![],
+                pgp_public_key: vec![],
+                email: "alice@example.com".into(),
+            }.insert(&fixture.conn).unwrap();
+            let rule = AlertRule {
+                name: "sudo burst".into(),
+                condition: AlertCondition::Threshold { query: query.into(), count, window_seconds },
+                sinks: vec![],
+            };
+            rule.validate().unwrap();
+            db::AlertRule {
+                team_public_key: fixture.team_public_key.clone(),
+                name: rule.name.clone(),
+                rule_json: serde_json::to_string(&rule).unwrap(),
+            }.set(&fixture.conn).unwrap();
+            fixture
+        }
+
+        fn conn(&self) -> TeamDBConnection {
+            TeamDBConnection { conn: &self.conn, team: &self.team_public_key }
+        }
+
+        /// Store a sudo log claiming `unix_seconds` as this client would at `received_unix_seconds`.
+        fn store(&self, unix_seconds: u64, received_unix_seconds: i64) -> (Log, String) {
+            let log = Log {
+                session: Session {
+                    device_name: "laptop".into(),
+                    workstation_public_key_double_hash: vec![4; 32],
+                },
+                unix_seconds,
+                body: LogBody::Sudo(SudoApproval {
+                    user: "deploy".into(),
+                    target_user: "root".into(),
+                    host: "build-server".into(),
+                    command: None,
+                    result: ApprovalResult::Approved(E{}),
+                }),
+                redactions: vec![],
+                main_chain_block_hash: None,
+            };
+            let log_json = serde_json::to_string(&log).unwrap();
+            db::Log {
+                team_public_key: self.team_public_key.clone(),
+                member_public_key: self.member_public_key.clone(),
+                log_json: log_json.clone(),
+                unix_seconds: unix_seconds as i64,
+                signature_verification: None,
+                host_key_verification: None,
+                received_unix_seconds,
+            }.insert(&self.conn).unwrap();
+            (log, log_json)
+        }
+
+        /// Store a log and evaluate the alert rules against it, returning the number of alerts.
+        fn ingest(&self, unix_seconds: u64, received_unix_seconds: i64) -> usize {
+            let (log, log_json) = self.store(unix_seconds, received_unix_seconds);
+            evaluate_alert_rules(&self.conn(), &self.member_public_key, &log, &log_json, received_unix_seconds).unwrap();
+            self.alerts()
+        }
+
+        fn alerts(&self) -> usize {
+            db::Alert::all(&self.conn(), None).unwrap().len()
+        }
+    }
+
+    const NOW: u64 = 1525000000;
+
+    #[test]
+    fn threshold_fires_when_crossed_by_more_than_one_log() {
+        let fixture = Fixture::new("type:sudo", 2, 600);
+        // Logs stored before the last one was evaluated, e.g. before the rule was added.
+        fixture.store(NOW, 1000);
+        fixture.store(NOW, 1001);
+        fixture.store(NOW, 1002);
+        assert_eq!(fixture.ingest(NOW, 1003), 1);
+    }
+
+    #[test]
+    fn threshold_alerts_once_per_window() {
+        let fixture = Fixture::new("type:sudo", 1, 600);
+        assert_eq!(fixture.ingest(NOW, 1000), 0);
+        assert_eq!(fixture.ingest(NOW, 1010), 1);
+        assert_eq!(fixture.ingest(NOW, 1020), 1);
+
+        // The earlier logs have left the window by the time the next pair arrives.
+        assert_eq!(fixture.ingest(NOW, 1700), 1);
+        assert_eq!(fixture.ingest(NOW, 1710), 2);
+    }
+
+    #[test]
+    fn threshold_window_ignores_device_times() {
+        let fixture = Fixture::new("type:sudo", 1, 600);
+        // A device backdating its logs does not spread them out of the window.
+        assert_eq!(fixture.ingest(NOW, 1000), 0);
+        assert_eq!(fixture.ingest(NOW - 86400, 1005), 1);
+
+        // Logs claiming the same time but received far apart are not counted together.
+        let fixture = Fixture::new("type:sudo", 1, 600);
+        assert_eq!(fixture.ingest(NOW, 1000), 0);
+        assert_eq!(fixture.ingest(NOW, 2000), 0);
+    }
+
+    #[test]
+    fn threshold_counts_only_matching_logs() {
+        let fixture = Fixture::new("result:rejected", 0, 600);
+        assert_eq!(fixture.ingest(NOW, 1000), 0);
+        assert_eq!(fixture.ingest(NOW, 1001), 0);
+    }
+}
@@ -4,7 +4,7 @@ use enclave_protocol;
 
 use enclave_protocol::{ServerEndpoints, TeamCheckpoint};
 
-use errors::Error;
+use errors::{Error, ErrorKind};
 use client::traits::{DBConnect, Broadcast, Identify};
 
 use std::sync::Arc;
@@ -25,7 +25,23 @@ impl traits::Broadcast for AndroidClient {
         time_fn!("request");
         let request_body = serde_json::to_vec(request)?;
         let response_bytes = self.http_client.put(&self.server_endpoints().url(endpoint))?.body(request_body.to_vec()).send()?;
-        let response: Response<T> = serde_json::from_reader(response_bytes)?;
+        let status = response_bytes.status();
+        let response: Response<T> = match serde_json::from_reader(response_bytes) {
+            Ok(response) => response,
+            Err(e) => {
+                if !status.is_success() {
+                    bail!(ErrorKind::HTTPStatus(status.as_u16(), e.to_string()));
+                }
+                return Err(e.into());
+            }
+        };
+        if !status.is_success() {
+            let message = match response {
+                Response::Error(s) => s,
+                Response::Success(_) => String::new(),
+            };
+            bail!(ErrorKind::HTTPStatus(status.as_u16(), message));
+        }
         match response {
             Response::Success(t) => Ok(t),
             Response::Error(s) => bail!(s),
@@ -47,6 +63,8 @@ impl AndroidClient {
         let conn = DBConnection::establish(&database_url)?;
 
         db::run_migrations(&conn)?;
+        db::column_encryption::load_key_for_database(&conn, &database_url)?;
+        db::column_encryption::encrypt_plaintext_columns(&conn)?;
         Ok(conn)
     }
     pub fn server_endpoints(&self) -> &ServerEndpoints {
@@ -141,6 +159,22 @@ impl AndroidClient {
                 self.remove_admin_pk(public_key)?;
                 None
             }
+            &VerifyMember(ref verification) => {
+                self.verify_member_pk(&verification.public_key, &verification.safety_number)?;
+                None
+            }
+            &UpdateKeys(ref key_update) => {
+                self.update_keys(key_update.clone())?;
+                None
+            }
+            &AddDevice(ref device) => {
+                self.add_device(device.clone())?;
+                None
+            }
+            &RemoveDevice(ref device_public_key) => {
+                self.remove_device(device_public_key)?;
+                None
+            }
         };
         Ok(enclave_protocol::TeamOperationResponse{
             posted_block_hash: self.get_last_block_hash()?.ok_or("no last_block_hash")?,
@@ -158,7 +192,7 @@ impl AndroidClient {
             .path_segments().ok_or("no url path")?
             .next().ok_or("no first path segment")?.into();
 
-        let symmetric_key = base64::decode_config(&symmetric_key_b64, base64::URL_SAFE)?;
+        let symmetric_key = SecretBytes::new(base64::decode_config(&symmetric_key_b64, base64::URL_SAFE)?);
 
         let symmetric_key_hash = sha256::hash(&symmetric_key).0.to_vec();
         let request = InviteSymmetricKeyHash{symmetric_key_hash};
@@ -173,8 +207,8 @@ impl AndroidClient {
             Response::Error(e) => bail!(e),
         };
 
-        let invite_secret_plaintext =
-            crypto::secretbox::decrypt(symmetric_key.to_vec(), ciphertext)?;
+        let invite_secret_plaintext = SecretBytes::new(
+            crypto::secretbox::decrypt(&symmetric_key, &ciphertext)?);
         let invite_secret : IndirectInvitationSecret = serde_json::from_slice(&invite_secret_plaintext)?;
 
         Ok(invite_secret)
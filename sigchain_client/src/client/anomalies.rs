@@ -0,0 +1,282 @@
+/// Score each new log against a per-member baseline of hosts, workstations, unix users and hours.
+///
+/// The baseline is a count of every value seen so far, updated after scoring. Until a member has
+/// `MIN_BASELINE_LOGS` logs nothing is flagged, since everything is new at first. Members with logs
+/// stored before their baseline existed are backfilled from those logs, see `backfill_baseline`.
+
+use std::collections::HashMap;
+use serde_json;
+use chrono::prelude::*;
+
+use Result;
+use db::{self, TeamDBConnection};
+use logs::{Log, LogBody};
+use base64;
+use sigchain_core::log_search::LogSearchFields;
+use sigchain_core::dashboard_protocol::AnomalyReason;
+
+const MIN_BASELINE_LOGS: i64 = 20;
+/// Any one reason is enough to record an anomaly, the score orders anomalies by severity.
+const ANOMALY_SCORE_THRESHOLD: i64 = 20;
+/// An hour is unusual if less than this fraction of the member's previous logs fell in it.
+const UNUSUAL_HOUR_FRACTION: f64 = 0.02;
+
+const HOST: &str = "host";
+const DEVICE: &str = "device";
+const UNIX_USER: &str = "unix_user";
+const HOUR: &str = "hour";
+
+fn weight(reason: &AnomalyReason) -> i64 {
+    match reason {
+        &AnomalyReason::NewHost { .. } => 40,
+        &AnomalyReason::NewDevice { .. } => 40,
+        &AnomalyReason::NewUnixUser { .. } => 20,
+        &AnomalyReason::UnusualHour { .. } => 20,
+    }
+}
+
+fn unix_user(log: &Log) -> Option<&str> {
+    match log.body {
+        LogBody::Ssh(ref ssh) => Some(&ssh.user),
+        LogBody::Sudo(ref sudo) => Some(&sudo.user),
+        LogBody::PamLogin(ref pam_login) => Some(&pam_login.user),
+        LogBody::SshAgentForwarding(ref agent_forwarding) => Some(&agent_forwarding.user),
+        _ => None,
+    }
+}
+
+/// Build the member's baseline from their stored logs if they have none yet, e.g. on the first run
+/// after upgrading from a version without anomaly detection. Must be called before the next log is
+/// stored, so that it is scored rather than counted in the baseline.
+pub fn backfill_baseline(conn: &TeamDBConnection, member_public_key: &[u8]) -> Result<()> {
+    if !db::AccessBaseline::for_member(conn, member_public_key)?.is_empty() {
+        return Ok(());
+    }
+    for stored_log in db::Log::for_member(conn, member_public_key)? {
+        match serde_json::from_str::<Log>(&stored_log.log_json) {
+            Ok(log) => observe(conn, member_public_key, &log)?,
+            Err(e) => warn!("skipping malformed log {} in anomaly baseline: {}", stored_log.id, e),
+        }
+    }
+    Ok(())
+}
+
+/// Score `log` against the member's baseline, record an anomaly if it scores high enough, then
+/// add the log to the baseline.
+pub fn score_and_record(conn: &TeamDBConnection, member_public_key: &[u8], log: &Log, log_json: &str) -> Result<()> {
+    let unix_seconds = log.unix_seconds as i64;
+    // Log times are set by the member's device and can be out of range. Such logs are scored
+    // without their hour.
+    let hour = Utc.timestamp_opt(unix_seconds, 0).single().map(|time| time.hour());
+    let device = base64::encode(&log.session.workstation_public_key_double_hash);
+    let host = LogSearchFields::from_log(log).host;
+    let user = unix_user(log).map(String::from);
+
+    // Baseline values are stored as keyed hashes.
+    let mut seen: HashMap<(String, String), i64> = HashMap::new();
+    for baseline in db::AccessBaseline::for_member(conn, member_public_key)? {
+        seen.insert((baseline.kind, baseline.value), baseline.count);
+    }
+    let count = |kind: &str, value: &str| -> Result<i64> {
+        let value_hash = db::AccessBaseline::value_hash(conn.conn, value)?;
+        Ok(seen.get(&(kind.to_string(), value_hash)).cloned().unwrap_or(0))
+    };
+    let is_new = |kind: &str, value: &str| -> Result<bool> { Ok(count(kind, value)? == 0) };
+    let total_logs: i64 = seen.iter().filter(|&(k, _)| k.0 == HOUR).map(|(_, c)| c).sum();
+
+    if total_logs >= MIN_BASELINE_LOGS {
+        let mut reasons = vec![];
+        if let Some(ref host) = host {
+            if is_new(HOST, host)? {
+                reasons.push(AnomalyReason::NewHost { host: host.clone() });
+            }
+        }
+        if is_new(DEVICE, &device)? {
+            reasons.push(AnomalyReason::NewDevice {
+                device_name: log.session.device_name.clone(),
+                workstation_public_key_double_hash: log.session.workstation_public_key_double_hash.clone(),
+            });
+        }
+        if let Some(ref user) = user {
+            if is_new(UNIX_USER, user)? {
+                reasons.push(AnomalyReason::NewUnixUser { user: user.clone() });
+            }
+        }
+        if let Some(hour) = hour {
+            let hour_count = count(HOUR, &hour.to_string())?;
+            let seen_fraction = hour_count as f64 / total_logs as f64;
+            if seen_fraction < UNUSUAL_HOUR_FRACTION {
+                reasons.push(AnomalyReason::UnusualHour { hour, seen_fraction });
+            }
+        }
+
+        let score = reasons.iter().map(weight).sum();
+        if score >= ANOMALY_SCORE_THRESHOLD {
+            db::AccessAnomaly::insert(conn.conn, &db::NewAccessAnomaly {
+                team_public_key: conn.team.into(),
+                member_public_key: member_public_key.into(),
+                log_json: log_json.into(),
+                score,
+                reasons_json: serde_json::to_string(&reasons)?,
+                unix_seconds,
+            })?;
+        }
+    }
+
+    observe(conn, member_public_key, log)
+}
+
+/// Add `log` to the member's baseline.
+fn observe(conn: &TeamDBConnection, member_public_key: &[u8], log: &Log) -> Result<()> {
+    let unix_seconds = log.unix_seconds as i64;
+    if let Some(time) = Utc.timestamp_opt(unix_seconds, 0).single() {
+        db::AccessBaseline::observe(conn, member_public_key, HOUR, &time.hour().to_string(), unix_seconds)?;
+    }
+    let device = base64::encode(&log.session.workstation_public_key_double_hash);
+    db::AccessBaseline::observe(conn, member_public_key, DEVICE, &device, unix_seconds)?;
+    if let Some(ref host) = LogSearchFields::from_log(log).host {
+        db::AccessBaseline::observe(conn, member_public_key, HOST, host, unix_seconds)?;
+    }
+    if let Some(user) = unix_user(log) {
+        db::AccessBaseline::observe(conn, member_public_key, UNIX_USER, user, unix_seconds)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::{Connection, DBConnection};
+    use logs::{ApprovalResult, Session, SudoApproval};
+    use protocol::E;
+
+    /// 11:06 UTC.
+    const NOW: u64 = 1525000000;
+
+    struct Fixture {
+        conn: DBConnection,
+        team_public_key: Vec<u8>,
+        member_public_key: Vec<u8>,
+    }
+
+    impl Fixture {
+        fn new() -> Fixture {
+            let conn = DBConnection::establish("").unwrap();
+            db::run_migrations(&conn).unwrap();
+            db::column_encryption::load_key_for_database(&conn, "").unwrap();
+            Fixture { conn, team_public_key: vec![1; 32], member_public_key: vec![2; 32] }
+        }
+
+        fn conn(&self) -> TeamDBConnection {
+            TeamDBConnection { conn: &self.conn, team: &self.team_public_key }
+        }
+
+        fn sudo_log(unix_seconds: u64, host: &str, user: &str) -> (Log, String) {
+            let log = Log {
+                session: Session {
+                    device_name: "laptop".into(),
+                    workstation_public_key_double_hash: vec![4; 32],
+                },
+                unix_seconds,
+                body: LogBody::Sudo(SudoApproval {
+                    user: user.into(),
+                    target_user: "root".into(),
+                    host: host.into(),
+                    command: None,
+                    result: ApprovalResult::Approved(E{}),
+                }),
+                redactions: vec![],
+                main_chain_block_hash: None,
+            };
+            let log_json = serde_json::to_string(&log).unwrap();
+            (log, log_json)
+        }
+
+        /// Score a log and return the reasons recorded for it, if it was an anomaly.
+        fn score(&self, unix_seconds: u64, host: &str, user: &str) -> Option<Vec<AnomalyReason>> {
+            let before = db::AccessAnomaly::all(&self.conn(), None).unwrap().len();
+            let (log, log_json) = Fixture::sudo_log(unix_seconds, host, user);
+            score_and_record(&self.conn(), &self.member_public_key, &log, &log_json).unwrap();
+            let anomalies = db::AccessAnomaly::all(&self.conn(), None).unwrap();
+            if anomalies.len() == before {
+                return None;
+            }
+            Some(serde_json::from_str(&anomalies[0].reasons_json).unwrap())
+        }
+
+        /// Store a log without scoring it, as versions before anomaly detection did.
+        fn store(&self, unix_seconds: u64, host: &str, user: &str) {
+            let (_, log_json) = Fixture::sudo_log(unix_seconds, host, user);
+            db::Log {
+                team_public_key: self.team_public_key.clone(),
+                member_public_key: self.member_public_key.clone(),
+                log_json,
+                unix_seconds: unix_seconds as i64,
+                signature_verification: None,
+                host_key_verification: None,
+                received_unix_seconds: 0,
+            }.insert(&self.conn).unwrap();
+        }
+
+        fn baseline_count(&self, kind: &str) -> i64 {
+            db::AccessBaseline::for_member(&self.conn(), &self.member_public_key).unwrap()
+                .into_iter()
+                .filter(|baseline| baseline.kind == kind)
+                .map(|baseline| baseline.count)
+                .sum()
+        }
+    }
+
+    fn only_reason(reasons: Option<Vec<AnomalyReason>>) -> AnomalyReason {
+        let mut reasons = reasons.expect("no anomaly recorded");
+        assert_eq!(reasons.len(), 1, "unexpected reasons {:?}", reasons);
+        reasons.remove(0)
+    }
+
+    #[test]
+    fn nothing_flagged_before_baseline() {
+        let fixture = Fixture::new();
+        for i in 0..MIN_BASELINE_LOGS as u64 {
+            assert!(fixture.score(NOW + i, &format!("host-{}", i), "deploy").is_none());
+        }
+    }
+
+    #[test]
+    fn single_reasons_are_flagged() {
+        let fixture = Fixture::new();
+        for i in 0..MIN_BASELINE_LOGS as u64 {
+            assert!(fixture.score(NOW + i, "db1", "deploy").is_none());
+        }
+        assert!(fixture.score(NOW, "db1", "deploy").is_none());
+
+        match only_reason(fixture.score(NOW, "db1", "root")) {
+            AnomalyReason::NewUnixUser { user } => assert_eq!(user, "root"),
+            reason => panic!("unexpected reason {:?}", reason),
+        }
+        match only_reason(fixture.score(NOW + 6 * 3600, "db1", "deploy")) {
+            AnomalyReason::UnusualHour { hour, .. } => assert_eq!(hour, 17),
+            reason => panic!("unexpected reason {:?}", reason),
+        }
+        match only_reason(fixture.score(NOW, "db2", "deploy")) {
+            AnomalyReason::NewHost { host } => assert_eq!(host, "db2"),
+            reason => panic!("unexpected reason {:?}", reason),
+        }
+    }
+
+    #[test]
+    fn backfill_baseline_from_stored_logs() {
+        let fixture = Fixture::new();
+        for i in 0..MIN_BASELINE_LOGS as u64 {
+            fixture.store(NOW + i, "db1", "deploy");
+        }
+        backfill_baseline(&fixture.conn(), &fixture.member_public_key).unwrap();
+        assert_eq!(fixture.baseline_count(HOUR), MIN_BASELINE_LOGS);
+
+        // Backfilling only happens while the member has no baseline.
+        backfill_baseline(&fixture.conn(), &fixture.member_public_key).unwrap();
+        assert_eq!(fixture.baseline_count(HOUR), MIN_BASELINE_LOGS);
+
+        assert!(fixture.score(NOW, "db1", "deploy").is_none());
+        assert!(fixture.score(NOW, "db2", "deploy").is_some());
+    }
+}
@@ -184,6 +184,31 @@ impl Client for DelegatedNetworkClient {
         self.request_operation(op)?;
         Ok(())
     }
+    fn verify_member(&self, identity_email: &str) -> Result<()> {
+        let matching_public_key = self.get_active_member_by_email(identity_email)?.public_key;
+        let safety_number = self.get_safety_number(&matching_public_key)?;
+        self.verify_member_pk(&matching_public_key, &safety_number)
+    }
+    fn verify_member_pk(&self, public_key: &[u8], safety_number: &str) -> Result<()> {
+        let op = RequestableTeamOperation::VerifyMember(team::MemberVerification {
+            public_key: public_key.into(),
+            safety_number: safety_number.into(),
+        });
+        self.request_operation(op)?;
+        Ok(())
+    }
+    fn update_keys(&self, key_update: team::KeyUpdate) -> Result<()> {
+        self.request_operation(RequestableTeamOperation::UpdateKeys(key_update))?;
+        Ok(())
+    }
+    fn add_device(&self, device: team::Device) -> Result<()> {
+        self.request_operation(RequestableTeamOperation::AddDevice(device))?;
+        Ok(())
+    }
+    fn remove_device(&self, device_public_key: &[u8]) -> Result<()> {
+        self.request_operation(RequestableTeamOperation::RemoveDevice(device_public_key.into()))?;
+        Ok(())
+    }
     fn enable_logging(&self) -> Result<()> {
         use LoggingEndpoint::*;
         let op = RequestableTeamOperation::AddLoggingEndpoint(CommandEncrypted(E{}));
@@ -196,7 +221,7 @@ impl Client for DelegatedNetworkClient {
         self.request_operation(op)?;
         Ok(())
     }
-    fn unwrap_log_encryption_key(&self, wrapped_key: &logging::BoxedMessage) -> Result<Vec<u8>> {
+    fn unwrap_log_encryption_key(&self, wrapped_key: &logging::BoxedMessage) -> Result<SecretBytes> {
         use enclave_protocol::{RequestBody, LogDecryptionRequest};
         let response = krd_client::daemon_enclave_control_request(
             &enclave_protocol::Request::new(
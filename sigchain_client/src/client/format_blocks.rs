@@ -2,7 +2,7 @@
 
 use serde_json;
 
-use {Result, Client, SignedMessage, Message};
+use {Result, Client, SignedMessage, Message, team};
 
 use b64data;
 
@@ -52,7 +52,7 @@ pub fn format<C: Client>(c: &C, block: &SignedMessage) -> Result<FormattedBlock>
     use db::TeamDBConnection;
     let conn = &TeamDBConnection{team: c.team_pk(), conn: c.db_conn()};
 
-    let author = db::Identity::find(conn, &block.public_key)?;
+    let author = db::Identity::find_for_signer(conn, &block.public_key)?;
 
     use MainChain::*;
     use team::Operation::*;
@@ -73,7 +73,12 @@ pub fn format<C: Client>(c: &C, block: &SignedMessage) -> Result<FormattedBlock>
                     }
                 }
                 CloseInvitations(_) => ("close invitations", None),
-                AcceptInvite(identity) => ("accept invite", Some(format!("{} joined the team", identity.email))),
+                AcceptInvite(identity) => ("accept invite", Some(
+                    match c.get_safety_number(&identity.public_key) {
+                        Ok(safety_number) => format!("{} joined the team\nsafety number {}", identity.email, safety_number),
+                        Err(_) => format!("{} joined the team", identity.email),
+                    }
+                )),
                 Remove(public_key) => (
                     "remove",
                     Some(format!("remove {} from the team",
@@ -99,6 +104,19 @@ pub fn format<C: Client>(c: &C, block: &SignedMessage) -> Result<FormattedBlock>
                 ))),
                 AddLoggingEndpoint(_) => ("enable audit logging", None),
                 RemoveLoggingEndpoint(_) => ("disable audit logging", None),
+                UpdateKeys(key_update) => ("update keys", Some(format!("updated {}", key_update_description(&key_update)))),
+                AddDevice(device) => ("add device", Some(format!("added device \"{}\"", device.name))),
+                RemoveDevice(public_key) => ("remove device", Some(
+                    match db::MemberDevice::find(conn, &public_key) {
+                        Ok(device) => format!("removed {}'s device \"{}\"", db::Identity::find(conn, &device.member_public_key)?.email, device.name),
+                        Err(_) => format!("removed a device"),
+                    }
+                )),
+                VerifyMember(verification) => ("verify member", Some(format!("verified {} with safety number {}",
+                                                                              db::Identity::find(conn, &verification.public_key)?.email,
+                                                                              verification.safety_number,
+                ))),
+                PublishLogChainHead(_) => ("publish log chain head", None),
             }
         }
         Create(genesis_block) => ("create chain", Some(
@@ -124,6 +142,20 @@ pub fn format<C: Client>(c: &C, block: &SignedMessage) -> Result<FormattedBlock>
     })
 }
 
+fn key_update_description(key_update: &team::KeyUpdate) -> String {
+    use ssh;
+    use sigchain_core::pgp::public_key::PGPPublicKey;
+
+    let mut keys = vec![];
+    if let Some(ref ssh_public_key) = key_update.ssh_public_key {
+        keys.push(format!("SSH key {}", ssh::ssh_public_key_fingerprint(ssh_public_key).unwrap_or_else(|_| "(invalid)".into())));
+    }
+    if let Some(ref pgp_public_key) = key_update.pgp_public_key {
+        keys.push(format!("PGP key {}", PGPPublicKey::parse(pgp_public_key).map(|k| k.fingerprint_string()).unwrap_or_else(|_| "(invalid)".into())));
+    }
+    keys.join(" and ")
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct FormattedRequestableOperation {
     pub header: String,
@@ -178,6 +210,15 @@ pub fn format_requestable_op<C: Client>(c: &C, op: enclave_protocol::Requestable
         Demote(pk) => ("Demote to Member", format!("Demote {} to member", db::Identity::find(conn, &pk)?.email)),
         Remove(pk) => ("Remove from Team", format!("Remove {} from the team", db::Identity::find(conn, &pk)?.email)),
         Leave(_) => ("Leave Team", format!("Leave the team")),
+        UpdateKeys(key_update) => ("Update Keys", format!("Replace your {} on the team", key_update_description(&key_update))),
+        AddDevice(device) => ("Add Device", format!("Allow the device \"{}\" to act for you on the team", device.name)),
+        RemoveDevice(pk) => {
+            let device = db::MemberDevice::find(conn, &pk)?;
+            ("Remove Device", format!("Remove {}'s device \"{}\" from the team", db::Identity::find(conn, &device.member_public_key)?.email, device.name))
+        }
+        VerifyMember(verification) => ("Verify Member", format!("Confirm you compared {}'s safety number in person: {}",
+                                                                db::Identity::find(conn, &verification.public_key)?.email,
+                                                                verification.safety_number)),
     };
 
     Ok(FormattedRequestableOperation{
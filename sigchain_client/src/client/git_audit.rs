@@ -0,0 +1,160 @@
+/// Cross-reference the signed commits and tags of a local git repository with the team's git logs.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use serde_json;
+
+use Result;
+use db::{self, TeamDBConnection};
+use logs::{Log, LogBody};
+use sigchain_core::git_hash::GitHash;
+use client::membership_history;
+use client::git_verify::{self, GitSignedObject};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GitAuditFinding {
+    pub object_id: String,
+    /// `commit` or `tag`.
+    pub object_type: String,
+    pub member_email: String,
+    pub unix_seconds: i64,
+    /// First line of the commit or tag message.
+    pub summary: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GitAuditReport {
+    pub commits_scanned: u64,
+    pub tags_scanned: u64,
+    /// Objects signed by a team member's key with no matching log.
+    pub unlogged: Vec<GitAuditFinding>,
+    /// Logged objects whose parent is in the repository but which are not, e.g. amended or
+    /// abandoned commits. Logs for other repositories are not reported.
+    pub never_landed: Vec<GitAuditFinding>,
+    /// Objects signed by a member after the block that removed them from the team.
+    pub signed_after_removal: Vec<GitAuditFinding>,
+}
+
+pub fn audit_git_repository(conn: &TeamDBConnection, repo: &Path) -> Result<GitAuditReport> {
+    let mut report = GitAuditReport::default();
+
+    let commit_ids = git_lines(repo, &["rev-list", "--all"])?;
+    let tag_ids = git_lines(repo, &["for-each-ref", "--format=%(objectname) %(objecttype)", "refs/tags"])?
+        .into_iter()
+        .filter(|l| l.ends_with(" tag"))
+        .filter_map(|l| l.split(' ').next().map(String::from))
+        .collect::<Vec<_>>();
+    report.commits_scanned = commit_ids.len() as u64;
+    report.tags_scanned = tag_ids.len() as u64;
+
+    let objects = cat_objects(repo, commit_ids.iter().chain(tag_ids.iter()))?.into_iter()
+        .filter_map(|(object_id, object_type, data)| GitSignedObject::parse(&object_type, object_id, &data))
+        .collect::<Vec<_>>();
+
+    let identities = db::Identity::find_all_for_team(conn)?;
+    let histories = membership_history::membership_history(conn)?;
+
+    let mut logged_ids = HashSet::new();
+    let mut logged = vec![];
+    for db_log in db::Log::all(conn, None)? {
+        let log: Log = match serde_json::from_str(&db_log.log_json) {
+            Ok(log) => log,
+            Err(_) => continue,
+        };
+        let (object_id, object_type, summary, parents) = match log.body {
+            LogBody::GitCommit(ref commit) => (commit.git_hash_hex_string(), "commit", commit.message_string.clone(), commit.parents.clone()),
+            LogBody::GitTag(ref tag) => (tag.git_hash_hex_string(), "tag", tag.message_string.clone(), vec![tag.object.clone()]),
+            _ => continue,
+        };
+        if let Some(object_id) = object_id {
+            logged_ids.insert(object_id.clone());
+            let email = identities.iter().find(|i| i.public_key == db_log.member_public_key)
+                .map(|i| i.email.clone()).unwrap_or_default();
+            logged.push((GitAuditFinding {
+                object_id,
+                object_type: object_type.into(),
+                member_email: email,
+                unix_seconds: log.unix_seconds as i64,
+                summary: summary.unwrap_or_default().trim().lines().next().unwrap_or("").into(),
+            }, parents));
+        }
+    }
+
+    let object_ids: HashSet<String> = commit_ids.iter().chain(tag_ids.iter()).cloned().collect();
+    for (finding, parents) in logged {
+        let parent_landed = parents.iter().any(|p| object_ids.contains(p));
+        if parent_landed && !object_ids.contains(&finding.object_id) {
+            report.never_landed.push(finding);
+        }
+    }
+
+    for object in objects {
+        let signers = git_verify::identities_at(conn, object.unix_seconds)?;
+        let signer = match object.signer(&signers).and_then(|(identity, _)| histories.iter().find(|h| h.public_key == identity.public_key)) {
+            Some(signer) => signer,
+            None => continue,
+        };
+        let finding = GitAuditFinding {
+            object_id: object.object_id.clone(),
+            object_type: object.object_type.into(),
+            member_email: signer.email.clone(),
+            unix_seconds: object.unix_seconds,
+            summary: object.summary.clone(),
+        };
+        if signer.removal_before(object.unix_seconds).is_some() {
+            report.signed_after_removal.push(finding.clone());
+        }
+        if !logged_ids.contains(&object.object_id) {
+            report.unlogged.push(finding);
+        }
+    }
+    Ok(report)
+}
+
+fn git_lines(repo: &Path, args: &[&str]) -> Result<Vec<String>> {
+    let output = Command::new("git").arg("-C").arg(repo).args(args).output()?;
+    if !output.status.success() {
+        bail!(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(String::from).collect())
+}
+
+/// Read objects with `git cat-file --batch`, returning each object's id, type and contents.
+fn cat_objects<'a, I: Iterator<Item = &'a String>>(repo: &Path, object_ids: I) -> Result<Vec<(String, String, Vec<u8>)>> {
+    let mut child = Command::new("git").arg("-C").arg(repo).args(&["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    {
+        let stdin = match child.stdin.as_mut() {
+            Some(stdin) => stdin,
+            None => bail!("failed to open git stdin"),
+        };
+        for object_id in object_ids {
+            writeln!(stdin, "{}", object_id)?;
+        }
+    }
+    let output = child.wait_with_output()?;
+
+    let mut objects = vec![];
+    let mut data = output.stdout.as_slice();
+    while let Some(header_end) = data.iter().position(|b| *b == b'\n') {
+        let header = String::from_utf8_lossy(&data[..header_end]).into_owned();
+        let fields = header.split(' ').collect::<Vec<_>>();
+        if fields.len() != 3 {
+            // `<id> missing`
+            data = &data[header_end + 1..];
+            continue;
+        }
+        let size: usize = fields[2].parse()?;
+        let start = header_end + 1;
+        if data.len() < start + size + 1 {
+            bail!("truncated git cat-file output");
+        }
+        objects.push((fields[0].into(), fields[1].into(), data[start..start + size].to_vec()));
+        data = &data[start + size + 1..];
+    }
+    Ok(objects)
+}
@@ -0,0 +1,231 @@
+/// Verify signed git commits and tags against team member keys and the member's standing on the
+/// main chain at signing time, using only the local copy of the chain.
+
+use Result;
+use db::{self, TeamDBConnection};
+use sigchain_core::git_hash::git_object_id;
+use sigchain_core::pgp::{self, packets};
+use sigchain_core::crypto::signature_verification::{verify_pgp_signature, verify_sshsig};
+use client::membership_history::{self, MemberStatus, MembershipEvent};
+
+const PGP_SIGNATURE_BEGIN: &str = "-----BEGIN PGP SIGNATURE-----";
+const SSH_SIGNATURE_BEGIN: &str = "-----BEGIN SSH SIGNATURE-----";
+/// The namespace git uses for SSH signatures.
+const SSH_GIT_NAMESPACE: &str = "git";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GitSigningKey {
+    PGP,
+    SSH,
+}
+
+/// A commit or tag together with the bytes its signature covers.
+#[derive(Debug, Clone)]
+pub struct GitSignedObject {
+    pub object_id: String,
+    /// `commit` or `tag`.
+    pub object_type: &'static str,
+    pub signed_data: Vec<u8>,
+    pub armored_signature: String,
+    /// Signature creation time, falling back to the committer or tagger time.
+    pub unix_seconds: i64,
+    /// First line of the commit or tag message.
+    pub summary: String,
+}
+
+impl GitSignedObject {
+    /// `None` if the object is not a commit or tag or is unsigned.
+    pub fn parse(object_type: &str, object_id: String, data: &[u8]) -> Option<GitSignedObject> {
+        match object_type {
+            "commit" => parse_signed_commit(object_id, data),
+            "tag" => parse_signed_tag(object_id, data),
+            _ => None,
+        }
+    }
+
+    /// Parse the raw contents of a commit or tag object, as printed by `git cat-file <type> <id>`.
+    pub fn parse_raw(data: &[u8]) -> Result<GitSignedObject> {
+        let object_type = if data.starts_with(b"tree ") {
+            "commit"
+        } else if data.starts_with(b"object ") {
+            "tag"
+        } else {
+            bail!("not a git commit or tag object");
+        };
+        match GitSignedObject::parse(object_type, git_object_id(object_type, data), data) {
+            Some(object) => Ok(object),
+            None => bail!(format!("{} is not signed", object_type)),
+        }
+    }
+
+    pub fn signing_key(&self) -> GitSigningKey {
+        if self.armored_signature.contains(SSH_SIGNATURE_BEGIN) {
+            GitSigningKey::SSH
+        } else {
+            GitSigningKey::PGP
+        }
+    }
+
+    /// The identity whose PGP or SSH key produced the signature.
+    pub fn signer<'a>(&self, identities: &'a [db::Identity]) -> Option<(&'a db::Identity, GitSigningKey)> {
+        match self.signing_key() {
+            GitSigningKey::PGP => {
+                let signature = pgp::dearmor(&self.armored_signature).ok()?;
+                let issuer = packets::first_signature(&signature).ok()?.issuer_key_id();
+                identities.iter()
+                    .filter(|identity| match issuer {
+                        Some(ref issuer) => packets::primary_public_key(&identity.pgp_public_key)
+                            .map(|k| k.key_id() == *issuer).unwrap_or(false),
+                        None => true,
+                    })
+                    .find(|identity| verify_pgp_signature(&identity.pgp_public_key, &self.signed_data, &signature).unwrap_or(false))
+                    .map(|identity| (identity, GitSigningKey::PGP))
+            }
+            GitSigningKey::SSH => {
+                identities.iter()
+                    .find(|identity| {
+                        verify_sshsig(&identity.ssh_public_key, SSH_GIT_NAMESPACE, &self.signed_data, &self.armored_signature).unwrap_or(false)
+                    })
+                    .map(|identity| (identity, GitSigningKey::SSH))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GitSignatureReport {
+    pub object_id: String,
+    pub object_type: &'static str,
+    pub unix_seconds: i64,
+    /// `None` if no current or former member's key produced the signature.
+    pub signer: Option<GitSigner>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitSigner {
+    pub email: String,
+    pub public_key: Vec<u8>,
+    pub signing_key: GitSigningKey,
+    /// The member's status on the main chain when the object was signed.
+    pub status: MemberStatus,
+    /// The block that removed the member, if they had been removed when the object was signed.
+    pub removal: Option<MembershipEvent>,
+}
+
+impl GitSignatureReport {
+    /// Whether the object was signed by a member of the team at signing time.
+    pub fn signed_by_member(&self) -> bool {
+        self.signer.as_ref().map_or(false, |s| s.status.is_member)
+    }
+}
+
+/// Determine which member signed the raw commit or tag object `data`.
+pub fn verify_git_object(conn: &TeamDBConnection, data: &[u8]) -> Result<GitSignatureReport> {
+    let object = GitSignedObject::parse_raw(data)?;
+    let identities = identities_at(conn, object.unix_seconds)?;
+
+    let signer = match object.signer(&identities) {
+        Some((identity, signing_key)) => {
+            let history = membership_history::membership_history(conn)?.into_iter()
+                .find(|h| h.public_key == identity.public_key);
+            Some(GitSigner {
+                email: identity.email.clone(),
+                public_key: identity.public_key.clone(),
+                signing_key,
+                status: history.as_ref().map(|h| h.status_at(object.unix_seconds)).unwrap_or_default(),
+                removal: history.as_ref().and_then(|h| h.removal_before(object.unix_seconds).cloned()),
+            })
+        }
+        None => None,
+    };
+
+    Ok(GitSignatureReport {
+        object_id: object.object_id,
+        object_type: object.object_type,
+        unix_seconds: object.unix_seconds,
+        signer,
+    })
+}
+
+/// The team's identities with the SSH and PGP keys each member had at `unix_seconds`.
+pub fn identities_at(conn: &TeamDBConnection, unix_seconds: i64) -> Result<Vec<db::Identity>> {
+    let mut identities = vec![];
+    for identity in db::Identity::find_all_for_team(conn)? {
+        identities.push(identity.with_keys_at(conn, unix_seconds)?);
+    }
+    Ok(identities)
+}
+
+fn parse_signed_commit(object_id: String, data: &[u8]) -> Option<GitSignedObject> {
+    let text = String::from_utf8_lossy(data);
+    let header_end = text.find("\n\n").map_or(text.len(), |i| i + 1);
+    let (headers, message) = text.split_at(header_end);
+
+    let mut signed_data = String::new();
+    let mut armored_signature = String::new();
+    let mut committer_seconds = 0;
+    let mut in_signature = false;
+    for line in headers.lines() {
+        if line.starts_with("gpgsig ") {
+            in_signature = true;
+            armored_signature.push_str(&line["gpgsig ".len()..]);
+            armored_signature.push('\n');
+            continue;
+        }
+        if in_signature && line.starts_with(' ') {
+            armored_signature.push_str(&line[1..]);
+            armored_signature.push('\n');
+            continue;
+        }
+        in_signature = false;
+        if line.starts_with("committer ") {
+            committer_seconds = identity_line_seconds(line);
+        }
+        signed_data.push_str(line);
+        signed_data.push('\n');
+    }
+    signed_data.push_str(message);
+
+    signed_object(object_id, "commit", signed_data, armored_signature, committer_seconds, message)
+}
+
+fn parse_signed_tag(object_id: String, data: &[u8]) -> Option<GitSignedObject> {
+    let text = String::from_utf8_lossy(data);
+    let signature_start = text.find(PGP_SIGNATURE_BEGIN).or_else(|| text.find(SSH_SIGNATURE_BEGIN))?;
+    let (signed_data, armored_signature) = text.split_at(signature_start);
+    let tagger_seconds = signed_data.lines()
+        .take_while(|l| !l.is_empty())
+        .find(|l| l.starts_with("tagger "))
+        .map_or(0, identity_line_seconds);
+    let message = signed_data.find("\n\n").map_or("", |i| &signed_data[i + 2..]);
+
+    signed_object(object_id, "tag", signed_data.into(), armored_signature.into(), tagger_seconds, message)
+}
+
+fn signed_object(object_id: String, object_type: &'static str, signed_data: String, armored_signature: String, fallback_seconds: i64, message: &str) -> Option<GitSignedObject> {
+    if armored_signature.is_empty() {
+        return None;
+    }
+    // SSH signatures carry no timestamp of their own.
+    let signature_seconds = if armored_signature.contains(PGP_SIGNATURE_BEGIN) {
+        pgp::dearmor(&armored_signature).ok()
+            .and_then(|signature| packets::first_signature(&signature).ok())
+            .and_then(|s| s.created_unix_seconds())
+    } else {
+        None
+    };
+    let unix_seconds = signature_seconds.map_or(fallback_seconds, |s| s as i64);
+    Some(GitSignedObject {
+        object_id,
+        object_type,
+        signed_data: signed_data.into_bytes(),
+        armored_signature,
+        unix_seconds,
+        summary: message.trim().lines().next().unwrap_or("").into(),
+    })
+}
+
+/// The timestamp of an `author`, `committer` or `tagger` line: `Name <email> 1523456789 +0000`.
+fn identity_line_seconds(line: &str) -> i64 {
+    line.rsplitn(3, ' ').nth(1).and_then(|s| s.parse().ok()).unwrap_or(0)
+}
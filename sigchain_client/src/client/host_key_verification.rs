@@ -0,0 +1,69 @@
+/// Check the host authorization in SSH logs against the host keys the team had pinned at the main
+/// chain position the log was written at.
+
+use Result;
+use db::{self, TeamDBConnection};
+use logs::{Log, LogBody};
+use sigchain_core::crypto::signature_verification::verify_ssh_signature;
+use sigchain_core::dashboard_protocol::HostKeyVerification;
+use sigchain_core::ssh_session::SSHSessionData;
+use sigchain_core::ssh_public_key::SSHPublicKey;
+
+/// `None` if the log has no host authorization.
+pub fn verify_host_authorization(conn: &TeamDBConnection, log: &Log, main_chain_index: i64) -> Result<Option<HostKeyVerification>> {
+    let (host_authorization, session_data) = match log.body {
+        LogBody::Ssh(ref ssh) => (ssh.host_authorization.as_ref(), Some(&ssh.session_data)),
+        LogBody::SshAgentForwarding(ref agent_forwarding) => (agent_forwarding.host_authorization.as_ref(), None),
+        _ => return Ok(None),
+    };
+    let host_authorization = match host_authorization {
+        Some(host_authorization) => host_authorization,
+        None => return Ok(None),
+    };
+
+    // The host signs the session identifier with its host key during the key exchange.
+    if let (Some(signature), Some(session_data)) = (host_authorization.signature.as_ref(), session_data) {
+        let verified = SSHSessionData::parse(session_data)
+            .and_then(|session| verify_ssh_signature(&host_authorization.public_key, &session.session_id, signature));
+        match verified {
+            Ok(true) => {}
+            Ok(false) => return Ok(Some(HostKeyVerification::InvalidSignature)),
+            Err(e) => debug!("unable to verify host signature for {}: {}", host_authorization.host, e),
+        }
+    }
+
+    // Pins are stored in canonical form.
+    let public_key = SSHPublicKey::parse(&host_authorization.public_key)
+        .map(|k| k.canonical_wire)
+        .unwrap_or_else(|_| host_authorization.public_key.clone());
+    let pinned_keys = pinned_host_keys_at(conn, &host_authorization.host, main_chain_index)?;
+    Ok(Some(if pinned_keys.is_empty() {
+        HostKeyVerification::Unpinned
+    } else if pinned_keys.contains(&public_key) {
+        HostKeyVerification::PinnedMatch
+    } else {
+        HostKeyVerification::Mismatch
+    }))
+}
+
+/// Host keys pinned for `host` at main chain block `main_chain_index`, replayed from the pin
+/// history. Keys pinned before the history was recorded are treated as always pinned.
+fn pinned_host_keys_at(conn: &TeamDBConnection, host: &str, main_chain_index: i64) -> Result<Vec<Vec<u8>>> {
+    let events = db::PinnedHostKeyEvent::for_host(conn, host)?;
+
+    let mut pinned_keys: Vec<Vec<u8>> = db::PinnedHostKey::filter_by_host(conn.conn, conn.team, host, false)?
+        .into_iter()
+        .map(|k| k.public_key)
+        .filter(|k| !events.iter().any(|e| e.public_key == *k))
+        .collect();
+    for event in events.into_iter().filter(|e| e.main_chain_index <= main_chain_index) {
+        if event.pinned {
+            if !pinned_keys.contains(&event.public_key) {
+                pinned_keys.push(event.public_key);
+            }
+        } else {
+            pinned_keys.retain(|k| *k != event.public_key);
+        }
+    }
+    Ok(pinned_keys)
+}
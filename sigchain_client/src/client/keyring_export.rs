@@ -0,0 +1,95 @@
+/// Export member keys from the synced chain in formats stock tooling consumes: an armored OpenPGP
+/// keyring for GnuPG and an `allowed_signers` file for `git` SSH signature verification.
+
+use chrono::{TimeZone, Utc};
+use sigchain_core::diesel::OptionalExtension;
+
+use Result;
+use db::{self, TeamDBConnection};
+use sigchain_core::pgp::pgp_public_key_ascii_armor_string;
+use ssh::ssh_public_key_wire_string;
+use client::membership_history::{self, MembershipChange};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TeamKeyringExport {
+    /// ASCII armored keyring of member PGP public keys.
+    pub pgp_keyring: String,
+    /// `allowed_signers` lines (see `ssh-keygen(1)`) for member SSH public keys.
+    pub allowed_signers: String,
+    pub members_exported: u64,
+}
+
+/// Export the keys of the team's active members. With `include_removed`, removed members are
+/// exported too and every `allowed_signers` line is limited to the period the member was on the
+/// team with `valid-after` and `valid-before`.
+pub fn export_team_keyrings(conn: &TeamDBConnection, include_removed: bool) -> Result<TeamKeyringExport> {
+    let histories = membership_history::membership_history(conn)?;
+
+    let mut pgp_packets = vec![];
+    let mut allowed_signers = String::new();
+    let mut members_exported = 0;
+    for identity in db::Identity::find_all_for_team(conn)? {
+        let is_active = db::TeamMembership::find(conn, &identity.public_key).optional()?.is_some();
+        if !is_active && !include_removed {
+            continue;
+        }
+        members_exported += 1;
+        pgp_packets.extend_from_slice(&identity.pgp_public_key);
+
+        let ssh_public_key = match ssh_public_key_wire_string(&identity.ssh_public_key) {
+            Ok(ssh_public_key) => ssh_public_key,
+            Err(e) => {
+                warn!("skipping invalid SSH public key of {}: {}", identity.email, e);
+                continue;
+            }
+        };
+        if !include_removed {
+            allowed_signers.push_str(&allowed_signers_line(&identity.email, &[], &ssh_public_key));
+            continue;
+        }
+
+        let events = histories.iter()
+            .find(|h| h.public_key == identity.public_key)
+            .map(|h| h.events.clone())
+            .unwrap_or_default();
+        let mut joined = None;
+        for event in events {
+            match event.change {
+                MembershipChange::Joined => joined = Some(event.unix_seconds),
+                MembershipChange::Removed => if let Some(joined) = joined.take() {
+                    let options = [valid_after(joined), valid_before(event.unix_seconds)];
+                    allowed_signers.push_str(&allowed_signers_line(&identity.email, &options, &ssh_public_key));
+                },
+                _ => {}
+            }
+        }
+        if let Some(joined) = joined {
+            allowed_signers.push_str(&allowed_signers_line(&identity.email, &[valid_after(joined)], &ssh_public_key));
+        }
+    }
+
+    Ok(TeamKeyringExport {
+        pgp_keyring: pgp_public_key_ascii_armor_string(&pgp_packets)?,
+        allowed_signers,
+        members_exported,
+    })
+}
+
+fn allowed_signers_line(email: &str, options: &[String], ssh_public_key: &str) -> String {
+    let mut all_options = vec!["namespaces=\"git\"".to_string()];
+    all_options.extend_from_slice(options);
+    format!("{} {} {}\n", email, all_options.join(","), ssh_public_key)
+}
+
+fn valid_after(unix_seconds: i64) -> String {
+    format!("valid-after=\"{}\"", allowed_signers_time(unix_seconds))
+}
+
+fn valid_before(unix_seconds: i64) -> String {
+    format!("valid-before=\"{}\"", allowed_signers_time(unix_seconds))
+}
+
+/// `YYYYMMDDHHMMSSZ`, the `Z` marking the time as UTC.
+fn allowed_signers_time(unix_seconds: i64) -> String {
+    Utc.timestamp(unix_seconds, 0).format("%Y%m%d%H%M%SZ").to_string()
+}
@@ -0,0 +1,111 @@
+use super::*;
+use db::Connection;
+use db::LogFailureType::*;
+use super::traits::DBConnect;
+
+struct TestDB {
+    team_public_key: Vec<u8>,
+    db_connection: DBConnection,
+}
+
+impl TestDB {
+    fn new() -> TestDB {
+        let db_connection = DBConnection::establish("").unwrap();
+        db::run_migrations(&db_connection).unwrap();
+        db::column_encryption::load_key_for_database(&db_connection, "").unwrap();
+        TestDB { team_public_key: vec![1; 32], db_connection }
+    }
+
+    fn record(&self, member_public_key: &[u8], block_hash: &[u8], failure_type: db::LogFailureType, unix_seconds: i64) {
+        db::LogProcessingFailure::upsert(&self.db_connection, &db::NewLogProcessingFailure {
+            team_public_key: self.team_public_key.clone(),
+            member_public_key: member_public_key.into(),
+            block_hash: block_hash.into(),
+            failure_type: failure_type.as_str().into(),
+            error: failure_type.as_str().into(),
+            unix_seconds,
+        }).unwrap();
+    }
+
+    fn failures(&self) -> Vec<(Vec<u8>, Option<db::LogFailureType>)> {
+        db::LogProcessingFailure::all(&self.team_db_conn()).unwrap().into_iter()
+            .map(|f| (f.block_hash.clone(), f.failure_type()))
+            .collect()
+    }
+}
+
+impl traits::Identify for TestDB {
+    fn identity_pk(&self) -> &[u8] {
+        &[]
+    }
+    fn team_pk(&self) -> &[u8] {
+        &self.team_public_key
+    }
+}
+
+impl traits::DBConnect for TestDB {
+    fn db_conn(&self) -> &DBConnection {
+        &self.db_connection
+    }
+}
+
+#[test]
+fn upsert_keeps_one_failure_per_block() {
+    let test_db = TestDB::new();
+    test_db.record(&[2; 32], &[3; 32], MissingKey, 1);
+    test_db.record(&[2; 32], &[4; 32], MissingKey, 2);
+    test_db.record(&[2; 32], &[3; 32], BadCiphertext, 3);
+
+    assert_eq!(test_db.failures(), vec![
+        (vec![3; 32], Some(BadCiphertext)),
+        (vec![4; 32], Some(MissingKey)),
+    ]);
+    let updated = &db::LogProcessingFailure::all(&test_db.team_db_conn()).unwrap()[0];
+    assert_eq!(updated.unix_seconds, 3);
+    assert_eq!(updated.error, "bad_ciphertext");
+}
+
+#[test]
+fn failures_before_first_key_are_not_gaps() {
+    let test_db = TestDB::new();
+    let member = vec![2; 32];
+    let other_member = vec![5; 32];
+    test_db.record(&member, &[3; 32], MissingKey, 1);
+    test_db.record(&member, &[4; 32], MissingKey, 2);
+    test_db.record(&member, &[6; 32], BadWrappedKey, 3);
+    test_db.record(&other_member, &[7; 32], MissingKey, 4);
+
+    // The member wraps their log key for us, then a later block is still missing a key.
+    assert_eq!(db::LogProcessingFailure::mark_before_first_key(&test_db.team_db_conn(), &member).unwrap(), 2);
+    test_db.record(&member, &[8; 32], MissingKey, 5);
+
+    let integrity = test_db.get_member_log_integrity(&member).unwrap();
+    assert_eq!(integrity.before_first_key, 2);
+    assert_eq!(integrity.missing_key, 1);
+    assert_eq!(integrity.bad_wrapped_key, 1);
+    assert_eq!(integrity.missing_logs(), 1);
+    assert!(integrity.has_gaps());
+    assert_eq!(integrity.last_failure_unix_seconds, Some(5));
+
+    let other_integrity = test_db.get_member_log_integrity(&other_member).unwrap();
+    assert_eq!(other_integrity.missing_key, 1);
+    assert_eq!(other_integrity.before_first_key, 0);
+}
+
+#[test]
+fn chain_readable_from_first_key_has_no_gaps() {
+    let test_db = TestDB::new();
+    let member = vec![2; 32];
+    test_db.record(&member, &[3; 32], MissingKey, 1);
+    db::LogProcessingFailure::mark_before_first_key(&test_db.team_db_conn(), &member).unwrap();
+
+    // Re-reading the chain records the early block again, and the key block marks it again.
+    test_db.record(&member, &[3; 32], MissingKey, 2);
+    db::LogProcessingFailure::mark_before_first_key(&test_db.team_db_conn(), &member).unwrap();
+
+    let integrity = test_db.get_member_log_integrity(&member).unwrap();
+    assert_eq!(integrity.before_first_key, 1);
+    assert_eq!(integrity.missing_logs(), 0);
+    assert!(!integrity.has_gaps());
+    assert_eq!(integrity.last_failure_unix_seconds, None);
+}
@@ -0,0 +1,365 @@
+/// Walk the log chains stored locally and check that each one is a single, fully linked chain.
+
+use serde_json;
+use std::collections::{HashMap, HashSet};
+
+use {Result, SignedMessage};
+use db::{self, TeamDBConnection};
+use logging::LogChainHead;
+use crypto::ed25519;
+
+use b64data;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum LogChainIssue {
+    /// Blocks are stored for the member but none of them starts the chain.
+    NoGenesisBlock,
+    MultipleGenesisBlocks(
+        #[serde(with = "b64data::vec")]
+        Vec<Vec<u8>>
+    ),
+    /// More than one block claims the same previous block.
+    Fork {
+        #[serde(with = "b64data")]
+        parent: Vec<u8>,
+        #[serde(with = "b64data::vec")]
+        children: Vec<Vec<u8>>,
+    },
+    /// A block points to a previous block that is not stored.
+    MissingLink {
+        #[serde(with = "b64data")]
+        block_hash: Vec<u8>,
+        #[serde(with = "b64data")]
+        last_block_hash: Vec<u8>,
+    },
+    /// The stored hash does not match the block contents.
+    HashMismatch(
+        #[serde(with = "b64data")]
+        Vec<u8>
+    ),
+    BadSignature(
+        #[serde(with = "b64data")]
+        Vec<u8>
+    ),
+    /// The head recorded in `log_chains` is not the end of the walked chain.
+    HeadMismatch {
+        #[serde(with = "b64data::option", default)]
+        recorded: Option<Vec<u8>>,
+        #[serde(with = "b64data::option", default)]
+        walked: Option<Vec<u8>>,
+    },
+    /// The member published a head that is not part of the stored chain, i.e. blocks were withheld.
+    PublishedHeadNotFound {
+        #[serde(with = "b64data")]
+        last_block_hash: Vec<u8>,
+        unix_seconds: i64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogChainReport {
+    #[serde(with = "b64data")]
+    pub member_public_key: Vec<u8>,
+    pub block_count: u64,
+    #[serde(with = "b64data::option", default)]
+    pub head: Option<Vec<u8>>,
+    pub published_heads_checked: u64,
+    pub issues: Vec<LogChainIssue>,
+}
+
+impl LogChainReport {
+    pub fn is_intact(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Verify a member-signed log chain head and return its contents.
+pub fn verify_published_head(signed_head: &SignedMessage) -> Result<LogChainHead> {
+    let sig = match ed25519::Signature::from_slice(&signed_head.signature) {
+        Some(sig) => sig,
+        None => bail!("invalid signature"),
+    };
+    let pk = match ed25519::PublicKey::from_slice(&signed_head.public_key) {
+        Some(pk) => pk,
+        None => bail!("invalid public key"),
+    };
+    if !ed25519::verify_detached(&sig, &LogChainHead::signed_data(&signed_head.message), &pk) {
+        bail!("signature verification failed");
+    }
+    let head: LogChainHead = serde_json::from_str(&signed_head.message)?;
+    if head.member_public_key != signed_head.public_key {
+        bail!("log chain head not signed by its member");
+    }
+    Ok(head)
+}
+
+pub fn verify_log_chain(
+    conn: &TeamDBConnection,
+    member_public_key: &[u8],
+    published_heads: &[LogChainHead],
+) -> Result<LogChainReport> {
+    use sigchain_core::diesel::OptionalExtension;
+
+    let blocks = db::LogBlock::filter_by_member(conn, member_public_key)?;
+    let mut issues = vec![];
+
+    let mut children_by_parent: HashMap<Option<Vec<u8>>, Vec<&db::LogBlock>> = HashMap::new();
+    let hashes: HashSet<&[u8]> = blocks.iter().map(|b| b.hash.as_slice()).collect();
+    let mut published_heads = published_heads.iter()
+        .filter(|h| h.member_public_key == member_public_key && h.team_public_key == conn.team)
+        .cloned()
+        .collect::<Vec<_>>();
+    // Heads on the main chain are authenticated by the main chain block that published them.
+    published_heads.extend(db::PublishedLogChainHead::filter_by_member(conn, member_public_key)?.into_iter()
+        .map(|head| LogChainHead {
+            team_public_key: head.team_public_key,
+            member_public_key: head.member_public_key,
+            last_block_hash: head.last_block_hash,
+            unix_seconds: head.unix_seconds,
+        }));
+
+    for block in &blocks {
+        let signed_block = SignedMessage {
+            public_key: block.member_public_key.clone(),
+            message: block.operation.clone(),
+            signature: block.signature.clone(),
+        };
+        if signed_block.payload_hash() != block.hash {
+            issues.push(LogChainIssue::HashMismatch(block.hash.clone()));
+        }
+        let valid_signature = match (ed25519::Signature::from_slice(&block.signature), ed25519::PublicKey::from_slice(&block.member_public_key)) {
+            (Some(sig), Some(pk)) => ed25519::verify_detached(&sig, block.operation.as_bytes(), &pk),
+            _ => false,
+        };
+        if !valid_signature {
+            issues.push(LogChainIssue::BadSignature(block.hash.clone()));
+        }
+
+        if let Some(ref last_block_hash) = block.last_block_hash {
+            if !hashes.contains(last_block_hash.as_slice()) {
+                issues.push(LogChainIssue::MissingLink {
+                    block_hash: block.hash.clone(),
+                    last_block_hash: last_block_hash.clone(),
+                });
+            }
+        }
+        children_by_parent.entry(block.last_block_hash.clone()).or_insert(vec![]).push(block);
+    }
+
+    for (parent, children) in &children_by_parent {
+        if children.len() < 2 {
+            continue;
+        }
+        let children = children.iter().map(|b| b.hash.clone()).collect();
+        match parent {
+            &None => issues.push(LogChainIssue::MultipleGenesisBlocks(children)),
+            &Some(ref parent) => issues.push(LogChainIssue::Fork { parent: parent.clone(), children }),
+        }
+    }
+
+    // Follow the chain from its genesis block, taking the first child at any fork.
+    let mut reachable = HashSet::new();
+    let mut head: Option<Vec<u8>> = None;
+    while let Some(next) = children_by_parent.get(&head).and_then(|children| children.first()) {
+        if !reachable.insert(next.hash.clone()) {
+            break;
+        }
+        head = Some(next.hash.clone());
+    }
+
+    if !blocks.is_empty() && !children_by_parent.contains_key(&None) {
+        issues.push(LogChainIssue::NoGenesisBlock);
+    }
+
+    let recorded = db::LogChain::find(conn, member_public_key).optional()?.map(|c| c.last_block_hash.clone());
+    if recorded != head {
+        issues.push(LogChainIssue::HeadMismatch { recorded, walked: head.clone() });
+    }
+
+    for published_head in &published_heads {
+        if !reachable.contains(&published_head.last_block_hash) {
+            issues.push(LogChainIssue::PublishedHeadNotFound {
+                last_block_hash: published_head.last_block_hash.clone(),
+                unix_seconds: published_head.unix_seconds,
+            });
+        }
+    }
+
+    Ok(LogChainReport {
+        member_public_key: member_public_key.into(),
+        block_count: blocks.len() as u64,
+        head,
+        published_heads_checked: published_heads.len() as u64,
+        issues,
+    })
+}
+
+/// Verify every log chain of the team against the heads published on the main chain and `published_heads`.
+/// Heads in `published_heads` whose signature does not verify are ignored.
+pub fn verify_team_log_chains(
+    conn: &TeamDBConnection,
+    published_heads: &[SignedMessage],
+) -> Result<Vec<LogChainReport>> {
+    let published_heads = published_heads.iter()
+        .filter_map(|h| verify_published_head(h).map_err(|e| error!("invalid published log chain head: {}", e)).ok())
+        .collect::<Vec<_>>();
+
+    let mut member_public_keys = db::LogBlock::member_public_keys(conn)?;
+    let published_member_public_keys = published_heads.iter()
+        .map(|head| head.member_public_key.clone())
+        .chain(db::PublishedLogChainHead::member_public_keys(conn)?);
+    for member_public_key in published_member_public_keys {
+        if !member_public_keys.contains(&member_public_key) {
+            member_public_keys.push(member_public_key);
+        }
+    }
+
+    member_public_keys.iter()
+        .map(|member_public_key| verify_log_chain(conn, member_public_key, &published_heads))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {DBConnection, Message, Body, TeamPointer};
+    use logging::{LogChain, GenesisLogBlock, LogBlock, LogOperation, EncryptedLog};
+    use crypto::{gen_sign_key_pair, SignKeyPair};
+
+    /// A member's log chain of which the server delivered only the first `stored` blocks.
+    struct Fixture {
+        conn: DBConnection,
+        team_public_key: Vec<u8>,
+        key_pair: SignKeyPair,
+        hashes: Vec<Vec<u8>>,
+    }
+
+    impl Fixture {
+        fn new(length: usize, stored: usize) -> Fixture {
+            let conn = DBConnection::establish("").unwrap();
+            db::run_migrations(&conn).unwrap();
+            db::column_encryption::load_key_for_database(&conn, "").unwrap();
+            let team_public_key = vec![1; 32];
+            let key_pair = gen_sign_key_pair().unwrap();
+
+            let mut hashes: Vec<Vec<u8>> = vec![];
+            for i in 0..length {
+                let payload = match hashes.last() {
+                    None => LogChain::Create(GenesisLogBlock {
+                        team_pointer: TeamPointer::PublicKey(team_public_key.clone()),
+                        wrapped_keys: vec![],
+                    }),
+                    Some(last_block_hash) => LogChain::Append(LogBlock {
+                        last_block_hash: last_block_hash.clone(),
+                        operation: LogOperation::EncryptLog(EncryptedLog { ciphertext: vec![i as u8; 64] }),
+                    }),
+                };
+                let request = SignedMessage::from_message(Message::new(Body::Log(payload.clone())), &key_pair).unwrap();
+                if i < stored {
+                    db::LogBlock::build(&request, &payload, team_public_key.clone()).unwrap().insert(&conn).unwrap();
+                }
+                hashes.push(request.payload_hash());
+            }
+            db::LogChain {
+                team_public_key: team_public_key.clone(),
+                member_public_key: key_pair.public_key_bytes().into(),
+                last_block_hash: hashes[stored - 1].clone(),
+                symmetric_encryption_key: None,
+            }.insert(&conn).unwrap();
+
+            Fixture { conn, team_public_key, key_pair, hashes }
+        }
+
+        fn team_conn(&self) -> TeamDBConnection {
+            TeamDBConnection { conn: &self.conn, team: &self.team_public_key }
+        }
+
+        fn member_public_key(&self) -> &[u8] {
+            self.key_pair.public_key_bytes()
+        }
+
+        /// Publish a head on the main chain as the block at `main_chain_index`.
+        fn publish_head(&self, member_public_key: &[u8], last_block_hash: &[u8], main_chain_index: i64) {
+            db::PublishedLogChainHead {
+                team_public_key: self.team_public_key.clone(),
+                main_chain_block_hash: vec![main_chain_index as u8; 32],
+                member_public_key: member_public_key.into(),
+                last_block_hash: last_block_hash.into(),
+                main_chain_index,
+                unix_seconds: 1525000000 + main_chain_index,
+            }.insert(&self.conn).unwrap();
+        }
+
+        fn verify(&self, published_heads: &[LogChainHead]) -> LogChainReport {
+            verify_log_chain(&self.team_conn(), self.member_public_key(), published_heads).unwrap()
+        }
+    }
+
+    fn missing_heads(report: &LogChainReport) -> Vec<Vec<u8>> {
+        report.issues.iter().filter_map(|issue| match issue {
+            &LogChainIssue::PublishedHeadNotFound { ref last_block_hash, .. } => Some(last_block_hash.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn complete_chain_matches_published_heads() {
+        let fixture = Fixture::new(3, 3);
+        fixture.publish_head(fixture.member_public_key(), &fixture.hashes[1], 4);
+        fixture.publish_head(fixture.member_public_key(), &fixture.hashes[2], 7);
+
+        let report = fixture.verify(&[]);
+        assert!(report.is_intact(), "{:?}", report.issues);
+        assert_eq!(report.block_count, 3);
+        assert_eq!(report.published_heads_checked, 2);
+    }
+
+    #[test]
+    fn truncated_chain_is_detected_from_main_chain_head() {
+        let fixture = Fixture::new(3, 2);
+        fixture.publish_head(fixture.member_public_key(), &fixture.hashes[0], 4);
+        fixture.publish_head(fixture.member_public_key(), &fixture.hashes[2], 7);
+
+        let report = fixture.verify(&[]);
+        assert_eq!(missing_heads(&report), vec![fixture.hashes[2].clone()]);
+        assert_eq!(report.head, Some(fixture.hashes[1].clone()));
+    }
+
+    #[test]
+    fn truncated_chain_is_detected_from_shared_head() {
+        let fixture = Fixture::new(3, 2);
+        let shared_head = LogChainHead {
+            team_public_key: fixture.team_public_key.clone(),
+            member_public_key: fixture.member_public_key().into(),
+            last_block_hash: fixture.hashes[2].clone(),
+            unix_seconds: 1525000000,
+        };
+
+        let report = fixture.verify(&[shared_head]);
+        assert_eq!(missing_heads(&report), vec![fixture.hashes[2].clone()]);
+    }
+
+    #[test]
+    fn heads_of_other_members_are_not_compared() {
+        let fixture = Fixture::new(2, 2);
+        fixture.publish_head(&[9; 32], &fixture.hashes[0], 4);
+        fixture.publish_head(&[9; 32], &[8; 32], 5);
+
+        let report = fixture.verify(&[]);
+        assert!(report.is_intact(), "{:?}", report.issues);
+        assert_eq!(report.published_heads_checked, 0);
+    }
+
+    #[test]
+    fn withheld_chain_is_reported_for_its_member() {
+        let fixture = Fixture::new(2, 2);
+        fixture.publish_head(&[9; 32], &[8; 32], 5);
+
+        let reports = verify_team_log_chains(&fixture.team_conn(), &[]).unwrap();
+        let withheld = reports.iter().find(|r| r.member_public_key == vec![9; 32]).unwrap();
+        assert_eq!(withheld.block_count, 0);
+        assert_eq!(missing_heads(withheld), vec![vec![8; 32]]);
+        assert!(reports.iter().find(|r| r.member_public_key == fixture.member_public_key()).unwrap().is_intact());
+    }
+}
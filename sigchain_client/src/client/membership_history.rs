@@ -0,0 +1,122 @@
+/// Replay the main chain into per-member timelines of joining, leaving and admin changes, so that
+/// past actions can be judged against the member's status at the time.
+
+use serde_json;
+
+use Result;
+use db::{self, TeamDBConnection};
+use protocol::{Message, Body, MainChain};
+use team::Operation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MembershipChange {
+    Joined,
+    /// Removed by an admin or left the team.
+    Removed,
+    Promoted,
+    Demoted,
+}
+
+#[derive(Debug, Clone)]
+pub struct MembershipEvent {
+    pub change: MembershipChange,
+    pub unix_seconds: i64,
+    pub block_hash: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemberHistory {
+    pub public_key: Vec<u8>,
+    pub email: String,
+    /// In chain order.
+    pub events: Vec<MembershipEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemberStatus {
+    pub is_member: bool,
+    pub is_admin: bool,
+}
+
+impl MemberHistory {
+    pub fn status_at(&self, unix_seconds: i64) -> MemberStatus {
+        let mut status = MemberStatus::default();
+        for event in self.events.iter().take_while(|e| e.unix_seconds <= unix_seconds) {
+            match event.change {
+                MembershipChange::Joined => status = MemberStatus { is_member: true, is_admin: status.is_admin },
+                MembershipChange::Removed => status = MemberStatus::default(),
+                MembershipChange::Promoted => status.is_admin = true,
+                MembershipChange::Demoted => status.is_admin = false,
+            }
+        }
+        status
+    }
+
+    /// The most recent removal at or before `unix_seconds` that the member has not rejoined since.
+    pub fn removal_before(&self, unix_seconds: i64) -> Option<&MembershipEvent> {
+        match self.events.iter().take_while(|e| e.unix_seconds <= unix_seconds)
+            .filter(|e| e.change == MembershipChange::Joined || e.change == MembershipChange::Removed)
+            .last() {
+            Some(event) if event.change == MembershipChange::Removed => Some(event),
+            _ => None,
+        }
+    }
+}
+
+/// Timelines for every member that has ever joined the team.
+pub fn membership_history(conn: &TeamDBConnection) -> Result<Vec<MemberHistory>> {
+    let mut histories: Vec<MemberHistory> = vec![];
+    {
+        let mut record = |public_key: &[u8], change: MembershipChange, unix_seconds: i64, block_hash: &[u8]| -> Result<()> {
+            let index = match histories.iter().position(|h| h.public_key == public_key) {
+                Some(index) => index,
+                None => {
+                    histories.push(MemberHistory {
+                        public_key: public_key.into(),
+                        email: db::Identity::find(conn, public_key)?.email,
+                        events: vec![],
+                    });
+                    histories.len() - 1
+                }
+            };
+            histories[index].events.push(MembershipEvent { change, unix_seconds, block_hash: block_hash.into() });
+            Ok(())
+        };
+
+        for block in db::Block::collect_all(conn)? {
+            let message: Message = serde_json::from_str(&block.operation)?;
+            let unix_seconds = message.header.utc_time;
+            let main_chain = match message.body {
+                Body::Main(main_chain) => main_chain,
+                _ => continue,
+            };
+            match main_chain {
+                MainChain::Create(genesis) => {
+                    let creator = &genesis.creator_identity.public_key;
+                    record(creator, MembershipChange::Joined, unix_seconds, &block.hash)?;
+                    record(creator, MembershipChange::Promoted, unix_seconds, &block.hash)?;
+                }
+                MainChain::Append(append) => match append.operation {
+                    Operation::AcceptInvite(ref identity) => {
+                        record(&identity.public_key, MembershipChange::Joined, unix_seconds, &block.hash)?;
+                    }
+                    Operation::Remove(ref public_key) => {
+                        record(public_key, MembershipChange::Removed, unix_seconds, &block.hash)?;
+                    }
+                    Operation::Leave(_) => {
+                        record(&block.member_public_key, MembershipChange::Removed, unix_seconds, &block.hash)?;
+                    }
+                    Operation::Promote(ref public_key) => {
+                        record(public_key, MembershipChange::Promoted, unix_seconds, &block.hash)?;
+                    }
+                    Operation::Demote(ref public_key) => {
+                        record(public_key, MembershipChange::Demoted, unix_seconds, &block.hash)?;
+                    }
+                    _ => {}
+                },
+                MainChain::Read(_) => {}
+            }
+        }
+    }
+    Ok(histories)
+}
@@ -15,6 +15,7 @@ use std;
 
 use errors::{Result, Error};
 use super::crypto::*;
+use secret::{self, SecretBytes};
 use serde;
 
 #[cfg(feature = "network_client")]
@@ -35,14 +36,38 @@ pub use self::android_client::*;
 mod test_client;
 pub use self::test_client::*;
 
+#[cfg(test)]
+mod queued_log_tests;
+#[cfg(test)]
+mod log_failure_tests;
+
 pub mod traits;
 
 pub mod format_blocks;
 
+pub mod log_integrity;
+
+pub mod redaction;
+
+pub mod alerts;
+
+pub mod anomalies;
+
+pub mod host_key_verification;
+
+pub mod membership_history;
+
+pub mod git_audit;
+
+pub mod git_verify;
+
+pub mod keyring_export;
+
 pub mod verify;
-use self::verify::{verify_and_process_request, team_pointer_to_public_key};
+use self::verify::{verify_and_process_request, verify_and_process_replayed_request, team_pointer_to_public_key};
 
 use protocol::*;
+use sigchain_core::dashboard_protocol::SignatureVerification;
 
 pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
     fn read_next_block(&self) -> Result<ReadBlocksResponse> {
@@ -108,13 +133,35 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
         })
     }
 
+    fn record_log_processing_failure(
+        &self,
+        conn: &db::TeamDBConnection,
+        logger_identity_public_key: &[u8],
+        block_hash: &[u8],
+        failure_type: db::LogFailureType,
+        error: &str,
+    ) -> Result<()> {
+        error!("failed to process log block ({}): {}", failure_type.as_str(), error);
+        db::LogProcessingFailure::upsert(conn.conn, &db::NewLogProcessingFailure {
+            team_public_key: self.team_pk().into(),
+            member_public_key: logger_identity_public_key.into(),
+            block_hash: block_hash.into(),
+            failure_type: failure_type.as_str().into(),
+            error: error.into(),
+            unix_seconds: time::get_time().sec,
+        })?;
+        Ok(())
+    }
+
     fn process_wrapped_key(
         &self,
         conn: &db::TeamDBConnection,
         logger_identity_public_key: &[u8],
+        block_hash: &[u8],
         wrapped_key: &logging::WrappedKey,
     ) -> Result<()> {
         if wrapped_key.recipient_public_key == self.get_encryption_public_key(&self.identity_pk())? {
+            let first_key = db::LogChain::find(conn, logger_identity_public_key)?.symmetric_encryption_key.is_none();
             match self.unwrap_log_encryption_key(
                 &BoxedMessage{
                     recipient_public_key: wrapped_key.recipient_public_key.clone(),
@@ -126,12 +173,19 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
                     db::LogChain::update_symmetric_encryption_key(
                         conn,
                         logger_identity_public_key,
-                        Some(symmetric_encryption_key),
+                        Some(&symmetric_encryption_key[..]),
                     )?;
+                    // Blocks before the first key wrapped for us are not gaps, we were not meant to read them.
+                    if first_key {
+                        db::LogProcessingFailure::mark_before_first_key(conn, logger_identity_public_key)?;
+                    }
                 }
                 Err(e) => {
-                    // Ignore encryption/encoding errors caused by member sending malformed wrapped key.
-                    error!("failed to unwrap log encryption key {:?}", e);
+                    // Don't fail the block on a malformed wrapped key sent by a member, but keep a record of it.
+                    self.record_log_processing_failure(
+                        conn, logger_identity_public_key, block_hash,
+                        db::LogFailureType::BadWrappedKey, &format!("{}", e),
+                    )?;
                 }
             }
         }
@@ -143,27 +197,99 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
         &self,
         conn: &db::TeamDBConnection,
         logger_identity_public_key: &[u8],
+        block_hash: &[u8],
         encrypted_log: EncryptedLog
     ) -> Result<()> {
+        use db::LogFailureType::*;
+
+        let symmetric_key = match db::LogChain::find(conn, logger_identity_public_key)?.symmetric_encryption_key.take() {
+            Some(symmetric_key) => SecretBytes::new(symmetric_key),
+            None => {
+                return self.record_log_processing_failure(
+                    conn, logger_identity_public_key, block_hash,
+                    MissingKey, "no log encryption key for member",
+                );
+            }
+        };
 
-        if let Some(symmetric_key) = db::LogChain::find(conn, logger_identity_public_key)?.symmetric_encryption_key {
-            let result = || -> Result<(Log, String)> {
-                let plaintext_log = secretbox::decrypt(symmetric_key, encrypted_log.ciphertext)?;
-                let log_json = std::str::from_utf8(&plaintext_log)?;
-                let log: Log = serde_json::from_str(log_json)?;
-                Ok((log, log_json.into()))
-            }();
+        let plaintext_log = match secretbox::decrypt(&symmetric_key, &encrypted_log.ciphertext) {
+            Ok(plaintext_log) => plaintext_log,
+            Err(e) => {
+                return self.record_log_processing_failure(
+                    conn, logger_identity_public_key, block_hash,
+                    BadCiphertext, &format!("{}", e),
+                );
+            }
+        };
 
-            if let Ok((log, log_json)) = result {
+        let result = || -> Result<(Log, String)> {
+            let log_json = std::str::from_utf8(&plaintext_log)?;
+            let log: Log = serde_json::from_str(log_json)?;
+            Ok((log, log_json.into()))
+        }();
+
+        match result {
+            Ok((log, log_json)) => {
+                // The log is checked at the later of its own block's position and the main chain
+                // block it names, so a member cannot move it before their earlier logs.
+                let referenced_index = match log.main_chain_block_hash {
+                    Some(ref main_chain_block_hash) => db::BlockPosition::find(conn, main_chain_block_hash)?,
+                    None => None,
+                };
+                let main_chain_index = db::BlockPosition::find(conn, block_hash)?.into_iter()
+                    .chain(referenced_index)
+                    .max();
+                if let Some(main_chain_index) = main_chain_index {
+                    db::BlockPosition::set(conn, block_hash, main_chain_index)?;
+                }
+                // Logs without a position are checked against the current keys and pins.
+                let main_chain_index = main_chain_index.unwrap_or(i64::max_value());
+
+                let (identity, device_revoked) = match db::MemberDevice::find(conn, logger_identity_public_key).optional()? {
+                    // Logs from a member's device are signed with the device's SSH key and attributed to the member.
+                    Some(device) => {
+                        let mut identity = db::Identity::find(conn, &device.member_public_key)?.into_identity();
+                        identity.ssh_public_key = device.ssh_public_key;
+                        (identity, device.revoked)
+                    }
+                    // Check the signature against the keys the member had when the log was written.
+                    None => (db::Identity::find(conn, logger_identity_public_key)?
+                        .with_keys_at_main_chain_index(conn, main_chain_index)?
+                        .into_identity(), false),
+                };
+                let mut verification = signature_verification::verify_log_signature(&identity, &log);
+                if verification == SignatureVerification::Invalid {
+                    warn!("invalid signature in log from {}: {}", identity.email, log.body.log_description());
+                } else if device_revoked {
+                    warn!("log from a revoked device of {}: {}", identity.email, log.body.log_description());
+                    verification = SignatureVerification::RevokedDevice;
+                }
+                let host_verification = host_key_verification::verify_host_authorization(conn, &log, main_chain_index)?;
+                // Anomaly detection must not hold up the block, it only adds to what is recorded.
+                if let Err(e) = anomalies::backfill_baseline(conn, &identity.public_key) {
+                    error!("failed to backfill anomaly baseline for {}: {}", identity.email, e);
+                }
+                let received_unix_seconds = time::get_time().sec;
                 db::Log {
                     team_public_key: self.team_pk().into(),
-                    member_public_key: logger_identity_public_key.to_vec(),
+                    member_public_key: identity.public_key.clone(),
                     log_json: log_json.to_string(),
                     unix_seconds: log.unix_seconds as i64,
+                    signature_verification: Some(verification.as_str().into()),
+                    host_key_verification: host_verification.map(|v| v.as_str().into()),
+                    received_unix_seconds,
                 }.insert(conn.conn)?;
-            } else {
-                // Ignore encryption/encoding errors caused by member sending malformed log.
-                error!("failed to process encrypted log {:?}", result);
+                if let Err(e) = anomalies::score_and_record(conn, &identity.public_key, &log, &log_json) {
+                    error!("failed to score log from {} for anomalies: {}", identity.email, e);
+                }
+                alerts::evaluate_alert_rules(conn, &identity.public_key, &log, &log_json, received_unix_seconds)?;
+            }
+            Err(e) => {
+                // Don't fail the block on a malformed log sent by a member, but keep a record of it.
+                self.record_log_processing_failure(
+                    conn, logger_identity_public_key, block_hash,
+                    MalformedLog, &format!("{}", e),
+                )?;
             }
         }
 
@@ -225,7 +351,7 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
             },
         };
 
-        verify_and_process_request(conn.conn, &block)?;
+        verify_and_process_replayed_request(conn.conn, &block)?;
 
         // Client POST-processing.
         match &unverified_message.body {
@@ -245,7 +371,7 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
                 match log_chain {
                     &Create(ref create_log_chain) => {
                         for wrapped_key in &create_log_chain.wrapped_keys {
-                            self.process_wrapped_key(conn, &block.public_key, wrapped_key)?;
+                            self.process_wrapped_key(conn, &block.public_key, &block.payload_hash(), wrapped_key)?;
                         }
                     }
                     &Append(ref append_log_block) => {
@@ -253,7 +379,7 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
                         match &append_log_block.operation {
                             &AddWrappedKeys(ref new_wrapped_keys) => {
                                 for wrapped_key in new_wrapped_keys {
-                                    self.process_wrapped_key(conn, &block.public_key, wrapped_key)?;
+                                    self.process_wrapped_key(conn, &block.public_key, &block.payload_hash(), wrapped_key)?;
                                 }
                                 if *block.public_key == *self.identity_pk() {
                                     db::CurrentWrappedKey::add(conn.conn, &new_wrapped_keys.iter()
@@ -269,7 +395,7 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
                                 )?;
 
                                 for wrapped_key in rotated_keys {
-                                    self.process_wrapped_key(conn, &block.public_key, wrapped_key)?;
+                                    self.process_wrapped_key(conn, &block.public_key, &block.payload_hash(), wrapped_key)?;
                                 }
 
                                 if *block.public_key == *self.identity_pk() {
@@ -279,7 +405,7 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
                                 }
                             },
                             &EncryptLog(ref encrypted_log) => {
-                                self.process_encrypted_log(conn, &block.public_key, encrypted_log.clone())?;
+                                self.process_encrypted_log(conn, &block.public_key, &block.payload_hash(), encrypted_log.clone())?;
                             },
                         }
                     }
@@ -322,6 +448,9 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
                 Ok(())
             })?;
         }
+        if let Err(e) = alerts::deliver_pending_alerts(&self.team_db_conn()) {
+            error!("error delivering alerts: {}", e);
+        }
         Ok(())
     }
 
@@ -343,9 +472,23 @@ pub trait Client: traits::DBConnect + traits::Broadcast + traits::Identify {
     fn add_admin_pk(&self, public_key: &[u8]) -> Result<()>;
     fn remove_admin_pk(&self, public_key: &[u8]) -> Result<()>;
 
+    /// Attest that the member's safety number, as computed from the local chain, was compared
+    /// with the member in person.
+    fn verify_member(&self, identity_email: &str) -> Result<()>;
+    fn verify_member_pk(&self, public_key: &[u8], safety_number: &str) -> Result<()>;
+
+    /// Replace this member's SSH and/or PGP public key.
+    fn update_keys(&self, key_update: team::KeyUpdate) -> Result<()>;
+
+    /// Authorize another device to act for this member. `device` must be signed by the new
+    /// device's keys, see `team::Device::authorize`.
+    fn add_device(&self, device: team::Device) -> Result<()>;
+    /// Revoke one of this member's devices or, as an admin, any member's device.
+    fn remove_device(&self, device_public_key: &[u8]) -> Result<()>;
+
     fn enable_logging(&self) -> Result<()>;
     fn disable_logging(&self) -> Result<()>;
-    fn unwrap_log_encryption_key(&self, wrapped_key: &logging::BoxedMessage) -> Result<Vec<u8>>;
+    fn unwrap_log_encryption_key(&self, wrapped_key: &logging::BoxedMessage) -> Result<SecretBytes>;
 
     fn request_billing_info(&self) -> Result<billing::BillingInfo>;
 }
@@ -443,30 +586,31 @@ pub trait OwnedKeyPair: traits::DBConnect + traits::Broadcast + traits::Identify
             db::CurrentWrappedKey::all(conn)?.into_iter().map(|k| k.destination_public_key)
         );
 
-        let expected_log_destination_pks = HashSet::<Vec<u8>>::from_iter(
+        let mut expected_log_destination_pks = HashSet::<Vec<u8>>::from_iter(
             self.get_admins()?.into_iter()
                 .map(|i| i.encryption_public_key)
                 .chain(Some(self.box_public_key().0.to_vec()))
         );
-
-        use logging::{PlaintextBody};
+        // Admins read logs on any of their devices.
+        for admin_public_key in db::TeamMembership::filter_admin_public_keys(team_conn)? {
+            for device in self.get_signing_devices(&admin_public_key)? {
+                expected_log_destination_pks.insert(device.encryption_public_key);
+            }
+        }
 
         let new_wrapped_keys = {
             if current_log_destination_pks == expected_log_destination_pks {
                 None
             } else if (&current_log_destination_pks - &expected_log_destination_pks).is_empty() {
                 //  noone removed
-                let symmetric_key = db::LogChain::find(team_conn, self.identity_pk())?.symmetric_encryption_key.unwrap_or(secretbox::gen());
+                let symmetric_key = db::LogChain::find(team_conn, self.identity_pk())?.symmetric_encryption_key.take()
+                    .map(SecretBytes::new)
+                    .unwrap_or_else(secretbox::gen);
                 Some(AddWrappedKeys(
                     (&expected_log_destination_pks - &current_log_destination_pks).iter().filter_map(|box_pk|{
                         PublicKey::from_slice(box_pk)
                     }).map(|box_pk| {
-                        let body = serde_json::to_vec(&PlaintextBody::LogEncryptionKey(symmetric_key.clone()))
-                            .map_err(|e| { error!("{:?}", e); e })?;
-                        Ok(WrappedKey {
-                            ciphertext: box_::seal(&body, self.box_secret_key(), &box_pk)?,
-                            recipient_public_key: box_pk.0.to_vec(),
-                        })
+                        self.wrap_log_encryption_key(&symmetric_key, &box_pk)
                     }).filter_map(Result::ok).collect::<Vec<_>>()
                 ))
             } else {
@@ -476,41 +620,49 @@ pub trait OwnedKeyPair: traits::DBConnect + traits::Broadcast + traits::Identify
                     expected_log_destination_pks.iter().filter_map(|box_pk|{
                         PublicKey::from_slice(box_pk)
                     }).map(|box_pk| {
-                        let body = serde_json::to_vec(&PlaintextBody::LogEncryptionKey(symmetric_key.clone()))
-                            .map_err(|e| { error!("{:?}", e); e })?;
-                        Ok(WrappedKey {
-                            ciphertext: box_::seal(&body, self.box_secret_key(), &box_pk)?,
-                            recipient_public_key: box_pk.0.to_vec(),
-                        })
+                        self.wrap_log_encryption_key(&symmetric_key, &box_pk)
                     }).filter_map(Result::ok).collect::<Vec<_>>()
                 ))
             }
         };
 
         if let Some(new_wrapped_keys) = new_wrapped_keys {
+            let last_block_hash = db::LogChain::find(team_conn, self.identity_pk())?.last_block_hash.clone();
             let wrapped_key_block = Log(Append(LogBlock{
-                last_block_hash: db::LogChain::find(team_conn, self.identity_pk())?.last_block_hash,
+                last_block_hash,
                 operation: new_wrapped_keys,
             }));
             self.sign_commit_send::<E>(&Endpoint::Sigchain, &wrapped_key_block)?;
         }
         Ok(())
     }
-    fn encrypt_log(&self, log: logs::Log) -> Result<()> {
-        use protocol::Body::Log;
-        use logging::{LogBlock};
-        use logging::LogOperation::*;
-        use logging::LogChain::*;
-
+    fn wrap_log_encryption_key(&self, symmetric_key: &[u8], recipient_public_key: &box_::ed25519_box::PublicKey) -> Result<WrappedKey> {
+        let mut body = serde_json::to_vec(&logging::PlaintextBody::LogEncryptionKey(SecretBytes::new(symmetric_key.to_vec())))
+            .map_err(|e| { error!("failed to serialize log encryption key: {}", e); e })?;
+        let ciphertext = box_::seal(&body, self.box_secret_key(), recipient_public_key);
+        // The serialized body holds the key in plaintext.
+        secret::zeroize(&mut body);
+        Ok(WrappedKey {
+            ciphertext: ciphertext?,
+            recipient_public_key: recipient_public_key.0.to_vec(),
+        })
+    }
+    fn encrypt_log(&self, mut log: logs::Log) -> Result<()> where Self: Sized {
         let conn = self.db_conn();
-        let team_conn = &db::TeamDBConnection{conn, team: self.team_pk()};
 
         if !self.is_command_encrypted_logging_enabled()? {
             return Ok(())
         }
 
+        // Admins check the log against the keys and pins in effect at this block.
+        log.main_chain_block_hash = self.get_last_block_hash()?;
+        let log = redaction::redact_log(log, &self.get_log_redaction_rules()?, &self.box_secret_key().0);
+
         conn.transaction::<_, Error, _>(|| {
-            db::QueuedLog::add(conn, &db::NewQueuedLog{log_json: serde_json::to_vec(&log)?})?;
+            db::QueuedLog::add(conn, &db::NewQueuedLog{
+                log_json: serde_json::to_vec(&log)?,
+                created_unix_seconds: time::get_time().sec,
+            })?;
             Ok(())
         })?;
 
@@ -522,26 +674,217 @@ pub trait OwnedKeyPair: traits::DBConnect + traits::Broadcast + traits::Identify
             self.wrap_keys_if_admins_changed()
         })?;
 
-        while db::QueuedLog::any(conn)? {
-            conn.transaction::<_, Error, _>(|| {
-                let log = db::QueuedLog::next(conn)?;
-                let log_chain = db::LogChain::find(team_conn, self.identity_pk())?;
-                let encrypted_log = Log(Append(LogBlock{
-                    last_block_hash: log_chain.last_block_hash,
-                    operation: EncryptLog(EncryptedLog{
-                        ciphertext: secretbox::encrypt(
-                            &log.log_json,
-                            &log_chain.symmetric_encryption_key.ok_or("no symmetric key")?,
-                        )?,
-                    }),
-                }));
-                self.sign_commit_send::<E>(&Endpoint::Sigchain, &encrypted_log)?;
-                log.remove(conn)?;
-                Ok(())
-            })?;
+        self.send_queued_logs()?;
+
+        // The logs are sent, so only report a failure to publish the head.
+        if let Err(e) = self.publish_log_chain_head_if_due() {
+            error!("failed to publish log chain head: {}", e);
         }
         Ok(())
     }
+
+    /// Publish the head of this member's log chain on the main chain if it moved since the last
+    /// publication and that was at least `LOG_CHAIN_HEAD_PUBLISH_INTERVAL_SECONDS` ago. Admins
+    /// check the log chains they fetch against it, see `log_integrity::verify_log_chain`.
+    fn publish_log_chain_head_if_due(&self) -> Result<()> {
+        let team_conn = &self.team_db_conn();
+        let last_block_hash = match db::LogChain::find(team_conn, self.identity_pk()).optional()? {
+            Some(log_chain) => log_chain.last_block_hash.clone(),
+            None => return Ok(()),
+        };
+        if let Some(published) = db::PublishedLogChainHead::latest_for_member(team_conn, self.identity_pk())? {
+            let due = time::get_time().sec >= published.unix_seconds.saturating_add(LOG_CHAIN_HEAD_PUBLISH_INTERVAL_SECONDS);
+            if published.last_block_hash == last_block_hash || !due {
+                return Ok(());
+            }
+        }
+        let request = self.create_request(Operation::PublishLogChainHead(team::PublishedLogChainHead {
+            last_block_hash,
+        }))?;
+        self.sign_commit_send::<E>(&Endpoint::Sigchain, &request)?;
+        Ok(())
+    }
+
+    /// Send queued logs in order, making at most one attempt at each without blocking. A log that
+    /// failed before is only retried once its backoff delay has passed, so logging does not wait
+    /// on an unreachable server. Failures are handled as classified by `classify_send_failure`.
+    fn send_queued_logs(&self) -> Result<()> where Self: Sized {
+        self.send_queued_logs_with_retry(None)
+    }
+
+    /// Send queued logs in order, retrying each with exponential backoff for up to
+    /// `QUEUED_LOG_RETRY_SECONDS`. This blocks, so it is for explicit flushes rather than logging.
+    fn flush_queued_logs(&self) -> Result<()> where Self: Sized {
+        use std::time::Duration;
+        self.send_queued_logs_with_retry(Some(Duration::from_secs(QUEUED_LOG_RETRY_SECONDS)))
+    }
+
+    fn send_queued_logs_with_retry(&self, retry_for: Option<::std::time::Duration>) -> Result<()> where Self: Sized {
+        use protocol::Body::Log;
+        use logging::{LogBlock};
+        use logging::LogOperation::*;
+        use logging::LogChain::*;
+        use backoff::{self, ExponentialBackoff, Operation};
+
+        let conn = self.db_conn();
+        let team_conn = &db::TeamDBConnection{conn, team: self.team_pk()};
+
+        while let Some(queued_log) = db::QueuedLog::next(conn).optional()? {
+            if let Err(e) = serde_json::from_slice::<logs::Log>(&queued_log.log_json) {
+                error!("dead-lettering malformed queued log {}: {}", queued_log.id, e);
+                queued_log.dead_letter(conn, &format!("malformed log: {}", e), time::get_time().sec)?;
+                continue;
+            }
+            if retry_for.is_none() && !queued_log_is_due(&queued_log, time::get_time().sec) {
+                return Ok(());
+            }
+
+            let mut send_queued_log = || -> std::result::Result<(), backoff::Error<Error>> {
+                conn.transaction::<_, Error, _>(|| {
+                    let mut log_chain = db::LogChain::find(team_conn, self.identity_pk())?;
+                    let symmetric_key = SecretBytes::new(log_chain.symmetric_encryption_key.take().ok_or("no symmetric key")?);
+                    let encrypted_log = Log(Append(LogBlock{
+                        last_block_hash: log_chain.last_block_hash.clone(),
+                        operation: EncryptLog(EncryptedLog{
+                            ciphertext: secretbox::encrypt(
+                                &queued_log.log_json,
+                                &symmetric_key,
+                            )?,
+                        }),
+                    }));
+                    self.sign_commit_send::<E>(&Endpoint::Sigchain, &encrypted_log)?;
+                    queued_log.remove(conn)?;
+                    Ok(())
+                }).map_err(|e| {
+                    warn!("failed to send queued log {}: {}", queued_log.id, e);
+                    match classify_send_failure(&e) {
+                        SendFailure::Permanent => backoff::Error::Permanent(e),
+                        SendFailure::ChainConflict => {
+                            // Append to the server's head on the next attempt.
+                            if let Err(update_error) = self.update_my_log_blocks() {
+                                error!("failed to catch up log chain: {}", update_error);
+                            }
+                            backoff::Error::Transient(e)
+                        }
+                        SendFailure::Transient | SendFailure::Unknown => backoff::Error::Transient(e),
+                    }
+                })
+            };
+
+            let sent = match retry_for {
+                Some(retry_for) => send_queued_log.retry(&mut ExponentialBackoff{
+                    max_elapsed_time: Some(retry_for),
+                    ..ExponentialBackoff::default()
+                }),
+                // Once caught up, a chain conflict can be retried right away.
+                None => send_queued_log().or_else(|e| match e {
+                    backoff::Error::Transient(ref e) if classify_send_failure(e) == SendFailure::ChainConflict => send_queued_log(),
+                    e => Err(e),
+                }),
+            };
+
+            if let Err(e) = sent {
+                let e = match e {
+                    backoff::Error::Permanent(e) | backoff::Error::Transient(e) => e,
+                };
+                let now = time::get_time().sec;
+                let failed_log = db::QueuedLog{ attempts: queued_log.attempts + 1, ..queued_log.clone() };
+                match classify_send_failure(&e) {
+                    SendFailure::Permanent => {
+                        error!("dead-lettering queued log {} rejected by the server: {}", failed_log.id, e);
+                        failed_log.dead_letter(conn, &format!("{}", e), now)?;
+                        continue;
+                    }
+                    SendFailure::ChainConflict | SendFailure::Unknown if failed_log.attempts >= MAX_QUEUED_LOG_ATTEMPTS => {
+                        error!("dead-lettering queued log {} after {} attempts: {}", failed_log.id, failed_log.attempts, e);
+                        failed_log.dead_letter(conn, &format!("{}", e), now)?;
+                    }
+                    SendFailure::Transient | SendFailure::ChainConflict | SendFailure::Unknown => {
+                        queued_log.record_failure(conn, &format!("{}", e), now)?;
+                    }
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sign the current head of this member's log chain so it can be shared with admins and checked
+    /// by `log_integrity::verify_team_log_chains`.
+    fn sign_log_chain_head(&self) -> Result<SignedMessage> {
+        let conn = &self.team_db_conn();
+        let last_block_hash = db::LogChain::find(conn, self.identity_pk())?.last_block_hash.clone();
+        let head = logging::LogChainHead {
+            team_public_key: self.team_pk().into(),
+            member_public_key: self.identity_pk().into(),
+            last_block_hash,
+            unix_seconds: time::get_time().sec,
+        };
+        let head_json = serde_json::to_string(&head)?;
+        let sig = ed25519::sign_detached(&logging::LogChainHead::signed_data(&head_json), &self.sign_key_pair().secret_key);
+        Ok(SignedMessage {
+            public_key: self.identity_pk().into(),
+            message: head_json,
+            signature: sig.0.to_vec(),
+        })
+    }
+}
+
+/// Number of times a log may fail to send for an unclassified reason before it is dead-lettered.
+pub const MAX_QUEUED_LOG_ATTEMPTS: i64 = 10;
+/// Time spent retrying a single queued log within one `flush_queued_logs`.
+pub const QUEUED_LOG_RETRY_SECONDS: u64 = 30;
+/// Longest wait between attempts to send a queued log from `send_queued_logs`.
+pub const MAX_QUEUED_LOG_RETRY_DELAY_SECONDS: i64 = 60 * 60;
+/// Shortest time between publications of a member's log chain head on the main chain. A server can
+/// withhold at most the logs written since the last publication without admins noticing.
+pub const LOG_CHAIN_HEAD_PUBLISH_INTERVAL_SECONDS: i64 = 24 * 60 * 60;
+
+/// How a failure to send a queued log is handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendFailure {
+    /// Network errors and 5xx, 408 and 429 responses. Retried until the server is reachable, never
+    /// dead-lettered.
+    Transient,
+    /// The server's log chain is ahead of this client's, for example because the client crashed
+    /// before saving a block the server accepted. Retried after catching up with
+    /// `update_my_log_blocks`, and dead-lettered after `MAX_QUEUED_LOG_ATTEMPTS` attempts.
+    ChainConflict,
+    /// 400 and 403 responses, where the server rejected the log itself. Dead-lettered right away.
+    Permanent,
+    /// Anything else, including unreadable responses. Dead-lettered after `MAX_QUEUED_LOG_ATTEMPTS`
+    /// attempts.
+    Unknown,
+}
+
+pub fn classify_send_failure(e: &Error) -> SendFailure {
+    use errors::ErrorKind::*;
+    use errors::specified;
+    match e.kind() {
+        &HTTPStatus(409, _) => SendFailure::ChainConflict,
+        &HTTPStatus(_, ref message) if message.contains(NOT_APPENDING_TO_MAIN_CHAIN) => SendFailure::ChainConflict,
+        &Specified(specified::ErrorKind::NotAppendingToMainChain) => SendFailure::ChainConflict,
+        &HTTPStatus(status, _) if status >= 500 => SendFailure::Transient,
+        &HTTPStatus(408, _) | &HTTPStatus(429, _) => SendFailure::Transient,
+        &HTTPStatus(400, _) | &HTTPStatus(403, _) => SendFailure::Permanent,
+        &Io(_) => SendFailure::Transient,
+        #[cfg(feature = "reqwest")]
+        &HTTP(_) => SendFailure::Transient,
+        #[cfg(feature = "network_client")]
+        &Hyper(_) => SendFailure::Transient,
+        _ => SendFailure::Unknown,
+    }
+}
+
+/// How the server describes `specified::ErrorKind::NotAppendingToMainChain` in an error response.
+const NOT_APPENDING_TO_MAIN_CHAIN: &str = "NotAppendingToMainChain";
+
+/// Whether the backoff delay since the last failed attempt to send `queued_log` has passed.
+fn queued_log_is_due(queued_log: &db::QueuedLog, now: i64) -> bool {
+    let delay = 1i64.checked_shl(queued_log.attempts.max(0).min(32) as u32)
+        .unwrap_or(MAX_QUEUED_LOG_RETRY_DELAY_SECONDS)
+        .min(MAX_QUEUED_LOG_RETRY_DELAY_SECONDS);
+    queued_log.last_attempt_unix_seconds.map_or(true, |last_attempt| now >= last_attempt.saturating_add(delay))
 }
 
 impl <T: traits::DBConnect + traits::Broadcast + OwnedKeyPair> Client for T {
@@ -719,6 +1062,35 @@ impl <T: traits::DBConnect + traits::Broadcast + OwnedKeyPair> Client for T {
         self.sign_commit_send::<E>(&Endpoint::Sigchain, &request)?;
         Ok(())
     }
+    fn verify_member(&self, identity_email: &str) -> Result<()> {
+        let matching_public_key = self.get_active_member_by_email(identity_email)?.public_key;
+        let safety_number = self.get_safety_number(&matching_public_key)?;
+        self.verify_member_pk(&matching_public_key, &safety_number)
+    }
+    fn verify_member_pk(&self, public_key: &[u8], safety_number: &str) -> Result<()> {
+        let op = Operation::VerifyMember(team::MemberVerification {
+            public_key: public_key.into(),
+            safety_number: safety_number.into(),
+        });
+        let request = self.create_request(op)?;
+        self.sign_commit_send::<E>(&Endpoint::Sigchain, &request)?;
+        Ok(())
+    }
+    fn update_keys(&self, key_update: team::KeyUpdate) -> Result<()> {
+        let request = self.create_request(Operation::UpdateKeys(key_update))?;
+        self.sign_commit_send::<E>(&Endpoint::Sigchain, &request)?;
+        Ok(())
+    }
+    fn add_device(&self, device: team::Device) -> Result<()> {
+        let request = self.create_request(Operation::AddDevice(device))?;
+        self.sign_commit_send::<E>(&Endpoint::Sigchain, &request)?;
+        Ok(())
+    }
+    fn remove_device(&self, device_public_key: &[u8]) -> Result<()> {
+        let request = self.create_request(Operation::RemoveDevice(device_public_key.into()))?;
+        self.sign_commit_send::<E>(&Endpoint::Sigchain, &request)?;
+        Ok(())
+    }
     fn enable_logging(&self) -> Result<()> {
         use LoggingEndpoint::*;
         let op = Operation::AddLoggingEndpoint(CommandEncrypted(E{}));
@@ -735,19 +1107,21 @@ impl <T: traits::DBConnect + traits::Broadcast + OwnedKeyPair> Client for T {
         self.sign_commit_send::<team::E>(&Endpoint::Sigchain, &request)?;
         Ok(())
     }
-    fn unwrap_log_encryption_key(&self, wrapped_key: &logging::BoxedMessage) -> Result<Vec<u8>> {
+    fn unwrap_log_encryption_key(&self, wrapped_key: &logging::BoxedMessage) -> Result<SecretBytes> {
         let conn = &db::TeamDBConnection{team: self.team_pk(), conn: self.db_conn()};
 
-        // Verify sender is or used to be on team
+        // Verify sender is or used to be on team, or is one of a member's devices
         let identities = db::Identity::filter_by_encryption_public_key(
             conn, &wrapped_key.sender_public_key)?;
+        let devices = db::MemberDevice::filter_by_encryption_public_key(
+            conn, &wrapped_key.sender_public_key)?;
 
-        if identities.len() == 0 {
+        if identities.len() == 0 && devices.len() == 0 {
             bail!("No identity with given encryption public key")
         }
 
         use crypto;
-        let plaintext = crypto::box_::open(&wrapped_key.ciphertext, &wrapped_key.sender_public_key, self.box_secret_key())?;
+        let plaintext = SecretBytes::new(crypto::box_::open(&wrapped_key.ciphertext, &wrapped_key.sender_public_key, self.box_secret_key())?);
         let plaintext_body : logging::PlaintextBody = serde_json::from_slice(&plaintext)?;
         match plaintext_body {
             logging::PlaintextBody::LogEncryptionKey(symmetric_key) =>  Ok(symmetric_key),
@@ -2,7 +2,7 @@ extern crate reqwest;
 use super::*;
 
 use env;
-use errors::Error;
+use errors::{Error, ErrorKind};
 use dotenv;
 
 #[allow(dead_code)]
@@ -32,6 +32,8 @@ impl NetworkClient {
     pub fn make_db_conn_to_file(file_name: &str) -> Result<DBConnection> {
         let conn = DBConnection::establish(file_name)?;
         db::run_migrations(&conn)?;
+        db::column_encryption::load_key_for_database(&conn, file_name)?;
+        db::column_encryption::encrypt_plaintext_columns(&conn)?;
         Ok(conn)
     }
     pub fn make_db_conn() -> Result<DBConnection> {
@@ -46,6 +48,8 @@ impl NetworkClient {
         let conn = DBConnection::establish(&database_url)?;
 
         db::run_migrations(&conn)?;
+        db::column_encryption::load_key_for_database(&conn, &database_url)?;
+        db::column_encryption::encrypt_plaintext_columns(&conn)?;
         Ok(conn)
     }
 }
@@ -126,11 +130,20 @@ where T: serde::de::DeserializeOwned {
     let mut response_bytes = client.put(
         &server_endpoints.url(endpoint),
     )?.body(request_body).send()?;
+    let status = response_bytes.status();
     let mut response_str = String::new();
     response_bytes.read_to_string(&mut response_str)?;
 
     use team::Response;
-    match serde_json::from_str(&response_str) {
+    let response = serde_json::from_str::<Response<T>>(&response_str);
+    if !status.is_success() {
+        let message = match response {
+            Ok(Response::Error(s)) => s,
+            _ => response_str,
+        };
+        bail!(ErrorKind::HTTPStatus(status.as_u16(), message));
+    }
+    match response {
         Ok(Response::Success(t)) => Ok(t),
         Ok(Response::Error(s)) => bail!(s),
         Err(e) => bail!("Could not read json response\nError: {:?}\nResponse string: {:?}", e, response_str),
@@ -0,0 +1,266 @@
+use super::*;
+use db::Connection;
+use errors::ErrorKind;
+use errors::specified;
+use logs::{ApprovalResult, Log, LogBody, Session, SudoApproval};
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+
+/// A client whose log block sends fail with scripted errors. Reads of its own log chain return
+/// no new blocks.
+struct ScriptedClient {
+    sign_key_pair: SignKeyPair,
+    box_key_pair: BoxKeyPair,
+    team_public_key: Vec<u8>,
+    db_connection: DBConnection,
+    send_errors: RefCell<Vec<Error>>,
+    sends: Cell<usize>,
+    catch_ups: Cell<usize>,
+}
+
+impl ScriptedClient {
+    fn new(send_errors: Vec<Error>) -> ScriptedClient {
+        let db_connection = DBConnection::establish("").unwrap();
+        db::run_migrations(&db_connection).unwrap();
+        db::column_encryption::load_key_for_database(&db_connection, "").unwrap();
+
+        let client = ScriptedClient {
+            sign_key_pair: gen_sign_key_pair().unwrap(),
+            box_key_pair: gen_box_key_pair().unwrap(),
+            team_public_key: vec![1; 32],
+            db_connection,
+            send_errors: RefCell::new(send_errors),
+            sends: Cell::new(0),
+            catch_ups: Cell::new(0),
+        };
+        db::LogChain {
+            team_public_key: client.team_public_key.clone(),
+            member_public_key: client.identity_pk().into(),
+            last_block_hash: vec![2; 32],
+            symmetric_encryption_key: Some(secretbox::gen().to_vec()),
+        }.insert(&client.db_connection).unwrap();
+        client
+    }
+
+    fn queue_log(&self) {
+        let log = Log {
+            session: Session {
+                device_name: "laptop".into(),
+                workstation_public_key_double_hash: vec![3; 32],
+            },
+            unix_seconds: 1525000000,
+            body: LogBody::Sudo(SudoApproval {
+                user: "deploy".into(),
+                target_user: "root".into(),
+                host: "build-server".into(),
+                command: None,
+                result: ApprovalResult::Approved(E{}),
+            }),
+            redactions: vec![],
+            main_chain_block_hash: None,
+        };
+        db::QueuedLog::add(&self.db_connection, &db::NewQueuedLog {
+            log_json: serde_json::to_vec(&log).unwrap(),
+            created_unix_seconds: 1525000000,
+        }).unwrap();
+    }
+
+    fn queued_attempts(&self) -> Vec<i64> {
+        db::QueuedLog::all(&self.db_connection).unwrap().into_iter().map(|l| l.attempts).collect()
+    }
+
+    fn dead_lettered(&self) -> u64 {
+        db::DeadLetteredLog::count(&self.db_connection).unwrap()
+    }
+}
+
+impl traits::Identify for ScriptedClient {
+    fn identity_pk(&self) -> &[u8] {
+        self.sign_key_pair.public_key_bytes()
+    }
+    fn team_pk(&self) -> &[u8] {
+        &self.team_public_key
+    }
+}
+
+impl traits::DBConnect for ScriptedClient {
+    fn db_conn(&self) -> &DBConnection {
+        &self.db_connection
+    }
+}
+
+impl traits::Broadcast for ScriptedClient {
+    fn broadcast<'a, T>(&self, _endpoint: &protocol::Endpoint, _request: &SignedMessage) -> Result<T> where
+        T: serde::de::DeserializeOwned {
+        self.catch_ups.set(self.catch_ups.get() + 1);
+        Ok(serde_json::from_str(r#"{"blocks":[],"more":false}"#)?)
+    }
+}
+
+impl OwnedKeyPair for ScriptedClient {
+    fn commit_send<R: serde::de::DeserializeOwned>(&self, _endpoint: &Endpoint, _signed_message: &SignedMessage) -> Result<R> {
+        self.sends.set(self.sends.get() + 1);
+        let mut send_errors = self.send_errors.borrow_mut();
+        if !send_errors.is_empty() {
+            return Err(send_errors.remove(0));
+        }
+        Ok(serde_json::from_str("{}")?)
+    }
+    fn sign_key_pair(&self) -> &SignKeyPair {
+        &self.sign_key_pair
+    }
+    fn box_public_key(&self) -> &box_::ed25519_box::PublicKey {
+        &self.box_key_pair.public_key
+    }
+    fn box_secret_key(&self) -> &box_::ed25519_box::SecretKey {
+        &self.box_key_pair.secret_key
+    }
+}
+
+fn http_status(status: u16, message: &str) -> Error {
+    ErrorKind::HTTPStatus(status, message.into()).into()
+}
+
+#[test]
+fn explicit_rejections_are_permanent() {
+    assert_eq!(classify_send_failure(&http_status(400, "invalid log block")), SendFailure::Permanent);
+    assert_eq!(classify_send_failure(&http_status(403, "not a member")), SendFailure::Permanent);
+}
+
+#[test]
+fn stale_log_chain_is_a_chain_conflict() {
+    assert_eq!(classify_send_failure(&http_status(409, "")), SendFailure::ChainConflict);
+    assert_eq!(classify_send_failure(&http_status(400, "NotAppendingToMainChain")), SendFailure::ChainConflict);
+    assert_eq!(classify_send_failure(&specified::ErrorKind::NotAppendingToMainChain.into()), SendFailure::ChainConflict);
+}
+
+#[test]
+fn server_and_network_errors_are_transient() {
+    for status in &[500, 502, 503, 408, 429] {
+        assert_eq!(classify_send_failure(&http_status(*status, "")), SendFailure::Transient);
+    }
+    let io_error: Error = ::std::io::Error::new(::std::io::ErrorKind::ConnectionRefused, "refused").into();
+    assert_eq!(classify_send_failure(&io_error), SendFailure::Transient);
+}
+
+#[test]
+fn other_failures_are_unknown() {
+    assert_eq!(classify_send_failure(&http_status(404, "")), SendFailure::Unknown);
+    assert_eq!(classify_send_failure(&http_status(413, "")), SendFailure::Unknown);
+    // For example a garbled 200 response body.
+    let garbled_response: Error = serde_json::from_str::<E>("{").unwrap_err().into();
+    assert_eq!(classify_send_failure(&garbled_response), SendFailure::Unknown);
+}
+
+#[test]
+fn queued_log_backoff_delay_doubles_up_to_the_maximum() {
+    let mut queued_log = db::QueuedLog {
+        id: 1,
+        log_json: vec![],
+        created_unix_seconds: 0,
+        attempts: 0,
+        last_attempt_unix_seconds: None,
+        last_error: None,
+    };
+    assert!(queued_log_is_due(&queued_log, 0));
+
+    queued_log.last_attempt_unix_seconds = Some(1000);
+    queued_log.attempts = 3;
+    assert!(!queued_log_is_due(&queued_log, 1007));
+    assert!(queued_log_is_due(&queued_log, 1008));
+
+    queued_log.attempts = 40;
+    assert!(!queued_log_is_due(&queued_log, 1000 + MAX_QUEUED_LOG_RETRY_DELAY_SECONDS - 1));
+    assert!(queued_log_is_due(&queued_log, 1000 + MAX_QUEUED_LOG_RETRY_DELAY_SECONDS));
+}
+
+#[test]
+fn permanent_failure_is_dead_lettered_and_later_logs_are_sent() {
+    let client = ScriptedClient::new(vec![http_status(400, "invalid log block")]);
+    client.queue_log();
+    client.queue_log();
+
+    client.send_queued_logs().unwrap();
+
+    assert_eq!(client.sends.get(), 2);
+    assert_eq!(client.dead_lettered(), 1);
+    assert!(client.queued_attempts().is_empty());
+}
+
+#[test]
+fn transient_failure_keeps_the_log_queued_until_its_backoff_passes() {
+    let client = ScriptedClient::new(vec![http_status(503, "unavailable")]);
+    client.queue_log();
+
+    assert!(client.send_queued_logs().is_err());
+    assert_eq!(client.queued_attempts(), vec![1]);
+    assert_eq!(client.dead_lettered(), 0);
+
+    // Not due yet, so nothing is sent.
+    client.send_queued_logs().unwrap();
+    assert_eq!(client.sends.get(), 1);
+    assert_eq!(client.queued_attempts(), vec![1]);
+}
+
+#[test]
+fn chain_conflict_is_retried_after_catching_up() {
+    let client = ScriptedClient::new(vec![http_status(400, "NotAppendingToMainChain")]);
+    client.queue_log();
+
+    client.send_queued_logs().unwrap();
+
+    assert_eq!(client.catch_ups.get(), 1);
+    assert_eq!(client.sends.get(), 2);
+    assert_eq!(client.dead_lettered(), 0);
+    assert!(client.queued_attempts().is_empty());
+}
+
+#[test]
+fn repeated_chain_conflicts_keep_the_log_queued() {
+    let client = ScriptedClient::new(vec![http_status(409, ""), http_status(409, "")]);
+    client.queue_log();
+
+    assert!(client.send_queued_logs().is_err());
+
+    assert_eq!(client.catch_ups.get(), 2);
+    assert_eq!(client.dead_lettered(), 0);
+    assert_eq!(client.queued_attempts(), vec![1]);
+}
+
+#[test]
+fn unknown_failure_is_dead_lettered_after_max_attempts() {
+    let client = ScriptedClient::new(vec![http_status(404, "")]);
+    client.queue_log();
+
+    // Earlier failures, long enough ago that the log is due again.
+    for _ in 0..MAX_QUEUED_LOG_ATTEMPTS - 1 {
+        db::QueuedLog::next(&client.db_connection).unwrap()
+            .record_failure(&client.db_connection, "earlier failure", 0).unwrap();
+    }
+
+    assert!(client.send_queued_logs().is_err());
+    assert_eq!(client.dead_lettered(), 1);
+    assert!(client.queued_attempts().is_empty());
+}
+
+#[test]
+fn flush_retries_transient_failures_with_backoff() {
+    let client = ScriptedClient::new(vec![http_status(503, "unavailable")]);
+    client.queue_log();
+
+    client.send_queued_logs_with_retry(Some(Duration::from_secs(10))).unwrap();
+
+    assert_eq!(client.sends.get(), 2);
+    assert!(client.queued_attempts().is_empty());
+}
+
+#[test]
+fn flush_stops_retrying_on_permanent_failure() {
+    let client = ScriptedClient::new(vec![http_status(403, "not a member")]);
+    client.queue_log();
+
+    client.send_queued_logs_with_retry(Some(Duration::from_secs(10))).unwrap();
+
+    assert_eq!(client.sends.get(), 1);
+    assert_eq!(client.dead_lettered(), 1);
+}
@@ -0,0 +1,217 @@
+/// Apply a team's `LogRedactionRules` to a log before it is encrypted and queued.
+
+use sha256;
+use secret::SecretBytes;
+use team::LogRedactionRules;
+use logs::{Log, LogBody, Redaction};
+use git_hash::GitHash;
+use sigchain_core::ssh_session::SSHSessionData;
+
+/// Number of bytes of the keyed hash kept for a redacted username.
+const USERNAME_HASH_BYTES: usize = 8;
+
+const USERNAME_HASH_KEY_CONTEXT: &[u8] = b"krypton-log-username-hash-v1";
+
+/// Derive the username hash key from the logging device's box secret key, which never leaves
+/// the device.
+pub fn username_hash_key(box_secret_key: &[u8]) -> SecretBytes {
+    let data = SecretBytes::new([USERNAME_HASH_KEY_CONTEXT, box_secret_key].concat());
+    SecretBytes::new(sha256::hash(&data).0.to_vec())
+}
+
+/// Hash a username with a key from `username_hash_key`. The same user maps to the same value in
+/// one device's logs, but without the device's secret key nobody, admins included, can check a
+/// hashed value against guessed usernames.
+pub fn hash_username(key: &[u8], user: &str) -> String {
+    let data = SecretBytes::new([key, user.as_bytes()].concat());
+    let hex: Vec<String> = sha256::hash(&data).0[..USERNAME_HASH_BYTES].iter()
+        .map(|b| format!("{:02x}", b)).collect();
+    format!("h:{}", hex.join(""))
+}
+
+pub fn redact_log(log: Log, rules: &LogRedactionRules, box_secret_key: &[u8]) -> Log {
+    let Log { session, unix_seconds, mut body, mut redactions, main_chain_block_hash } = log;
+
+    if rules.drop_commit_messages {
+        match &mut body {
+            &mut LogBody::GitCommit(ref mut commit) => {
+                if !commit.message.is_empty() || commit.message_string.is_some() {
+                    redactions.push(Redaction::CommitMessageDropped {
+                        object_id: commit.git_hash_hex_string(),
+                    });
+                    commit.message = vec![];
+                    commit.message_string = None;
+                }
+            }
+            &mut LogBody::GitTag(ref mut tag) => {
+                if !tag.message.is_empty() || tag.message_string.is_some() {
+                    redactions.push(Redaction::CommitMessageDropped {
+                        object_id: tag.git_hash_hex_string(),
+                    });
+                    tag.message = vec![];
+                    tag.message_string = None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if rules.hash_usernames {
+        let key = username_hash_key(box_secret_key);
+        let user = match &mut body {
+            &mut LogBody::Ssh(ref mut ssh) => Some(&mut ssh.user),
+            &mut LogBody::Sudo(ref mut sudo) => Some(&mut sudo.user),
+            &mut LogBody::PamLogin(ref mut pam_login) => Some(&mut pam_login.user),
+            &mut LogBody::SshAgentForwarding(ref mut agent_forwarding) => Some(&mut agent_forwarding.user),
+            _ => None,
+        };
+        if let Some(user) = user {
+            *user = hash_username(&key, user);
+            redactions.push(Redaction::UsernameHashed);
+        }
+        if let &mut LogBody::Sudo(ref mut sudo) = &mut body {
+            sudo.target_user = hash_username(&key, &sudo.target_user);
+        }
+        // The userauth request in the session data names the user too. Drop the session data if
+        // it cannot be rewritten.
+        if let &mut LogBody::Ssh(ref mut ssh) = &mut body {
+            match SSHSessionData::replace_user(&ssh.session_data, &ssh.user) {
+                Ok(session_data) => ssh.session_data = session_data,
+                Err(e) => {
+                    debug!("dropping unparseable ssh session data: {}", e);
+                    let original_length = ssh.session_data.len() as u64;
+                    ssh.session_data = vec![];
+                    redactions.push(Redaction::SessionDataTruncated { original_length });
+                }
+            }
+        }
+    }
+
+    if let Some(max_length) = rules.truncate_session_data {
+        if let &mut LogBody::Ssh(ref mut ssh) = &mut body {
+            let original_length = ssh.session_data.len() as u64;
+            if original_length > max_length {
+                ssh.session_data.truncate(max_length as usize);
+                redactions.push(Redaction::SessionDataTruncated { original_length });
+            }
+        }
+    }
+
+    if rules.drop_sudo_commands {
+        if let &mut LogBody::Sudo(ref mut sudo) = &mut body {
+            if sudo.command.take().is_some() {
+                redactions.push(Redaction::SudoCommandDropped);
+            }
+        }
+    }
+
+    Log { session, unix_seconds, body, redactions, main_chain_block_hash }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logs::{Session, SSHSignature, SSHSignatureResult};
+    use sigchain_core::log_export::{ExportFormat, ExportRecord};
+
+    const SESSION_ID: [u8; 32] = [7; 32];
+
+    fn write_string(out: &mut Vec<u8>, string: &[u8]) {
+        let length = string.len() as u32;
+        out.extend_from_slice(&[(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8, length as u8]);
+        out.extend_from_slice(string);
+    }
+
+    fn userauth_request(user: &str) -> Vec<u8> {
+        let mut data = vec![];
+        write_string(&mut data, &SESSION_ID);
+        data.push(50);
+        write_string(&mut data, user.as_bytes());
+        write_string(&mut data, b"ssh-connection");
+        write_string(&mut data, b"publickey");
+        data.push(1);
+        write_string(&mut data, b"ssh-ed25519");
+        write_string(&mut data, b"public key");
+        data
+    }
+
+    fn ssh_log(user: &str, session_data: Vec<u8>) -> Log {
+        Log {
+            session: Session {
+                device_name: "laptop".into(),
+                workstation_public_key_double_hash: vec![1; 32],
+            },
+            unix_seconds: 1525000000,
+            body: LogBody::Ssh(SSHSignature {
+                user: user.into(),
+                host_authorization: None,
+                session_data,
+                result: SSHSignatureResult::Signature(vec![2; 64]),
+            }),
+            redactions: vec![],
+            main_chain_block_hash: None,
+        }
+    }
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle.as_bytes())
+    }
+
+    fn hash_usernames() -> LogRedactionRules {
+        LogRedactionRules { hash_usernames: true, ..Default::default() }
+    }
+
+    #[test]
+    fn hashed_ssh_user_is_removed_from_session_data() {
+        let redacted = redact_log(ssh_log("alice", userauth_request("alice")), &hash_usernames(), &[3; 32]);
+        let hashed_user = hash_username(&username_hash_key(&[3; 32]), "alice");
+
+        match redacted.body {
+            LogBody::Ssh(ref ssh) => {
+                assert_eq!(ssh.user, hashed_user);
+                assert!(!contains(&ssh.session_data, "alice"));
+            }
+            _ => panic!("expected an ssh log"),
+        }
+        assert_eq!(redacted.redactions, vec![Redaction::UsernameHashed]);
+        assert!(!contains(::serde_json::to_string(&redacted).unwrap().as_bytes(), "alice"));
+
+        let session = SSHSessionData::from_log(&redacted).unwrap();
+        assert_eq!(session.user, hashed_user);
+        assert_eq!(session.session_id, SESSION_ID.to_vec());
+
+        let record = ExportRecord::new(1, "member@example.com", &[4; 32], &redacted);
+        for format in &[ExportFormat::JsonLines, ExportFormat::Syslog, ExportFormat::Cef] {
+            assert!(!contains(record.format(*format).unwrap().as_bytes(), "alice"));
+        }
+    }
+
+    #[test]
+    fn unparseable_session_data_is_dropped_when_hashing_users() {
+        let redacted = redact_log(ssh_log("alice", b"\x00\x00\x00alice".to_vec()), &hash_usernames(), &[3; 32]);
+
+        match redacted.body {
+            LogBody::Ssh(ref ssh) => assert!(ssh.session_data.is_empty()),
+            _ => panic!("expected an ssh log"),
+        }
+        assert_eq!(redacted.redactions, vec![
+            Redaction::UsernameHashed,
+            Redaction::SessionDataTruncated { original_length: 8 },
+        ]);
+    }
+
+    #[test]
+    fn decoded_user_is_suppressed_for_logs_redacted_by_older_clients() {
+        // Older clients hashed the user in the body but left it in the session data.
+        let mut log = ssh_log("h:0011223344556677", userauth_request("alice"));
+        log.redactions.push(Redaction::UsernameHashed);
+
+        let session = SSHSessionData::from_log(&log).unwrap();
+        assert_eq!(session.user, "h:0011223344556677");
+
+        let record = ExportRecord::new(1, "member@example.com", &[4; 32], &log);
+        for format in &[ExportFormat::JsonLines, ExportFormat::Syslog, ExportFormat::Cef] {
+            assert!(!contains(record.format(*format).unwrap().as_bytes(), "alice"));
+        }
+    }
+}
@@ -59,6 +59,10 @@ impl<'s> TestClient<'s> {
         let conn = DBConnection::establish(client_db_url)?;
 
         db::run_migrations(&conn)?;
+        #[cfg(feature = "sqlite")]
+        db::column_encryption::load_key_for_database(&conn, client_db_url)?;
+        #[cfg(feature = "sqlite")]
+        db::column_encryption::encrypt_plaintext_columns(&conn)?;
         Ok(conn)
     }
 
@@ -1,5 +1,8 @@
 use super::*;
 use itertools;
+use sigchain_core::dashboard_protocol;
+use sigchain_core::log_search;
+use sigchain_core::log_export;
 
 pub trait Broadcast {
     fn broadcast<'a, T>(&self, endpoint: &protocol::Endpoint, request: &SignedMessage) -> super::Result<T>
@@ -25,7 +28,7 @@ pub trait DBConnect: Identify {
     fn my_log_pointer(&self) -> Result<LogChainPointer> {
         let conn = &db::TeamDBConnection{conn: self.db_conn(), team: &self.team_pk()};
         if let Some(log_chain) = db::LogChain::find(conn, self.identity_pk()).optional()? {
-            Ok(LogChainPointer::LastBlockHash(log_chain.last_block_hash))
+            Ok(LogChainPointer::LastBlockHash(log_chain.last_block_hash.clone()))
         } else {
             Ok(
                 LogChainPointer::GenesisBlock(LogChainGenesisPointer{
@@ -96,11 +99,12 @@ pub trait DBConnect: Identify {
 
     fn get_my_identity(&self) -> Result<team::Identity> {
         let conn = &db::TeamDBConnection{conn: self.db_conn(), team: self.team_pk()};
-        Ok(db::Identity::find(conn, self.identity_pk()).map(db::Identity::into_identity)?)
+        Ok(db::Identity::find_for_signer(conn, self.identity_pk()).map(db::Identity::into_identity)?)
     }
     fn is_admin(&self) -> Result<bool> {
         let conn = &db::TeamDBConnection { conn: self.db_conn(), team: self.team_pk() };
-        Ok(db::TeamMembership::find(conn, self.identity_pk())?.is_admin)
+        let member_public_key = db::MemberDevice::member_public_key_for_signer(conn, self.identity_pk())?;
+        Ok(db::TeamMembership::find(conn, &member_public_key)?.is_admin)
     }
     fn get_team_info(&self) -> Result<team::TeamInfo> {
         let conn = &db::TeamDBConnection{conn: self.db_conn(), team: self.team_pk()};
@@ -116,6 +120,14 @@ pub trait DBConnect: Identify {
         let temporary_approval_seconds = db::Team::find(conn)?.temporary_approval_seconds;
         Ok(Policy{
             temporary_approval_seconds,
+            log_redaction: Some(self.get_log_redaction_rules()?),
+        })
+    }
+    fn get_log_redaction_rules(&self) -> Result<team::LogRedactionRules> {
+        let conn = &self.team_db_conn();
+        Ok(match db::Team::find(conn)?.log_redaction_rules {
+            Some(rules) => serde_json::from_str(&rules)?,
+            None => team::LogRedactionRules::default(),
         })
     }
     fn get_pinned_host_keys(&self, for_host: &str, search: bool) -> Result<Vec<db::PinnedHostKey>> {
@@ -133,12 +145,245 @@ pub trait DBConnect: Identify {
     }
     fn get_encryption_public_key(&self, identity_public_key: &[u8]) -> Result<Vec<u8>> {
         let conn = &db::TeamDBConnection{conn: self.db_conn(), team: self.team_pk()};
+        // Devices wrap log keys with their own encryption key.
+        if let Some(device) = db::MemberDevice::find(conn, identity_public_key).optional()? {
+            return Ok(device.encryption_public_key);
+        }
         Ok(db::Identity::find(conn, identity_public_key)?.encryption_public_key)
     }
+    fn get_safety_number(&self, identity_public_key: &[u8]) -> Result<String> {
+        use crypto::safety_number::safety_number;
+        let identity = db::Identity::find(&self.team_db_conn(), identity_public_key)?.into_identity();
+        Ok(safety_number(self.team_pk(), &identity))
+    }
+    /// Admin attestations for the member's current safety number. Attestations made before the
+    /// member's keys changed are left out.
+    fn get_current_member_verifications(&self, identity_public_key: &[u8]) -> Result<Vec<db::MemberVerification>> {
+        use crypto::safety_number::safety_numbers_match;
+        let safety_number = self.get_safety_number(identity_public_key)?;
+        Ok(db::MemberVerification::for_member(&self.team_db_conn(), identity_public_key)?
+            .into_iter()
+            .filter(|v| safety_numbers_match(&v.safety_number, &safety_number))
+            .collect())
+    }
+    /// Devices the member authorized to sign for them, besides their own key.
+    fn get_signing_devices(&self, identity_public_key: &[u8]) -> Result<Vec<db::MemberDevice>> {
+        Ok(db::MemberDevice::filter_by_member(&self.team_db_conn(), identity_public_key)?)
+    }
     fn main_chain_block_count(&self) -> Result<u64> {
         let conn = &db::TeamDBConnection{conn: self.db_conn(), team: self.team_pk()};
         Ok(db::Block::count(conn)?)
     }
+    fn get_member_log_integrity(&self, member_public_key: &[u8]) -> Result<dashboard_protocol::MemberLogIntegrity> {
+        use db::LogFailureType::*;
+        let conn = &self.team_db_conn();
+        let mut integrity = dashboard_protocol::MemberLogIntegrity {
+            member_public_key: member_public_key.into(),
+            decrypted_logs: db::Log::count_for_member(conn, member_public_key)?,
+            ..Default::default()
+        };
+        for failure in db::LogProcessingFailure::for_member(conn, member_public_key)? {
+            match failure.failure_type() {
+                Some(MissingKey) => integrity.missing_key += 1,
+                Some(BadCiphertext) => integrity.bad_ciphertext += 1,
+                Some(MalformedLog) => integrity.malformed_log += 1,
+                Some(BadWrappedKey) => integrity.bad_wrapped_key += 1,
+                Some(BeforeFirstKey) => {
+                    integrity.before_first_key += 1;
+                    continue;
+                }
+                None => {}
+            }
+            integrity.last_failure_unix_seconds = Some(failure.unix_seconds);
+        }
+        use dashboard_protocol::SignatureVerification::*;
+        integrity.valid_signatures = db::Log::count_for_member_with_signature_verification(conn, member_public_key, Valid)?;
+        integrity.invalid_signatures = db::Log::count_for_member_with_signature_verification(conn, member_public_key, Invalid)?;
+        integrity.unverified_signatures = db::Log::count_for_member_with_signature_verification(conn, member_public_key, Unsupported)?;
+        integrity.revoked_device_logs = db::Log::count_for_member_with_signature_verification(conn, member_public_key, RevokedDevice)?;
+        integrity.redacted_signatures = db::Log::count_for_member_with_signature_verification(conn, member_public_key, Redacted)?;
+        Ok(integrity)
+    }
+    fn get_log_integrity_report(&self) -> Result<Vec<dashboard_protocol::MemberLogIntegrity>> {
+        itertools::process_results(
+            self.get_active_and_removed_members()?.iter()
+                .map(|m| self.get_member_log_integrity(&m.public_key)),
+            |i| i.collect::<Vec<_>>()
+        )
+    }
+    fn get_log_processing_failures(&self) -> Result<Vec<db::LogProcessingFailure>> {
+        Ok(db::LogProcessingFailure::all(&self.team_db_conn())?)
+    }
+    fn verify_log_chains(&self, published_heads: &[SignedMessage]) -> Result<Vec<log_integrity::LogChainReport>> {
+        log_integrity::verify_team_log_chains(&self.team_db_conn(), published_heads)
+    }
+    /// Add decrypted logs that are missing from the local search index, returning how many were added.
+    fn update_log_search_index(&self) -> Result<u64> {
+        let conn = &self.team_db_conn();
+        conn.conn.transaction::<_, Error, _>(|| {
+            let mut indexed = 0;
+            for log in db::LogSearchEntry::unindexed_logs(conn)? {
+                let parsed_log = match serde_json::from_str::<logs::Log>(&log.log_json) {
+                    Ok(parsed_log) => parsed_log,
+                    Err(e) => {
+                        error!("not indexing malformed log {}: {}", log.id, e);
+                        db::LogSearchEntry::malformed(&log).insert(conn.conn)?;
+                        continue;
+                    }
+                };
+                let email = db::Identity::find(conn, &log.member_public_key)?.email;
+                db::LogSearchEntry::new(&log, &parsed_log, &email).insert(conn.conn)?;
+                indexed += 1;
+            }
+            Ok(indexed)
+        })
+    }
+    /// Search decrypted logs with a query such as `member:alice host:db1 result:rejected type:git since:7d "text"`.
+    fn search_logs(&self, query: &str, limit: Option<i64>) -> Result<Vec<db::LogWithId>> {
+        let query = log_search::LogQuery::parse(query, time::get_time().sec)?;
+        self.update_log_search_index()?;
+        Ok(db::LogSearchEntry::search(&self.team_db_conn(), &query, limit)?)
+    }
+    /// Write every log received after the named cursor to `out`, one record per line, and return
+    /// how many were written. The cursor is advanced after each record is flushed, so an interrupted
+    /// export resumes without skipping or repeating logs, except for a record written just before
+    /// the interruption, which collectors can drop by its `log_id`. Logs that cannot be parsed are
+    /// exported as `log_export::MALFORMED_EVENT_TYPE` records rather than skipped.
+    fn export_logs<W: std::io::Write>(&self, cursor_name: &str, format: log_export::ExportFormat, out: &mut W) -> Result<u64> {
+        use sigchain_core::diesel::OptionalExtension;
+        use std::io::Write;
+        const BATCH_SIZE: i64 = 500;
+
+        let conn = &self.team_db_conn();
+        let mut last_log_id = db::LogExportCursor::find(conn, cursor_name).optional()?.map(|c| c.last_log_id);
+        let mut exported = 0;
+        loop {
+            let batch = db::Log::after_ascending(conn, last_log_id, BATCH_SIZE)?;
+            if batch.is_empty() {
+                return Ok(exported);
+            }
+            for db_log in &batch {
+                let email = db::Identity::find(conn, &db_log.member_public_key)?.email;
+                let record = match serde_json::from_str::<logs::Log>(&db_log.log_json) {
+                    Ok(log) => log_export::ExportRecord::new(db_log.id, &email, &db_log.member_public_key, &log),
+                    Err(e) => {
+                        error!("exporting malformed log {}: {}", db_log.id, e);
+                        log_export::ExportRecord::malformed(
+                            db_log.id, db_log.unix_seconds, &email, &db_log.member_public_key, &e.to_string())
+                    }
+                };
+                writeln!(out, "{}", record.format(format)?)?;
+                out.flush()?;
+                exported += 1;
+
+                db::LogExportCursor {
+                    team_public_key: self.team_pk().into(),
+                    name: cursor_name.into(),
+                    last_log_id: db_log.id,
+                    updated_unix_seconds: time::get_time().sec,
+                }.set(conn.conn)?;
+                last_log_id = Some(db_log.id);
+            }
+        }
+    }
+    fn get_alert_rules(&self) -> Result<Vec<alerts::AlertRule>> {
+        alerts::get_alert_rules(&self.team_db_conn())
+    }
+    /// Add an alert rule, replacing any existing rule with the same name.
+    fn set_alert_rule(&self, rule: &alerts::AlertRule) -> Result<()> {
+        rule.validate()?;
+        db::AlertRule {
+            team_public_key: self.team_pk().into(),
+            name: rule.name.clone(),
+            rule_json: serde_json::to_string(rule)?,
+        }.set(self.db_conn())?;
+        Ok(())
+    }
+    fn remove_alert_rule(&self, name: &str) -> Result<()> {
+        if db::AlertRule::delete(&self.team_db_conn(), name)? == 0 {
+            bail!(format!("no alert rule named {:?}", name));
+        }
+        Ok(())
+    }
+    fn get_alerts(&self, limit: Option<i64>) -> Result<Vec<db::Alert>> {
+        Ok(db::Alert::all(&self.team_db_conn(), limit)?)
+    }
+    fn get_access_anomalies(&self, limit: Option<i64>) -> Result<Vec<dashboard_protocol::AccessAnomaly>> {
+        let conn = &self.team_db_conn();
+        itertools::process_results(
+            db::AccessAnomaly::all(conn, limit)?.into_iter().map(|a| -> Result<_> {
+                Ok(dashboard_protocol::AccessAnomaly {
+                    email: db::Identity::find(conn, &a.member_public_key)?.email,
+                    member_public_key: a.member_public_key,
+                    unix_seconds: a.unix_seconds,
+                    score: a.score,
+                    reasons: serde_json::from_str(&a.reasons_json)?,
+                    log: serde_json::from_str(&a.log_json).ok(),
+                })
+            }),
+            |i| i.collect::<Vec<_>>()
+        )
+    }
+    fn get_member_devices(&self, member_public_key: &[u8]) -> Result<dashboard_protocol::MemberDevices> {
+        let conn = &self.team_db_conn();
+        let logs = db::Log::for_member(conn, member_public_key)?.iter()
+            .filter_map(|l| serde_json::from_str::<logs::Log>(&l.log_json).ok())
+            .collect::<Vec<_>>();
+        let is_member_active = db::TeamMembership::find(conn, member_public_key).optional()?.is_some();
+        Ok(dashboard_protocol::MemberDevices {
+            email: db::Identity::find(conn, member_public_key)?.email,
+            member_public_key: member_public_key.into(),
+            is_member_active,
+            devices: dashboard_protocol::devices_from_logs(&logs, is_member_active, time::get_time().sec),
+        })
+    }
+    /// Every workstation seen in the team's logs, grouped by member.
+    fn get_device_inventory(&self) -> Result<Vec<dashboard_protocol::MemberDevices>> {
+        itertools::process_results(
+            self.get_active_and_removed_members()?.iter()
+                .map(|m| self.get_member_devices(&m.public_key)),
+            |i| i.filter(|m| !m.devices.is_empty()).collect::<Vec<_>>()
+        )
+    }
+    /// Match the signed commits and tags of the git repository at `repo` against the team's git logs.
+    fn audit_git_repository(&self, repo: &::std::path::Path) -> Result<git_audit::GitAuditReport> {
+        git_audit::audit_git_repository(&self.team_db_conn(), repo)
+    }
+    /// Export member PGP keys as an armored keyring and SSH keys as a git `allowed_signers` file.
+    fn export_team_keyrings(&self, include_removed: bool) -> Result<keyring_export::TeamKeyringExport> {
+        keyring_export::export_team_keyrings(&self.team_db_conn(), include_removed)
+    }
+    /// Determine which member signed a raw git commit or tag object and their status at signing time.
+    fn verify_git_object(&self, object: &[u8]) -> Result<git_verify::GitSignatureReport> {
+        git_verify::verify_git_object(&self.team_db_conn(), object)
+    }
+    fn get_log_queue_status(&self) -> Result<dashboard_protocol::LogQueueStatus> {
+        let conn = self.db_conn();
+        let dead_lettered = db::DeadLetteredLog::all(conn)?.into_iter()
+            .map(|l| dashboard_protocol::DeadLetteredLog {
+                log: serde_json::from_slice::<logs::Log>(&l.log_json).ok(),
+                created_unix_seconds: l.created_unix_seconds,
+                dead_lettered_unix_seconds: l.dead_lettered_unix_seconds,
+                attempts: l.attempts,
+                reason: l.reason,
+            }).collect();
+        Ok(dashboard_protocol::LogQueueStatus {
+            queued: db::QueuedLog::count(conn)?,
+            oldest_queued_unix_seconds: db::QueuedLog::oldest_created_unix_seconds(conn)?,
+            max_attempts: db::QueuedLog::max_attempts(conn)?.unwrap_or(0),
+            dead_lettered,
+        })
+    }
+    fn requeue_dead_lettered_logs(&self) -> Result<u64> {
+        let conn = self.db_conn();
+        conn.transaction::<_, Error, _>(|| {
+            let dead_lettered = db::DeadLetteredLog::all(conn)?;
+            for log in &dead_lettered {
+                log.requeue(conn)?;
+            }
+            Ok(dead_lettered.len() as u64)
+        })
+    }
 }
 
 pub trait Identify {
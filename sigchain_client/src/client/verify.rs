@@ -5,6 +5,9 @@ use self::errors::{Result};
 use protocol::*;
 use db::{TeamDBConnection, DBConnection, uniqueness_to};
 use crypto::ed25519;
+use crypto::signature_verification::validate_pgp_public_key;
+use crypto::safety_number::{safety_number, safety_numbers_match};
+use sigchain_core::ssh_public_key::SSHPublicKey;
 use time;
 
 use db;
@@ -14,12 +17,27 @@ use std::borrow::Borrow;
 
 use notification::*;
 
+/// Whether a request is new, or a block the server already accepted that a client is replaying.
+/// Clients read log chains after the main chain, so a device can already be revoked in a client's
+/// database when logs it wrote before its revocation arrive.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RequestOrigin {
+    New,
+    Replayed,
+}
+
 pub fn verify_and_process_request(conn: &DBConnection, request: &SignedMessage) -> Result<NotificationsAndResponse> {
     let verified_payload = &verify_signature_and_version(request)?;
-    verify_and_process_request_payload(conn, request, verified_payload)
+    verify_and_process_request_payload(conn, request, verified_payload, RequestOrigin::New)
+}
+
+/// Verify and process a block read from the server.
+pub fn verify_and_process_replayed_request(conn: &DBConnection, request: &SignedMessage) -> Result<NotificationsAndResponse> {
+    let verified_payload = &verify_signature_and_version(request)?;
+    verify_and_process_request_payload(conn, request, verified_payload, RequestOrigin::Replayed)
 }
 
-pub fn verify_and_process_request_payload(conn: &DBConnection, request: &SignedMessage, verified_payload: &Message) -> Result<NotificationsAndResponse> {
+pub fn verify_and_process_request_payload(conn: &DBConnection, request: &SignedMessage, verified_payload: &Message, origin: RequestOrigin) -> Result<NotificationsAndResponse> {
     match verified_payload.body.clone() {
         Body::Main(main_chain) => {
             use MainChain::*;
@@ -28,6 +46,9 @@ pub fn verify_and_process_request_payload(conn: &DBConnection, request: &SignedM
                     bail!("unexpected ReadBlock")
                 }
                 &Append(ref write_block) => {
+                    if verified_payload.header.protocol_version.major < verified_payload.body.protocol_version().major {
+                        bail!(errors::VersionIncompatible)
+                    }
                     return append_block(conn, request, &main_chain, write_block);
                 }
                 &Create(ref create_chain) => {
@@ -43,11 +64,11 @@ pub fn verify_and_process_request_payload(conn: &DBConnection, request: &SignedM
             use LogChain::*;
             match &log_chain {
                 &Create(ref log_chain_genesis_block) => {
-                    return create_log_chain(conn, request, &log_chain, log_chain_genesis_block)
+                    return create_log_chain(conn, request, &log_chain, log_chain_genesis_block, origin)
                         .map(NotificationsAndResponse::no_notifications);
                 }
                 &Append(ref log_op) => {
-                    return append_log(conn, request, &log_chain, log_op)
+                    return append_log(conn, request, &log_chain, log_op, origin)
                         .map(NotificationsAndResponse::no_notifications);
                 }
                 &Read(_) => {
@@ -91,6 +112,11 @@ pub fn create_team(conn: &DBConnection, request: &SignedMessage, verified_payloa
         bail!(errors::NotAnAdmin)
     }
 
+    if validates_keys(request)? {
+        validate_pgp_public_key(&create_chain.creator_identity.pgp_public_key, &create_chain.creator_identity.email)?;
+        verify_encryption_key_proof(&create_chain.creator_identity, &create_chain.creator_identity.public_key)?;
+    }
+
     let team_public_key = create_chain.creator_identity.public_key.clone();
     db::TeamMembership{
         team_public_key: team_public_key.clone(),
@@ -106,11 +132,16 @@ pub fn create_team(conn: &DBConnection, request: &SignedMessage, verified_payloa
         temporary_approval_seconds: None,
         last_read_log_chain_logical_timestamp: None,
         command_encrypted_logging_enabled: false,
+        log_redaction_rules: None,
     }.insert(conn)?;
 
-    db::Identity::from_identity(
-        team_public_key.clone(), create_chain.creator_identity.clone()
-    ).insert(conn)?;
+    let mut creator_identity = create_chain.creator_identity.clone();
+    creator_identity.ssh_public_key = block_ssh_public_key(request, &creator_identity.ssh_public_key)?;
+    let creator_identity = db::Identity::from_identity(team_public_key.clone(), creator_identity);
+    creator_identity.insert(conn)?;
+    let team_conn = &db::TeamDBConnection{conn, team: &team_public_key};
+    db::BlockPosition::set(team_conn, &request.payload_hash(), 0)?;
+    record_identity_keys(team_conn, request, &creator_identity)?;
 
     db::Block::build(
         request,
@@ -127,6 +158,9 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
     let conn = &db::TeamDBConnection{conn, team: &team_public_key};
     let mut notification_actions = Vec::new();
 
+    //  Blocks signed by a member's device act for the member
+    let member_public_key = db::MemberDevice::member_public_key_for_signer(conn, &request.public_key)?;
+
     match write_block.operation {
         Operation::AcceptInvite(ref identity) => {
             if let Some(indirect_invite) = db::IndirectInvitation::find(conn, &request.public_key).optional()? {
@@ -153,16 +187,29 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
             } else {
                 bail!(errors::InviteNotValid);
             }
+            if validates_keys(request)? {
+                validate_pgp_public_key(&identity.pgp_public_key, &identity.email)?;
+                verify_encryption_key_proof(identity, &team_public_key)?;
+            }
+            block_ssh_public_key(request, &identity.ssh_public_key)?;
             if let Some(existing_direct_invite) = db::DirectInvitation::find(conn, &identity.public_key).optional()? {
                 existing_direct_invite.delete(conn)?;
             }
         }
         Operation::Leave(_) => {
             //  Any member can leave the team
-            db::TeamMembership::find(conn, &request.public_key)?;
+            db::TeamMembership::find(conn, &member_public_key)?;
+        }
+        Operation::UpdateKeys(_) | Operation::AddDevice(_) | Operation::RemoveDevice(_) => {
+            //  Any member can manage their own keys and devices
+            db::TeamMembership::find(conn, &member_public_key)?;
+        }
+        Operation::PublishLogChainHead(_) => {
+            //  Any member can publish the head of their log chain
+            db::TeamMembership::find(conn, &member_public_key)?;
         }
         _ => {
-            if !db::TeamMembership::find(conn, &request.public_key)?.is_admin {
+            if !db::TeamMembership::find(conn, &member_public_key)?.is_admin {
                 bail!(errors::NotAnAdmin)
             }
         }
@@ -178,6 +225,9 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
 
     block.insert(conn.conn).map_err(|e| uniqueness_to(e, errors::NotAppendingToMainChain))?;
     db::Team::update_last_block_hash(conn, &block.hash)?;
+    let last_block_index = db::BlockPosition::find(conn, &write_block.last_block_hash)?
+        .ok_or("no main chain index for the last block")?;
+    db::BlockPosition::set(conn, &block.hash, last_block_index + 1)?;
 
     use Operation::*;
     match &write_block.operation {
@@ -204,7 +254,8 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
                     if db::IndirectInvitation::exists(conn, &direct_invitation.public_key)? {
                         bail!("invitation key already in use");
                     }
-                    if db::TeamMembership::find(conn, &direct_invitation.public_key).optional()?.is_some() {
+                    if db::TeamMembership::find(conn, &direct_invitation.public_key).optional()?.is_some() ||
+                        db::MemberDevice::find(conn, &direct_invitation.public_key).optional()?.is_some() {
                         bail!(errors::AlreadyOnTeam)
                     }
                     if db::TeamMembership::find_email(conn, &direct_invitation.email).optional()?.is_some() {
@@ -225,6 +276,9 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
             if db::TeamMembership::find_email(conn, &identity.email).optional()?.is_some() {
                 bail!(errors::EmailInUse{})
             }
+            if db::MemberDevice::find(conn, &identity.public_key).optional()?.is_some() {
+                bail!(errors::AlreadyOnTeam)
+            }
 
             db::TeamMembership{
                 team_public_key: team_public_key.clone(),
@@ -233,15 +287,19 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
                 is_admin: false,
             }.insert(conn.conn)?;
 
-            db::Identity::from_identity(team_public_key.clone(), identity.clone())
-                .insert_or_update(conn.conn)?;
+            let mut identity = identity.clone();
+            identity.ssh_public_key = block_ssh_public_key(request, &identity.ssh_public_key)?;
+            let identity = db::Identity::from_identity(team_public_key.clone(), identity);
+            identity.insert_or_update(conn.conn)?;
+            record_identity_keys(conn, request, &identity)?;
         }
         &Remove(ref public_key) => {
-            if *public_key == request.public_key {
+            if *public_key == member_public_key {
                 bail!("cannot remove self, use Leave op instead")
             }
             let removed_membership = db::TeamMembership::find(conn, &public_key)?;
             removed_membership.delete(conn.conn)?;
+            db::MemberDevice::revoke_all_for_member(conn, &public_key)?;
 
             notification_actions.push(NotificationAction::Unsubscribe(removed_membership.clone()));
 
@@ -250,8 +308,9 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
             db::DirectInvitation::delete_team_invites(conn)?;
         }
         &Leave(_) => {
-            let removed_membership = db::TeamMembership::find(conn, &request.public_key)?;
+            let removed_membership = db::TeamMembership::find(conn, &member_public_key)?;
             removed_membership.delete(conn.conn)?;
+            db::MemberDevice::revoke_all_for_member(conn, &member_public_key)?;
 
             notification_actions.push(NotificationAction::Unsubscribe(removed_membership.clone()));
         }
@@ -274,6 +333,13 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
         &SetPolicy(ref policy) => {
             let mut team = db::Team::find(conn)?;
             team.temporary_approval_seconds = policy.temporary_approval_seconds;
+            if let Some(ref log_redaction) = policy.log_redaction {
+                team.log_redaction_rules = if log_redaction.is_empty() {
+                    None
+                } else {
+                    Some(serde_json::to_string(log_redaction)?)
+                };
+            }
             team.update(conn.conn)?;
         }
         &SetTeamInfo(ref team_info) => {
@@ -282,13 +348,23 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
             team.update(conn.conn)?;
         }
         &PinHostKey(ref host_key) => {
+            let host_key = &SSHHostKey {
+                host: host_key.host.clone(),
+                public_key: block_ssh_public_key(request, &host_key.public_key)?,
+            };
             db::PinnedHostKey {
                 team_public_key: team_public_key.clone(),
                 host: host_key.host.clone(),
                 public_key: host_key.public_key.clone(),
             }.insert(conn.conn)?;
+            record_pinned_host_key_event(conn, request, host_key, true)?;
         }
         &UnpinHostKey(ref host_key) => {
+            // Keys pinned before pins were canonicalized can still be unpinned as they were written.
+            let host_key = &SSHHostKey {
+                host: host_key.host.clone(),
+                public_key: canonical_ssh_public_key(&host_key.public_key).unwrap_or_else(|_| host_key.public_key.clone()),
+            };
             let db_pinned_key = db::PinnedHostKey {
                 team_public_key: team_public_key.clone(),
                 host: host_key.host.clone(),
@@ -298,6 +374,7 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
                 bail!("host key not pinned")
             }
             db_pinned_key.delete(conn.conn)?;
+            record_pinned_host_key_event(conn, request, host_key, false)?;
         }
         &AddLoggingEndpoint(ref logging_endpoint) => {
             match logging_endpoint {
@@ -323,6 +400,71 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
                 }
             }
         }
+        &UpdateKeys(ref key_update) => {
+            if key_update.ssh_public_key.is_none() && key_update.pgp_public_key.is_none() {
+                bail!("no keys to update")
+            }
+            let mut identity = db::Identity::find(conn, &member_public_key)?;
+            if let Some(ref ssh_public_key) = key_update.ssh_public_key {
+                identity.ssh_public_key = canonical_ssh_public_key(ssh_public_key)?;
+            }
+            if let Some(ref pgp_public_key) = key_update.pgp_public_key {
+                validate_pgp_public_key(pgp_public_key, &identity.email)?;
+                identity.pgp_public_key = pgp_public_key.clone();
+            }
+            identity.insert_or_update(conn.conn)?;
+            record_identity_keys(conn, request, &identity)?;
+        }
+        &VerifyMember(ref verification) => {
+            db::TeamMembership::find(conn, &verification.public_key)?;
+            let identity = db::Identity::find(conn, &verification.public_key)?.into_identity();
+            if !safety_numbers_match(&safety_number(&team_public_key, &identity), &verification.safety_number) {
+                bail!(errors::SafetyNumberMismatch)
+            }
+            let message: Message = serde_json::from_str(&request.message)?;
+            db::MemberVerification::insert(conn.conn, &db::NewMemberVerification {
+                team_public_key: team_public_key.clone(),
+                member_public_key: verification.public_key.clone(),
+                verifier_public_key: member_public_key.clone(),
+                safety_number: verification.safety_number.clone(),
+                unix_seconds: message.header.utc_time,
+            })?;
+        }
+        &AddDevice(ref device) => {
+            if db::MemberDevice::find(conn, &device.public_key).optional()?.is_some() ||
+                db::Identity::find(conn, &device.public_key).optional()?.is_some() ||
+                db::DirectInvitation::exists(conn, &device.public_key)? ||
+                db::IndirectInvitation::exists(conn, &device.public_key)? {
+                bail!(errors::AlreadyOnTeam)
+            }
+            verify_device_authorization(device, &team_public_key, &member_public_key)?;
+            let mut device = device.clone();
+            device.ssh_public_key = canonical_ssh_public_key(&device.ssh_public_key)?;
+            db::MemberDevice::from_device(team_public_key.clone(), member_public_key.clone(), device)
+                .insert(conn.conn)?;
+        }
+        &RemoveDevice(ref device_public_key) => {
+            let mut device = db::MemberDevice::find_active(conn, device_public_key)?;
+            //  Members can remove their own devices, admins can remove any member's
+            if device.member_public_key != member_public_key &&
+                !db::TeamMembership::find(conn, &member_public_key)?.is_admin {
+                bail!(errors::NotAnAdmin)
+            }
+            device.revoked = true;
+            device.update(conn.conn)?;
+        }
+        &PublishLogChainHead(ref head) => {
+            //  Log chains are signed by the device that writes them
+            let message: Message = serde_json::from_str(&request.message)?;
+            db::PublishedLogChainHead {
+                team_public_key: team_public_key.clone(),
+                main_chain_block_hash: block.hash.clone(),
+                member_public_key: request.public_key.clone(),
+                last_block_hash: head.last_block_hash.clone(),
+                main_chain_index: last_block_index + 1,
+                unix_seconds: message.header.utc_time,
+            }.insert(conn.conn)?;
+        }
     };
 
     notification_actions.push(NotificationAction::TeamPush(team_public_key.clone()));
@@ -332,7 +474,104 @@ pub fn append_block(conn: &DBConnection, request: &SignedMessage, verified_paylo
     })
 }
 
-pub fn create_log_chain(conn: &DBConnection, request: &SignedMessage, verified_payload: &LogChain, create_log_chain: &GenesisLogBlock) -> Result<String> {
+/// Whether keys in `request` must pass validation, i.e. it was signed at or after
+/// `KEY_VALIDATION_VERSION`.
+fn validates_keys(request: &SignedMessage) -> Result<bool> {
+    let message: Message = serde_json::from_str(&request.message)?;
+    Ok(message.header.protocol_version >= *KEY_VALIDATION_VERSION)
+}
+
+/// The canonical form of an SSH public key in `request`. Keys in blocks from before
+/// `KEY_VALIDATION_VERSION` that cannot be parsed are kept as they were written.
+fn block_ssh_public_key(request: &SignedMessage, public_key_wire: &[u8]) -> Result<Vec<u8>> {
+    if validates_keys(request)? {
+        return canonical_ssh_public_key(public_key_wire);
+    }
+    Ok(::ssh::canonical_ssh_public_key_or_raw(public_key_wire.to_vec()))
+}
+
+/// Reject unknown or malformed SSH public keys, returning the key re-encoded in canonical form.
+fn canonical_ssh_public_key(public_key_wire: &[u8]) -> Result<Vec<u8>> {
+    match SSHPublicKey::parse(public_key_wire) {
+        Ok(public_key) => Ok(public_key.canonical_wire),
+        Err(e) => {
+            debug!("invalid SSH public key: {}", e);
+            bail!(errors::SSHPublicKeyNotValid)
+        }
+    }
+}
+
+/// Reject identities without a valid proof that they hold the secret key for their
+/// `encryption_public_key`, so that nobody can register another member's encryption key.
+fn verify_encryption_key_proof(identity: &Identity, team_public_key: &[u8]) -> Result<()> {
+    match identity.verify_encryption_key_proof(team_public_key) {
+        Ok(true) => Ok(()),
+        Ok(false) => bail!(errors::EncryptionKeyProofNotValid),
+        Err(e) => {
+            debug!("invalid encryption key proof: {}", e);
+            bail!(errors::EncryptionKeyProofNotValid)
+        }
+    }
+}
+
+/// Reject devices whose keys did not sign their authorization to act for `member_public_key`.
+fn verify_device_authorization(device: &Device, team_public_key: &[u8], member_public_key: &[u8]) -> Result<()> {
+    match device.verify_authorization(team_public_key, member_public_key) {
+        Ok(true) => Ok(()),
+        Ok(false) => bail!(errors::DeviceAuthorizationNotValid),
+        Err(e) => {
+            debug!("invalid device authorization: {}", e);
+            bail!(errors::DeviceAuthorizationNotValid)
+        }
+    }
+}
+
+/// Only active devices can write new log blocks. Replayed blocks from revoked devices are accepted,
+/// their logs being marked when they are decrypted.
+fn reject_new_log_block_from_revoked_device(conn: &TeamDBConnection, signer_public_key: &[u8], origin: RequestOrigin) -> Result<()> {
+    if origin == RequestOrigin::New &&
+        db::MemberDevice::find(conn, signer_public_key).optional()?.is_some() &&
+        db::MemberDevice::find_active(conn, signer_public_key).optional()?.is_none() {
+        bail!(errors::DeviceRevoked)
+    }
+    Ok(())
+}
+
+/// Key history is ordered by the index of the block that set the keys. The time the member claims
+/// is kept for objects outside the chain, and cannot move the keys before the member's previous keys.
+fn record_identity_keys(conn: &TeamDBConnection, request: &SignedMessage, identity: &db::Identity) -> Result<()> {
+    let message: Message = serde_json::from_str(&request.message)?;
+    let previous_unix_seconds = db::IdentityKeyEvent::last_for_member(conn, &identity.public_key)?
+        .map_or(message.header.utc_time, |previous| previous.unix_seconds);
+    db::IdentityKeyEvent::insert(conn.conn, &db::NewIdentityKeyEvent {
+        team_public_key: identity.team_public_key.clone(),
+        member_public_key: identity.public_key.clone(),
+        ssh_public_key: identity.ssh_public_key.clone(),
+        pgp_public_key: identity.pgp_public_key.clone(),
+        unix_seconds: message.header.utc_time.max(previous_unix_seconds),
+        main_chain_index: block_main_chain_index(conn, request)?,
+    })?;
+    Ok(())
+}
+
+fn record_pinned_host_key_event(conn: &db::TeamDBConnection, request: &SignedMessage, host_key: &SSHHostKey, pinned: bool) -> Result<()> {
+    let message: Message = serde_json::from_str(&request.message)?;
+    db::PinnedHostKeyEvent::insert(conn.conn, &db::NewPinnedHostKeyEvent {
+        team_public_key: conn.team.into(),
+        host: host_key.host.clone(),
+        public_key: host_key.public_key.clone(),
+        pinned,
+        unix_seconds: message.header.utc_time,
+        main_chain_index: block_main_chain_index(conn, request)?,
+    })?;
+    Ok(())
+}
+
+fn block_main_chain_index(conn: &TeamDBConnection, request: &SignedMessage) -> Result<i64> {
+    Ok(db::BlockPosition::find(conn, &request.payload_hash())?.ok_or("no main chain index for block")?)
+}
+
+pub fn create_log_chain(conn: &DBConnection, request: &SignedMessage, verified_payload: &LogChain, create_log_chain: &GenesisLogBlock, origin: RequestOrigin) -> Result<String> {
     if db::LogBlock::exists(conn, &request.payload_hash())? {
         bail!(errors::BlockExists);
     }
@@ -340,7 +579,11 @@ pub fn create_log_chain(conn: &DBConnection, request: &SignedMessage, verified_p
     let conn = &db::TeamDBConnection{conn, team: &team_public_key};
 
     //  Allow removed members' blocks to still be processed so that admins can read a LogChain of a since-removed member
-    db::Identity::find(conn, &request.public_key)?;
+    //  Each device of a member keeps its own LogChain
+    if db::MemberDevice::find(conn, &request.public_key).optional()?.is_none() {
+        db::Identity::find(conn, &request.public_key)?;
+    }
+    reject_new_log_block_from_revoked_device(conn, &request.public_key, origin)?;
 
     let block = db::LogBlock::build(
         request,
@@ -349,6 +592,13 @@ pub fn create_log_chain(conn: &DBConnection, request: &SignedMessage, verified_p
     )?;
     block.insert(conn.conn)?;
 
+    // A log chain starts at the main chain block its member had read.
+    if let TeamPointer::LastBlockHash(ref main_chain_block_hash) = create_log_chain.team_pointer {
+        if let Some(main_chain_index) = db::BlockPosition::find(conn, main_chain_block_hash)? {
+            db::BlockPosition::set(conn, &block.hash, main_chain_index)?;
+        }
+    }
+
     db::LogChain{
         team_public_key: team_public_key.clone(),
         member_public_key: request.public_key.clone(),
@@ -359,7 +609,7 @@ pub fn create_log_chain(conn: &DBConnection, request: &SignedMessage, verified_p
     success!(E{})
 }
 
-pub fn append_log(conn: &DBConnection, request: &SignedMessage, verified_payload: &LogChain, append_log: &LogBlock) -> Result<String> {
+pub fn append_log(conn: &DBConnection, request: &SignedMessage, verified_payload: &LogChain, append_log: &LogBlock, origin: RequestOrigin) -> Result<String> {
     if db::LogBlock::exists(conn, &request.payload_hash())? {
         bail!(errors::BlockExists);
     }
@@ -368,6 +618,8 @@ pub fn append_log(conn: &DBConnection, request: &SignedMessage, verified_payload
 
     let conn = &TeamDBConnection{conn, team: &last_block.team_public_key};
 
+    reject_new_log_block_from_revoked_device(conn, &request.public_key, origin)?;
+
     if db::LogBlock::next_block_exists(conn, &request.public_key, &Some(last_block.hash))? {
         bail!(errors::NotAppendingToMainChain{})
     }
@@ -378,6 +630,11 @@ pub fn append_log(conn: &DBConnection, request: &SignedMessage, verified_payload
         conn.team.into(),
     )?.insert(conn.conn)?;
 
+    // A log block is never positioned before the block it appends to.
+    if let Some(main_chain_index) = db::BlockPosition::find(conn, &append_log.last_block_hash)? {
+        db::BlockPosition::set(conn, &request.payload_hash(), main_chain_index)?;
+    }
+
     db::LogChain::update_last_block_hash(conn, &request.public_key, request.payload_hash())?;
 
     success!(E{})
@@ -2,6 +2,7 @@ extern crate semver;
 use self::semver::Version;
 
 use {base64, b64data, time, crypto, serde_json, team, logging, protocol};
+use secret::SecretBytes;
 use std::result::Result as StdResult;
 
 lazy_static! {
@@ -102,7 +103,7 @@ pub enum RequestBody {
     LogDecryptionRequest(LogDecryptionRequest),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Response {
     request_id: String,
     sns_endpoint_arn: Option<String>,
@@ -112,7 +113,7 @@ pub struct Response {
     pub body: ResponseBody,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum ResponseBody {
     MeResponse(Result<MeResponse>),
@@ -151,7 +152,7 @@ impl ResponseBody {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug)]
 struct ResponseDe {
     request_id: String,
     sns_endpoint_arn: Option<String>,
@@ -367,6 +368,12 @@ pub enum RequestableTeamOperation {
         #[serde(with = "b64data")]
         Vec<u8>),
     Leave(team::E),
+    VerifyMember(team::MemberVerification),
+    UpdateKeys(team::KeyUpdate),
+    AddDevice(team::Device),
+    RemoveDevice(
+        #[serde(with = "b64data")]
+        Vec<u8>),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -374,8 +381,7 @@ pub struct LogDecryptionRequest {
     pub wrapped_key: logging::BoxedMessage,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct LogDecryptionResponse {
-    #[serde(with = "b64data")]
-    pub log_decryption_key: Vec<u8>,
+    pub log_decryption_key: SecretBytes,
 }
@@ -96,8 +96,8 @@ fn daemon_control_request<R: serde::de::DeserializeOwned>(request: hyper::Reques
         .request(request).from_err::<errors::Error>()
         .and_then(|res| {
             res.body().concat2().from_err().and_then(move |body| {
-                use std::string::String;
-                debug!("Response from krd: {}", String::from_utf8_lossy(&body));
+                // Responses can carry log decryption keys and read tokens, so never log the body.
+                debug!("Response from krd: {} bytes", body.len());
                 Ok(serde_json::from_slice::<R>(&body)?)
             })
         });
@@ -126,7 +126,7 @@ pub fn daemon_enclave_control_request(enclave_request: &enclave_protocol::Reques
 
     let mut request = hyper::Request::new(hyper::Method::Put, Uri::new(daemon_control_path()?, "/enclave").into());
     let json_body = serde_json::to_string(&enclave_request)?;
-    debug!("Sending enclave request: {}", json_body);
+    debug!("Sending enclave request {}", enclave_request.id);
     request.set_body(json_body);
 
 
@@ -151,7 +151,7 @@ pub fn daemon_me_request() -> Result<enclave_protocol::Profile> {
 
     let mut request = hyper::Request::new(hyper::Method::Get, Uri::new(daemon_control_path()?, "/enclave").into());
     let json_body = serde_json::to_string(&me_request)?;
-    debug!("Sending me request to krd: {}", json_body);
+    debug!("Sending me request {} to krd", me_request.id);
     request.set_body(json_body);
 
     let response_result = daemon_control_request::<enclave_protocol::Response>(request)?;
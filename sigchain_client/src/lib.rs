@@ -32,6 +32,7 @@ extern crate reqwest;
 extern crate url;
 
 extern crate clap;
+extern crate backoff;
 
 #[macro_export]
 macro_rules! success(
@@ -61,13 +61,15 @@ pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_createTeam(
         let pk : Vec<u8> = sign_key_pair.public_key_bytes().into();
 
         let creator_profile = &create_team_args.creator_profile;
-        let admin_identity = Identity{
+        let mut admin_identity = Identity{
             public_key: pk.clone(),
             encryption_public_key: box_key_pair.public_key_bytes().into(),
             ssh_public_key: creator_profile.ssh_wire_public_key.clone(),
             pgp_public_key: creator_profile.pgp_public_key.clone().ok_or("no PGP public key")?,
             email: creator_profile.email.clone(),
+            encryption_key_proof: None,
         };
+        admin_identity.prove_encryption_key(&pk, &box_key_pair.secret_key.0)?;
         let create_chain = GenesisBlock {
             team_info: TeamInfo {
                 name: create_team_args.name.clone(),
@@ -79,7 +81,7 @@ pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_createTeam(
 
         let cli = AndroidClient{
             key_pair: sign_key_pair,
-            box_key_pair: box_key_pair.clone(),
+            box_key_pair,
             team_checkpoint: TeamCheckpoint {
                 public_key: pk.clone(),
                 team_public_key: pk.clone(),
@@ -101,10 +103,13 @@ pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_createTeam(
                 server_endpoints: SERVER_ENDPOINTS.lock().unwrap().clone(),
             })?,
             sign_key_pair: Some(serde_json::to_vec(&cli.key_pair)?),
-            box_key_pair: Some(serde_json::to_vec(&box_key_pair)?),
+            box_key_pair: Some(serde_json::to_vec(&cli.box_key_pair)?),
         }.set(cli.db_conn())?;
 
-        cli.set_policy(Policy{temporary_approval_seconds: Some(create_team_args.temporary_approval_seconds)})?;
+        cli.set_policy(Policy{
+            temporary_approval_seconds: Some(create_team_args.temporary_approval_seconds),
+            log_redaction: None,
+        })?;
         for pinned_host in create_team_args.pinned_hosts {
             debug_log(&format!("pinning {:?}", &pinned_host));
             cli.pin_host_key(&pinned_host.host, &pinned_host.public_key)?;
@@ -150,7 +155,7 @@ pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_generateClient(
         db::CurrentTeam{
             team_checkpoint: serde_json::to_vec(&TeamCheckpoint{
                 public_key: sign_kp.public_key_bytes().into(),
-                team_public_key: args.team_public_key,
+                team_public_key: args.team_public_key.clone(),
                 last_block_hash: args.last_block_hash,
                 server_endpoints: SERVER_ENDPOINTS.lock().unwrap().clone(),
             })?,
@@ -158,13 +163,75 @@ pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_generateClient(
             box_key_pair: Some(serde_json::to_vec(&box_kp)?),
         }.set(conn)?;
 
-        Ok(Identity{
+        let mut identity = Identity{
             public_key: sign_kp.public_key_bytes().into(),
             encryption_public_key: box_kp.public_key_bytes().into(),
             ssh_public_key: profile.ssh_wire_public_key.clone(),
             pgp_public_key: profile.pgp_public_key.clone().ok_or("no PGP public key")?,
             email: profile.email,
-        })
+            encryption_key_proof: None,
+        };
+        identity.prove_encryption_key(&args.team_public_key, &box_kp.secret_key.0)?;
+        Ok(identity)
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct GenerateDeviceInput {
+    #[serde(with = "b64data")]
+    team_public_key: Vec<u8>,
+    #[serde(with = "b64data")]
+    last_block_hash: Vec<u8>,
+    /// The member this device will act for.
+    #[serde(with = "b64data")]
+    member_public_key: Vec<u8>,
+    name: String,
+    profile: Profile,
+}
+
+/// Create a client for an additional device of an existing member, returning the signed
+/// `Device` for one of the member's current devices to add with `AddDevice`
+#[no_mangle]
+pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_generateDevice(
+    env: JNIEnv, _ : JClass,
+    dir: JString,
+    args: JString,
+) -> jstring {
+    time_fn!("generateDevice");
+    android_wrapper(&env, || -> Result<_> {
+        let dir = env.get_string(dir)?;
+        let dir : String = dir.into();
+
+        let args = env.get_string(args)?;
+        let args : String = args.into();
+
+        let args : GenerateDeviceInput = serde_json::from_str(&args)?;
+
+        let sign_kp = gen_sign_key_pair()?;
+        let box_kp = gen_box_key_pair()?;
+
+        let conn = &AndroidClient::db_conn(dir.clone())?;
+        db::CurrentTeam{
+            team_checkpoint: serde_json::to_vec(&TeamCheckpoint{
+                public_key: sign_kp.public_key_bytes().into(),
+                team_public_key: args.team_public_key.clone(),
+                last_block_hash: args.last_block_hash,
+                server_endpoints: SERVER_ENDPOINTS.lock().unwrap().clone(),
+            })?,
+            sign_key_pair: Some(serde_json::to_vec(&sign_kp)?),
+            box_key_pair: Some(serde_json::to_vec(&box_kp)?),
+        }.set(conn)?;
+
+        let mut device = team::Device{
+            public_key: sign_kp.public_key_bytes().into(),
+            encryption_public_key: box_kp.public_key_bytes().into(),
+            ssh_public_key: args.profile.ssh_wire_public_key,
+            name: args.name,
+            device_signature: vec![],
+            encryption_key_proof: vec![],
+        };
+        device.authorize(&args.team_public_key, &args.member_public_key, &sign_kp, &box_kp.secret_key.0)?;
+        Ok(device)
     })
 }
 
@@ -200,7 +267,12 @@ pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_deleteDB(
         let dir = env.get_string(dir)?;
         let dir : String = dir.into();
         use std::fs;
-        fs::remove_file(dir + "/team.db")?;
+        fs::remove_file(dir.clone() + "/team.db")?;
+        // Databases created before column encryption have no key file.
+        match fs::remove_file(dir + "/team.db.key") {
+            Err(ref e) if e.kind() == ::std::io::ErrorKind::NotFound => {}
+            r => r?,
+        }
         Ok(E{})
     })
 }
@@ -242,8 +314,8 @@ fn load_team_home_data(dir: &str) -> Result<TeamHomeData> {
     let team_checkpoint : TeamCheckpoint = serde_json::from_slice(&current_team.team_checkpoint)?;
 
     let cli = AndroidClient{
-        key_pair: serde_json::from_slice(&current_team.sign_key_pair.ok_or("no sign_key_pair")?)?,
-        box_key_pair: serde_json::from_slice(&current_team.box_key_pair.ok_or("no box_key_pair")?)?,
+        key_pair: serde_json::from_slice(current_team.sign_key_pair.as_ref().ok_or("no sign_key_pair")?)?,
+        box_key_pair: serde_json::from_slice(current_team.box_key_pair.as_ref().ok_or("no box_key_pair")?)?,
         team_checkpoint: team_checkpoint.clone(),
         db_connection: conn,
         http_client: get_shared_http_client()?,
@@ -324,6 +396,7 @@ pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_getPolicy(
 
         Ok(team::Policy{
             temporary_approval_seconds: db::Team::find(conn)?.temporary_approval_seconds,
+            log_redaction: Some(cli.get_log_redaction_rules()?),
         })
     })
 }
@@ -473,14 +546,18 @@ pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_acceptInvite(
         let args : String = args.into();
         let args : AcceptInviteArgs = serde_json::from_str(&args)?;
 
+        let mut identity = Identity{
+            email: args.profile.email,
+            public_key: cli.identity_pk().into(),
+            encryption_public_key: cli.box_public_key().0.to_vec(),
+            ssh_public_key: args.profile.ssh_wire_public_key,
+            pgp_public_key: args.profile.pgp_public_key.ok_or("no PGP public key")?,
+            encryption_key_proof: None,
+        };
+        identity.prove_encryption_key(cli.team_pk(), &cli.box_secret_key().0)?;
+
         cli.accept_invite(
-            Identity{
-                email: args.profile.email,
-                public_key: cli.identity_pk().into(),
-                encryption_public_key: cli.box_public_key().0.to_vec(),
-                ssh_public_key: args.profile.ssh_wire_public_key,
-                pgp_public_key: args.profile.pgp_public_key.ok_or("no PGP public key")?,
-            },
+            identity,
             args.email_challenge_nonce,
             args.invite_secret,
         )?;
@@ -552,6 +629,47 @@ pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_encryptLog(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_logQueueStatus(
+    env: JNIEnv, _ : JClass,
+    dir: JString,
+) -> jstring {
+    time_fn!("logQueueStatus");
+    use sigchain_core::dashboard_protocol::LogQueueStatus;
+    android_cli_wrapper(&env, dir, |cli| -> Result<LogQueueStatus> {
+        cli.get_log_queue_status()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_requeueDeadLetteredLogs(
+    env: JNIEnv, _ : JClass,
+    dir: JString,
+) -> jstring {
+    time_fn!("requeueDeadLetteredLogs");
+    android_cli_wrapper(&env, dir, |cli| -> Result<E> {
+        cli.requeue_dead_lettered_logs()?;
+        if let Err(e) = cli.flush_queued_logs() {
+            // The log chain may be behind the server's, so catch up before the next attempt.
+            error!("failed to send requeued logs: {}", e);
+            cli.update_my_log_blocks()?;
+            return Err(e);
+        }
+        Ok(E{})
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_signedLogChainHead(
+    env: JNIEnv, _ : JClass,
+    dir: JString,
+) -> jstring {
+    time_fn!("signedLogChainHead");
+    android_cli_wrapper(&env, dir, |cli| -> Result<SignedMessage> {
+        cli.sign_log_chain_head()
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn Java_co_krypt_krypton_team_Native_formatBlocks(
     env: JNIEnv, _ : JClass,
@@ -746,8 +864,8 @@ fn android_cli_wrapper<T: serde::Serialize, F: FnOnce(&AndroidClient) -> Result<
         let team_checkpoint : TeamCheckpoint = serde_json::from_slice(&current_team.team_checkpoint)?;
 
         let cli = AndroidClient{
-            key_pair: serde_json::from_slice(&current_team.sign_key_pair.ok_or("no sign_key_pair")?)?,
-            box_key_pair: serde_json::from_slice(&current_team.box_key_pair.ok_or("no box_key_pair")?)?,
+            key_pair: serde_json::from_slice(current_team.sign_key_pair.as_ref().ok_or("no sign_key_pair")?)?,
+            box_key_pair: serde_json::from_slice(current_team.box_key_pair.as_ref().ok_or("no box_key_pair")?)?,
             team_checkpoint,
             http_client: get_shared_http_client()?,
             db_connection: conn,
@@ -2,12 +2,14 @@ use {Result, sigchain_core::errors::Error};
 use std::process::*;
 
 use crypto;
+use db;
 use base64;
 use krd_client;
 use client::try_with_delegated_network_cli;
 use sigchain_core::protocol::Identity;
 use client::traits::DBConnect;
 use colored::Colorize;
+use chrono::NaiveDateTime;
 use sigchain_core::util::text;
 
 use std::io::prelude::*;
@@ -109,30 +111,52 @@ pub fn list_cli_command(args: &[&str]) -> Result<()> {
 fn query_and_print_acl(server: &str, port: Option<u16>, ignore_unknown: bool, update_team_blocks: bool) -> Result<()> {
     let public_keys = list_keys(server, port)?;
 
-    let (matched_members, unmatched_keys) = try_with_delegated_network_cli(update_team_blocks, |client| -> Result<(Vec<Identity>, Vec<Vec<u8>>)> {
+    let (matched_members, replaced_keys, unmatched_keys) = try_with_delegated_network_cli(update_team_blocks, |client| -> Result<(Vec<Identity>, Vec<(String, i64)>, Vec<Vec<u8>>)> {
         use std::collections::HashMap;
-        use std::iter::FromIterator;
         use client::traits::DBConnect;
 
         let members = client.get_active_members()?;
-        let members_by_ssh_pk = HashMap::<Vec<u8>, Identity>::from_iter(
-            members.into_iter().map(|i| (i.ssh_public_key.clone(), i))
-        );
+        let mut members_by_ssh_pk = HashMap::<Vec<u8>, Identity>::new();
+        for member in members {
+            for device in client.get_signing_devices(&member.public_key)? {
+                members_by_ssh_pk.insert(device.ssh_public_key, member.clone());
+            }
+            members_by_ssh_pk.insert(member.ssh_public_key.clone(), member);
+        }
+
+        // Keys a member has since replaced, with the time they were replaced.
+        let conn = &client.team_db_conn();
+        let mut replaced_by_ssh_pk = HashMap::<Vec<u8>, (Vec<u8>, i64)>::new();
+        let key_events = db::IdentityKeyEvent::find_all_for_team(conn)?;
+        for (i, event) in key_events.iter().enumerate() {
+            let replacement = key_events[i + 1..].iter()
+                .find(|e| e.member_public_key == event.member_public_key && e.ssh_public_key != event.ssh_public_key);
+            if let Some(replacement) = replacement {
+                replaced_by_ssh_pk.entry(event.ssh_public_key.clone())
+                    .or_insert((event.member_public_key.clone(), replacement.unix_seconds));
+            }
+        }
 
         let mut matched_members = vec![];
+        let mut replaced_keys = vec![];
         let mut unmatched_keys = vec![];
         for pk in public_keys.iter() {
-            match members_by_ssh_pk.get(pk) {
-                Some(member) => matched_members.push(member.clone()),
-                None => unmatched_keys.push(pk.clone()),
-            };
+            if let Some(member) = members_by_ssh_pk.get(pk) {
+                matched_members.push(member.clone());
+            } else if let Some(&(ref member_public_key, replaced_at)) = replaced_by_ssh_pk.get(pk) {
+                let email = db::Identity::find(conn, member_public_key)?.email;
+                replaced_keys.push((email, replaced_at));
+            } else {
+                unmatched_keys.push(pk.clone());
+            }
         }
 
         use std::cmp::Ord;
         matched_members.sort_by(|a, b| String::cmp(&a.email, &b.email));
         matched_members.dedup_by(|a, b| a.public_key == b.public_key);
+        replaced_keys.sort();
 
-        Ok((matched_members, unmatched_keys))
+        Ok((matched_members, replaced_keys, unmatched_keys))
     })?;
 
     if matched_members.len() > 0 {
@@ -144,6 +168,14 @@ fn query_and_print_acl(server: &str, port: Option<u16>, ignore_unknown: bool, up
         eprintln!("No team members have access");
     }
 
+    if replaced_keys.len() > 0 {
+        eprintln!("{}", format!("\n{} replaced keys of team members were found:", replaced_keys.len()).bright_yellow());
+        for (email, replaced_at) in replaced_keys {
+            let replaced_at = NaiveDateTime::from_timestamp(replaced_at, 0).format("%Y-%m-%d");
+            eprintln!("{} (replaced {})", email, replaced_at);
+        }
+    }
+
     if unmatched_keys.len() > 0 && !ignore_unknown {
         eprintln!("{}", format!("\n{} unknown keys were found:", unmatched_keys.len()).bright_yellow());
         for unmatched_key in unmatched_keys {
@@ -173,7 +205,9 @@ pub fn add_cli_command(args: &[&str]) -> Result<()> {
                 try_with_delegated_network_cli(true, |client| -> Result<Vec<Vec<u8>>> {
                     let mut member_pks = vec![];
                     for member_email in &member_emails {
-                        member_pks.push(client.get_active_member_by_email(member_email)?.ssh_public_key);
+                        let member = client.get_active_member_by_email(member_email)?;
+                        member_pks.extend(client.get_signing_devices(&member.public_key)?.into_iter().map(|d| d.ssh_public_key));
+                        member_pks.push(member.ssh_public_key);
                     }
                     Ok(member_pks)
                 })?
@@ -206,11 +240,14 @@ pub fn rm_cli_command(args: &[&str]) -> Result<()> {
                 try_with_delegated_network_cli(true, |client| -> Result<Vec<Vec<u8>>> {
                     let mut member_pks = vec![];
                     for member_email in &member_emails {
-                        // Remove current and any removed members' keys with matching email
-                        member_pks.extend(
-                            client.get_active_and_removed_by_email(member_email)?.into_iter()
-                                .map(|i| i.ssh_public_key).collect::<Vec<_>>()
-                        );
+                        // Remove current and any removed members' keys with matching email, and their devices' keys
+                        for identity in client.get_active_and_removed_by_email(member_email)? {
+                            member_pks.extend(
+                                db::MemberDevice::filter_by_member_including_revoked(&client.team_db_conn(), &identity.public_key)?
+                                    .into_iter().map(|d| d.ssh_public_key)
+                            );
+                            member_pks.push(identity.ssh_public_key);
+                        }
                     }
                     Ok(member_pks)
                 })?
@@ -10,9 +10,17 @@ mod add;
 #[cfg(feature = "network_client")]
 pub use self::add::*;
 
-use sshwire::ssh::PublicKeyHeader;
+use sigchain_core::ssh_public_key::SSHPublicKey;
 
 pub fn ssh_public_key_wire_string(public_key_wire: &[u8]) -> Result<String> {
-    let header = sshwire::serde_de::from_slice::<PublicKeyHeader>(public_key_wire)?;
-    Ok(format!("{} {}", header._type, base64::encode(public_key_wire)))
+    Ok(SSHPublicKey::parse(public_key_wire)?.wire_string())
+}
+
+pub fn ssh_public_key_fingerprint(public_key_wire: &[u8]) -> Result<String> {
+    Ok(SSHPublicKey::parse(public_key_wire)?.fingerprint())
+}
+
+/// The canonical encoding of `public_key_wire`, or the key as is if it cannot be parsed.
+pub fn canonical_ssh_public_key_or_raw(public_key_wire: Vec<u8>) -> Vec<u8> {
+    SSHPublicKey::parse(&public_key_wire).map(|k| k.canonical_wire).unwrap_or(public_key_wire)
 }
@@ -0,0 +1,9 @@
+DROP TABLE dead_lettered_logs;
+
+CREATE TABLE queued_logs_without_retry_state (
+    id INTEGER PRIMARY KEY,
+    log_json BYTEA NOT NULL
+);
+INSERT INTO queued_logs_without_retry_state (id, log_json) SELECT id, log_json FROM queued_logs;
+DROP TABLE queued_logs;
+ALTER TABLE queued_logs_without_retry_state RENAME TO queued_logs
@@ -0,0 +1,13 @@
+ALTER TABLE queued_logs ADD COLUMN created_unix_seconds BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE queued_logs ADD COLUMN attempts BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE queued_logs ADD COLUMN last_attempt_unix_seconds BIGINT;
+ALTER TABLE queued_logs ADD COLUMN last_error VARCHAR;
+
+CREATE TABLE dead_lettered_logs (
+    id INTEGER PRIMARY KEY,
+    log_json BYTEA NOT NULL,
+    created_unix_seconds BIGINT NOT NULL,
+    dead_lettered_unix_seconds BIGINT NOT NULL,
+    attempts BIGINT NOT NULL,
+    reason VARCHAR NOT NULL
+)
@@ -0,0 +1 @@
+DROP TABLE log_processing_failures
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE log_processing_failures (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    block_hash BYTEA NOT NULL,
+    failure_type VARCHAR NOT NULL,
+    error VARCHAR NOT NULL,
+    unix_seconds BIGINT NOT NULL
+)
@@ -0,0 +1,11 @@
+CREATE TABLE teams_without_log_redaction_rules (
+	public_key BYTEA PRIMARY KEY,
+	last_block_hash BYTEA,
+	name VARCHAR NOT NULL,
+	temporary_approval_seconds BIGINT,
+	last_read_log_chain_logical_timestamp BIGINT, -- client only
+	command_encrypted_logging_enabled BOOLEAN NOT NULL
+);
+INSERT INTO teams_without_log_redaction_rules SELECT public_key, last_block_hash, name, temporary_approval_seconds, last_read_log_chain_logical_timestamp, command_encrypted_logging_enabled FROM teams;
+DROP TABLE teams;
+ALTER TABLE teams_without_log_redaction_rules RENAME TO teams
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE teams ADD COLUMN log_redaction_rules VARCHAR
\ No newline at end of file
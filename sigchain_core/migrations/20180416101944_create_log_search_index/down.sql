@@ -0,0 +1 @@
+DROP TABLE log_search_index
\ No newline at end of file
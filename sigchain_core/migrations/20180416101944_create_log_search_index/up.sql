@@ -0,0 +1,12 @@
+CREATE TABLE log_search_index (
+    log_id BIGINT PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    member_email VARCHAR NOT NULL,
+    log_type VARCHAR NOT NULL,
+    host VARCHAR,
+    result VARCHAR NOT NULL,
+    text VARCHAR NOT NULL,
+    unix_seconds BIGINT NOT NULL
+);
+CREATE INDEX log_search_index_team_time ON log_search_index (team_public_key, unix_seconds)
\ No newline at end of file
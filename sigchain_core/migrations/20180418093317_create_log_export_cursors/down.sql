@@ -0,0 +1 @@
+DROP TABLE log_export_cursors
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE log_export_cursors (
+    team_public_key BYTEA NOT NULL,
+    name VARCHAR NOT NULL,
+    last_log_id BIGINT NOT NULL,
+    updated_unix_seconds BIGINT NOT NULL,
+    PRIMARY KEY (team_public_key, name)
+)
\ No newline at end of file
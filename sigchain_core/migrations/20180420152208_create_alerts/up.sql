@@ -0,0 +1,16 @@
+CREATE TABLE alert_rules (
+    team_public_key BYTEA NOT NULL,
+    name VARCHAR NOT NULL,
+    rule_json VARCHAR NOT NULL,
+    PRIMARY KEY (team_public_key, name)
+);
+CREATE TABLE alerts (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    rule_name VARCHAR NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    log_json VARCHAR NOT NULL,
+    message VARCHAR NOT NULL,
+    unix_seconds BIGINT NOT NULL,
+    delivered BOOLEAN NOT NULL
+)
\ No newline at end of file
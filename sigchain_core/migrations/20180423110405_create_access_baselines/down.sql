@@ -0,0 +1,2 @@
+DROP TABLE access_anomalies;
+DROP TABLE access_baselines
\ No newline at end of file
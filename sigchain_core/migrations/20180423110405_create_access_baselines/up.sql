@@ -0,0 +1,19 @@
+CREATE TABLE access_baselines (
+    team_public_key BYTEA NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    kind VARCHAR NOT NULL,
+    value VARCHAR NOT NULL,
+    count BIGINT NOT NULL,
+    first_seen_unix_seconds BIGINT NOT NULL,
+    last_seen_unix_seconds BIGINT NOT NULL,
+    PRIMARY KEY (team_public_key, member_public_key, kind, value)
+);
+CREATE TABLE access_anomalies (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    log_json VARCHAR NOT NULL,
+    score BIGINT NOT NULL,
+    reasons_json VARCHAR NOT NULL,
+    unix_seconds BIGINT NOT NULL
+)
\ No newline at end of file
@@ -0,0 +1,12 @@
+CREATE TABLE logs_without_signature_verification (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA,
+    member_public_key BYTEA,
+    log_json VARCHAR,
+    unix_seconds BIGINT NOT NULL
+);
+INSERT INTO logs_without_signature_verification SELECT id, team_public_key, member_public_key, log_json, unix_seconds FROM logs;
+DROP TABLE logs;
+ALTER TABLE logs_without_signature_verification RENAME TO logs;
+CREATE INDEX logs_team_public_key_unix_seconds_index ON logs (team_public_key, unix_seconds);
+CREATE INDEX logs_team_public_key_id_index ON logs (team_public_key, id)
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE logs ADD COLUMN signature_verification VARCHAR
\ No newline at end of file
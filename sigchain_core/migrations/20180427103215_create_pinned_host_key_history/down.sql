@@ -0,0 +1,14 @@
+DROP TABLE pinned_host_key_history;
+CREATE TABLE logs_without_host_key_verification (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA,
+    member_public_key BYTEA,
+    log_json VARCHAR,
+    unix_seconds BIGINT NOT NULL,
+    signature_verification VARCHAR
+);
+INSERT INTO logs_without_host_key_verification SELECT id, team_public_key, member_public_key, log_json, unix_seconds, signature_verification FROM logs;
+DROP TABLE logs;
+ALTER TABLE logs_without_host_key_verification RENAME TO logs;
+CREATE INDEX logs_team_public_key_unix_seconds_index ON logs (team_public_key, unix_seconds);
+CREATE INDEX logs_team_public_key_id_index ON logs (team_public_key, id)
\ No newline at end of file
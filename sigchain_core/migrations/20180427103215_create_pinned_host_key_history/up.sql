@@ -0,0 +1,10 @@
+CREATE TABLE pinned_host_key_history (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    host VARCHAR NOT NULL,
+    public_key BYTEA NOT NULL,
+    pinned BOOLEAN NOT NULL,
+    unix_seconds BIGINT NOT NULL
+);
+CREATE INDEX pinned_host_key_history_team_public_key_host_index ON pinned_host_key_history (team_public_key, host);
+ALTER TABLE logs ADD COLUMN host_key_verification VARCHAR
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE member_verifications (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    verifier_public_key BYTEA NOT NULL,
+    safety_number VARCHAR NOT NULL,
+    unix_seconds BIGINT NOT NULL
+);
+CREATE INDEX member_verifications_team_public_key_member_public_key_index ON member_verifications (team_public_key, member_public_key);
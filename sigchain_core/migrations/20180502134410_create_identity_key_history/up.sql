@@ -0,0 +1,11 @@
+CREATE TABLE identity_key_history (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    ssh_public_key BYTEA NOT NULL,
+    pgp_public_key BYTEA NOT NULL,
+    unix_seconds BIGINT NOT NULL
+);
+CREATE INDEX identity_key_history_team_public_key_member_public_key_index ON identity_key_history (team_public_key, member_public_key);
+INSERT INTO identity_key_history (team_public_key, member_public_key, ssh_public_key, pgp_public_key, unix_seconds)
+    SELECT team_public_key, public_key, ssh_public_key, pgp_public_key, 0 FROM identities;
@@ -0,0 +1,11 @@
+CREATE TABLE member_devices (
+	team_public_key BYTEA,
+	public_key BYTEA,
+	member_public_key BYTEA NOT NULL,
+	encryption_public_key BYTEA NOT NULL,
+	ssh_public_key BYTEA NOT NULL,
+	name VARCHAR NOT NULL,
+	revoked BOOLEAN NOT NULL,
+	PRIMARY KEY (team_public_key, public_key)
+);
+CREATE INDEX member_devices_team_public_key_member_public_key on member_devices (team_public_key, member_public_key)
@@ -0,0 +1,27 @@
+DROP TABLE block_positions;
+
+CREATE TABLE identity_key_history_without_main_chain_index (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    ssh_public_key BYTEA NOT NULL,
+    pgp_public_key BYTEA NOT NULL,
+    unix_seconds BIGINT NOT NULL
+);
+INSERT INTO identity_key_history_without_main_chain_index SELECT id, team_public_key, member_public_key, ssh_public_key, pgp_public_key, unix_seconds FROM identity_key_history;
+DROP TABLE identity_key_history;
+ALTER TABLE identity_key_history_without_main_chain_index RENAME TO identity_key_history;
+CREATE INDEX identity_key_history_team_public_key_member_public_key_index ON identity_key_history (team_public_key, member_public_key);
+
+CREATE TABLE pinned_host_key_history_without_main_chain_index (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    host VARCHAR NOT NULL,
+    public_key BYTEA NOT NULL,
+    pinned BOOLEAN NOT NULL,
+    unix_seconds BIGINT NOT NULL
+);
+INSERT INTO pinned_host_key_history_without_main_chain_index SELECT id, team_public_key, host, public_key, pinned, unix_seconds FROM pinned_host_key_history;
+DROP TABLE pinned_host_key_history;
+ALTER TABLE pinned_host_key_history_without_main_chain_index RENAME TO pinned_host_key_history;
+CREATE INDEX pinned_host_key_history_team_public_key_host_index ON pinned_host_key_history (team_public_key, host)
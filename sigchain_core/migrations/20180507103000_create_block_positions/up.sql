@@ -0,0 +1,16 @@
+CREATE TABLE block_positions (
+    team_public_key BYTEA NOT NULL,
+    hash BYTEA NOT NULL,
+    main_chain_index BIGINT NOT NULL,
+    PRIMARY KEY (team_public_key, hash)
+);
+INSERT INTO block_positions (team_public_key, hash, main_chain_index)
+    WITH RECURSIVE main_chain (team_public_key, hash, main_chain_index) AS (
+        SELECT team_public_key, hash, 0 FROM blocks WHERE last_block_hash IS NULL
+        UNION ALL
+        SELECT blocks.team_public_key, blocks.hash, main_chain.main_chain_index + 1
+            FROM blocks JOIN main_chain ON blocks.last_block_hash = main_chain.hash
+    )
+    SELECT team_public_key, hash, main_chain_index FROM main_chain;
+ALTER TABLE identity_key_history ADD COLUMN main_chain_index BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE pinned_host_key_history ADD COLUMN main_chain_index BIGINT NOT NULL DEFAULT 0
@@ -0,0 +1 @@
+DROP INDEX log_processing_failures_team_public_key_block_hash_index
\ No newline at end of file
@@ -0,0 +1,4 @@
+DELETE FROM log_processing_failures WHERE id NOT IN (
+    SELECT MAX(id) FROM log_processing_failures GROUP BY team_public_key, block_hash
+);
+CREATE UNIQUE INDEX log_processing_failures_team_public_key_block_hash_index ON log_processing_failures (team_public_key, block_hash)
@@ -0,0 +1 @@
+DROP TABLE published_log_chain_heads;
@@ -0,0 +1,10 @@
+CREATE TABLE published_log_chain_heads (
+    team_public_key BYTEA NOT NULL,
+    main_chain_block_hash BYTEA NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    last_block_hash BYTEA NOT NULL,
+    main_chain_index BIGINT NOT NULL,
+    unix_seconds BIGINT NOT NULL,
+    PRIMARY KEY (team_public_key, main_chain_block_hash)
+);
+CREATE INDEX published_log_chain_heads_team_public_key_member_public_key_index ON published_log_chain_heads (team_public_key, member_public_key);
@@ -0,0 +1,28 @@
+DROP INDEX logs_team_public_key_member_public_key_received_unix_seconds_index;
+CREATE TABLE logs_without_received_unix_seconds (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA,
+    member_public_key BYTEA,
+    log_json VARCHAR,
+    unix_seconds BIGINT NOT NULL,
+    signature_verification VARCHAR,
+    host_key_verification VARCHAR
+);
+INSERT INTO logs_without_received_unix_seconds SELECT id, team_public_key, member_public_key, log_json, unix_seconds, signature_verification, host_key_verification FROM logs;
+DROP TABLE logs;
+ALTER TABLE logs_without_received_unix_seconds RENAME TO logs;
+CREATE INDEX logs_team_public_key_unix_seconds_index ON logs (team_public_key, unix_seconds);
+CREATE INDEX logs_team_public_key_id_index ON logs (team_public_key, id);
+CREATE TABLE alerts_without_received_unix_seconds (
+    id INTEGER PRIMARY KEY,
+    team_public_key BYTEA NOT NULL,
+    rule_name VARCHAR NOT NULL,
+    member_public_key BYTEA NOT NULL,
+    log_json VARCHAR NOT NULL,
+    message VARCHAR NOT NULL,
+    unix_seconds BIGINT NOT NULL,
+    delivered BOOLEAN NOT NULL
+);
+INSERT INTO alerts_without_received_unix_seconds SELECT id, team_public_key, rule_name, member_public_key, log_json, message, unix_seconds, delivered FROM alerts;
+DROP TABLE alerts;
+ALTER TABLE alerts_without_received_unix_seconds RENAME TO alerts
\ No newline at end of file
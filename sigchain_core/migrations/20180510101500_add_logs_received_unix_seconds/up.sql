@@ -0,0 +1,3 @@
+ALTER TABLE logs ADD COLUMN received_unix_seconds BIGINT NOT NULL DEFAULT 0;
+CREATE INDEX logs_team_public_key_member_public_key_received_unix_seconds_index ON logs (team_public_key, member_public_key, received_unix_seconds);
+ALTER TABLE alerts ADD COLUMN received_unix_seconds BIGINT NOT NULL DEFAULT 0
\ No newline at end of file
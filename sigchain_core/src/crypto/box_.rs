@@ -1,7 +1,9 @@
 pub use super::sodiumoxide::crypto::box_::curve25519xsalsa20poly1305 as ed25519_box;
 use super::sodiumoxide::init;
 
-#[derive(Serialize, Deserialize, Clone)]
+/// The secret key is zeroized when dropped and is never printed by `Debug`. The pair is not `Clone`;
+/// copies of the secret key must be made explicitly with `duplicate`.
+#[derive(Serialize, Deserialize)]
 pub struct BoxKeyPair {
     #[serde(with="serde_publickey")]
     pub public_key: ed25519_box::PublicKey,
@@ -14,6 +16,20 @@ impl BoxKeyPair {
         use std::borrow::Borrow;
         self.public_key.0.borrow()
     }
+    pub fn duplicate(&self) -> BoxKeyPair {
+        BoxKeyPair {
+            public_key: self.public_key,
+            secret_key: self.secret_key.clone(),
+        }
+    }
+}
+
+impl ::std::fmt::Debug for BoxKeyPair {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        use base64;
+        write!(f, "BoxKeyPair {{ public_key: {}, secret_key: **** }}",
+               base64::encode_config(self.public_key_bytes(), base64::URL_SAFE))
+    }
 }
 
 pub fn seal(plaintext: &[u8], secret_key: &ed25519_box::SecretKey, public_key: &ed25519_box::PublicKey) -> Result<Vec<u8>> {
@@ -74,14 +90,16 @@ mod serde_secretkey {
         where S: Serializer,
               T: Index<RangeFull, Output = [u8]>
     {
-        serializer.serialize_str(&base64::encode(&data[..]))
+        // Wipe the encoded copy of the secret key once it is written.
+        let encoded = ::secret::SecretBytes::new(base64::encode(&data[..]).into_bytes());
+        serializer.serialize_str(::std::str::from_utf8(&encoded).map_err(serde::ser::Error::custom)?)
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<ed25519_box::SecretKey, D::Error>
         where D: Deserializer<'de>
     {
-        let b64 = String::deserialize(deserializer)?;
-        let bytes = base64::decode(&b64).map_err(serde::de::Error::custom)?;
+        let b64 = ::secret::SecretBytes::new(String::deserialize(deserializer)?.into_bytes());
+        let bytes = ::secret::SecretBytes::new(base64::decode(&b64[..]).map_err(serde::de::Error::custom)?);
         Ok(
             ed25519_box::SecretKey::from_slice(&bytes)
                 .ok_or(serde::de::Error::custom("ed25519::SecretKey::from_slice failed"))?
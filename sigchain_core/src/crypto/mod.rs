@@ -10,6 +10,12 @@ pub mod secretbox;
 pub mod box_;
 pub use self::box_::*;
 
+pub mod signature_verification;
+
+pub mod xeddsa;
+
+pub mod safety_number;
+
 extern crate rand;
 use self::rand::Rng;
 
@@ -0,0 +1,58 @@
+/// Safety numbers: a number derived from a member's keys and the team public key that an admin
+/// and the member can compare in person, in the style of Signal safety numbers.
+
+use protocol::team::Identity;
+
+extern crate ring;
+use self::ring::digest;
+
+const VERSION: [u8; 2] = [0, 0];
+/// Hash iterations, making it costly to search for keys with a colliding safety number.
+const ITERATIONS: usize = 5200;
+/// Groups of five digits in a safety number.
+const GROUPS: usize = 12;
+
+/// Sixty digits in groups of five derived from the identity's signing, encryption and SSH keys,
+/// its email and the team public key. Expects the SSH key in the canonical form stored in
+/// `db::Identity`.
+pub fn safety_number(team_public_key: &[u8], identity: &Identity) -> String {
+    let mut keys = vec![];
+    for key in [
+        team_public_key,
+        identity.public_key.as_slice(),
+        identity.encryption_public_key.as_slice(),
+        identity.ssh_public_key.as_slice(),
+    ].iter() {
+        let length = key.len();
+        keys.extend_from_slice(&[(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8, length as u8]);
+        keys.extend_from_slice(key);
+    }
+
+    let mut context = digest::Context::new(&digest::SHA512);
+    context.update(&VERSION);
+    context.update(&keys);
+    context.update(identity.email.as_bytes());
+    let mut hash = context.finish();
+    for _ in 0..ITERATIONS {
+        let mut context = digest::Context::new(&digest::SHA512);
+        context.update(hash.as_ref());
+        context.update(&keys);
+        hash = context.finish();
+    }
+
+    hash.as_ref()
+        .chunks(5)
+        .take(GROUPS)
+        .map(|chunk| {
+            let value = chunk.iter().fold(0u64, |value, b| (value << 8) | *b as u64);
+            format!("{:05}", value % 100000)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether two safety numbers match, ignoring spacing.
+pub fn safety_numbers_match(a: &str, b: &str) -> bool {
+    let digits = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    digits(a) == digits(b)
+}
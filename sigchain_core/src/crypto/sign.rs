@@ -1,6 +1,8 @@
 pub use super::sodiumoxide::crypto::sign::ed25519;
 
-#[derive(Serialize, Deserialize, Clone)]
+/// The secret key is zeroized when dropped and is never printed by `Debug`. The pair is not `Clone`;
+/// copies of the secret key must be made explicitly with `duplicate`.
+#[derive(Serialize, Deserialize)]
 pub struct SignKeyPair {
     #[serde(with="serde_publickey")]
     pub public_key: ed25519::PublicKey,
@@ -17,6 +19,18 @@ impl SignKeyPair {
         use base64;
         base64::encode_config(self.public_key_bytes(), base64::URL_SAFE)
     }
+    pub fn duplicate(&self) -> SignKeyPair {
+        SignKeyPair {
+            public_key: self.public_key,
+            secret_key: self.secret_key.clone(),
+        }
+    }
+}
+
+impl ::std::fmt::Debug for SignKeyPair {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "SignKeyPair {{ public_key: {}, secret_key: **** }}", self.public_key_base64_string())
+    }
 }
 
 pub fn sign_keypair_from_seed(seed: &[u8]) -> Result<SignKeyPair> {
@@ -68,14 +82,16 @@ mod serde_secretkey {
         where S: Serializer,
               T: Index<RangeFull, Output = [u8]>
     {
-        serializer.serialize_str(&base64::encode(&data[..]))
+        // Wipe the encoded copy of the secret key once it is written.
+        let encoded = ::secret::SecretBytes::new(base64::encode(&data[..]).into_bytes());
+        serializer.serialize_str(::std::str::from_utf8(&encoded).map_err(serde::ser::Error::custom)?)
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<ed25519::SecretKey, D::Error>
         where D: Deserializer<'de>
     {
-        let b64 = String::deserialize(deserializer)?;
-        let bytes = base64::decode(&b64).map_err(serde::de::Error::custom)?;
+        let b64 = ::secret::SecretBytes::new(String::deserialize(deserializer)?.into_bytes());
+        let bytes = ::secret::SecretBytes::new(base64::decode(&b64[..]).map_err(serde::de::Error::custom)?);
         Ok(
             ed25519::SecretKey::from_slice(&bytes)
                 .ok_or(serde::de::Error::custom("ed25519::SecretKey::from_slice failed"))?
@@ -89,7 +105,10 @@ use self::rand::Rng;
 use super::Result;
 
 pub fn gen_sign_key_pair() -> Result<SignKeyPair> {
-    sign_keypair_from_seed(&gen_sign_key_pair_seed()?)
+    let mut seed = gen_sign_key_pair_seed()?;
+    let sign_key_pair = sign_keypair_from_seed(&seed);
+    ::secret::zeroize(&mut seed);
+    sign_key_pair
 }
 
 pub fn gen_sign_key_pair_seed() -> Result<Vec<u8>> {
@@ -0,0 +1,546 @@
+/// Verify the SSH and git signatures recorded in audit logs against the signing member's
+/// `Identity.ssh_public_key` and `Identity.pgp_public_key`.
+
+use errors::{self, Result};
+use protocol::team::Identity;
+use protocol::logs::*;
+use dashboard_protocol::SignatureVerification;
+use git_hash::GitSignedData;
+use pgp::packets::*;
+use pgp::public_key::{PGPPublicKey, PGPUserId};
+
+extern crate ring;
+extern crate untrusted;
+use self::ring::digest;
+use self::ring::signature as ring_signature;
+use self::ring::signature::VerificationAlgorithm;
+
+/// Check the signature in `log`, if it has one, against the member's keys. Signatures over data
+/// that was redacted before the log was sent are reported as `Redacted` rather than `Invalid`.
+pub fn verify_log_signature(identity: &Identity, log: &Log) -> SignatureVerification {
+    let signed_data_redacted = log.redactions.iter().any(|r| match r {
+        &Redaction::CommitMessageDropped { .. } => match log.body {
+            LogBody::GitCommit(_) | LogBody::GitTag(_) => true,
+            _ => false,
+        },
+        &Redaction::SessionDataTruncated { .. } | &Redaction::UsernameHashed => match log.body {
+            LogBody::Ssh(_) => true,
+            _ => false,
+        },
+        _ => false,
+    });
+    let result = match log.body {
+        LogBody::Ssh(ref ssh) => match ssh.result {
+            SSHSignatureResult::Signature(_) if signed_data_redacted => return SignatureVerification::Redacted,
+            SSHSignatureResult::Signature(ref sig) => verify_ssh_signature(&identity.ssh_public_key, &ssh.session_data, sig),
+            _ => return SignatureVerification::NoSignature,
+        },
+        LogBody::GitCommit(ref commit) => match commit.result {
+            GitSignatureResult::Signature(_) if signed_data_redacted => return SignatureVerification::Redacted,
+            GitSignatureResult::Signature(ref sig) => verify_pgp_signature(&identity.pgp_public_key, &commit.signed_data(), sig),
+            _ => return SignatureVerification::NoSignature,
+        },
+        LogBody::GitTag(ref tag) => match tag.result {
+            GitSignatureResult::Signature(_) if signed_data_redacted => return SignatureVerification::Redacted,
+            GitSignatureResult::Signature(ref sig) => verify_pgp_signature(&identity.pgp_public_key, &tag.signed_data(), sig),
+            _ => return SignatureVerification::NoSignature,
+        },
+        _ => return SignatureVerification::NoSignature,
+    };
+    match result {
+        Ok(true) => SignatureVerification::Valid,
+        Ok(false) => SignatureVerification::Invalid,
+        Err(e) => {
+            debug!("unable to verify log signature: {}", e);
+            SignatureVerification::Unsupported
+        }
+    }
+}
+
+/// Verify an SSH signature over `data`. `public_key_wire` is an SSH wire format public key and
+/// `signature` either a wire format signature blob (algorithm name followed by the signature) or
+/// the bare signature. Errors if the key type is unsupported.
+pub fn verify_ssh_signature(public_key_wire: &[u8], data: &[u8], signature: &[u8]) -> Result<bool> {
+    let mut key = public_key_wire;
+    let key_type = read_string(&mut key)?;
+
+    let (signature_algorithm, signature) = match parse_ssh_signature_blob(signature) {
+        Some((algorithm, blob)) => (Some(algorithm), blob),
+        None => (None, signature),
+    };
+
+    match key_type {
+        b"ssh-ed25519" => {
+            let public_key = read_string(&mut key)?;
+            Ok(verify(&ring_signature::ED25519, public_key, data, signature))
+        }
+        b"ssh-rsa" => {
+            let e = read_string(&mut key)?;
+            let n = read_string(&mut key)?;
+            check_rsa_modulus_size(n)?;
+            let public_key = der_rsa_public_key(n, e);
+            let algorithms: Vec<&VerificationAlgorithm> = match signature_algorithm {
+                Some(b"ssh-rsa") => vec![&ring_signature::RSA_PKCS1_2048_8192_SHA1],
+                Some(b"rsa-sha2-256") => vec![&ring_signature::RSA_PKCS1_2048_8192_SHA256],
+                Some(b"rsa-sha2-512") => vec![&ring_signature::RSA_PKCS1_2048_8192_SHA512],
+                Some(_) => return Ok(false),
+                None => vec![
+                    &ring_signature::RSA_PKCS1_2048_8192_SHA1,
+                    &ring_signature::RSA_PKCS1_2048_8192_SHA256,
+                    &ring_signature::RSA_PKCS1_2048_8192_SHA512,
+                ],
+            };
+            Ok(algorithms.into_iter().any(|alg| verify(alg, &public_key, data, signature)))
+        }
+        b"ecdsa-sha2-nistp256" => {
+            if read_string(&mut key)? != b"nistp256" {
+                bail!("unsupported ecdsa curve");
+            }
+            let point = read_string(&mut key)?;
+            let der_signature = match signature_algorithm {
+                Some(_) => {
+                    let mut blob = signature;
+                    let r = read_string(&mut blob)?;
+                    let s = read_string(&mut blob)?;
+                    der_ecdsa_signature(r, s)
+                }
+                None if signature.len() == 64 => der_ecdsa_signature(&signature[..32], &signature[32..]),
+                None => signature.to_vec(),
+            };
+            Ok(verify(&ring_signature::ECDSA_P256_SHA256_ASN1, point, data, &der_signature))
+        }
+        _ => bail!(format!("unsupported ssh key type {}", String::from_utf8_lossy(key_type))),
+    }
+}
+
+/// Verify binary OpenPGP signature packets over `data` with the primary key in `public_key_packets`.
+/// Errors if the key or signature is malformed or uses an unsupported algorithm.
+pub fn verify_pgp_signature(public_key_packets: &[u8], data: &[u8], signature_packets: &[u8]) -> Result<bool> {
+    verify_pgp_signature_packet(&primary_public_key(public_key_packets)?, data, &first_signature(signature_packets)?)
+}
+
+/// Whether `user_id` carries a valid certification by the key's own primary key.
+pub fn has_valid_self_signature(public_key: &PGPPublicKey, user_id: &PGPUserId) -> bool {
+    let data = user_id.certification_data(&public_key.primary_key);
+    public_key.self_signatures(user_id).into_iter()
+        .any(|signature| verify_pgp_signature_packet(&public_key.primary_key, &data, signature).unwrap_or(false))
+}
+
+/// Check that `public_key_packets` is a well formed public key with a self-signed user ID for
+/// `email`.
+pub fn validate_pgp_public_key(public_key_packets: &[u8], email: &str) -> Result<PGPPublicKey> {
+    let public_key = match PGPPublicKey::parse(public_key_packets) {
+        Ok(public_key) => public_key,
+        Err(e) => {
+            debug!("malformed PGP public key: {}", e);
+            bail!(errors::PGPPublicKeyNotValid)
+        }
+    };
+    let matching_user_ids = public_key.user_ids.iter()
+        .filter(|u| u.email().map_or(false, |e| e.eq_ignore_ascii_case(email)))
+        .collect::<Vec<_>>();
+    if matching_user_ids.is_empty() {
+        bail!(errors::PGPUserIdMismatch);
+    }
+    if !matching_user_ids.into_iter().any(|u| has_valid_self_signature(&public_key, u)) {
+        bail!(errors::PGPPublicKeyNotValid);
+    }
+    Ok(public_key)
+}
+
+fn verify_pgp_signature_packet(public_key: &PublicKeyPacket, data: &[u8], signature: &SignaturePacket) -> Result<bool> {
+    if signature.public_key_algorithm != public_key.algorithm {
+        return Ok(false);
+    }
+
+    let mut message = data.to_vec();
+    message.extend(signature.hash_trailer());
+
+    match (&public_key.material, signature.mpis.len()) {
+        (&PublicKeyMaterial::RSA { ref n, ref e }, 1) => {
+            check_rsa_modulus_size(n)?;
+            let s = &signature.mpis[0];
+            let alg: &VerificationAlgorithm = match signature.hash_algorithm {
+                HashAlgorithm::SHA1 => &ring_signature::RSA_PKCS1_2048_8192_SHA1,
+                HashAlgorithm::SHA256 => &ring_signature::RSA_PKCS1_2048_8192_SHA256,
+                HashAlgorithm::SHA384 => &ring_signature::RSA_PKCS1_2048_8192_SHA384,
+                HashAlgorithm::SHA512 => &ring_signature::RSA_PKCS1_2048_8192_SHA512,
+                h => bail!(format!("unsupported hash algorithm {:?}", h)),
+            };
+            Ok(verify(alg, &der_rsa_public_key(n, e), &message, &left_pad(s, n.len())))
+        }
+        (&PublicKeyMaterial::EllipticCurve { ref curve_oid, ref point }, 2) => {
+            let (r, s) = (&signature.mpis[0], &signature.mpis[1]);
+            match public_key.algorithm {
+                PublicKeyAlgorithm::ECDSA if curve_oid.as_slice() == OID_NIST_P256 => {
+                    let alg: &VerificationAlgorithm = match signature.hash_algorithm {
+                        HashAlgorithm::SHA256 => &ring_signature::ECDSA_P256_SHA256_ASN1,
+                        HashAlgorithm::SHA384 => &ring_signature::ECDSA_P256_SHA384_ASN1,
+                        h => bail!(format!("unsupported hash algorithm {:?}", h)),
+                    };
+                    Ok(verify(alg, point, &message, &der_ecdsa_signature(r, s)))
+                }
+                PublicKeyAlgorithm::EdDSA if curve_oid.as_slice() == OID_ED25519 => {
+                    // EdDSA signs the digest rather than the message. The point is prefixed with 0x40.
+                    if point.len() != 33 || point[0] != 0x40 {
+                        bail!("malformed ed25519 public key");
+                    }
+                    let digest_alg = match signature.hash_algorithm {
+                        HashAlgorithm::SHA1 => &digest::SHA1,
+                        HashAlgorithm::SHA256 => &digest::SHA256,
+                        HashAlgorithm::SHA384 => &digest::SHA384,
+                        HashAlgorithm::SHA512 => &digest::SHA512,
+                        h => bail!(format!("unsupported hash algorithm {:?}", h)),
+                    };
+                    let hashed = digest::digest(digest_alg, &message);
+                    let mut sig = left_pad(r, 32);
+                    sig.extend(left_pad(s, 32));
+                    Ok(verify(&ring_signature::ED25519, &point[1..], hashed.as_ref(), &sig))
+                }
+                _ => bail!("unsupported elliptic curve"),
+            }
+        }
+        _ => bail!(format!("unsupported public key algorithm {:?}", public_key.algorithm)),
+    }
+}
+
+/// Verify an armored OpenSSH signature (`ssh-keygen -Y sign`, as used by git for SSH signed
+/// commits) over `data`, made with `public_key_wire` in `namespace`.
+pub fn verify_sshsig(public_key_wire: &[u8], namespace: &str, data: &[u8], armored_signature: &str) -> Result<bool> {
+    const MAGIC: &[u8] = b"SSHSIG";
+
+    let body: String = armored_signature.lines()
+        .map(|l| l.trim())
+        .skip_while(|l| *l != "-----BEGIN SSH SIGNATURE-----")
+        .skip(1)
+        .take_while(|l| *l != "-----END SSH SIGNATURE-----")
+        .collect();
+    let blob = ::base64::decode(&body)?;
+    if blob.len() < MAGIC.len() + 4 || &blob[..MAGIC.len()] != MAGIC {
+        bail!("not an ssh signature");
+    }
+    let mut data_in = &blob[MAGIC.len() + 4..];
+    let signing_key = read_string(&mut data_in)?;
+    let signed_namespace = read_string(&mut data_in)?;
+    let reserved = read_string(&mut data_in)?;
+    let hash_algorithm = read_string(&mut data_in)?;
+    let signature = read_string(&mut data_in)?;
+
+    if signing_key != public_key_wire || signed_namespace != namespace.as_bytes() {
+        return Ok(false);
+    }
+    let hashed = match hash_algorithm {
+        b"sha256" => digest::digest(&digest::SHA256, data),
+        b"sha512" => digest::digest(&digest::SHA512, data),
+        _ => bail!(format!("unsupported sshsig hash {}", String::from_utf8_lossy(hash_algorithm))),
+    };
+
+    let mut signed_data = MAGIC.to_vec();
+    for field in &[signed_namespace, reserved, hash_algorithm, hashed.as_ref()] {
+        write_string(&mut signed_data, field);
+    }
+    verify_ssh_signature(public_key_wire, &signed_data, signature)
+}
+
+fn verify(alg: &VerificationAlgorithm, public_key: &[u8], message: &[u8], sig: &[u8]) -> bool {
+    ring_signature::verify(
+        alg,
+        untrusted::Input::from(public_key),
+        untrusted::Input::from(message),
+        untrusted::Input::from(sig),
+    ).is_ok()
+}
+
+fn parse_ssh_signature_blob(blob: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut data = blob;
+    let algorithm = read_string(&mut data).ok()?;
+    let signature = read_string(&mut data).ok()?;
+    let is_algorithm_name = !algorithm.is_empty() && algorithm.iter().all(|b| b.is_ascii_graphic());
+    if data.is_empty() && is_algorithm_name {
+        Some((algorithm, signature))
+    } else {
+        None
+    }
+}
+
+fn read_string<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+    if data.len() < 4 {
+        bail!("truncated ssh wire data");
+    }
+    let length = data[..4].iter().fold(0usize, |n, b| (n << 8) | *b as usize);
+    if data.len() - 4 < length {
+        bail!("truncated ssh wire data");
+    }
+    let string = &data[4..4 + length];
+    *data = &data[4 + length..];
+    Ok(string)
+}
+
+fn write_string(out: &mut Vec<u8>, string: &[u8]) {
+    let length = string.len() as u32;
+    out.extend_from_slice(&[(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8, length as u8]);
+    out.extend_from_slice(string);
+}
+
+/// The RSA verification algorithms only accept 2048 to 8192 bit moduli and fail like a bad
+/// signature otherwise, so keys outside that range are reported as unsupported up front.
+fn check_rsa_modulus_size(n: &[u8]) -> Result<()> {
+    let n = &n[n.iter().position(|b| *b != 0).unwrap_or(n.len())..];
+    let bits = n.first().map_or(0, |b| n.len() * 8 - b.leading_zeros() as usize);
+    if bits < 2048 || bits > 8192 {
+        bail!(format!("unsupported {} bit rsa key", bits));
+    }
+    Ok(())
+}
+
+fn left_pad(bytes: &[u8], length: usize) -> Vec<u8> {
+    let mut padded = vec![0u8; length.saturating_sub(bytes.len())];
+    padded.extend_from_slice(bytes);
+    padded
+}
+
+/// DER `RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }`.
+fn der_rsa_public_key(n: &[u8], e: &[u8]) -> Vec<u8> {
+    let mut integers = der_unsigned_integer(n);
+    integers.extend(der_unsigned_integer(e));
+    der_tlv(0x30, &integers)
+}
+
+/// DER `ECDSA-Sig-Value ::= SEQUENCE { r INTEGER, s INTEGER }`.
+fn der_ecdsa_signature(r: &[u8], s: &[u8]) -> Vec<u8> {
+    let mut integers = der_unsigned_integer(r);
+    integers.extend(der_unsigned_integer(s));
+    der_tlv(0x30, &integers)
+}
+
+fn der_unsigned_integer(bytes: &[u8]) -> Vec<u8> {
+    let first_nonzero = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let mut value = bytes[first_nonzero..].to_vec();
+    if value.first().map_or(true, |b| b & 0x80 != 0) {
+        value.insert(0, 0);
+    }
+    der_tlv(0x02, &value)
+}
+
+fn der_tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    if value.len() < 0x80 {
+        encoded.push(value.len() as u8);
+    } else {
+        let length_bytes: Vec<u8> = (0..8).rev()
+            .map(|i| (value.len() >> (i * 8)) as u8)
+            .skip_while(|b| *b == 0)
+            .collect();
+        encoded.push(0x80 | length_bytes.len() as u8);
+        encoded.extend(length_bytes);
+    }
+    encoded.extend_from_slice(value);
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::{gen_sign_key_pair, ed25519, SignKeyPair};
+
+    fn ssh_ed25519_public_key(key_pair: &SignKeyPair) -> Vec<u8> {
+        let mut public_key = vec![];
+        write_string(&mut public_key, b"ssh-ed25519");
+        write_string(&mut public_key, key_pair.public_key_bytes());
+        public_key
+    }
+
+    fn ssh_rsa_public_key(modulus_bytes: usize) -> Vec<u8> {
+        let mut n = vec![0xff; modulus_bytes];
+        n.insert(0, 0);
+        let mut public_key = vec![];
+        write_string(&mut public_key, b"ssh-rsa");
+        write_string(&mut public_key, &[1, 0, 1]);
+        write_string(&mut public_key, &n);
+        public_key
+    }
+
+    fn ssh_signature_blob(algorithm: &[u8], signature: &[u8]) -> Vec<u8> {
+        let mut blob = vec![];
+        write_string(&mut blob, algorithm);
+        write_string(&mut blob, signature);
+        blob
+    }
+
+    fn pgp_packet(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0xc0 | tag, 0xff];
+        packet.extend_from_slice(&be_bytes(body.len() as u64, 4));
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    fn pgp_mpi(bytes: &[u8]) -> Vec<u8> {
+        let bytes = &bytes[bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len())..];
+        let bits = bytes.first().map_or(0, |b| (bytes.len() - 1) * 8 + (8 - b.leading_zeros() as usize));
+        let mut mpi = be_bytes(bits as u64, 2);
+        mpi.extend_from_slice(bytes);
+        mpi
+    }
+
+    fn be_bytes(n: u64, length: usize) -> Vec<u8> {
+        (0..length).rev().map(|i| (n >> (8 * i)) as u8).collect()
+    }
+
+    fn pgp_ed25519_public_key(key_pair: &SignKeyPair) -> Vec<u8> {
+        let mut point = vec![0x40];
+        point.extend_from_slice(key_pair.public_key_bytes());
+        let mut body = vec![4, 0x5a, 0xf0, 0x00, 0x00, 22, OID_ED25519.len() as u8];
+        body.extend_from_slice(OID_ED25519);
+        body.extend(pgp_mpi(&point));
+        pgp_packet(TAG_PUBLIC_KEY, &body)
+    }
+
+    fn pgp_rsa_public_key(modulus_bytes: usize) -> Vec<u8> {
+        let mut body = vec![4, 0x5a, 0xf0, 0x00, 0x00, 1];
+        body.extend(pgp_mpi(&vec![0xff; modulus_bytes]));
+        body.extend(pgp_mpi(&[1, 0, 1]));
+        pgp_packet(TAG_PUBLIC_KEY, &body)
+    }
+
+    /// A binary document signature over `data`, hashed with SHA-256.
+    fn pgp_ed25519_signature(key_pair: &SignKeyPair, data: &[u8]) -> Vec<u8> {
+        let mut body = vec![4, 0x00, 22, 8];
+        let subpackets = [5, SUBPACKET_CREATION_TIME, 0x5a, 0xf0, 0x00, 0x00];
+        body.extend(be_bytes(subpackets.len() as u64, 2));
+        body.extend_from_slice(&subpackets);
+
+        let mut message = data.to_vec();
+        message.extend_from_slice(&body);
+        message.extend_from_slice(&[4, 0xff]);
+        message.extend(be_bytes(body.len() as u64, 4));
+        let hashed = digest::digest(&digest::SHA256, &message);
+        let signature = ed25519::sign_detached(hashed.as_ref(), &key_pair.secret_key);
+
+        body.extend(be_bytes(0, 2));
+        body.extend_from_slice(&hashed.as_ref()[..2]);
+        body.extend(pgp_mpi(&signature.0[..32]));
+        body.extend(pgp_mpi(&signature.0[32..]));
+        pgp_packet(TAG_SIGNATURE, &body)
+    }
+
+    #[test]
+    fn parse_ssh_signature_blobs() {
+        let blob = ssh_signature_blob(b"ssh-ed25519", &[7; 64]);
+        assert_eq!(parse_ssh_signature_blob(&blob), Some((&b"ssh-ed25519"[..], &[7u8; 64][..])));
+
+        //  Bare signatures, trailing data and non-printable algorithm names are not blobs
+        assert_eq!(parse_ssh_signature_blob(&[7; 64]), None);
+        let mut trailing = blob.clone();
+        trailing.push(0);
+        assert_eq!(parse_ssh_signature_blob(&trailing), None);
+        assert_eq!(parse_ssh_signature_blob(&ssh_signature_blob(b"ssh ed25519", &[7; 64])), None);
+        assert_eq!(parse_ssh_signature_blob(&ssh_signature_blob(b"", &[7; 64])), None);
+    }
+
+    #[test]
+    fn read_ssh_strings() {
+        let mut data = &[0, 0, 0, 2, b'h', b'i', 0, 0, 0, 0][..];
+        assert_eq!(read_string(&mut data).unwrap(), b"hi");
+        assert_eq!(read_string(&mut data).unwrap(), b"");
+        assert!(data.is_empty());
+
+        assert!(read_string(&mut &[0, 0, 0][..]).is_err());
+        assert!(read_string(&mut &[0, 0, 0, 3, b'h', b'i'][..]).is_err());
+        assert!(read_string(&mut &[0xff, 0xff, 0xff, 0xff][..]).is_err());
+    }
+
+    #[test]
+    fn verify_ssh_ed25519_signatures() {
+        let key_pair = gen_sign_key_pair().unwrap();
+        let public_key = ssh_ed25519_public_key(&key_pair);
+        let data = b"ssh session data";
+        let signature = ed25519::sign_detached(data, &key_pair.secret_key).0.to_vec();
+
+        assert!(verify_ssh_signature(&public_key, data, &signature).unwrap());
+        assert!(verify_ssh_signature(&public_key, data, &ssh_signature_blob(b"ssh-ed25519", &signature)).unwrap());
+        assert!(!verify_ssh_signature(&public_key, b"other session data", &signature).unwrap());
+
+        let other_public_key = ssh_ed25519_public_key(&gen_sign_key_pair().unwrap());
+        assert!(!verify_ssh_signature(&other_public_key, data, &signature).unwrap());
+    }
+
+    #[test]
+    fn reject_malformed_and_unsupported_ssh_keys() {
+        assert!(verify_ssh_signature(&[0, 0, 0, 11], b"data", &[0; 64]).is_err());
+
+        let mut dsa_public_key = vec![];
+        write_string(&mut dsa_public_key, b"ssh-dss");
+        assert!(verify_ssh_signature(&dsa_public_key, b"data", &[0; 64]).is_err());
+    }
+
+    #[test]
+    fn report_rsa_keys_outside_supported_sizes_as_unsupported() {
+        assert!(check_rsa_modulus_size(&[0xff; 256]).is_ok());
+        assert!(check_rsa_modulus_size(&[0xff; 1024]).is_ok());
+        assert!(check_rsa_modulus_size(&[0xff; 128]).is_err());
+        assert!(check_rsa_modulus_size(&[0xff; 1025]).is_err());
+        //  Leading zero bytes do not count toward the size
+        let mut padded = vec![0; 128];
+        padded.extend_from_slice(&[0x01; 128]);
+        assert!(check_rsa_modulus_size(&padded).is_err());
+
+        let signature = ssh_signature_blob(b"rsa-sha2-256", &[1; 128]);
+        assert!(verify_ssh_signature(&ssh_rsa_public_key(128), b"data", &signature).is_err());
+        let signature = ssh_signature_blob(b"rsa-sha2-256", &[1; 256]);
+        assert!(!verify_ssh_signature(&ssh_rsa_public_key(256), b"data", &signature).unwrap());
+
+        let mut rsa_signature = vec![4, 0x00, 1, 8, 0, 0, 0, 0, 0xab, 0xcd];
+        rsa_signature.extend(pgp_mpi(&[1; 128]));
+        let rsa_signature = pgp_packet(TAG_SIGNATURE, &rsa_signature);
+        assert!(verify_pgp_signature(&pgp_rsa_public_key(128), b"data", &rsa_signature).is_err());
+    }
+
+    #[test]
+    fn verify_pgp_ed25519_signatures() {
+        let key_pair = gen_sign_key_pair().unwrap();
+        let public_key = pgp_ed25519_public_key(&key_pair);
+        let data = b"tree 0123456789abcdef\n\ncommit message\n";
+        let signature = pgp_ed25519_signature(&key_pair, data);
+
+        assert!(verify_pgp_signature(&public_key, data, &signature).unwrap());
+        assert!(!verify_pgp_signature(&public_key, b"other commit", &signature).unwrap());
+        let other_public_key = pgp_ed25519_public_key(&gen_sign_key_pair().unwrap());
+        assert!(!verify_pgp_signature(&other_public_key, data, &signature).unwrap());
+    }
+
+    #[test]
+    fn parse_pgp_signature_packets() {
+        let key_pair = gen_sign_key_pair().unwrap();
+        let signature_packets = pgp_ed25519_signature(&key_pair, b"data");
+        let signature = first_signature(&signature_packets).unwrap();
+        assert_eq!(signature.public_key_algorithm, PublicKeyAlgorithm::EdDSA);
+        assert_eq!(signature.hash_algorithm, HashAlgorithm::SHA256);
+        assert_eq!(signature.created_unix_seconds(), Some(0x5af00000));
+        assert_eq!(signature.mpis.len(), 2);
+
+        let public_key = primary_public_key(&pgp_ed25519_public_key(&key_pair)).unwrap();
+        assert_eq!(public_key.algorithm, PublicKeyAlgorithm::EdDSA);
+        match public_key.material {
+            PublicKeyMaterial::EllipticCurve { ref curve_oid, ref point } => {
+                assert_eq!(curve_oid.as_slice(), OID_ED25519);
+                assert_eq!(&point[1..], key_pair.public_key_bytes());
+            }
+            ref material => panic!("unexpected key material {:?}", material),
+        }
+    }
+
+    #[test]
+    fn reject_malformed_pgp_packets() {
+        let key_pair = gen_sign_key_pair().unwrap();
+        let public_key = pgp_ed25519_public_key(&key_pair);
+        let signature = pgp_ed25519_signature(&key_pair, b"data");
+
+        //  Truncated packets, a missing packet header bit, and packets of the wrong type
+        assert!(verify_pgp_signature(&public_key, b"data", &signature[..signature.len() - 1]).is_err());
+        assert!(verify_pgp_signature(&public_key[..public_key.len() - 1], b"data", &signature).is_err());
+        assert!(verify_pgp_signature(&[0x00], b"data", &signature).is_err());
+        assert!(verify_pgp_signature(&public_key, b"data", &public_key).is_err());
+        assert!(verify_pgp_signature(&signature, b"data", &signature).is_err());
+        assert!(verify_pgp_signature(&public_key, b"data", &[]).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+/// XEdDSA (https://signal.org/docs/specifications/xeddsa/): Ed25519 compatible signatures made
+/// with an X25519 `BoxKeyPair` secret key, verifiable by anyone holding its public key.
+///
+/// The field and group arithmetic follows TweetNaCl.
+
+use super::Result;
+
+extern crate rand;
+extern crate ring;
+extern crate untrusted;
+use self::rand::Rng;
+use self::ring::digest;
+use self::ring::signature as ring_signature;
+use self::ring::signature::VerificationAlgorithm;
+
+pub const SIGNATURE_BYTES: usize = 64;
+
+type FieldElement = [i64; 16];
+type Point = [FieldElement; 4];
+
+const FE_ZERO: FieldElement = [0; 16];
+const FE_ONE: FieldElement = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+/// 2 * d, d being the Edwards curve constant -121665/121666.
+const D2: FieldElement = [
+    0xf159, 0x26b2, 0x9b94, 0xebd6, 0xb156, 0x8283, 0x149a, 0x00e0,
+    0xd130, 0xeef3, 0x80f2, 0x198e, 0xfce7, 0x56df, 0xd9dc, 0x2406,
+];
+/// The base point's coordinates.
+const BASE_X: FieldElement = [
+    0xd51a, 0x8f25, 0x2d60, 0xc956, 0xa7b2, 0x9525, 0xc760, 0x692c,
+    0xdc5c, 0xfdd6, 0xe231, 0xc0a4, 0x53fe, 0xcd6e, 0x36d3, 0x2169,
+];
+const BASE_Y: FieldElement = [
+    0x6658, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+    0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+];
+/// The order of the base point, little endian.
+const L: [i64; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+];
+
+/// Sign `message` with the 32 byte X25519 secret key `box_secret_key`.
+pub fn sign(box_secret_key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+    if box_secret_key.len() != 32 {
+        bail!("x25519 secret key must be 32 bytes");
+    }
+    let mut clamped = [0u8; 32];
+    clamped.copy_from_slice(box_secret_key);
+    clamped[0] &= 248;
+    clamped[31] &= 127;
+    clamped[31] |= 64;
+
+    // The Edwards key pair with the same public u-coordinate, negated if needed so that the
+    // public key's sign bit is zero.
+    let mut secret_scalar = reduce(&clamped);
+    let mut public_key = pack_point(&scalar_base(&secret_scalar));
+    if public_key[31] & 0x80 != 0 {
+        secret_scalar = negate_scalar(&secret_scalar);
+        public_key[31] &= 0x7f;
+    }
+
+    let mut random = [0u8; 64];
+    rand::os::OsRng::new()?.fill_bytes(&mut random);
+    let mut prefix = [0xffu8; 32];
+    prefix[0] = 0xfe;
+    let r = reduce(&sha512(&[&prefix, &secret_scalar, message, &random]));
+    let big_r = pack_point(&scalar_base(&r));
+    let h = reduce(&sha512(&[&big_r, &public_key, message]));
+
+    let mut x = [0i64; 64];
+    for i in 0..32 {
+        x[i] = r[i] as i64;
+    }
+    for i in 0..32 {
+        for j in 0..32 {
+            x[i + j] += h[i] as i64 * secret_scalar[j] as i64;
+        }
+    }
+    let s = mod_l(&mut x);
+
+    let mut signature = big_r.to_vec();
+    signature.extend_from_slice(&s);
+    Ok(signature)
+}
+
+/// Verify `signature` over `message` against the 32 byte X25519 public key `box_public_key`.
+pub fn verify(box_public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
+    if box_public_key.len() != 32 {
+        bail!("x25519 public key must be 32 bytes");
+    }
+    if signature.len() != SIGNATURE_BYTES {
+        return Ok(false);
+    }
+    let u = unpack25519(box_public_key);
+    let mut canonical_u = [0u8; 32];
+    pack25519(&mut canonical_u, &u);
+    if box_public_key[31] & 0x80 != 0 || canonical_u[..] != box_public_key[..] {
+        bail!("x25519 public key is not canonical");
+    }
+    let u_plus_one = fe_add(&u, &FE_ONE);
+    let mut packed_u_plus_one = [0u8; 32];
+    pack25519(&mut packed_u_plus_one, &u_plus_one);
+    if packed_u_plus_one == [0u8; 32] {
+        bail!("x25519 public key has no Edwards equivalent");
+    }
+
+    // The Edwards y-coordinate (u - 1) / (u + 1), with sign bit zero.
+    let y = fe_mul(&fe_sub(&u, &FE_ONE), &fe_invert(&u_plus_one));
+    let mut edwards_public_key = [0u8; 32];
+    pack25519(&mut edwards_public_key, &y);
+
+    Ok(ring_signature::ED25519.verify(
+        untrusted::Input::from(&edwards_public_key),
+        untrusted::Input::from(message),
+        untrusted::Input::from(signature),
+    ).is_ok())
+}
+
+fn sha512(parts: &[&[u8]]) -> [u8; 64] {
+    let mut context = digest::Context::new(&digest::SHA512);
+    for part in parts {
+        context.update(part);
+    }
+    let mut hash = [0u8; 64];
+    hash.copy_from_slice(context.finish().as_ref());
+    hash
+}
+
+fn carry25519(o: &mut FieldElement) {
+    for i in 0..16 {
+        o[i] += 1 << 16;
+        let c = o[i] >> 16;
+        if i < 15 {
+            o[i + 1] += c - 1;
+        } else {
+            o[0] += 38 * (c - 1);
+        }
+        o[i] -= c << 16;
+    }
+}
+
+/// Swap `p` and `q` in constant time if `b` is 1.
+fn select25519(p: &mut FieldElement, q: &mut FieldElement, b: i64) {
+    let c = !(b - 1);
+    for i in 0..16 {
+        let t = c & (p[i] ^ q[i]);
+        p[i] ^= t;
+        q[i] ^= t;
+    }
+}
+
+fn pack25519(o: &mut [u8; 32], n: &FieldElement) {
+    let mut t = *n;
+    carry25519(&mut t);
+    carry25519(&mut t);
+    carry25519(&mut t);
+    let mut m = FE_ZERO;
+    for _ in 0..2 {
+        m[0] = t[0] - 0xffed;
+        for i in 1..15 {
+            m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+            m[i - 1] &= 0xffff;
+        }
+        m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+        let b = (m[15] >> 16) & 1;
+        m[14] &= 0xffff;
+        select25519(&mut t, &mut m, 1 - b);
+    }
+    for i in 0..16 {
+        o[2 * i] = t[i] as u8;
+        o[2 * i + 1] = (t[i] >> 8) as u8;
+    }
+}
+
+fn unpack25519(n: &[u8]) -> FieldElement {
+    let mut o = FE_ZERO;
+    for i in 0..16 {
+        o[i] = n[2 * i] as i64 + ((n[2 * i + 1] as i64) << 8);
+    }
+    o[15] &= 0x7fff;
+    o
+}
+
+fn parity25519(a: &FieldElement) -> u8 {
+    let mut d = [0u8; 32];
+    pack25519(&mut d, a);
+    d[0] & 1
+}
+
+fn fe_add(a: &FieldElement, b: &FieldElement) -> FieldElement {
+    let mut o = FE_ZERO;
+    for i in 0..16 {
+        o[i] = a[i] + b[i];
+    }
+    o
+}
+
+fn fe_sub(a: &FieldElement, b: &FieldElement) -> FieldElement {
+    let mut o = FE_ZERO;
+    for i in 0..16 {
+        o[i] = a[i] - b[i];
+    }
+    o
+}
+
+fn fe_mul(a: &FieldElement, b: &FieldElement) -> FieldElement {
+    let mut t = [0i64; 31];
+    for i in 0..16 {
+        for j in 0..16 {
+            t[i + j] += a[i] * b[j];
+        }
+    }
+    for i in 0..15 {
+        t[i] += 38 * t[i + 16];
+    }
+    let mut o = FE_ZERO;
+    o.copy_from_slice(&t[..16]);
+    carry25519(&mut o);
+    carry25519(&mut o);
+    o
+}
+
+/// `i` raised to p - 2.
+fn fe_invert(i: &FieldElement) -> FieldElement {
+    let mut c = *i;
+    for a in (0..254).rev() {
+        c = fe_mul(&c, &c);
+        if a != 2 && a != 4 {
+            c = fe_mul(&c, i);
+        }
+    }
+    c
+}
+
+fn point_add(p: &mut Point, q: &Point) {
+    let a = fe_mul(&fe_sub(&p[1], &p[0]), &fe_sub(&q[1], &q[0]));
+    let b = fe_mul(&fe_add(&p[0], &p[1]), &fe_add(&q[0], &q[1]));
+    let c = fe_mul(&fe_mul(&p[3], &q[3]), &D2);
+    let d = fe_mul(&p[2], &q[2]);
+    let d = fe_add(&d, &d);
+    let e = fe_sub(&b, &a);
+    let f = fe_sub(&d, &c);
+    let g = fe_add(&d, &c);
+    let h = fe_add(&b, &a);
+
+    p[0] = fe_mul(&e, &f);
+    p[1] = fe_mul(&h, &g);
+    p[2] = fe_mul(&g, &f);
+    p[3] = fe_mul(&e, &h);
+}
+
+fn point_swap(p: &mut Point, q: &mut Point, b: u8) {
+    for i in 0..4 {
+        select25519(&mut p[i], &mut q[i], b as i64);
+    }
+}
+
+fn pack_point(p: &Point) -> [u8; 32] {
+    let z_inverse = fe_invert(&p[2]);
+    let x = fe_mul(&p[0], &z_inverse);
+    let y = fe_mul(&p[1], &z_inverse);
+    let mut r = [0u8; 32];
+    pack25519(&mut r, &y);
+    r[31] ^= parity25519(&x) << 7;
+    r
+}
+
+fn scalar_base(s: &[u8; 32]) -> Point {
+    let mut q = [BASE_X, BASE_Y, FE_ONE, fe_mul(&BASE_X, &BASE_Y)];
+    let mut p = [FE_ZERO, FE_ONE, FE_ONE, FE_ZERO];
+    for i in (0..256).rev() {
+        let b = (s[i / 8] >> (i & 7)) & 1;
+        point_swap(&mut p, &mut q, b);
+        let p_copy = p;
+        point_add(&mut q, &p_copy);
+        point_add(&mut p, &p_copy);
+        point_swap(&mut p, &mut q, b);
+    }
+    p
+}
+
+/// Reduce the little endian number `x` modulo `L`.
+fn mod_l(x: &mut [i64; 64]) -> [u8; 32] {
+    for i in (32..64).rev() {
+        let mut carry = 0;
+        let mut j = i - 32;
+        while j < i - 12 {
+            x[j] += carry - 16 * x[i] * L[j - (i - 32)];
+            carry = (x[j] + 128) >> 8;
+            x[j] -= carry << 8;
+            j += 1;
+        }
+        x[j] += carry;
+        x[i] = 0;
+    }
+    let mut carry = 0;
+    for j in 0..32 {
+        x[j] += carry - (x[31] >> 4) * L[j];
+        carry = x[j] >> 8;
+        x[j] &= 255;
+    }
+    for j in 0..32 {
+        x[j] -= carry * L[j];
+    }
+    let mut r = [0u8; 32];
+    for i in 0..32 {
+        x[i + 1] += x[i] >> 8;
+        r[i] = (x[i] & 255) as u8;
+    }
+    r
+}
+
+fn reduce(bytes: &[u8]) -> [u8; 32] {
+    let mut x = [0i64; 64];
+    for (i, b) in bytes.iter().enumerate() {
+        x[i] = *b as i64;
+    }
+    mod_l(&mut x)
+}
+
+/// `L - s` for a reduced, non-zero scalar `s`.
+fn negate_scalar(s: &[u8; 32]) -> [u8; 32] {
+    let mut r = [0u8; 32];
+    let mut borrow = 0;
+    for i in 0..32 {
+        let mut v = L[i] - s[i] as i64 - borrow;
+        borrow = 0;
+        if v < 0 {
+            v += 256;
+            borrow = 1;
+        }
+        r[i] = v as u8;
+    }
+    r
+}
@@ -1,4 +1,6 @@
 use super::*;
+use std::collections::HashMap;
+use ssh_session::SSHSessionData;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Identity {
@@ -7,6 +9,12 @@ pub struct Identity {
     pub public_key: Vec<u8>,
     pub pgp_public_key: String,
     pub ssh_public_key: String,
+    /// Fingerprint of the primary PGP key, `None` if the key could not be parsed.
+    #[serde(default)]
+    pub pgp_fingerprint: Option<String>,
+    /// `SHA256:` fingerprint of the SSH key, `None` if the key could not be parsed.
+    #[serde(default)]
+    pub ssh_fingerprint: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -14,6 +22,13 @@ pub struct HostAccess {
     pub host: String,
     pub accesses: i64,
     pub last_access_unix_seconds: i64,
+    /// Accesses whose host key was pinned for the host at the time.
+    #[serde(default)]
+    pub pinned_accesses: i64,
+    /// Accesses whose host key differed from the keys pinned for the host, or whose host signature
+    /// did not verify.
+    #[serde(default)]
+    pub mismatched_accesses: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -25,6 +40,252 @@ pub struct TeamMember {
     pub logins_today: i64,
     pub last_24_hours_accesses: Vec<logs::Log>,
     pub hosts: Vec<HostAccess>,
+    pub log_integrity: MemberLogIntegrity,
+    #[serde(default)]
+    pub devices: Vec<Device>,
+    /// Decoded `session_data` of the SSH logs in `last_24_hours_accesses`, keyed by the base64
+    /// encoded session data.
+    #[serde(default)]
+    pub ssh_sessions: HashMap<String, SSHSessionData>,
+    /// Safety number to compare with the member in person.
+    #[serde(default)]
+    pub safety_number: Option<String>,
+    /// Emails of the admins who attested to the current safety number.
+    #[serde(default)]
+    pub verified_by: Vec<String>,
+    /// Devices the member authorized to sign for them, besides their own key.
+    #[serde(default)]
+    pub signing_devices: Vec<SigningDevice>,
+}
+
+/// A member's additional device from an `AddDevice` block.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SigningDevice {
+    pub name: String,
+    #[serde(with = "super::b64data")]
+    pub public_key: Vec<u8>,
+}
+
+impl TeamMember {
+    pub fn ssh_session(&self, log: &logs::Log) -> Option<SSHSessionData> {
+        match log.body {
+            logs::LogBody::Ssh(ref ssh) => self.ssh_sessions.get(&::base64::encode(&ssh.session_data)).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// A workstation seen in a member's logs, identified by `Session::workstation_public_key_double_hash`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Device {
+    pub device_name: String,
+    #[serde(with = "super::b64data")]
+    pub workstation_public_key_double_hash: Vec<u8>,
+    pub first_seen_unix_seconds: i64,
+    pub last_seen_unix_seconds: i64,
+    pub ssh_operations: u64,
+    pub git_operations: u64,
+    pub other_operations: u64,
+    pub hosts: Vec<String>,
+    /// Used recently by a member who is still on the team.
+    pub is_active: bool,
+}
+
+/// Devices unused for longer than this are no longer considered active.
+pub const DEVICE_ACTIVE_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// Aggregate a member's logs into one `Device` per workstation, most recently used first.
+pub fn devices_from_logs(logs: &[logs::Log], member_is_active: bool, now_unix_seconds: i64) -> Vec<Device> {
+    use std::collections::BTreeSet;
+
+    let mut devices: Vec<(Device, BTreeSet<String>)> = vec![];
+    for log in logs {
+        let unix_seconds = log.unix_seconds as i64;
+        let index = match devices.iter().position(|&(ref d, _)| d.workstation_public_key_double_hash == log.session.workstation_public_key_double_hash) {
+            Some(index) => index,
+            None => {
+                devices.push((Device {
+                    device_name: log.session.device_name.clone(),
+                    workstation_public_key_double_hash: log.session.workstation_public_key_double_hash.clone(),
+                    first_seen_unix_seconds: unix_seconds,
+                    last_seen_unix_seconds: unix_seconds,
+                    ssh_operations: 0,
+                    git_operations: 0,
+                    other_operations: 0,
+                    hosts: vec![],
+                    is_active: false,
+                }, BTreeSet::new()));
+                devices.len() - 1
+            }
+        };
+        let &mut (ref mut device, ref mut hosts) = &mut devices[index];
+
+        device.first_seen_unix_seconds = device.first_seen_unix_seconds.min(unix_seconds);
+        if unix_seconds >= device.last_seen_unix_seconds {
+            device.last_seen_unix_seconds = unix_seconds;
+            device.device_name = log.session.device_name.clone();
+        }
+        let host = match log.body {
+            logs::LogBody::Ssh(ref ssh) => {
+                device.ssh_operations += 1;
+                ssh.host_authorization.as_ref().map(|h| h.host.clone())
+            }
+            logs::LogBody::SshAgentForwarding(ref agent_forwarding) => {
+                device.ssh_operations += 1;
+                agent_forwarding.host_authorization.as_ref().map(|h| h.host.clone())
+            }
+            logs::LogBody::GitCommit(_) | logs::LogBody::GitTag(_) => {
+                device.git_operations += 1;
+                None
+            }
+            logs::LogBody::GitPush(ref push) => {
+                device.git_operations += 1;
+                Some(push.remote.clone())
+            }
+            logs::LogBody::Sudo(ref sudo) => {
+                device.other_operations += 1;
+                Some(sudo.host.clone())
+            }
+            logs::LogBody::PamLogin(ref pam_login) => {
+                device.other_operations += 1;
+                Some(pam_login.host.clone())
+            }
+        };
+        hosts.extend(host);
+    }
+
+    let mut devices = devices.into_iter().map(|(mut device, hosts)| {
+        device.hosts = hosts.into_iter().collect();
+        device.is_active = member_is_active && now_unix_seconds - device.last_seen_unix_seconds <= DEVICE_ACTIVE_SECONDS;
+        device
+    }).collect::<Vec<_>>();
+    devices.sort_by_key(|d| -d.last_seen_unix_seconds);
+    devices
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MemberDevices {
+    pub email: String,
+    #[serde(with = "super::b64data")]
+    pub member_public_key: Vec<u8>,
+    pub is_member_active: bool,
+    pub devices: Vec<Device>,
+}
+
+/// Counts of a member's log blocks that could not be turned into readable logs on this client.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MemberLogIntegrity {
+    #[serde(with = "super::b64data")]
+    pub member_public_key: Vec<u8>,
+    pub decrypted_logs: u64,
+    pub missing_key: u64,
+    pub bad_ciphertext: u64,
+    pub malformed_log: u64,
+    pub bad_wrapped_key: u64,
+    pub last_failure_unix_seconds: Option<i64>,
+    #[serde(default)]
+    pub valid_signatures: u64,
+    #[serde(default)]
+    pub invalid_signatures: u64,
+    /// Signatures made with a key type or algorithm this client cannot check.
+    #[serde(default)]
+    pub unverified_signatures: u64,
+    /// Logs written by devices the member had revoked.
+    #[serde(default)]
+    pub revoked_device_logs: u64,
+    /// Signatures over data that was redacted before the log was sent.
+    #[serde(default)]
+    pub redacted_signatures: u64,
+    /// Log blocks written before the member first shared a key with this client. Not counted as gaps.
+    #[serde(default)]
+    pub before_first_key: u64,
+}
+
+impl MemberLogIntegrity {
+    /// Logs written by the member that this client will never be able to show.
+    pub fn missing_logs(&self) -> u64 {
+        self.missing_key + self.bad_ciphertext + self.malformed_log
+    }
+    pub fn has_gaps(&self) -> bool {
+        self.missing_logs() > 0 || self.bad_wrapped_key > 0
+    }
+}
+
+/// Outcome of checking the SSH or git signature in a log against the member's keys when the log
+/// was received.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureVerification {
+    Valid,
+    /// The signature does not verify: the log is forged or corrupted.
+    Invalid,
+    /// The log records no signature, e.g. a rejected request or a sudo approval.
+    NoSignature,
+    /// The key or signature uses an algorithm this client cannot check.
+    Unsupported,
+    /// The log was written by a device that had been revoked when this client received it.
+    RevokedDevice,
+    /// The team's redaction rules removed part of the signed data, so the signature cannot be checked.
+    Redacted,
+}
+
+impl SignatureVerification {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &SignatureVerification::Valid => "valid",
+            &SignatureVerification::Invalid => "invalid",
+            &SignatureVerification::NoSignature => "no_signature",
+            &SignatureVerification::Unsupported => "unsupported",
+            &SignatureVerification::RevokedDevice => "revoked_device",
+            &SignatureVerification::Redacted => "redacted",
+        }
+    }
+    pub fn from_str(s: &str) -> Option<SignatureVerification> {
+        match s {
+            "valid" => Some(SignatureVerification::Valid),
+            "invalid" => Some(SignatureVerification::Invalid),
+            "no_signature" => Some(SignatureVerification::NoSignature),
+            "unsupported" => Some(SignatureVerification::Unsupported),
+            "revoked_device" => Some(SignatureVerification::RevokedDevice),
+            "redacted" => Some(SignatureVerification::Redacted),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of checking the host key presented in an SSH log against the team's pinned host keys
+/// at the time of the log.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HostKeyVerification {
+    /// The host key is pinned for the host.
+    PinnedMatch,
+    /// No keys were pinned for the host.
+    Unpinned,
+    /// Other keys were pinned for the host.
+    Mismatch,
+    /// The host's signature over the session does not verify with the presented host key.
+    InvalidSignature,
+}
+
+impl HostKeyVerification {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &HostKeyVerification::PinnedMatch => "pinned_match",
+            &HostKeyVerification::Unpinned => "unpinned",
+            &HostKeyVerification::Mismatch => "mismatch",
+            &HostKeyVerification::InvalidSignature => "invalid_signature",
+        }
+    }
+    pub fn from_str(s: &str) -> Option<HostKeyVerification> {
+        match s {
+            "pinned_match" => Some(HostKeyVerification::PinnedMatch),
+            "unpinned" => Some(HostKeyVerification::Unpinned),
+            "mismatch" => Some(HostKeyVerification::Mismatch),
+            "invalid_signature" => Some(HostKeyVerification::InvalidSignature),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -38,6 +299,76 @@ pub struct LogsResponse {
     pub logs: Vec<logs::Log>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogSearchRequest {
+    pub query: String,
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogSearchResult {
+    pub email: String,
+    #[serde(with = "super::b64data")]
+    pub member_public_key: Vec<u8>,
+    pub log: logs::Log,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogSearchResponse {
+    pub results: Vec<LogSearchResult>,
+}
+
+/// Why a log stood out from its member's usual access pattern.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyReason {
+    NewHost {
+        host: String,
+    },
+    NewDevice {
+        device_name: String,
+        #[serde(with = "super::b64data")]
+        workstation_public_key_double_hash: Vec<u8>,
+    },
+    NewUnixUser {
+        user: String,
+    },
+    /// Fewer than `seen_fraction` of the member's previous logs were in this UTC hour.
+    UnusualHour {
+        hour: u32,
+        seen_fraction: f64,
+    },
+}
+
+impl AnomalyReason {
+    pub fn explanation(&self) -> String {
+        match self {
+            &AnomalyReason::NewHost { ref host } => format!("first access to {}", host),
+            &AnomalyReason::NewDevice { ref device_name, .. } => format!("first use of workstation {}", device_name),
+            &AnomalyReason::NewUnixUser { ref user } => format!("first use of unix user {}", user),
+            &AnomalyReason::UnusualHour { hour, seen_fraction } =>
+                format!("{:02}:00 UTC is unusual ({:.1}% of previous activity)", hour, seen_fraction * 100.0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccessAnomaly {
+    pub email: String,
+    #[serde(with = "super::b64data")]
+    pub member_public_key: Vec<u8>,
+    pub unix_seconds: i64,
+    pub score: i64,
+    pub reasons: Vec<AnomalyReason>,
+    pub log: Option<logs::Log>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnomaliesResponse {
+    pub anomalies: Vec<AccessAnomaly>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct PublicKeyRequest {
     #[serde(with = "super::b64data")]
@@ -49,6 +380,23 @@ pub struct LinkResponse {
     pub link: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogQueueStatus {
+    pub queued: u64,
+    pub oldest_queued_unix_seconds: Option<i64>,
+    pub max_attempts: i64,
+    pub dead_lettered: Vec<DeadLetteredLog>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeadLetteredLog {
+    pub log: Option<logs::Log>,
+    pub created_unix_seconds: i64,
+    pub dead_lettered_unix_seconds: i64,
+    pub attempts: i64,
+    pub reason: String,
+}
+
 /** Dashboard request / response **/
 pub type SearchQueryFilter = Option<String>;
 
@@ -184,6 +532,24 @@ impl logs::LogBody {
                 let message = &tag.message_string.clone().unwrap_or("unknown".into());
 
                 return format!("git tag {} -m \"{}\"", tag_string, message);
+            },
+            &logs::LogBody::Sudo(ref sudo) => {
+                let command = sudo.command.clone().unwrap_or("unknown command".into());
+                return format!("sudo -u {} {} @ {}", sudo.target_user, command, sudo.host);
+            },
+            &logs::LogBody::PamLogin(ref pam_login) => {
+                return format!("login {} @ {} ({})", pam_login.user, pam_login.host, pam_login.service);
+            },
+            &logs::LogBody::SshAgentForwarding(ref agent_forwarding) => {
+                let host = agent_forwarding.host_authorization.as_ref().map(|h| h.host.clone()).unwrap_or("unknown host".into());
+                return match agent_forwarding.destination_host {
+                    Some(ref destination) => format!("ssh -A {} @ {} -> {}", agent_forwarding.user, host, destination),
+                    None => format!("ssh -A {} @ {}", agent_forwarding.user, host),
+                };
+            },
+            &logs::LogBody::GitPush(ref push) => {
+                let refs = push.ref_updates.iter().map(|r| r.name.clone()).collect::<Vec<_>>().join(" ");
+                return format!("git push {} {}", push.remote, refs);
             }
         }
     }
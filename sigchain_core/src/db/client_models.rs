@@ -1,9 +1,11 @@
 use super::*;
 use diesel::prelude::*;
-use diesel::{insert_into, delete};
+use diesel::{insert_into, update, delete};
 use diesel::associations::HasTable;
 
-#[derive(Identifiable, Queryable, Insertable, Debug, Clone)]
+/// `reader_key_pair` is a JSON `SignKeyPair`; it is encrypted with the column key in the database,
+/// zeroized on drop and redacted from `Debug` output.
+#[derive(Identifiable, Queryable, Insertable)]
 #[table_name="read_tokens"]
 #[primary_key(team_public_key)]
 pub struct ReadToken {
@@ -12,19 +14,43 @@ pub struct ReadToken {
     pub reader_key_pair: Vec<u8>,
 }
 
+impl ::std::fmt::Debug for ReadToken {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("ReadToken")
+            .field("team_public_key", &self.team_public_key)
+            .field("token", &self.token)
+            .field("reader_key_pair", &"****")
+            .finish()
+    }
+}
+
+impl Drop for ReadToken {
+    fn drop(&mut self) {
+        ::secret::zeroize(&mut self.reader_key_pair);
+    }
+}
+
 impl ReadToken {
     pub fn find(conn: &DBConnection, team_public_key: &[u8]) -> QueryResult<Self> {
-        Self::table().find(team_public_key).get_result(conn)
+        let mut read_token: Self = Self::table().find(team_public_key).get_result(conn)?;
+        read_token.reader_key_pair = column_encryption::decrypt_bytes(conn, &read_token.reader_key_pair)?;
+        Ok(read_token)
     }
     pub fn insert(&self, conn: &DBConnection) -> QueryResult<usize> {
-        insert_into(Self::table()).values(self).execute(conn)
+        insert_into(Self::table()).values(&ReadToken {
+            team_public_key: self.team_public_key.clone(),
+            token: self.token.clone(),
+            reader_key_pair: column_encryption::encrypt_bytes(conn, &self.reader_key_pair)?,
+        }).execute(conn)
     }
     pub fn delete(conn: &DBConnection, team_public_key: &[u8]) -> QueryResult<usize> {
         delete(Self::table().find(team_public_key)).execute(conn)
     }
 }
 
-#[derive(Queryable, Insertable, Debug, Clone)]
+/// The JSON key pairs are encrypted with the column key in the database, zeroized on drop and
+/// redacted from `Debug` output.
+#[derive(Queryable, Insertable)]
 #[table_name="current_team"]
 pub struct CurrentTeam {
     pub team_checkpoint: Vec<u8>,
@@ -32,22 +58,50 @@ pub struct CurrentTeam {
     pub box_key_pair: Option<Vec<u8>>,
 }
 
+impl ::std::fmt::Debug for CurrentTeam {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("CurrentTeam")
+            .field("team_checkpoint", &String::from_utf8_lossy(&self.team_checkpoint))
+            .field("sign_key_pair", &self.sign_key_pair.as_ref().map(|_| "****"))
+            .field("box_key_pair", &self.box_key_pair.as_ref().map(|_| "****"))
+            .finish()
+    }
+}
+
+impl Drop for CurrentTeam {
+    fn drop(&mut self) {
+        for key_pair in self.sign_key_pair.iter_mut().chain(self.box_key_pair.iter_mut()) {
+            ::secret::zeroize(key_pair);
+        }
+    }
+}
+
 impl CurrentTeam {
     pub fn find(conn: &DBConnection) -> QueryResult<Self> {
-        current_team::table.first(conn)
+        let mut team: Self = current_team::table.first(conn)?;
+        team.sign_key_pair = column_encryption::decrypt_optional_bytes(conn, team.sign_key_pair.as_ref().map(|k| &k[..]))?;
+        team.box_key_pair = column_encryption::decrypt_optional_bytes(conn, team.box_key_pair.as_ref().map(|k| &k[..]))?;
+        Ok(team)
     }
     pub fn set(&self, conn: &DBConnection) -> QueryResult<usize> {
+        let encrypted = CurrentTeam {
+            team_checkpoint: self.team_checkpoint.clone(),
+            sign_key_pair: column_encryption::encrypt_optional_bytes(conn, self.sign_key_pair.as_ref().map(|k| &k[..]))?,
+            box_key_pair: column_encryption::encrypt_optional_bytes(conn, self.box_key_pair.as_ref().map(|k| &k[..]))?,
+        };
         Self::delete(conn)?;
-        insert_into(current_team::table).values(self).execute(conn)
+        insert_into(current_team::table).values(&encrypted).execute(conn)
     }
     pub fn delete(conn: &DBConnection) -> QueryResult<()> {
         delete(current_team::table).execute(conn)?;
         delete(current_wrapped_keys::table).execute(conn)?;
         delete(queued_logs::table).execute(conn)?;
+        delete(dead_lettered_logs::table).execute(conn)?;
         Ok(())
     }
 }
 
+/// `log_json` is encrypted with the column key in the database.
 #[derive(Insertable, Debug, Clone)]
 #[table_name="logs"]
 pub struct Log {
@@ -55,6 +109,10 @@ pub struct Log {
     pub member_public_key: Vec<u8>,
     pub log_json: String,
     pub unix_seconds: i64,
+    pub signature_verification: Option<String>,
+    pub host_key_verification: Option<String>,
+    /// When this client stored the log, unlike `unix_seconds` which the member's device sets.
+    pub received_unix_seconds: i64,
 }
 
 #[derive(Queryable, Debug, Clone)]
@@ -64,20 +122,45 @@ pub struct LogWithId {
     pub member_public_key: Vec<u8>,
     pub log_json: String,
     pub unix_seconds: i64,
+    /// `None` for logs received before signatures were verified.
+    pub signature_verification: Option<String>,
+    /// `None` for logs without a host authorization or received before host keys were checked.
+    pub host_key_verification: Option<String>,
+    /// 0 for logs received before the receiving time was recorded.
+    pub received_unix_seconds: i64,
+}
+
+impl LogWithId {
+    pub fn signature_verification(&self) -> Option<::dashboard_protocol::SignatureVerification> {
+        self.signature_verification.as_ref().and_then(|s| ::dashboard_protocol::SignatureVerification::from_str(s))
+    }
+    pub fn host_key_verification(&self) -> Option<::dashboard_protocol::HostKeyVerification> {
+        self.host_key_verification.as_ref().and_then(|s| ::dashboard_protocol::HostKeyVerification::from_str(s))
+    }
+}
+
+fn decrypt_logs(conn: &DBConnection, logs: Vec<LogWithId>) -> QueryResult<Vec<LogWithId>> {
+    logs.into_iter().map(|mut log| {
+        log.log_json = column_encryption::decrypt_text(conn, &log.log_json)?;
+        Ok(log)
+    }).collect()
 }
 
 impl Log {
     pub fn insert(&self, conn: &DBConnection) -> QueryResult<usize> {
-        insert_into(logs::table).values(self).execute(conn)
+        insert_into(logs::table).values(&Log {
+            log_json: column_encryption::encrypt_text(conn, &self.log_json)?,
+            ..self.clone()
+        }).execute(conn)
     }
     pub fn all(conn: &TeamDBConnection, limit: Option<i64>) -> QueryResult<Vec<LogWithId>> {
         let sorted = logs::table.filter(logs::team_public_key.eq(conn.team))
             .order(logs::id.desc());
 
-        match limit {
-            Some(lim) => sorted.limit(lim).get_results(conn.conn),
-            None => sorted.get_results(conn.conn)
-        }
+        decrypt_logs(conn.conn, match limit {
+            Some(lim) => sorted.limit(lim).get_results(conn.conn)?,
+            None => sorted.get_results(conn.conn)?
+        })
     }
 
     pub fn after(conn: &TeamDBConnection, id: Option<i64>, limit: Option<i64>) -> QueryResult<Vec<LogWithId>> {
@@ -90,15 +173,48 @@ impl Log {
             .filter(logs::id.gt(the_id))
             .order(logs::id.desc());
 
-        match limit {
-            Some(lim) => sorted.limit(lim).get_results(conn.conn),
-            None => sorted.get_results(conn.conn)
-        }
+        decrypt_logs(conn.conn, match limit {
+            Some(lim) => sorted.limit(lim).get_results(conn.conn)?,
+            None => sorted.get_results(conn.conn)?
+        })
+    }
+
+    /// Logs with an id greater than `id`, oldest first.
+    pub fn after_ascending(conn: &TeamDBConnection, id: Option<i64>, limit: i64) -> QueryResult<Vec<LogWithId>> {
+        decrypt_logs(conn.conn, logs::table.filter(logs::team_public_key.eq(conn.team))
+            .filter(logs::id.gt(id.unwrap_or(0)))
+            .order(logs::id.asc())
+            .limit(limit)
+            .get_results(conn.conn)?)
+    }
+
+    pub fn for_member_received_since(conn: &TeamDBConnection, member_public_key: &[u8], received_unix_seconds: i64) -> QueryResult<Vec<LogWithId>> {
+        decrypt_logs(conn.conn, logs::table.filter(logs::team_public_key.eq(conn.team))
+            .filter(logs::member_public_key.eq(member_public_key))
+            .filter(logs::received_unix_seconds.ge(received_unix_seconds))
+            .get_results(conn.conn)?)
     }
 
     pub fn for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Vec<LogWithId>> {
+        decrypt_logs(conn.conn, logs::table.filter(logs::team_public_key.eq(conn.team))
+            .filter(logs::member_public_key.eq(member_public_key)).get_results(conn.conn)?)
+    }
+
+    pub fn count_for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> Result<u64> {
+        logs::table.filter(logs::team_public_key.eq(conn.team))
+            .filter(logs::member_public_key.eq(member_public_key))
+            .count().get_result::<i64>(conn.conn)?.to_u64()
+    }
+
+    pub fn count_for_member_with_signature_verification(
+        conn: &TeamDBConnection,
+        member_public_key: &[u8],
+        verification: ::dashboard_protocol::SignatureVerification,
+    ) -> Result<u64> {
         logs::table.filter(logs::team_public_key.eq(conn.team))
-            .filter(logs::member_public_key.eq(member_public_key)).get_results(conn.conn)
+            .filter(logs::member_public_key.eq(member_public_key))
+            .filter(logs::signature_verification.eq(verification.as_str()))
+            .count().get_result::<i64>(conn.conn)?.to_u64()
     }
 }
 
@@ -132,23 +248,33 @@ impl CurrentWrappedKey {
     }
 }
 
+/// `log_json` is encrypted with the column key in the database.
 #[derive(Queryable, Debug, Clone, Identifiable)]
 #[table_name="queued_logs"]
 #[primary_key(id)]
 pub struct QueuedLog {
     pub id: i64,
     pub log_json: Vec<u8>,
+    pub created_unix_seconds: i64,
+    pub attempts: i64,
+    pub last_attempt_unix_seconds: Option<i64>,
+    pub last_error: Option<String>,
 }
 
 #[derive(Debug, Clone, Insertable)]
 #[table_name="queued_logs"]
 pub struct NewQueuedLog {
     pub log_json: Vec<u8>,
+    pub created_unix_seconds: i64,
 }
 
 impl QueuedLog {
+    fn decrypted(mut self, conn: &DBConnection) -> QueryResult<Self> {
+        self.log_json = column_encryption::decrypt_bytes(conn, &self.log_json)?;
+        Ok(self)
+    }
     pub fn next(conn: &DBConnection) -> QueryResult<Self> {
-        Self::table().order(queued_logs::id.asc()).limit(1).first(conn)
+        Self::table().order(queued_logs::id.asc()).limit(1).first::<Self>(conn)?.decrypted(conn)
     }
     pub fn remove(&self, conn: &DBConnection) -> QueryResult<()> {
         delete(Self::table().find(self.id)).execute(conn)?;
@@ -159,13 +285,811 @@ impl QueuedLog {
         Ok(())
     }
     pub fn all(conn: &DBConnection) -> QueryResult<Vec<Self>> {
-        Self::table().get_results(conn)
+        Self::table().order(queued_logs::id.asc()).get_results::<Self>(conn)?
+            .into_iter().map(|log| log.decrypted(conn)).collect()
     }
     pub fn add(conn: &DBConnection, new_log: &NewQueuedLog) -> QueryResult<()> {
-        insert_into(Self::table()).values(new_log).execute(conn)?;
+        insert_into(Self::table()).values(&NewQueuedLog {
+            log_json: column_encryption::encrypt_bytes(conn, &new_log.log_json)?,
+            ..new_log.clone()
+        }).execute(conn)?;
         Ok(())
     }
     pub fn any(conn: &DBConnection) -> QueryResult<bool> {
-        Self::next(conn).optional().map(|o| o.is_some())
+        Self::table().select(queued_logs::id).first::<i64>(conn).optional().map(|o| o.is_some())
+    }
+    pub fn count(conn: &DBConnection) -> Result<u64> {
+        Self::table().count().get_result::<i64>(conn)?.to_u64()
+    }
+    pub fn oldest_created_unix_seconds(conn: &DBConnection) -> QueryResult<Option<i64>> {
+        use diesel::dsl::min;
+        Self::table().select(min(queued_logs::created_unix_seconds)).first(conn)
+    }
+    pub fn max_attempts(conn: &DBConnection) -> QueryResult<Option<i64>> {
+        use diesel::dsl::max;
+        Self::table().select(max(queued_logs::attempts)).first(conn)
+    }
+    pub fn record_failure(&self, conn: &DBConnection, error: &str, unix_seconds: i64) -> QueryResult<()> {
+        update(Self::table().find(self.id))
+            .set((
+                queued_logs::attempts.eq(self.attempts + 1),
+                queued_logs::last_attempt_unix_seconds.eq(Some(unix_seconds)),
+                queued_logs::last_error.eq(Some(error)),
+            )).execute(conn)?;
+        Ok(())
+    }
+    /// Move a log that can never be sent out of the queue so it does not block the logs behind it.
+    pub fn dead_letter(&self, conn: &DBConnection, reason: &str, unix_seconds: i64) -> QueryResult<()> {
+        conn.transaction(|| {
+            insert_into(dead_lettered_logs::table).values(&NewDeadLetteredLog {
+                log_json: column_encryption::encrypt_bytes(conn, &self.log_json)?,
+                created_unix_seconds: self.created_unix_seconds,
+                dead_lettered_unix_seconds: unix_seconds,
+                attempts: self.attempts,
+                reason: reason.into(),
+            }).execute(conn)?;
+            self.remove(conn)
+        })
+    }
+}
+
+/// `log_json` is encrypted with the column key in the database.
+#[derive(Queryable, Debug, Clone, Identifiable)]
+#[table_name="dead_lettered_logs"]
+#[primary_key(id)]
+pub struct DeadLetteredLog {
+    pub id: i64,
+    pub log_json: Vec<u8>,
+    pub created_unix_seconds: i64,
+    pub dead_lettered_unix_seconds: i64,
+    pub attempts: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name="dead_lettered_logs"]
+pub struct NewDeadLetteredLog {
+    pub log_json: Vec<u8>,
+    pub created_unix_seconds: i64,
+    pub dead_lettered_unix_seconds: i64,
+    pub attempts: i64,
+    pub reason: String,
+}
+
+impl DeadLetteredLog {
+    pub fn all(conn: &DBConnection) -> QueryResult<Vec<Self>> {
+        Self::table().order(dead_lettered_logs::id.asc()).get_results::<Self>(conn)?
+            .into_iter().map(|mut log| {
+                log.log_json = column_encryption::decrypt_bytes(conn, &log.log_json)?;
+                Ok(log)
+            }).collect()
+    }
+    pub fn count(conn: &DBConnection) -> Result<u64> {
+        Self::table().count().get_result::<i64>(conn)?.to_u64()
+    }
+    pub fn clear(conn: &DBConnection) -> QueryResult<()> {
+        delete(Self::table()).execute(conn)?;
+        Ok(())
+    }
+    /// Put a dead-lettered log back at the end of the queue with a fresh attempt count.
+    pub fn requeue(&self, conn: &DBConnection) -> QueryResult<()> {
+        conn.transaction(|| {
+            QueuedLog::add(conn, &NewQueuedLog {
+                log_json: self.log_json.clone(),
+                created_unix_seconds: self.created_unix_seconds,
+            })?;
+            delete(Self::table().find(self.id)).execute(conn)?;
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFailureType {
+    /// No symmetric key has been unwrapped for the member's log chain.
+    MissingKey,
+    /// The ciphertext did not decrypt with the member's symmetric key.
+    BadCiphertext,
+    /// The plaintext decrypted but is not a valid log.
+    MalformedLog,
+    /// A key wrapped for us by the member could not be unwrapped.
+    BadWrappedKey,
+    /// The block was written before the member first wrapped a key for us, for example before we
+    /// were promoted to admin. These blocks were never meant to be readable by us.
+    BeforeFirstKey,
+}
+
+impl LogFailureType {
+    pub fn as_str(&self) -> &'static str {
+        use self::LogFailureType::*;
+        match self {
+            &MissingKey => "missing_key",
+            &BadCiphertext => "bad_ciphertext",
+            &MalformedLog => "malformed_log",
+            &BadWrappedKey => "bad_wrapped_key",
+            &BeforeFirstKey => "before_first_key",
+        }
+    }
+    pub fn from_str(s: &str) -> Option<Self> {
+        use self::LogFailureType::*;
+        match s {
+            "missing_key" => Some(MissingKey),
+            "bad_ciphertext" => Some(BadCiphertext),
+            "malformed_log" => Some(MalformedLog),
+            "bad_wrapped_key" => Some(BadWrappedKey),
+            "before_first_key" => Some(BeforeFirstKey),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Queryable, Debug, Clone, Identifiable)]
+#[table_name="log_processing_failures"]
+#[primary_key(id)]
+pub struct LogProcessingFailure {
+    pub id: i64,
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub block_hash: Vec<u8>,
+    pub failure_type: String,
+    pub error: String,
+    pub unix_seconds: i64,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name="log_processing_failures"]
+pub struct NewLogProcessingFailure {
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub block_hash: Vec<u8>,
+    pub failure_type: String,
+    pub error: String,
+    pub unix_seconds: i64,
+}
+
+impl LogProcessingFailure {
+    /// Record `failure`, replacing an earlier failure of the same block so that re-reading a log
+    /// chain does not count it again.
+    pub fn upsert(conn: &DBConnection, failure: &NewLogProcessingFailure) -> QueryResult<()> {
+        let existing = Self::table().filter(log_processing_failures::team_public_key.eq(&failure.team_public_key))
+            .filter(log_processing_failures::block_hash.eq(&failure.block_hash));
+        let updated = update(existing).set((
+            log_processing_failures::member_public_key.eq(&failure.member_public_key),
+            log_processing_failures::failure_type.eq(&failure.failure_type),
+            log_processing_failures::error.eq(&failure.error),
+            log_processing_failures::unix_seconds.eq(failure.unix_seconds),
+        )).execute(conn)?;
+        if updated == 0 {
+            insert_into(Self::table()).values(failure).execute(conn)?;
+        }
+        Ok(())
+    }
+    pub fn all(conn: &TeamDBConnection) -> QueryResult<Vec<Self>> {
+        Self::table().filter(log_processing_failures::team_public_key.eq(conn.team))
+            .order(log_processing_failures::id.asc())
+            .get_results(conn.conn)
+    }
+    pub fn for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Vec<Self>> {
+        Self::table().filter(log_processing_failures::team_public_key.eq(conn.team))
+            .filter(log_processing_failures::member_public_key.eq(member_public_key))
+            .order(log_processing_failures::id.asc())
+            .get_results(conn.conn)
+    }
+    /// Mark the `MissingKey` failures of a log chain as written before the first key we were given
+    /// for it. Called when that key is unwrapped, so every such failure comes from an earlier block.
+    pub fn mark_before_first_key(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<usize> {
+        update(Self::table().filter(log_processing_failures::team_public_key.eq(conn.team))
+            .filter(log_processing_failures::member_public_key.eq(member_public_key))
+            .filter(log_processing_failures::failure_type.eq(LogFailureType::MissingKey.as_str())))
+            .set(log_processing_failures::failure_type.eq(LogFailureType::BeforeFirstKey.as_str()))
+            .execute(conn.conn)
+    }
+    pub fn failure_type(&self) -> Option<LogFailureType> {
+        LogFailureType::from_str(&self.failure_type)
+    }
+}
+
+/// `member_email`, `host`, `result` and `text` are encrypted with the column key in the database,
+/// so terms on them are matched after the time and type terms have narrowed down the entries.
+/// Entries with an empty `log_type` mark logs that could not be parsed, so that they are not
+/// re-read on every search.
+#[derive(Queryable, Insertable, Debug, Clone)]
+#[table_name="log_search_index"]
+pub struct LogSearchEntry {
+    pub log_id: i64,
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub member_email: String,
+    pub log_type: String,
+    pub host: Option<String>,
+    pub result: String,
+    pub text: String,
+    pub unix_seconds: i64,
+}
+
+impl LogSearchEntry {
+    pub fn new(log: &LogWithId, parsed_log: &::protocol::logs::Log, member_email: &str) -> Self {
+        let fields = ::util::log_search::LogSearchFields::from_log(parsed_log);
+        LogSearchEntry {
+            log_id: log.id,
+            team_public_key: log.team_public_key.clone(),
+            member_public_key: log.member_public_key.clone(),
+            member_email: member_email.to_lowercase(),
+            log_type: fields.log_type,
+            host: fields.host,
+            result: fields.result,
+            text: fields.text,
+            unix_seconds: log.unix_seconds,
+        }
+    }
+    pub fn insert(&self, conn: &DBConnection) -> QueryResult<usize> {
+        insert_into(log_search_index::table).values(&LogSearchEntry {
+            member_email: column_encryption::encrypt_text(conn, &self.member_email)?,
+            host: column_encryption::encrypt_optional_text(conn, self.host.as_ref().map(String::as_str))?,
+            result: column_encryption::encrypt_text(conn, &self.result)?,
+            text: column_encryption::encrypt_text(conn, &self.text)?,
+            ..self.clone()
+        }).execute(conn)
+    }
+    /// An entry for a log that could not be parsed, which no search matches.
+    pub fn malformed(log: &LogWithId) -> Self {
+        LogSearchEntry {
+            log_id: log.id,
+            team_public_key: log.team_public_key.clone(),
+            member_public_key: log.member_public_key.clone(),
+            member_email: String::new(),
+            log_type: String::new(),
+            host: None,
+            result: String::new(),
+            text: String::new(),
+            unix_seconds: log.unix_seconds,
+        }
+    }
+    /// Logs of the team that have not been added to the index yet, oldest first.
+    pub fn unindexed_logs(conn: &TeamDBConnection) -> QueryResult<Vec<LogWithId>> {
+        decrypt_logs(conn.conn, logs::table.filter(logs::team_public_key.eq(conn.team))
+            .filter(logs::id.ne_all(log_search_index::table.select(log_search_index::log_id)))
+            .order(logs::id.asc())
+            .get_results(conn.conn)?)
+    }
+    /// Logs matching `query`, newest first.
+    ///
+    /// Terms on the encrypted fields are matched by decrypting each candidate entry, so for those
+    /// queries only the newest `MAX_DECRYPTED_SEARCH_ENTRIES` entries left by the time and type
+    /// terms are searched.
+    pub fn search(conn: &TeamDBConnection, query: &::util::log_search::LogQuery, limit: Option<i64>) -> QueryResult<Vec<LogWithId>> {
+        use self::log_search_index::dsl;
+
+        let mut matching = dsl::log_search_index.filter(dsl::team_public_key.eq(conn.team)).into_boxed();
+        if let Some(since) = query.since_unix_seconds {
+            matching = matching.filter(dsl::unix_seconds.ge(since));
+        }
+        if !query.types.is_empty() {
+            matching = matching.filter(dsl::log_type.eq_any(query.log_types()));
+        } else {
+            matching = matching.filter(dsl::log_type.ne(""));
+        }
+
+        let log_ids: Vec<i64> = if query.members.is_empty() && query.hosts.is_empty() && query.results.is_empty() && query.text.is_empty() {
+            let matching = matching.select(dsl::log_id).order(dsl::log_id.desc());
+            match limit {
+                Some(lim) => matching.limit(lim).get_results(conn.conn)?,
+                None => matching.get_results(conn.conn)?,
+            }
+        } else {
+            let mut log_ids = vec![];
+            let matching = matching.select((dsl::log_id, dsl::member_email, dsl::host, dsl::result, dsl::text))
+                .order(dsl::log_id.desc())
+                .limit(::util::log_search::MAX_DECRYPTED_SEARCH_ENTRIES);
+            for (log_id, member_email, host, result, text) in matching.load::<(i64, String, Option<String>, String, String)>(conn.conn)? {
+                if limit.map_or(false, |lim| log_ids.len() as i64 >= lim) {
+                    break;
+                }
+                let result = column_encryption::decrypt_text(conn.conn, &result)?;
+                if !query.results.is_empty() && !query.results.contains(&result) {
+                    continue;
+                }
+                let member_email = column_encryption::decrypt_text(conn.conn, &member_email)?;
+                let host = column_encryption::decrypt_optional_text(conn.conn, host.as_ref().map(String::as_str))?;
+                let text = column_encryption::decrypt_text(conn.conn, &text)?;
+                if query.matches_substrings(&member_email, host.as_ref().map(String::as_str), &text) {
+                    log_ids.push(log_id);
+                }
+            }
+            log_ids
+        };
+
+        //  Each id is bound as a separate variable, and SQLite allows at most 999 per statement
+        let mut found = vec![];
+        for chunk in log_ids.chunks(SEARCH_LOG_ID_CHUNK_SIZE) {
+            found.extend(logs::table.filter(logs::id.eq_any(chunk))
+                .order(logs::id.desc())
+                .get_results(conn.conn)?);
+        }
+        decrypt_logs(conn.conn, found)
+    }
+}
+
+const SEARCH_LOG_ID_CHUNK_SIZE: usize = 500;
+
+/// Position of a named log exporter, so that tailing resumes after the last exported log.
+#[derive(Queryable, Insertable, Identifiable, AsChangeset, Debug, Clone)]
+#[table_name="log_export_cursors"]
+#[primary_key(team_public_key, name)]
+pub struct LogExportCursor {
+    pub team_public_key: Vec<u8>,
+    pub name: String,
+    pub last_log_id: i64,
+    pub updated_unix_seconds: i64,
+}
+
+impl LogExportCursor {
+    pub fn find(conn: &TeamDBConnection, name: &str) -> QueryResult<Self> {
+        Self::table().find((conn.team, name)).first(conn.conn)
+    }
+    pub fn set(&self, conn: &DBConnection) -> QueryResult<()> {
+        conn.transaction(|| {
+            delete(Self::table().find((&self.team_public_key, &self.name))).execute(conn)?;
+            insert_into(Self::table()).values(self).execute(conn)?;
+            Ok(())
+        })
+    }
+    pub fn all(conn: &TeamDBConnection) -> QueryResult<Vec<Self>> {
+        Self::table().filter(log_export_cursors::team_public_key.eq(conn.team)).get_results(conn.conn)
+    }
+}
+
+#[derive(Queryable, Insertable, Identifiable, Debug, Clone)]
+#[table_name="alert_rules"]
+#[primary_key(team_public_key, name)]
+pub struct AlertRule {
+    pub team_public_key: Vec<u8>,
+    pub name: String,
+    pub rule_json: String,
+}
+
+impl AlertRule {
+    pub fn all(conn: &TeamDBConnection) -> QueryResult<Vec<Self>> {
+        Self::table().filter(alert_rules::team_public_key.eq(conn.team))
+            .order(alert_rules::name.asc())
+            .get_results(conn.conn)
+    }
+    pub fn set(&self, conn: &DBConnection) -> QueryResult<()> {
+        delete(Self::table().find((&self.team_public_key, &self.name))).execute(conn)?;
+        insert_into(Self::table()).values(self).execute(conn)?;
+        Ok(())
+    }
+    pub fn delete(conn: &TeamDBConnection, name: &str) -> QueryResult<usize> {
+        delete(Self::table().find((conn.team, name))).execute(conn.conn)
+    }
+}
+
+/// `log_json` is encrypted with the column key in the database.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name="alerts"]
+pub struct Alert {
+    pub id: i64,
+    pub team_public_key: Vec<u8>,
+    pub rule_name: String,
+    pub member_public_key: Vec<u8>,
+    pub log_json: String,
+    pub message: String,
+    pub unix_seconds: i64,
+    pub delivered: bool,
+    pub received_unix_seconds: i64,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[table_name="alerts"]
+pub struct NewAlert {
+    pub team_public_key: Vec<u8>,
+    pub rule_name: String,
+    pub member_public_key: Vec<u8>,
+    pub log_json: String,
+    pub message: String,
+    pub unix_seconds: i64,
+    pub delivered: bool,
+    /// When the log that triggered the alert was stored.
+    pub received_unix_seconds: i64,
+}
+
+impl Alert {
+    fn decrypt_all(conn: &DBConnection, alerts: Vec<Self>) -> QueryResult<Vec<Self>> {
+        alerts.into_iter().map(|mut alert| {
+            alert.log_json = column_encryption::decrypt_text(conn, &alert.log_json)?;
+            Ok(alert)
+        }).collect()
+    }
+    pub fn insert(conn: &DBConnection, alert: &NewAlert) -> QueryResult<()> {
+        insert_into(Self::table()).values(&NewAlert {
+            log_json: column_encryption::encrypt_text(conn, &alert.log_json)?,
+            ..alert.clone()
+        }).execute(conn)?;
+        Ok(())
+    }
+    pub fn all(conn: &TeamDBConnection, limit: Option<i64>) -> QueryResult<Vec<Self>> {
+        let sorted = Self::table().filter(alerts::team_public_key.eq(conn.team))
+            .order(alerts::id.desc());
+        Self::decrypt_all(conn.conn, match limit {
+            Some(lim) => sorted.limit(lim).get_results(conn.conn)?,
+            None => sorted.get_results(conn.conn)?,
+        })
+    }
+    pub fn undelivered(conn: &TeamDBConnection) -> QueryResult<Vec<Self>> {
+        Self::decrypt_all(conn.conn, Self::table().filter(alerts::team_public_key.eq(conn.team))
+            .filter(alerts::delivered.eq(false))
+            .order(alerts::id.asc())
+            .get_results(conn.conn)?)
+    }
+    /// When the latest alert of `rule_name` for the member was triggered, by the time its log was stored.
+    pub fn latest_received_unix_seconds(conn: &TeamDBConnection, rule_name: &str, member_public_key: &[u8]) -> QueryResult<Option<i64>> {
+        Self::table().filter(alerts::team_public_key.eq(conn.team))
+            .filter(alerts::rule_name.eq(rule_name))
+            .filter(alerts::member_public_key.eq(member_public_key))
+            .select(alerts::received_unix_seconds)
+            .order(alerts::received_unix_seconds.desc())
+            .first::<i64>(conn.conn)
+            .optional()
+    }
+    pub fn mark_delivered(&self, conn: &DBConnection) -> QueryResult<usize> {
+        update(self).set(alerts::delivered.eq(true)).execute(conn)
+    }
+}
+
+/// How often a member has been seen with one value of an access attribute such as a host or device.
+/// `value` is stored as a keyed hash, see `value_hash`.
+#[derive(Queryable, Insertable, Identifiable, AsChangeset, Debug, Clone)]
+#[table_name="access_baselines"]
+#[primary_key(team_public_key, member_public_key, kind, value)]
+pub struct AccessBaseline {
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub kind: String,
+    pub value: String,
+    pub count: i64,
+    pub first_seen_unix_seconds: i64,
+    pub last_seen_unix_seconds: i64,
+}
+
+impl AccessBaseline {
+    /// The stored form of `value`, a hash keyed with the column key so that values can be looked up
+    /// without being readable from the database.
+    pub fn value_hash(conn: &DBConnection, value: &str) -> QueryResult<String> {
+        column_encryption::hash_text(conn, value)
+    }
+    pub fn for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Vec<Self>> {
+        Self::table().filter(access_baselines::team_public_key.eq(conn.team))
+            .filter(access_baselines::member_public_key.eq(member_public_key))
+            .get_results(conn.conn)
+    }
+    /// Count one more observation of `value`.
+    pub fn observe(conn: &TeamDBConnection, member_public_key: &[u8], kind: &str, value: &str, unix_seconds: i64) -> QueryResult<()> {
+        let value = Self::value_hash(conn.conn, value)?;
+        let existing = Self::table().find((conn.team, member_public_key, kind, &value))
+            .first::<Self>(conn.conn).optional()?;
+        match existing {
+            Some(mut baseline) => {
+                baseline.count += 1;
+                baseline.first_seen_unix_seconds = baseline.first_seen_unix_seconds.min(unix_seconds);
+                baseline.last_seen_unix_seconds = baseline.last_seen_unix_seconds.max(unix_seconds);
+                update(Self::table().find((conn.team, member_public_key, kind, &value)))
+                    .set(&baseline).execute(conn.conn)?;
+            }
+            None => {
+                insert_into(Self::table()).values(&AccessBaseline {
+                    team_public_key: conn.team.into(),
+                    member_public_key: member_public_key.into(),
+                    kind: kind.into(),
+                    value,
+                    count: 1,
+                    first_seen_unix_seconds: unix_seconds,
+                    last_seen_unix_seconds: unix_seconds,
+                }).execute(conn.conn)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `log_json` is encrypted with the column key in the database.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name="access_anomalies"]
+pub struct AccessAnomaly {
+    pub id: i64,
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub log_json: String,
+    pub score: i64,
+    pub reasons_json: String,
+    pub unix_seconds: i64,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[table_name="access_anomalies"]
+pub struct NewAccessAnomaly {
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub log_json: String,
+    pub score: i64,
+    pub reasons_json: String,
+    pub unix_seconds: i64,
+}
+
+impl AccessAnomaly {
+    fn decrypt_all(conn: &DBConnection, anomalies: Vec<Self>) -> QueryResult<Vec<Self>> {
+        anomalies.into_iter().map(|mut anomaly| {
+            anomaly.log_json = column_encryption::decrypt_text(conn, &anomaly.log_json)?;
+            Ok(anomaly)
+        }).collect()
+    }
+    pub fn insert(conn: &DBConnection, anomaly: &NewAccessAnomaly) -> QueryResult<()> {
+        insert_into(Self::table()).values(&NewAccessAnomaly {
+            log_json: column_encryption::encrypt_text(conn, &anomaly.log_json)?,
+            ..anomaly.clone()
+        }).execute(conn)?;
+        Ok(())
+    }
+    pub fn all(conn: &TeamDBConnection, limit: Option<i64>) -> QueryResult<Vec<Self>> {
+        let sorted = Self::table().filter(access_anomalies::team_public_key.eq(conn.team))
+            .order(access_anomalies::id.desc());
+        Self::decrypt_all(conn.conn, match limit {
+            Some(lim) => sorted.limit(lim).get_results(conn.conn)?,
+            None => sorted.get_results(conn.conn)?,
+        })
+    }
+    pub fn for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Vec<Self>> {
+        Self::decrypt_all(conn.conn, Self::table().filter(access_anomalies::team_public_key.eq(conn.team))
+            .filter(access_anomalies::member_public_key.eq(member_public_key))
+            .order(access_anomalies::id.desc())
+            .get_results(conn.conn)?)
+    }
+}
+
+/// Pin and unpin operations from the main chain, so that a log can be checked against the host
+/// keys that were pinned at the main chain position the log was written at.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name="pinned_host_key_history"]
+pub struct PinnedHostKeyEvent {
+    pub id: i64,
+    pub team_public_key: Vec<u8>,
+    pub host: String,
+    pub public_key: Vec<u8>,
+    pub pinned: bool,
+    pub unix_seconds: i64,
+    /// Index of the main chain block that made the change. Events recorded before the index was
+    /// kept are at 0.
+    pub main_chain_index: i64,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[table_name="pinned_host_key_history"]
+pub struct NewPinnedHostKeyEvent {
+    pub team_public_key: Vec<u8>,
+    pub host: String,
+    pub public_key: Vec<u8>,
+    pub pinned: bool,
+    pub unix_seconds: i64,
+    pub main_chain_index: i64,
+}
+
+impl PinnedHostKeyEvent {
+    pub fn insert(conn: &DBConnection, event: &NewPinnedHostKeyEvent) -> QueryResult<()> {
+        insert_into(Self::table()).values(event).execute(conn)?;
+        Ok(())
+    }
+    /// Events for `host` in chain order.
+    pub fn for_host(conn: &TeamDBConnection, host: &str) -> QueryResult<Vec<Self>> {
+        Self::table().filter(pinned_host_key_history::team_public_key.eq(conn.team))
+            .filter(pinned_host_key_history::host.eq(host))
+            .order((pinned_host_key_history::main_chain_index.asc(), pinned_host_key_history::id.asc()))
+            .get_results(conn.conn)
+    }
+}
+
+/// `VerifyMember` attestations from the main chain. `safety_number` is encrypted with the column
+/// key in the database.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name="member_verifications"]
+pub struct MemberVerification {
+    pub id: i64,
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    /// The admin who compared the safety number.
+    pub verifier_public_key: Vec<u8>,
+    pub safety_number: String,
+    pub unix_seconds: i64,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[table_name="member_verifications"]
+pub struct NewMemberVerification {
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub verifier_public_key: Vec<u8>,
+    pub safety_number: String,
+    pub unix_seconds: i64,
+}
+
+impl MemberVerification {
+    pub fn insert(conn: &DBConnection, verification: &NewMemberVerification) -> QueryResult<()> {
+        insert_into(Self::table()).values(&NewMemberVerification {
+            safety_number: column_encryption::encrypt_text(conn, &verification.safety_number)?,
+            ..verification.clone()
+        }).execute(conn)?;
+        Ok(())
+    }
+    /// Attestations for `member_public_key` in chain order.
+    pub fn for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Vec<Self>> {
+        Self::table().filter(member_verifications::team_public_key.eq(conn.team))
+            .filter(member_verifications::member_public_key.eq(member_public_key))
+            .order(member_verifications::id.asc())
+            .get_results::<Self>(conn.conn)?
+            .into_iter().map(|mut verification| {
+                verification.safety_number = column_encryption::decrypt_text(conn.conn, &verification.safety_number)?;
+                Ok(verification)
+            }).collect()
+    }
+}
+
+/// The SSH and PGP keys a member joined with or updated to, so that signatures can be checked
+/// against the keys in effect when they were made.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name="identity_key_history"]
+pub struct IdentityKeyEvent {
+    pub id: i64,
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub ssh_public_key: Vec<u8>,
+    pub pgp_public_key: Vec<u8>,
+    /// When the keys took effect according to the member, but never before the member's previous
+    /// keys. Keys recorded before history was kept start at 0.
+    pub unix_seconds: i64,
+    /// Index of the main chain block that set the keys. Keys recorded before the index was kept
+    /// are at 0.
+    pub main_chain_index: i64,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[table_name="identity_key_history"]
+pub struct NewIdentityKeyEvent {
+    pub team_public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub ssh_public_key: Vec<u8>,
+    pub pgp_public_key: Vec<u8>,
+    pub unix_seconds: i64,
+    pub main_chain_index: i64,
+}
+
+impl IdentityKeyEvent {
+    pub fn insert(conn: &DBConnection, event: &NewIdentityKeyEvent) -> QueryResult<()> {
+        insert_into(Self::table()).values(event).execute(conn)?;
+        Ok(())
+    }
+    /// Events for `member_public_key` in chain order.
+    pub fn for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Vec<Self>> {
+        Self::table().filter(identity_key_history::team_public_key.eq(conn.team))
+            .filter(identity_key_history::member_public_key.eq(member_public_key))
+            .order((identity_key_history::main_chain_index.asc(), identity_key_history::id.asc()))
+            .get_results(conn.conn)
+    }
+    /// Events for all current and former members in chain order.
+    pub fn find_all_for_team(conn: &TeamDBConnection) -> QueryResult<Vec<Self>> {
+        Self::table().filter(identity_key_history::team_public_key.eq(conn.team))
+            .order((identity_key_history::main_chain_index.asc(), identity_key_history::id.asc()))
+            .get_results(conn.conn)
+    }
+    /// The keys in effect at `unix_seconds`: the last keys set at or before then, or the first
+    /// keys recorded if `unix_seconds` predates them all. Only for objects such as git commits that
+    /// have no position in the chain.
+    pub fn at(conn: &TeamDBConnection, member_public_key: &[u8], unix_seconds: i64) -> QueryResult<Option<Self>> {
+        let events = Self::for_member(conn, member_public_key)?;
+        let first = events.first().cloned();
+        Ok(events.into_iter().filter(|e| e.unix_seconds <= unix_seconds).last().or(first))
+    }
+    /// The keys in effect at main chain block `main_chain_index`: the last keys set at or before
+    /// it, or the first keys recorded if it predates them all.
+    pub fn at_main_chain_index(conn: &TeamDBConnection, member_public_key: &[u8], main_chain_index: i64) -> QueryResult<Option<Self>> {
+        let events = Self::for_member(conn, member_public_key)?;
+        let first = events.first().cloned();
+        Ok(events.into_iter().filter(|e| e.main_chain_index <= main_chain_index).last().or(first))
+    }
+    /// The member's latest event, which later events must not predate.
+    pub fn last_for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Option<Self>> {
+        Ok(Self::for_member(conn, member_public_key)?.pop())
+    }
+}
+
+impl Identity {
+    /// This identity with the SSH and PGP keys that were in effect at `unix_seconds`.
+    pub fn with_keys_at(mut self, conn: &TeamDBConnection, unix_seconds: i64) -> QueryResult<Identity> {
+        if let Some(keys) = IdentityKeyEvent::at(conn, &self.public_key, unix_seconds)? {
+            self.ssh_public_key = keys.ssh_public_key;
+            self.pgp_public_key = keys.pgp_public_key;
+        }
+        Ok(self)
+    }
+    /// This identity with the SSH and PGP keys that were in effect at main chain block
+    /// `main_chain_index`.
+    pub fn with_keys_at_main_chain_index(mut self, conn: &TeamDBConnection, main_chain_index: i64) -> QueryResult<Identity> {
+        if let Some(keys) = IdentityKeyEvent::at_main_chain_index(conn, &self.public_key, main_chain_index)? {
+            self.ssh_public_key = keys.ssh_public_key;
+            self.pgp_public_key = keys.pgp_public_key;
+        }
+        Ok(self)
+    }
+}
+
+/// The position of a block relative to the main chain: the index of a main chain block, or for a
+/// log block the main chain index it was written at. Logs are checked against the keys and pins
+/// in effect at their block's position rather than at the time they claim.
+#[derive(Queryable, Insertable, Debug, Clone)]
+#[table_name="block_positions"]
+pub struct BlockPosition {
+    pub team_public_key: Vec<u8>,
+    pub hash: Vec<u8>,
+    pub main_chain_index: i64,
+}
+
+impl BlockPosition {
+    pub fn find(conn: &TeamDBConnection, hash: &[u8]) -> QueryResult<Option<i64>> {
+        block_positions::table.filter(block_positions::team_public_key.eq(conn.team))
+            .filter(block_positions::hash.eq(hash))
+            .select(block_positions::main_chain_index)
+            .first(conn.conn)
+            .optional()
+    }
+    pub fn set(conn: &TeamDBConnection, hash: &[u8], main_chain_index: i64) -> QueryResult<()> {
+        let existing = block_positions::table.filter(block_positions::team_public_key.eq(conn.team))
+            .filter(block_positions::hash.eq(hash));
+        if update(existing).set(block_positions::main_chain_index.eq(main_chain_index)).execute(conn.conn)? == 0 {
+            insert_into(block_positions::table).values(&BlockPosition {
+                team_public_key: conn.team.into(),
+                hash: hash.into(),
+                main_chain_index,
+            }).execute(conn.conn)?;
+        }
+        Ok(())
+    }
+}
+
+/// Log chain heads published on the main chain with `PublishLogChainHead`. `member_public_key` is
+/// the key that signs the log chain, a member's or one of their devices'.
+#[derive(Queryable, Insertable, Debug, Clone)]
+#[table_name="published_log_chain_heads"]
+pub struct PublishedLogChainHead {
+    pub team_public_key: Vec<u8>,
+    pub main_chain_block_hash: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub last_block_hash: Vec<u8>,
+    pub main_chain_index: i64,
+    pub unix_seconds: i64,
+}
+
+impl PublishedLogChainHead {
+    pub fn insert(&self, conn: &DBConnection) -> QueryResult<usize> {
+        insert_into(published_log_chain_heads::table).values(self).execute(conn)
+    }
+    /// Heads published for the log chain of `member_public_key`, oldest first.
+    pub fn filter_by_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Vec<Self>> {
+        published_log_chain_heads::table
+            .filter(published_log_chain_heads::team_public_key.eq(conn.team))
+            .filter(published_log_chain_heads::member_public_key.eq(member_public_key))
+            .order(published_log_chain_heads::main_chain_index.asc())
+            .get_results(conn.conn)
+    }
+    pub fn latest_for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Option<Self>> {
+        published_log_chain_heads::table
+            .filter(published_log_chain_heads::team_public_key.eq(conn.team))
+            .filter(published_log_chain_heads::member_public_key.eq(member_public_key))
+            .order(published_log_chain_heads::main_chain_index.desc())
+            .first(conn.conn)
+            .optional()
+    }
+    pub fn member_public_keys(conn: &TeamDBConnection) -> QueryResult<Vec<Vec<u8>>> {
+        published_log_chain_heads::table
+            .filter(published_log_chain_heads::team_public_key.eq(conn.team))
+            .select(published_log_chain_heads::member_public_key)
+            .distinct()
+            .get_results(conn.conn)
     }
 }
@@ -13,6 +13,9 @@ table! {
         member_public_key -> Binary,
         log_json -> Text,
         unix_seconds -> BigInt,
+        signature_verification -> Nullable<Text>,
+        host_key_verification -> Nullable<Text>,
+        received_unix_seconds -> BigInt,
     }
 }
 
@@ -34,5 +37,155 @@ table! {
     queued_logs (id) {
         id -> BigInt,
         log_json -> Binary,
+        created_unix_seconds -> BigInt,
+        attempts -> BigInt,
+        last_attempt_unix_seconds -> Nullable<BigInt>,
+        last_error -> Nullable<Text>,
+    }
+}
+
+table! {
+    dead_lettered_logs (id) {
+        id -> BigInt,
+        log_json -> Binary,
+        created_unix_seconds -> BigInt,
+        dead_lettered_unix_seconds -> BigInt,
+        attempts -> BigInt,
+        reason -> Text,
+    }
+}
+
+table! {
+    log_processing_failures (id) {
+        id -> BigInt,
+        team_public_key -> Binary,
+        member_public_key -> Binary,
+        block_hash -> Binary,
+        failure_type -> Text,
+        error -> Text,
+        unix_seconds -> BigInt,
+    }
+}
+
+table! {
+    log_search_index (log_id) {
+        log_id -> BigInt,
+        team_public_key -> Binary,
+        member_public_key -> Binary,
+        member_email -> Text,
+        log_type -> Text,
+        host -> Nullable<Text>,
+        result -> Text,
+        text -> Text,
+        unix_seconds -> BigInt,
+    }
+}
+
+table! {
+    log_export_cursors (team_public_key, name) {
+        team_public_key -> Binary,
+        name -> Text,
+        last_log_id -> BigInt,
+        updated_unix_seconds -> BigInt,
+    }
+}
+
+table! {
+    alert_rules (team_public_key, name) {
+        team_public_key -> Binary,
+        name -> Text,
+        rule_json -> Text,
+    }
+}
+
+table! {
+    alerts (id) {
+        id -> BigInt,
+        team_public_key -> Binary,
+        rule_name -> Text,
+        member_public_key -> Binary,
+        log_json -> Text,
+        message -> Text,
+        unix_seconds -> BigInt,
+        delivered -> Bool,
+        received_unix_seconds -> BigInt,
+    }
+}
+
+table! {
+    access_baselines (team_public_key, member_public_key, kind, value) {
+        team_public_key -> Binary,
+        member_public_key -> Binary,
+        kind -> Text,
+        value -> Text,
+        count -> BigInt,
+        first_seen_unix_seconds -> BigInt,
+        last_seen_unix_seconds -> BigInt,
+    }
+}
+
+table! {
+    access_anomalies (id) {
+        id -> BigInt,
+        team_public_key -> Binary,
+        member_public_key -> Binary,
+        log_json -> Text,
+        score -> BigInt,
+        reasons_json -> Text,
+        unix_seconds -> BigInt,
+    }
+}
+
+table! {
+    pinned_host_key_history (id) {
+        id -> BigInt,
+        team_public_key -> Binary,
+        host -> Text,
+        public_key -> Binary,
+        pinned -> Bool,
+        unix_seconds -> BigInt,
+        main_chain_index -> BigInt,
+    }
+}
+
+table! {
+    member_verifications (id) {
+        id -> BigInt,
+        team_public_key -> Binary,
+        member_public_key -> Binary,
+        verifier_public_key -> Binary,
+        safety_number -> Text,
+        unix_seconds -> BigInt,
+    }
+}
+
+table! {
+    identity_key_history (id) {
+        id -> BigInt,
+        team_public_key -> Binary,
+        member_public_key -> Binary,
+        ssh_public_key -> Binary,
+        pgp_public_key -> Binary,
+        unix_seconds -> BigInt,
+        main_chain_index -> BigInt,
+    }
+}
+
+table! {
+    block_positions (team_public_key, hash) {
+        team_public_key -> Binary,
+        hash -> Binary,
+        main_chain_index -> BigInt,
+    }
+}
+
+table! {
+    published_log_chain_heads (team_public_key, main_chain_block_hash) {
+        team_public_key -> Binary,
+        main_chain_block_hash -> Binary,
+        member_public_key -> Binary,
+        last_block_hash -> Binary,
+        main_chain_index -> BigInt,
+        unix_seconds -> BigInt,
     }
 }
@@ -0,0 +1,429 @@
+//! At-rest encryption of the sensitive columns of the client database.
+//!
+//! Unwrapped log chain keys, read token key pairs, the enclave key pairs in `current_team`, decrypted
+//! log JSON, the fields of the search index derived from logs and recorded safety numbers are sealed
+//! with a column key that is kept outside the database, in a `<database>.key` file readable only by
+//! its owner. Encrypted binary columns are `MAGIC || nonce || ciphertext` and encrypted text columns
+//! are `MAGIC` followed by the base64 of `nonce || ciphertext`, so that rows written before encryption
+//! was introduced can be recognized and encrypted in place by `encrypt_plaintext_columns`. Columns
+//! that are looked up by value, such as access baseline values, store a keyed hash prefixed with
+//! `HASH_MAGIC` instead.
+//!
+//! The key is held by the connection it was loaded for, in a temporary table that lives in memory
+//! and is only visible to that connection, so that connections to different databases in the same
+//! process each use their own key.
+//!
+//! Only client databases, built with the `sqlite` feature, are encrypted. Other builds store these
+//! columns as they are given, so that the `db` feature does not depend on `crypto`.
+
+use super::*;
+use diesel::prelude::*;
+#[cfg(feature = "sqlite")]
+use std::fs;
+#[cfg(feature = "sqlite")]
+use std::io::{self, Read, Write};
+#[cfg(feature = "sqlite")]
+use std::path::Path;
+
+#[cfg(feature = "sqlite")]
+use base64;
+#[cfg(feature = "sqlite")]
+use crypto::secretbox;
+#[cfg(feature = "sqlite")]
+use secret::SecretBytes;
+#[cfg(feature = "sqlite")]
+use sodiumoxide::crypto::auth::hmacsha256;
+
+pub const MAGIC: &str = "krenc1:";
+pub const HASH_MAGIC: &str = "krhash1:";
+pub const KEY_BYTES: usize = 32;
+
+#[cfg(feature = "sqlite")]
+table! {
+    column_key (id) {
+        id -> Integer,
+        key -> Binary,
+    }
+}
+
+/// Use `key` for the encrypted columns read and written through `conn`, for example a key
+/// supplied by krd.
+#[cfg(feature = "sqlite")]
+pub fn set_key(conn: &DBConnection, key: SecretBytes) -> Result<()> {
+    use diesel::connection::SimpleConnection;
+    if key.len() != KEY_BYTES {
+        bail!("column key must be 32 bytes")
+    }
+    conn.batch_execute("PRAGMA temp_store = MEMORY; \
+                        CREATE TEMP TABLE IF NOT EXISTS column_key (id INTEGER PRIMARY KEY NOT NULL, key BLOB NOT NULL)")?;
+    conn.transaction::<_, Error, _>(|| {
+        diesel::delete(column_key::table).execute(conn)?;
+        diesel::insert_into(column_key::table)
+            .values((column_key::id.eq(0), column_key::key.eq(&key[..])))
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+/// Load the column key for the database at `database_url` into `conn`, creating it on first use.
+///
+/// An in-memory database gets a key that only lives as long as the connection.
+#[cfg(feature = "sqlite")]
+pub fn load_key_for_database(conn: &DBConnection, database_url: &str) -> Result<()> {
+    if database_url.is_empty() || database_url == ":memory:" {
+        return set_key(conn, secretbox::gen());
+    }
+    load_or_create_key(conn, format!("{}.key", database_url))
+}
+
+/// Load the column key from `key_path` into `conn`, or generate one and write it with 0600
+/// permissions.
+#[cfg(feature = "sqlite")]
+pub fn load_or_create_key<P: AsRef<Path>>(conn: &DBConnection, key_path: P) -> Result<()> {
+    let key_path = key_path.as_ref();
+    let key = match fs::File::open(key_path) {
+        Ok(file) => read_key_file(file, key_path)?,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            match create_key_file(key_path) {
+                Ok(key) => key,
+                // Another process created it first.
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => read_key_file(fs::File::open(key_path)?, key_path)?,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(e) => return Err(e.into()),
+    };
+    set_key(conn, key)
+}
+
+#[cfg(feature = "sqlite")]
+fn read_key_file(mut file: fs::File, key_path: &Path) -> Result<SecretBytes> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if file.metadata()?.permissions().mode() & 0o077 != 0 {
+            bail!(format!("{} must only be accessible by its owner", key_path.display()))
+        }
+    }
+    let mut key = vec![0u8; KEY_BYTES];
+    file.read_exact(&mut key)?;
+    let key = SecretBytes::new(key);
+    if file.read(&mut [0u8; 1])? != 0 {
+        bail!(format!("{} is not a column key", key_path.display()))
+    }
+    Ok(key)
+}
+
+#[cfg(feature = "sqlite")]
+fn create_key_file(key_path: &Path) -> io::Result<SecretBytes> {
+    let key = secretbox::gen();
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(key_path)?;
+    file.write_all(&key)?;
+    file.sync_all()?;
+    Ok(key)
+}
+
+#[cfg(feature = "sqlite")]
+fn with_key<T, F>(conn: &DBConnection, f: F) -> Result<T>
+    where F: FnOnce(&[u8]) -> Result<T> {
+    let key = match column_key::table.select(column_key::key).first::<Vec<u8>>(conn) {
+        Ok(key) => SecretBytes::new(key),
+        Err(_) => bail!("column key not loaded"),
+    };
+    f(&key[..])
+}
+
+#[cfg(feature = "sqlite")]
+fn serialization_error(e: Error) -> diesel::result::Error {
+    diesel::result::Error::SerializationError(Box::<::std::error::Error + Send + Sync>::from(e.to_string()))
+}
+
+#[cfg(feature = "sqlite")]
+fn deserialization_error(e: Error) -> diesel::result::Error {
+    diesel::result::Error::DeserializationError(Box::<::std::error::Error + Send + Sync>::from(e.to_string()))
+}
+
+pub fn is_encrypted_bytes(column: &[u8]) -> bool {
+    column.starts_with(MAGIC.as_bytes())
+}
+
+pub fn is_encrypted_text(column: &str) -> bool {
+    column.starts_with(MAGIC)
+}
+
+#[cfg(feature = "sqlite")]
+pub fn encrypt_bytes(conn: &DBConnection, plaintext: &[u8]) -> QueryResult<Vec<u8>> {
+    with_key(conn, |key| {
+        let nonce_and_ciphertext = secretbox::encrypt(plaintext, key)?;
+        Ok([MAGIC.as_bytes(), &nonce_and_ciphertext[..]].concat())
+    }).map_err(serialization_error)
+}
+
+#[cfg(feature = "sqlite")]
+pub fn decrypt_bytes(conn: &DBConnection, column: &[u8]) -> QueryResult<Vec<u8>> {
+    with_key(conn, |key| {
+        if !is_encrypted_bytes(column) {
+            bail!("column is not encrypted")
+        }
+        secretbox::decrypt(key, &column[MAGIC.len()..])
+    }).map_err(deserialization_error)
+}
+
+#[cfg(feature = "sqlite")]
+pub fn encrypt_text(conn: &DBConnection, plaintext: &str) -> QueryResult<String> {
+    with_key(conn, |key| {
+        let nonce_and_ciphertext = secretbox::encrypt(plaintext.as_bytes(), key)?;
+        Ok(MAGIC.to_string() + &base64::encode(&nonce_and_ciphertext))
+    }).map_err(serialization_error)
+}
+
+#[cfg(feature = "sqlite")]
+pub fn decrypt_text(conn: &DBConnection, column: &str) -> QueryResult<String> {
+    with_key(conn, |key| {
+        if !is_encrypted_text(column) {
+            bail!("column is not encrypted")
+        }
+        let nonce_and_ciphertext = base64::decode(&column[MAGIC.len()..])?;
+        Ok(String::from_utf8(secretbox::decrypt(key, &nonce_and_ciphertext)?).map_err(|e| e.utf8_error())?)
+    }).map_err(deserialization_error)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn encrypt_bytes(_conn: &DBConnection, plaintext: &[u8]) -> QueryResult<Vec<u8>> {
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn decrypt_bytes(_conn: &DBConnection, column: &[u8]) -> QueryResult<Vec<u8>> {
+    Ok(column.to_vec())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn encrypt_text(_conn: &DBConnection, plaintext: &str) -> QueryResult<String> {
+    Ok(plaintext.to_string())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn decrypt_text(_conn: &DBConnection, column: &str) -> QueryResult<String> {
+    Ok(column.to_string())
+}
+
+pub fn encrypt_optional_bytes(conn: &DBConnection, plaintext: Option<&[u8]>) -> QueryResult<Option<Vec<u8>>> {
+    plaintext.map_or(Ok(None), |p| encrypt_bytes(conn, p).map(Some))
+}
+
+pub fn decrypt_optional_bytes(conn: &DBConnection, column: Option<&[u8]>) -> QueryResult<Option<Vec<u8>>> {
+    column.map_or(Ok(None), |c| decrypt_bytes(conn, c).map(Some))
+}
+
+pub fn encrypt_optional_text(conn: &DBConnection, plaintext: Option<&str>) -> QueryResult<Option<String>> {
+    plaintext.map_or(Ok(None), |p| encrypt_text(conn, p).map(Some))
+}
+
+pub fn decrypt_optional_text(conn: &DBConnection, column: Option<&str>) -> QueryResult<Option<String>> {
+    column.map_or(Ok(None), |c| decrypt_text(conn, c).map(Some))
+}
+
+pub fn is_hashed_text(column: &str) -> bool {
+    column.starts_with(HASH_MAGIC)
+}
+
+/// A keyed hash of `plaintext`, for columns that are looked up by value.
+#[cfg(feature = "sqlite")]
+pub fn hash_text(conn: &DBConnection, plaintext: &str) -> QueryResult<String> {
+    with_key(conn, |key| {
+        let key = hmacsha256::Key::from_slice(key).ok_or("invalid column key")?;
+        let tag = hmacsha256::authenticate(plaintext.as_bytes(), &key);
+        Ok(HASH_MAGIC.to_string() + &base64::encode(&tag.0))
+    }).map_err(serialization_error)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn hash_text(_conn: &DBConnection, plaintext: &str) -> QueryResult<String> {
+    Ok(plaintext.to_string())
+}
+
+/// Encrypt each `(key, value)` row whose value is still plaintext with `update_row`.
+#[cfg(feature = "sqlite")]
+fn encrypt_rows<K, V, E, U>(rows: Vec<(K, V)>, is_encrypted: E, mut update_row: U) -> QueryResult<usize>
+    where E: Fn(&V) -> bool, U: FnMut(K, V) -> QueryResult<usize> {
+    let mut encrypted = 0;
+    for (key, value) in rows {
+        if !is_encrypted(&value) {
+            encrypted += update_row(key, value)?;
+        }
+    }
+    Ok(encrypted)
+}
+
+#[cfg(feature = "sqlite")]
+fn is_encrypted_optional_bytes(column: &Option<Vec<u8>>) -> bool {
+    column.as_ref().map_or(true, |c| is_encrypted_bytes(c))
+}
+
+/// Encrypt the sensitive columns of rows written before column encryption, then rebuild the
+/// database so that the plaintext does not survive in free pages.
+///
+/// `secure_delete` is enabled for the connection so that later updates and deletes overwrite the
+/// old contents too.
+#[cfg(feature = "sqlite")]
+pub fn encrypt_plaintext_columns(conn: &DBConnection) -> Result<()> {
+    use diesel::connection::SimpleConnection;
+    use diesel::update;
+    conn.batch_execute("PRAGMA secure_delete = ON")?;
+
+    let encrypted = conn.transaction::<_, Error, _>(|| {
+        let mut encrypted = 0;
+
+        encrypted += encrypt_rows(
+            log_chains::table.filter(log_chains::symmetric_encryption_key.is_not_null())
+                .select((log_chains::team_public_key, log_chains::member_public_key, log_chains::symmetric_encryption_key))
+                .load::<(Vec<u8>, Vec<u8>, Option<Vec<u8>>)>(conn)?
+                .into_iter().map(|(team, member, key)| ((team, member), key)).collect(),
+            is_encrypted_optional_bytes,
+            |(team, member), mut key| {
+                let updated = update(log_chains::table.find((team, member)))
+                    .set(log_chains::symmetric_encryption_key.eq(encrypt_optional_bytes(conn, key.as_ref().map(|k| &k[..]))?))
+                    .execute(conn);
+                if let Some(ref mut key) = key {
+                    ::secret::zeroize(key);
+                }
+                updated
+            })?;
+
+        encrypted += encrypt_rows(
+            read_tokens::table.select((read_tokens::team_public_key, read_tokens::reader_key_pair))
+                .load::<(Vec<u8>, Vec<u8>)>(conn)?,
+            |key_pair| is_encrypted_bytes(key_pair),
+            |team, mut key_pair| {
+                let updated = update(read_tokens::table.find(team))
+                    .set(read_tokens::reader_key_pair.eq(encrypt_bytes(conn, &key_pair)?))
+                    .execute(conn);
+                ::secret::zeroize(&mut key_pair);
+                updated
+            })?;
+
+        encrypted += encrypt_rows(
+            current_team::table.select((current_team::team_checkpoint, current_team::sign_key_pair))
+                .load::<(Vec<u8>, Option<Vec<u8>>)>(conn)?,
+            is_encrypted_optional_bytes,
+            |checkpoint, mut key_pair| {
+                let updated = update(current_team::table.find(checkpoint))
+                    .set(current_team::sign_key_pair.eq(encrypt_optional_bytes(conn, key_pair.as_ref().map(|k| &k[..]))?))
+                    .execute(conn);
+                if let Some(ref mut key_pair) = key_pair {
+                    ::secret::zeroize(key_pair);
+                }
+                updated
+            })?;
+
+        encrypted += encrypt_rows(
+            current_team::table.select((current_team::team_checkpoint, current_team::box_key_pair))
+                .load::<(Vec<u8>, Option<Vec<u8>>)>(conn)?,
+            is_encrypted_optional_bytes,
+            |checkpoint, mut key_pair| {
+                let updated = update(current_team::table.find(checkpoint))
+                    .set(current_team::box_key_pair.eq(encrypt_optional_bytes(conn, key_pair.as_ref().map(|k| &k[..]))?))
+                    .execute(conn);
+                if let Some(ref mut key_pair) = key_pair {
+                    ::secret::zeroize(key_pair);
+                }
+                updated
+            })?;
+
+        encrypted += encrypt_rows(
+            logs::table.select((logs::id, logs::log_json)).load::<(i64, String)>(conn)?,
+            |log_json| is_encrypted_text(log_json),
+            |id, log_json| update(logs::table.find(id))
+                .set(logs::log_json.eq(encrypt_text(conn, &log_json)?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            log_search_index::table.select((log_search_index::log_id, log_search_index::text)).load::<(i64, String)>(conn)?,
+            |text| is_encrypted_text(text),
+            |id, text| update(log_search_index::table.find(id))
+                .set(log_search_index::text.eq(encrypt_text(conn, &text)?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            log_search_index::table.select((log_search_index::log_id, log_search_index::member_email)).load::<(i64, String)>(conn)?,
+            |member_email| is_encrypted_text(member_email),
+            |id, member_email| update(log_search_index::table.find(id))
+                .set(log_search_index::member_email.eq(encrypt_text(conn, &member_email)?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            log_search_index::table.select((log_search_index::log_id, log_search_index::host)).load::<(i64, Option<String>)>(conn)?,
+            |host| host.as_ref().map_or(true, |h| is_encrypted_text(h)),
+            |id, host| update(log_search_index::table.find(id))
+                .set(log_search_index::host.eq(encrypt_optional_text(conn, host.as_ref().map(String::as_str))?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            log_search_index::table.select((log_search_index::log_id, log_search_index::result)).load::<(i64, String)>(conn)?,
+            |result| is_encrypted_text(result),
+            |id, result| update(log_search_index::table.find(id))
+                .set(log_search_index::result.eq(encrypt_text(conn, &result)?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            member_verifications::table.select((member_verifications::id, member_verifications::safety_number))
+                .load::<(i64, String)>(conn)?,
+            |safety_number| is_encrypted_text(safety_number),
+            |id, safety_number| update(member_verifications::table.find(id))
+                .set(member_verifications::safety_number.eq(encrypt_text(conn, &safety_number)?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            access_baselines::table
+                .select((access_baselines::team_public_key, access_baselines::member_public_key, access_baselines::kind, access_baselines::value))
+                .load::<(Vec<u8>, Vec<u8>, String, String)>(conn)?
+                .into_iter().map(|(team, member, kind, value)| ((team, member, kind, value.clone()), value)).collect(),
+            |value| is_hashed_text(value),
+            |(team, member, kind, value), _| update(access_baselines::table.find((team, member, kind, &value)))
+                .set(access_baselines::value.eq(hash_text(conn, &value)?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            queued_logs::table.select((queued_logs::id, queued_logs::log_json)).load::<(i64, Vec<u8>)>(conn)?,
+            |log_json| is_encrypted_bytes(log_json),
+            |id, log_json| update(queued_logs::table.find(id))
+                .set(queued_logs::log_json.eq(encrypt_bytes(conn, &log_json)?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            dead_lettered_logs::table.select((dead_lettered_logs::id, dead_lettered_logs::log_json)).load::<(i64, Vec<u8>)>(conn)?,
+            |log_json| is_encrypted_bytes(log_json),
+            |id, log_json| update(dead_lettered_logs::table.find(id))
+                .set(dead_lettered_logs::log_json.eq(encrypt_bytes(conn, &log_json)?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            alerts::table.select((alerts::id, alerts::log_json)).load::<(i64, String)>(conn)?,
+            |log_json| is_encrypted_text(log_json),
+            |id, log_json| update(alerts::table.find(id))
+                .set(alerts::log_json.eq(encrypt_text(conn, &log_json)?))
+                .execute(conn))?;
+
+        encrypted += encrypt_rows(
+            access_anomalies::table.select((access_anomalies::id, access_anomalies::log_json)).load::<(i64, String)>(conn)?,
+            |log_json| is_encrypted_text(log_json),
+            |id, log_json| update(access_anomalies::table.find(id))
+                .set(access_anomalies::log_json.eq(encrypt_text(conn, &log_json)?))
+                .execute(conn))?;
+
+        Ok(encrypted)
+    })?;
+
+    if encrypted > 0 {
+        conn.batch_execute("VACUUM")?;
+    }
+    Ok(())
+}
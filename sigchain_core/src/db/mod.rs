@@ -48,10 +48,13 @@ use self::shared_schema::direct_invitations;
 use self::shared_schema::identities;
 use self::shared_schema::pinned_host_keys;
 use self::shared_schema::log_chains;
+use self::shared_schema::member_devices;
 
 pub mod connection;
 pub use self::connection::*;
 
+pub mod column_encryption;
+
 pub mod models;
 pub use self::models::*;
 
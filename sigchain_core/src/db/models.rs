@@ -146,6 +146,13 @@ impl LogBlock {
             .filter(log_blocks::member_public_key.eq(member_public_key))
             .get_results(conn.conn)
     }
+    pub fn member_public_keys(conn: &TeamDBConnection) -> QueryResult<Vec<Vec<u8>>> {
+        Self::table()
+            .filter(log_blocks::team_public_key.eq(conn.team))
+            .select(log_blocks::member_public_key)
+            .distinct()
+            .get_results(conn.conn)
+    }
     pub fn find_next(conn: &TeamDBConnection, member_public_key: &[u8], last_block_hash: &Option<Vec<u8>>) -> QueryResult<Option<Self>> {
         let log_chain_filter = Self::table().filter(log_blocks::team_public_key.eq(conn.team))
             .filter(log_blocks::member_public_key.eq(member_public_key));
@@ -390,11 +397,20 @@ impl Identity {
             ssh_public_key: self.ssh_public_key,
             pgp_public_key: self.pgp_public_key,
             email: self.email,
+            encryption_key_proof: None,
         }
     }
     pub fn find(conn: &TeamDBConnection, identity_public_key: &[u8]) -> QueryResult<Self> {
         Self::table().find((conn.team, identity_public_key)).first::<Self>(conn.conn)
     }
+    /// The identity that `signer_public_key` belongs to, either as its own key or as one of its
+    /// devices, including revoked devices.
+    pub fn find_for_signer(conn: &TeamDBConnection, signer_public_key: &[u8]) -> QueryResult<Self> {
+        match MemberDevice::find(conn, signer_public_key).optional()? {
+            Some(device) => Self::find(conn, &device.member_public_key),
+            None => Self::find(conn, signer_public_key),
+        }
+    }
     pub fn insert(&self, conn: &DBConnection) -> QueryResult<usize> {
         insert_into(Self::table()).values(self).execute(conn)
     }
@@ -434,6 +450,88 @@ impl Identity {
     }
 }
 
+#[derive(Identifiable, Queryable, Insertable, AsChangeset, Debug, Clone)]
+#[table_name="member_devices"]
+#[primary_key(team_public_key, public_key)]
+pub struct MemberDevice {
+    pub team_public_key: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub member_public_key: Vec<u8>,
+    pub encryption_public_key: Vec<u8>,
+    pub ssh_public_key: Vec<u8>,
+    pub name: String,
+    /// Revoked devices are kept so that logs they wrote can still be attributed.
+    pub revoked: bool,
+}
+
+impl MemberDevice {
+    pub fn from_device(team: Vec<u8>, member_public_key: Vec<u8>, d: team::Device) -> MemberDevice {
+        MemberDevice {
+            team_public_key: team,
+            public_key: d.public_key,
+            member_public_key,
+            encryption_public_key: d.encryption_public_key,
+            ssh_public_key: d.ssh_public_key,
+            name: d.name,
+            revoked: false,
+        }
+    }
+    /// Find a device, revoked or not.
+    pub fn find(conn: &TeamDBConnection, device_public_key: &[u8]) -> QueryResult<Self> {
+        Self::table().find((conn.team, device_public_key)).first::<Self>(conn.conn)
+    }
+    pub fn find_active(conn: &TeamDBConnection, device_public_key: &[u8]) -> QueryResult<Self> {
+        use self::member_devices::dsl;
+        Self::table().filter(dsl::team_public_key.eq(conn.team))
+            .filter(dsl::public_key.eq(device_public_key))
+            .filter(dsl::revoked.eq(false))
+            .first(conn.conn)
+    }
+    pub fn insert(&self, conn: &DBConnection) -> QueryResult<usize> {
+        insert_into(Self::table()).values(self).execute(conn)
+    }
+    pub fn update(&self, conn: &DBConnection) -> QueryResult<Self> {
+        self.save_changes(conn)
+    }
+    /// Active devices of `member_public_key`.
+    pub fn filter_by_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Vec<Self>> {
+        use self::member_devices::dsl;
+        Self::table().filter(dsl::team_public_key.eq(conn.team))
+            .filter(dsl::member_public_key.eq(member_public_key))
+            .filter(dsl::revoked.eq(false))
+            .get_results(conn.conn)
+    }
+    /// Active and revoked devices of `member_public_key`.
+    pub fn filter_by_member_including_revoked(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Vec<Self>> {
+        use self::member_devices::dsl;
+        Self::table().filter(dsl::team_public_key.eq(conn.team))
+            .filter(dsl::member_public_key.eq(member_public_key))
+            .get_results(conn.conn)
+    }
+    pub fn filter_by_encryption_public_key(conn: &TeamDBConnection, encryption_public_key: &[u8]) -> QueryResult<Vec<Self>> {
+        use self::member_devices::dsl;
+        Self::table().filter(dsl::team_public_key.eq(conn.team))
+            .filter(dsl::encryption_public_key.eq(encryption_public_key))
+            .get_results(conn.conn)
+    }
+    pub fn revoke_all_for_member(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<()> {
+        use self::member_devices::dsl;
+        update(Self::table().filter(dsl::team_public_key.eq(conn.team))
+                .filter(dsl::member_public_key.eq(member_public_key)))
+            .set(dsl::revoked.eq(true))
+            .execute(conn.conn)?;
+        Ok(())
+    }
+    /// The public key of the member that `signer_public_key` acts for: the key itself for
+    /// a member's own key, or the owning member's key for an active device.
+    pub fn member_public_key_for_signer(conn: &TeamDBConnection, signer_public_key: &[u8]) -> QueryResult<Vec<u8>> {
+        Ok(match Self::find_active(conn, signer_public_key).optional()? {
+            Some(device) => device.member_public_key,
+            None => signer_public_key.into(),
+        })
+    }
+}
+
 #[derive(Identifiable, Queryable, Insertable, AsChangeset, Debug, Clone)]
 #[table_name="teams"]
 #[primary_key(public_key)]
@@ -448,6 +546,9 @@ pub struct Team {
     pub last_read_log_chain_logical_timestamp: Option<i64>,
 
     pub command_encrypted_logging_enabled: bool,
+
+    /// JSON encoded `team::LogRedactionRules` from the latest policy that set them.
+    pub log_redaction_rules: Option<String>,
 }
 
 impl Team {
@@ -530,10 +631,11 @@ impl Into<team::SSHHostKey> for PinnedHostKey {
     }
 }
 
-#[derive(Queryable, Insertable, Identifiable, AsChangeset, Debug, Clone, PartialEq, Eq)]
+/// `symmetric_encryption_key` is encrypted with the client's column key in the database, zeroized on
+/// drop and redacted from `Debug` output.
+#[derive(Queryable, Insertable, Identifiable, Clone, PartialEq, Eq)]
 #[table_name="log_chains"]
 #[primary_key(team_public_key, member_public_key)]
-#[changeset_options(treat_none_as_null = "true")]
 pub struct LogChain {
     pub team_public_key: Vec<u8>,
     pub member_public_key: Vec<u8>,
@@ -541,12 +643,40 @@ pub struct LogChain {
     pub symmetric_encryption_key: Option<Vec<u8>>,
 }
 
+impl ::std::fmt::Debug for LogChain {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("LogChain")
+            .field("team_public_key", &self.team_public_key)
+            .field("member_public_key", &self.member_public_key)
+            .field("last_block_hash", &self.last_block_hash)
+            .field("symmetric_encryption_key", &self.symmetric_encryption_key.as_ref().map(|_| "****"))
+            .finish()
+    }
+}
+
+impl Drop for LogChain {
+    fn drop(&mut self) {
+        if let Some(ref mut key) = self.symmetric_encryption_key {
+            ::secret::zeroize(key);
+        }
+    }
+}
+
 impl LogChain {
     pub fn find(conn: &TeamDBConnection, member_public_key: &[u8]) -> QueryResult<Self> {
-        Self::table().find((conn.team, member_public_key)).first::<Self>(conn.conn)
+        let mut log_chain = Self::table().find((conn.team, member_public_key)).first::<Self>(conn.conn)?;
+        log_chain.symmetric_encryption_key = column_encryption::decrypt_optional_bytes(conn.conn,
+            log_chain.symmetric_encryption_key.as_ref().map(|k| &k[..]))?;
+        Ok(log_chain)
     }
     pub fn insert(&self, conn: &DBConnection) -> QueryResult<usize> {
-        insert_into(Self::table()).values(self).execute(conn)
+        insert_into(Self::table()).values(&LogChain {
+            team_public_key: self.team_public_key.clone(),
+            member_public_key: self.member_public_key.clone(),
+            last_block_hash: self.last_block_hash.clone(),
+            symmetric_encryption_key: column_encryption::encrypt_optional_bytes(conn,
+                self.symmetric_encryption_key.as_ref().map(|k| &k[..]))?,
+        }).execute(conn)
     }
     pub fn update_last_block_hash(
         conn: &TeamDBConnection,
@@ -561,14 +691,11 @@ impl LogChain {
     pub fn update_symmetric_encryption_key(
         conn: &TeamDBConnection,
         member_public_key: &[u8],
-        symmetric_encrpytion_key: Option<Vec<u8>>,
+        symmetric_encrpytion_key: Option<&[u8]>,
     ) -> QueryResult<usize> {
         use self::log_chains::dsl;
         update(Self::table().find((conn.team, member_public_key)))
-            .set(dsl::symmetric_encryption_key.eq(symmetric_encrpytion_key))
+            .set(dsl::symmetric_encryption_key.eq(column_encryption::encrypt_optional_bytes(conn.conn, symmetric_encrpytion_key)?))
             .execute(conn.conn)
     }
-    pub fn update(&self, conn: &DBConnection) -> QueryResult<Self> {
-        self.save_changes(conn)
-    }
 }
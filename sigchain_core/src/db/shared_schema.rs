@@ -41,6 +41,7 @@ table! {
         temporary_approval_seconds -> Nullable<BigInt>,
         last_read_log_chain_logical_timestamp -> Nullable<BigInt>,
         command_encrypted_logging_enabled -> Bool,
+        log_redaction_rules -> Nullable<Text>,
     }
 }
 
@@ -87,3 +88,15 @@ table! {
         symmetric_encryption_key -> Nullable<Binary>,
     }
 }
+
+table! {
+    member_devices (team_public_key, public_key) {
+        team_public_key -> Binary,
+        public_key -> Binary,
+        member_public_key -> Binary,
+        encryption_public_key -> Binary,
+        ssh_public_key -> Binary,
+        name -> Text,
+        revoked -> Bool,
+    }
+}
@@ -4,6 +4,9 @@ error_chain!{
     errors {
         TryFromIntError {}
         NilError {}
+        HTTPStatus(status: u16, message: String) {
+            display("server responded with HTTP status {}: {}", status, message)
+        }
     }
     foreign_links {
         SerdeJson(serde_json::Error);
@@ -52,6 +55,13 @@ pub mod specified {
             InviteLastBlockHashNotReached {}
             TeamCheckpointLastBlockHashNotReached {}
             VersionIncompatible {}
+            PGPPublicKeyNotValid {}
+            PGPUserIdMismatch {}
+            SSHPublicKeyNotValid {}
+            EncryptionKeyProofNotValid {}
+            SafetyNumberMismatch {}
+            DeviceAuthorizationNotValid {}
+            DeviceRevoked {}
         }
     }
 }
@@ -3,6 +3,10 @@ use base64;
 
 extern crate crc24;
 extern crate byteorder;
+extern crate sha1;
+
+pub mod packets;
+pub mod public_key;
 
 pub fn pgp_public_key_ascii_armor_string(public_key_packets: &[u8]) -> Result<String> {
     let crc = crc24::hash_raw(public_key_packets);
@@ -48,3 +52,213 @@ Comment: Created with Krypton
         base64::encode(&crc_bytes)))
 }
 
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArmoredBlock {
+    /// The text between `BEGIN PGP ` and the closing dashes, e.g. `PUBLIC KEY BLOCK` or `SIGNATURE`.
+    pub label: String,
+    /// Armor headers such as `Comment` and `Version`.
+    pub headers: Vec<(String, String)>,
+    pub data: Vec<u8>,
+}
+
+pub const LABEL_PUBLIC_KEY: &str = "PUBLIC KEY BLOCK";
+pub const LABEL_SIGNATURE: &str = "SIGNATURE";
+const LABEL_SIGNED_MESSAGE: &str = "SIGNED MESSAGE";
+
+/// Decode every ASCII armored block in `armored`, verifying each block's CRC-24 checksum when
+/// present. Text outside of the blocks is ignored.
+pub fn dearmor_all(armored: &str) -> Result<Vec<ArmoredBlock>> {
+    let mut blocks = vec![];
+    let mut lines = armored.lines().map(|l| l.trim());
+    while let Some(line) = lines.next() {
+        let label = match armor_line_label(line, "BEGIN") {
+            Some(label) => label,
+            None => continue,
+        };
+        if label == LABEL_SIGNED_MESSAGE {
+            // The cleartext is not armored, its signature follows in a separate block.
+            continue;
+        }
+
+        let mut headers = vec![];
+        let mut body = String::new();
+        let mut checksum = None;
+        let mut in_headers = true;
+        let mut ended = false;
+        for line in lines.by_ref() {
+            if let Some(end_label) = armor_line_label(line, "END") {
+                if end_label != label {
+                    bail!(format!("armor block BEGIN PGP {} ended with END PGP {}", label, end_label));
+                }
+                ended = true;
+                break;
+            }
+            if in_headers {
+                if line.is_empty() {
+                    in_headers = false;
+                    continue;
+                }
+                if let Some(separator) = line.find(": ") {
+                    headers.push((line[..separator].to_string(), line[separator + 2..].to_string()));
+                    continue;
+                }
+                // Some producers omit the blank line when there are no headers.
+                in_headers = false;
+            }
+            if line.starts_with('=') {
+                checksum = Some(base64::decode(&line[1..])?);
+            } else {
+                body.push_str(line);
+            }
+        }
+        if !ended {
+            bail!(format!("missing END PGP {}", label));
+        }
+
+        let data = base64::decode(&body)?;
+        if let Some(checksum) = checksum {
+            let crc = crc24::hash_raw(&data);
+            if checksum.len() != 3 || checksum != [(crc >> 16) as u8, (crc >> 8) as u8, crc as u8] {
+                bail!(format!("armor checksum mismatch in PGP {}", label));
+            }
+        }
+        blocks.push(ArmoredBlock { label: label.into(), headers, data });
+    }
+    if blocks.is_empty() {
+        bail!("missing armor header line");
+    }
+    Ok(blocks)
+}
+
+/// Decode the first ASCII armored block in `armored`.
+pub fn dearmor(armored: &str) -> Result<Vec<u8>> {
+    Ok(dearmor_all(armored)?.remove(0).data)
+}
+
+/// The packets of every block labelled `label`, concatenated, e.g. to read a keyring exported as
+/// several public key blocks.
+pub fn dearmor_label(armored: &str, label: &str) -> Result<Vec<u8>> {
+    let blocks = dearmor_all(armored)?.into_iter().filter(|b| b.label == label).collect::<Vec<_>>();
+    if blocks.is_empty() {
+        bail!(format!("no PGP {} found", label));
+    }
+    Ok(blocks.into_iter().flat_map(|b| b.data).collect())
+}
+
+/// Binary packets from user input that is either ASCII armored or plain base64.
+pub fn packets_from_input(input: &str, label: &str) -> Result<Vec<u8>> {
+    if input.contains("-----BEGIN PGP ") {
+        return dearmor_label(input, label);
+    }
+    let base64_input: String = input.split_whitespace().collect();
+    Ok(base64::decode(&base64_input)?)
+}
+
+fn armor_line_label<'a>(line: &'a str, kind: &str) -> Option<&'a str> {
+    let prefix = format!("-----{} PGP ", kind);
+    if line.starts_with(&prefix) && line.ends_with("-----") && line.len() >= prefix.len() + 5 {
+        Some(&line[prefix.len()..line.len() - 5])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checksum_line(data: &[u8]) -> String {
+        let crc = crc24::hash_raw(data);
+        format!("={}", base64::encode(&[(crc >> 16) as u8, (crc >> 8) as u8, crc as u8]))
+    }
+
+    fn armor(label: &str, data: &[u8], checksum: Option<String>) -> String {
+        format!("-----BEGIN PGP {}-----\nVersion: Test\n\n{}\n{}-----END PGP {}-----\n",
+                label,
+                base64::encode(data),
+                checksum.map(|c| c + "\n").unwrap_or_default(),
+                label)
+    }
+
+    #[test]
+    fn dearmor_round_trip() {
+        let packets = (0..200).map(|i| i as u8).collect::<Vec<_>>();
+        let armored = pgp_public_key_ascii_armor_string(&packets).unwrap();
+        let blocks = dearmor_all(&armored).unwrap();
+        assert_eq!(blocks, vec![ArmoredBlock {
+            label: LABEL_PUBLIC_KEY.into(),
+            headers: vec![("Comment".into(), "Created with Krypton".into())],
+            data: packets.clone(),
+        }]);
+        assert_eq!(dearmor(&pgp_signature_ascii_armor_string(&packets).unwrap()).unwrap(), packets);
+    }
+
+    #[test]
+    fn dearmor_bad_checksum() {
+        let armored = armor(LABEL_PUBLIC_KEY, b"key packets", Some(checksum_line(b"other packets")));
+        assert!(dearmor_all(&armored).is_err());
+
+        let armored = armor(LABEL_PUBLIC_KEY, b"key packets", Some("=AAAAAA==".into()));
+        assert!(dearmor_all(&armored).is_err());
+    }
+
+    #[test]
+    fn dearmor_missing_checksum() {
+        let armored = armor(LABEL_PUBLIC_KEY, b"key packets", None);
+        assert_eq!(dearmor(&armored).unwrap(), b"key packets".to_vec());
+    }
+
+    #[test]
+    fn dearmor_without_blank_line() {
+        let armored = format!("-----BEGIN PGP SIGNATURE-----\n{}\n{}\n-----END PGP SIGNATURE-----",
+                              base64::encode(b"signature"), checksum_line(b"signature"));
+        let blocks = dearmor_all(&armored).unwrap();
+        assert!(blocks[0].headers.is_empty());
+        assert_eq!(blocks[0].data, b"signature".to_vec());
+    }
+
+    #[test]
+    fn dearmor_concatenated_blocks() {
+        let armored = format!("leading text\n{}{}\n-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA256\n\ncleartext\n{}trailing text",
+                              armor(LABEL_PUBLIC_KEY, b"first key", Some(checksum_line(b"first key"))),
+                              armor(LABEL_PUBLIC_KEY, b"second key", None),
+                              armor(LABEL_SIGNATURE, b"signature", Some(checksum_line(b"signature"))));
+        let blocks = dearmor_all(&armored).unwrap();
+        assert_eq!(blocks.iter().map(|b| b.label.as_str()).collect::<Vec<_>>(),
+                   vec![LABEL_PUBLIC_KEY, LABEL_PUBLIC_KEY, LABEL_SIGNATURE]);
+        assert_eq!(blocks[0].headers, vec![("Version".to_string(), "Test".to_string())]);
+
+        assert_eq!(dearmor_label(&armored, LABEL_PUBLIC_KEY).unwrap(), b"first keysecond key".to_vec());
+        assert_eq!(dearmor_label(&armored, LABEL_SIGNATURE).unwrap(), b"signature".to_vec());
+        assert!(dearmor_label(&armored, "PRIVATE KEY BLOCK").is_err());
+
+        // A bad checksum in any block fails the whole input.
+        let armored = format!("{}{}",
+                              armor(LABEL_PUBLIC_KEY, b"first key", None),
+                              armor(LABEL_PUBLIC_KEY, b"second key", Some(checksum_line(b"first key"))));
+        assert!(dearmor_label(&armored, LABEL_PUBLIC_KEY).is_err());
+    }
+
+    #[test]
+    fn dearmor_malformed() {
+        assert!(dearmor_all("").is_err());
+        assert!(dearmor_all("no armor here").is_err());
+        assert!(dearmor_all(&format!("-----BEGIN PGP SIGNATURE-----\n\n{}\n", base64::encode(b"signature"))).is_err());
+        assert!(dearmor_all(&format!("-----BEGIN PGP SIGNATURE-----\n\n{}\n-----END PGP PUBLIC KEY BLOCK-----",
+                                     base64::encode(b"signature"))).is_err());
+        assert!(dearmor_all("-----BEGIN PGP SIGNATURE-----\n\nnot base64!\n-----END PGP SIGNATURE-----").is_err());
+    }
+
+    #[test]
+    fn packets_from_armored_or_base64_input() {
+        let armored = armor(LABEL_PUBLIC_KEY, b"key packets", Some(checksum_line(b"key packets")));
+        assert_eq!(packets_from_input(&armored, LABEL_PUBLIC_KEY).unwrap(), b"key packets".to_vec());
+        assert!(packets_from_input(&armored, LABEL_SIGNATURE).is_err());
+
+        let encoded = base64::encode(b"key packets");
+        let wrapped = format!(" {}\n{} \n", &encoded[..8], &encoded[8..]);
+        assert_eq!(packets_from_input(&wrapped, LABEL_PUBLIC_KEY).unwrap(), b"key packets".to_vec());
+        assert!(packets_from_input("not base64!", LABEL_PUBLIC_KEY).is_err());
+    }
+}
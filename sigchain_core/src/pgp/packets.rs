@@ -0,0 +1,324 @@
+/// Minimal OpenPGP (RFC 4880) packet parsing for the key and signature packets Krypton produces.
+
+use super::Result;
+use super::byteorder::{BigEndian, ByteOrder};
+use super::sha1;
+
+pub const TAG_SIGNATURE: u8 = 2;
+pub const TAG_PUBLIC_KEY: u8 = 6;
+pub const TAG_USER_ID: u8 = 13;
+pub const TAG_PUBLIC_SUBKEY: u8 = 14;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub tag: u8,
+    pub body: Vec<u8>,
+}
+
+/// The primary public key in a transferable public key.
+pub fn primary_public_key(public_key_packets: &[u8]) -> Result<PublicKeyPacket> {
+    match parse_packets(public_key_packets)?.into_iter().find(|p| p.tag == TAG_PUBLIC_KEY) {
+        Some(packet) => PublicKeyPacket::parse(&packet.body),
+        None => bail!("no public key packet"),
+    }
+}
+
+/// The first signature in `signature_packets`.
+pub fn first_signature(signature_packets: &[u8]) -> Result<SignaturePacket> {
+    match parse_packets(signature_packets)?.into_iter().find(|p| p.tag == TAG_SIGNATURE) {
+        Some(packet) => SignaturePacket::parse(&packet.body),
+        None => bail!("no signature packet"),
+    }
+}
+
+/// Split a byte string into its packets.
+pub fn parse_packets(mut data: &[u8]) -> Result<Vec<Packet>> {
+    let mut packets = vec![];
+    while !data.is_empty() {
+        let header = data[0];
+        if header & 0x80 == 0 {
+            bail!("invalid packet header");
+        }
+        data = &data[1..];
+        if header & 0x40 == 0 {
+            // Old format packet.
+            let tag = (header >> 2) & 0x0f;
+            let length = match header & 0x03 {
+                0 => read_be(&mut data, 1)?,
+                1 => read_be(&mut data, 2)?,
+                2 => read_be(&mut data, 4)?,
+                _ => data.len(),
+            };
+            packets.push(Packet { tag, body: take(&mut data, length)?.to_vec() });
+        } else {
+            let tag = header & 0x3f;
+            let mut body = vec![];
+            loop {
+                let first = take(&mut data, 1)?[0] as usize;
+                match first {
+                    0...191 => {
+                        body.extend_from_slice(take(&mut data, first)?);
+                        break;
+                    }
+                    192...223 => {
+                        let second = take(&mut data, 1)?[0] as usize;
+                        body.extend_from_slice(take(&mut data, ((first - 192) << 8) + second + 192)?);
+                        break;
+                    }
+                    255 => {
+                        let length = read_be(&mut data, 4)?;
+                        body.extend_from_slice(take(&mut data, length)?);
+                        break;
+                    }
+                    _ => {
+                        // Partial body length, more chunks follow.
+                        body.extend_from_slice(take(&mut data, 1 << (first & 0x1f))?);
+                    }
+                }
+            }
+            packets.push(Packet { tag, body });
+        }
+    }
+    Ok(packets)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublicKeyAlgorithm {
+    RSA,
+    DSA,
+    Elgamal,
+    ECDH,
+    ECDSA,
+    EdDSA,
+    Unknown(u8),
+}
+
+impl PublicKeyAlgorithm {
+    pub fn from_u8(id: u8) -> Self {
+        use self::PublicKeyAlgorithm::*;
+        match id {
+            1 | 2 | 3 => RSA,
+            16 => Elgamal,
+            17 => DSA,
+            18 => ECDH,
+            19 => ECDSA,
+            22 => EdDSA,
+            _ => Unknown(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    SHA1,
+    SHA256,
+    SHA384,
+    SHA512,
+    SHA224,
+    Unknown(u8),
+}
+
+impl HashAlgorithm {
+    pub fn from_u8(id: u8) -> Self {
+        use self::HashAlgorithm::*;
+        match id {
+            2 => SHA1,
+            8 => SHA256,
+            9 => SHA384,
+            10 => SHA512,
+            11 => SHA224,
+            _ => Unknown(id),
+        }
+    }
+}
+
+/// Algorithm specific public key material.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PublicKeyMaterial {
+    RSA { n: Vec<u8>, e: Vec<u8> },
+    /// ECDSA, EdDSA and ECDH keys: the curve OID and the encoded point.
+    EllipticCurve { curve_oid: Vec<u8>, point: Vec<u8> },
+    Other(Vec<u8>),
+}
+
+pub const OID_NIST_P256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+pub const OID_ED25519: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x0f, 0x01];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicKeyPacket {
+    pub version: u8,
+    pub created_unix_seconds: u32,
+    pub algorithm: PublicKeyAlgorithm,
+    pub material: PublicKeyMaterial,
+    /// The full packet body, which the fingerprint is computed over.
+    pub body: Vec<u8>,
+}
+
+impl PublicKeyPacket {
+    pub fn parse(body: &[u8]) -> Result<Self> {
+        let mut data = body;
+        let version = take(&mut data, 1)?[0];
+        if version != 4 {
+            bail!(format!("unsupported public key packet version {}", version));
+        }
+        let created_unix_seconds = read_be(&mut data, 4)? as u32;
+        let algorithm = PublicKeyAlgorithm::from_u8(take(&mut data, 1)?[0]);
+        let material = match algorithm {
+            PublicKeyAlgorithm::RSA => {
+                let n = read_mpi(&mut data)?;
+                let e = read_mpi(&mut data)?;
+                PublicKeyMaterial::RSA { n, e }
+            }
+            PublicKeyAlgorithm::ECDSA | PublicKeyAlgorithm::EdDSA | PublicKeyAlgorithm::ECDH => {
+                let oid_length = take(&mut data, 1)?[0] as usize;
+                let curve_oid = take(&mut data, oid_length)?.to_vec();
+                let point = read_mpi(&mut data)?;
+                PublicKeyMaterial::EllipticCurve { curve_oid, point }
+            }
+            _ => PublicKeyMaterial::Other(data.to_vec()),
+        };
+        Ok(PublicKeyPacket { version, created_unix_seconds, algorithm, material, body: body.to_vec() })
+    }
+
+    /// The v4 fingerprint: SHA-1 over the packet body with an old format packet header.
+    pub fn fingerprint(&self) -> [u8; 20] {
+        let mut hash = sha1::Sha1::new();
+        hash.update(&[0x99, (self.body.len() >> 8) as u8, self.body.len() as u8]);
+        hash.update(&self.body);
+        hash.digest().bytes()
+    }
+
+    /// The low 8 bytes of the fingerprint.
+    pub fn key_id(&self) -> Vec<u8> {
+        self.fingerprint()[12..].to_vec()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subpacket {
+    pub subpacket_type: u8,
+    pub critical: bool,
+    pub body: Vec<u8>,
+}
+
+pub const SUBPACKET_CREATION_TIME: u8 = 2;
+pub const SUBPACKET_ISSUER: u8 = 16;
+pub const SUBPACKET_ISSUER_FINGERPRINT: u8 = 33;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignaturePacket {
+    pub version: u8,
+    pub signature_type: u8,
+    pub public_key_algorithm: PublicKeyAlgorithm,
+    pub hash_algorithm: HashAlgorithm,
+    pub hashed_subpackets: Vec<Subpacket>,
+    pub unhashed_subpackets: Vec<Subpacket>,
+    pub hash_prefix: [u8; 2],
+    /// The signature MPIs: `s` for RSA, `r` and `s` for ECDSA and EdDSA.
+    pub mpis: Vec<Vec<u8>>,
+    /// Bytes of the packet covered by the signature hash, from the version through the hashed subpackets.
+    pub hashed_section: Vec<u8>,
+}
+
+impl SignaturePacket {
+    pub fn parse(body: &[u8]) -> Result<Self> {
+        let mut data = body;
+        let version = take(&mut data, 1)?[0];
+        if version != 4 {
+            bail!(format!("unsupported signature packet version {}", version));
+        }
+        let signature_type = take(&mut data, 1)?[0];
+        let public_key_algorithm = PublicKeyAlgorithm::from_u8(take(&mut data, 1)?[0]);
+        let hash_algorithm = HashAlgorithm::from_u8(take(&mut data, 1)?[0]);
+        let hashed_length = read_be(&mut data, 2)?;
+        let hashed_subpackets = parse_subpackets(take(&mut data, hashed_length)?)?;
+        let hashed_section = body[..6 + hashed_length].to_vec();
+        let unhashed_length = read_be(&mut data, 2)?;
+        let unhashed_subpackets = parse_subpackets(take(&mut data, unhashed_length)?)?;
+        let prefix = take(&mut data, 2)?;
+        let hash_prefix = [prefix[0], prefix[1]];
+
+        let mut mpis = vec![];
+        while !data.is_empty() {
+            mpis.push(read_mpi(&mut data)?);
+        }
+
+        Ok(SignaturePacket {
+            version, signature_type, public_key_algorithm, hash_algorithm,
+            hashed_subpackets, unhashed_subpackets, hash_prefix, mpis, hashed_section,
+        })
+    }
+
+    /// The data appended to the signed content before hashing.
+    pub fn hash_trailer(&self) -> Vec<u8> {
+        let mut trailer = self.hashed_section.clone();
+        trailer.extend_from_slice(&[self.version, 0xff]);
+        let mut length = [0u8; 4];
+        BigEndian::write_u32(&mut length, self.hashed_section.len() as u32);
+        trailer.extend_from_slice(&length);
+        trailer
+    }
+
+    fn subpackets(&self) -> ::std::iter::Chain<::std::slice::Iter<Subpacket>, ::std::slice::Iter<Subpacket>> {
+        self.hashed_subpackets.iter().chain(self.unhashed_subpackets.iter())
+    }
+
+    /// The 8 byte key ID of the signing key, if the signature names it.
+    pub fn issuer_key_id(&self) -> Option<Vec<u8>> {
+        self.subpackets().filter_map(|s| match s.subpacket_type {
+            SUBPACKET_ISSUER if s.body.len() == 8 => Some(s.body.clone()),
+            SUBPACKET_ISSUER_FINGERPRINT if s.body.len() == 21 => Some(s.body[13..].to_vec()),
+            _ => None,
+        }).next()
+    }
+
+    pub fn created_unix_seconds(&self) -> Option<u32> {
+        self.hashed_subpackets.iter()
+            .find(|s| s.subpacket_type == SUBPACKET_CREATION_TIME && s.body.len() == 4)
+            .map(|s| BigEndian::read_u32(&s.body))
+    }
+}
+
+fn parse_subpackets(mut data: &[u8]) -> Result<Vec<Subpacket>> {
+    let mut subpackets = vec![];
+    while !data.is_empty() {
+        let first = take(&mut data, 1)?[0] as usize;
+        let length = match first {
+            0...191 => first,
+            192...254 => {
+                let second = take(&mut data, 1)?[0] as usize;
+                ((first - 192) << 8) + second + 192
+            }
+            _ => read_be(&mut data, 4)?,
+        };
+        if length == 0 {
+            bail!("empty signature subpacket");
+        }
+        let subpacket = take(&mut data, length)?;
+        subpackets.push(Subpacket {
+            subpacket_type: subpacket[0] & 0x7f,
+            critical: subpacket[0] & 0x80 != 0,
+            body: subpacket[1..].to_vec(),
+        });
+    }
+    Ok(subpackets)
+}
+
+/// Read a multiprecision integer, returning its big-endian bytes.
+fn read_mpi(data: &mut &[u8]) -> Result<Vec<u8>> {
+    let bits = read_be(data, 2)?;
+    Ok(take(data, (bits + 7) / 8)?.to_vec())
+}
+
+fn read_be(data: &mut &[u8], length: usize) -> Result<usize> {
+    Ok(take(data, length)?.iter().fold(0usize, |n, b| (n << 8) | *b as usize))
+}
+
+fn take<'a>(data: &mut &'a [u8], length: usize) -> Result<&'a [u8]> {
+    if data.len() < length {
+        bail!("truncated packet");
+    }
+    let (taken, rest) = data.split_at(length);
+    *data = rest;
+    Ok(taken)
+}
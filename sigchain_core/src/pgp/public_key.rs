@@ -0,0 +1,250 @@
+/// Transferable public keys (RFC 4880 section 11.1): a primary key followed by its user IDs,
+/// their certifications and any subkeys.
+
+use super::Result;
+use super::byteorder::{BigEndian, ByteOrder};
+use super::packets::*;
+
+pub const SIGNATURE_TYPE_GENERIC_CERTIFICATION: u8 = 0x10;
+pub const SIGNATURE_TYPE_POSITIVE_CERTIFICATION: u8 = 0x13;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PGPUserId {
+    pub user_id: String,
+    /// Certification signatures over this user ID, by any key.
+    pub signatures: Vec<SignaturePacket>,
+}
+
+impl PGPUserId {
+    /// The address in a `Name <email>` user ID, or the whole user ID if it is a bare address.
+    pub fn email(&self) -> Option<&str> {
+        match (self.user_id.rfind('<'), self.user_id.rfind('>')) {
+            (Some(start), Some(end)) if start < end => Some(&self.user_id[start + 1..end]),
+            _ if self.user_id.contains('@') => Some(self.user_id.trim()),
+            _ => None,
+        }
+    }
+
+    /// The data a certification of this user ID by `primary_key` is computed over.
+    pub fn certification_data(&self, primary_key: &PublicKeyPacket) -> Vec<u8> {
+        let mut data = vec![0x99, (primary_key.body.len() >> 8) as u8, primary_key.body.len() as u8];
+        data.extend_from_slice(&primary_key.body);
+        let mut length = [0u8; 4];
+        BigEndian::write_u32(&mut length, self.user_id.len() as u32);
+        data.push(0xb4);
+        data.extend_from_slice(&length);
+        data.extend_from_slice(self.user_id.as_bytes());
+        data
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PGPPublicKey {
+    pub primary_key: PublicKeyPacket,
+    pub user_ids: Vec<PGPUserId>,
+    pub subkeys: Vec<PublicKeyPacket>,
+}
+
+impl PGPPublicKey {
+    /// Parse binary public key packets. Errors unless the packets start with a primary key
+    /// followed by at least one user ID.
+    pub fn parse(public_key_packets: &[u8]) -> Result<PGPPublicKey> {
+        PGPPublicKey::from_packets(parse_packets(public_key_packets)?)
+    }
+
+    /// Parse a keyring: one or more transferable public keys back to back.
+    pub fn parse_keyring(public_key_packets: &[u8]) -> Result<Vec<PGPPublicKey>> {
+        let mut keys = vec![];
+        let mut key_packets: Vec<Packet> = vec![];
+        for packet in parse_packets(public_key_packets)? {
+            if packet.tag == TAG_PUBLIC_KEY && !key_packets.is_empty() {
+                keys.push(PGPPublicKey::from_packets(key_packets)?);
+                key_packets = vec![];
+            }
+            key_packets.push(packet);
+        }
+        keys.push(PGPPublicKey::from_packets(key_packets)?);
+        Ok(keys)
+    }
+
+    fn from_packets(packets: Vec<Packet>) -> Result<PGPPublicKey> {
+        let mut packets = packets.into_iter();
+        let primary_key = match packets.next() {
+            Some(ref packet) if packet.tag == TAG_PUBLIC_KEY => PublicKeyPacket::parse(&packet.body)?,
+            _ => bail!("public key does not start with a primary key packet"),
+        };
+
+        let mut user_ids: Vec<PGPUserId> = vec![];
+        let mut subkeys = vec![];
+        for packet in packets {
+            match packet.tag {
+                TAG_USER_ID => user_ids.push(PGPUserId {
+                    user_id: String::from_utf8(packet.body)
+                        .map_err(|_| "user ID is not valid UTF-8")?,
+                    signatures: vec![],
+                }),
+                TAG_SIGNATURE if subkeys.is_empty() => {
+                    // Direct key signatures precede the user IDs and are ignored.
+                    if let Some(user_id) = user_ids.last_mut() {
+                        user_id.signatures.push(SignaturePacket::parse(&packet.body)?);
+                    }
+                }
+                TAG_PUBLIC_SUBKEY => subkeys.push(PublicKeyPacket::parse(&packet.body)?),
+                TAG_PUBLIC_KEY => bail!("more than one primary key"),
+                _ => {}
+            }
+        }
+        if user_ids.is_empty() {
+            bail!("public key has no user ID");
+        }
+        Ok(PGPPublicKey { primary_key, user_ids, subkeys })
+    }
+
+    pub fn algorithm(&self) -> PublicKeyAlgorithm {
+        self.primary_key.algorithm
+    }
+
+    pub fn created_unix_seconds(&self) -> u32 {
+        self.primary_key.created_unix_seconds
+    }
+
+    pub fn fingerprint(&self) -> [u8; 20] {
+        self.primary_key.fingerprint()
+    }
+
+    pub fn key_id(&self) -> Vec<u8> {
+        self.primary_key.key_id()
+    }
+
+    /// The fingerprint as upper case hex in groups of four, as printed by `gpg --fingerprint`.
+    pub fn fingerprint_string(&self) -> String {
+        fingerprint_string(&self.fingerprint())
+    }
+
+    /// Certifications of `user_id` issued by the primary key itself.
+    pub fn self_signatures<'a>(&self, user_id: &'a PGPUserId) -> Vec<&'a SignaturePacket> {
+        let key_id = self.key_id();
+        user_id.signatures.iter()
+            .filter(|s| s.signature_type >= SIGNATURE_TYPE_GENERIC_CERTIFICATION && s.signature_type <= SIGNATURE_TYPE_POSITIVE_CERTIFICATION)
+            .filter(|s| s.issuer_key_id().map_or(true, |issuer| issuer == key_id))
+            .collect()
+    }
+}
+
+pub fn fingerprint_string(fingerprint: &[u8]) -> String {
+    fingerprint.chunks(2)
+        .map(|c| c.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0xc0 | tag, body.len() as u8];
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    fn key_body(created_unix_seconds: u32, seed: u8) -> Vec<u8> {
+        let mut body = vec![4];
+        let mut created = [0u8; 4];
+        BigEndian::write_u32(&mut created, created_unix_seconds);
+        body.extend_from_slice(&created);
+        body.push(22);
+        body.push(OID_ED25519.len() as u8);
+        body.extend_from_slice(OID_ED25519);
+        // A 0x40 prefixed point is 263 bits.
+        body.extend_from_slice(&[0x01, 0x07, 0x40]);
+        body.extend_from_slice(&[seed; 32]);
+        body
+    }
+
+    fn certification(signature_type: u8, issuer_key_id: &[u8]) -> Vec<u8> {
+        let mut body = vec![4, signature_type, 22, 8];
+        body.extend_from_slice(&[0, 6, 5, SUBPACKET_CREATION_TIME, 0x5a, 0xe5, 0xa8, 0x00]);
+        body.extend_from_slice(&[0, 10, 9, SUBPACKET_ISSUER]);
+        body.extend_from_slice(issuer_key_id);
+        // Hash prefix, then the `r` and `s` MPIs.
+        body.extend_from_slice(&[0xab, 0xcd, 0, 8, 0xff, 0, 8, 0xff]);
+        packet(TAG_SIGNATURE, &body)
+    }
+
+    fn transferable_key(seed: u8, user_id: &str) -> Vec<u8> {
+        let primary_key = PublicKeyPacket::parse(&key_body(1525000000, seed)).unwrap();
+        let mut packets = packet(TAG_PUBLIC_KEY, &primary_key.body);
+        // A direct key signature before the user IDs.
+        packets.extend(certification(0x1f, &primary_key.key_id()));
+        packets.extend(packet(TAG_USER_ID, user_id.as_bytes()));
+        packets.extend(certification(SIGNATURE_TYPE_POSITIVE_CERTIFICATION, &primary_key.key_id()));
+        packets.extend(certification(SIGNATURE_TYPE_GENERIC_CERTIFICATION, &[0x11; 8]));
+        packets.extend(packet(TAG_PUBLIC_SUBKEY, &key_body(1525000000, seed.wrapping_add(1))));
+        // Subkey binding signatures are not certifications of a user ID.
+        packets.extend(certification(0x18, &primary_key.key_id()));
+        packets
+    }
+
+    #[test]
+    fn parse_transferable_key() {
+        let key = PGPPublicKey::parse(&transferable_key(1, "Alice <alice@example.com>")).unwrap();
+        assert_eq!(key.algorithm(), PublicKeyAlgorithm::EdDSA);
+        assert_eq!(key.created_unix_seconds(), 1525000000);
+        assert_eq!(key.user_ids.len(), 1);
+        assert_eq!(key.user_ids[0].email(), Some("alice@example.com"));
+        assert_eq!(key.user_ids[0].signatures.len(), 2);
+        assert_eq!(key.subkeys.len(), 1);
+
+        let self_signatures = key.self_signatures(&key.user_ids[0]);
+        assert_eq!(self_signatures.len(), 1);
+        assert_eq!(self_signatures[0].signature_type, SIGNATURE_TYPE_POSITIVE_CERTIFICATION);
+        assert_eq!(self_signatures[0].created_unix_seconds(), Some(0x5ae5a800));
+        assert_eq!(key.key_id(), key.fingerprint()[12..].to_vec());
+    }
+
+    #[test]
+    fn parse_keyring() {
+        let mut keyring = transferable_key(1, "alice@example.com");
+        keyring.extend(transferable_key(2, "Bob <bob@example.com>"));
+        let keys = PGPPublicKey::parse_keyring(&keyring).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].user_ids[0].email(), Some("alice@example.com"));
+        assert_eq!(keys[1].user_ids[0].email(), Some("bob@example.com"));
+        assert!(keys[0].fingerprint() != keys[1].fingerprint());
+
+        assert!(PGPPublicKey::parse(&keyring).is_err());
+    }
+
+    #[test]
+    fn parse_malformed_keys() {
+        let primary_key = packet(TAG_PUBLIC_KEY, &key_body(1525000000, 1));
+        assert!(PGPPublicKey::parse(&[]).is_err());
+        assert!(PGPPublicKey::parse(&primary_key).is_err());
+        assert!(PGPPublicKey::parse(&packet(TAG_USER_ID, b"alice@example.com")).is_err());
+
+        let mut invalid_user_id = primary_key.clone();
+        invalid_user_id.extend(packet(TAG_USER_ID, &[0xff, 0xfe]));
+        assert!(PGPPublicKey::parse(&invalid_user_id).is_err());
+
+        let mut truncated = transferable_key(1, "alice@example.com");
+        truncated.pop();
+        assert!(PGPPublicKey::parse(&truncated).is_err());
+    }
+
+    #[test]
+    fn user_id_email() {
+        let email = |user_id: &str| PGPUserId { user_id: user_id.into(), signatures: vec![] }.email().map(String::from);
+        assert_eq!(email("Alice <alice@example.com>"), Some("alice@example.com".into()));
+        assert_eq!(email(" alice@example.com "), Some("alice@example.com".into()));
+        assert_eq!(email("Alice (work)"), None);
+        assert_eq!(email("Alice >alice@example.com<"), Some("Alice >alice@example.com<".into()));
+    }
+
+    #[test]
+    fn fingerprint_groups() {
+        let fingerprint = (0..20).collect::<Vec<u8>>();
+        assert_eq!(fingerprint_string(&fingerprint),
+                   "0001 0203 0405 0607 0809 0A0B 0C0D 0E0F 1011 1213");
+    }
+}
@@ -3,6 +3,7 @@ use b64data;
 #[cfg(feature = "db")]
 use db;
 use team::{TeamPointer, SignedReadToken};
+use secret::SecretBytes;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
@@ -71,12 +72,10 @@ pub struct WrappedKey {
     pub ciphertext: Vec<u8>, // box(..., JSON(PlaintextBody))
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum PlaintextBody {
-    LogEncryptionKey(
-        #[serde(with = "b64data")]
-        Vec<u8>),
+    LogEncryptionKey(SecretBytes),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -100,6 +99,30 @@ pub enum LogOperation {
     EncryptLog(EncryptedLog),
 }
 
+/// A member's statement of the latest block in their log chain, shared with admins directly so that
+/// a server withholding blocks can be detected. Heads are also published on the main chain with
+/// `Operation::PublishLogChainHead`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogChainHead {
+    #[serde(with = "b64data")]
+    pub team_public_key: Vec<u8>,
+    #[serde(with = "b64data")]
+    pub member_public_key: Vec<u8>,
+    #[serde(with = "b64data")]
+    pub last_block_hash: Vec<u8>,
+    pub unix_seconds: i64,
+}
+
+/// Prefixed to the JSON of a `LogChainHead` before signing, so that a head signature can never be
+/// mistaken for a signature over a block or any other message signed with the identity key.
+pub const LOG_CHAIN_HEAD_SIGNATURE_CONTEXT: &str = "krypton-log-chain-head-v1:";
+
+impl LogChainHead {
+    pub fn signed_data(head_json: &str) -> Vec<u8> {
+        [LOG_CHAIN_HEAD_SIGNATURE_CONTEXT.as_bytes(), head_json.as_bytes()].concat()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EncryptedLog {
     #[serde(with = "b64data")]
@@ -6,6 +6,26 @@ pub struct Log {
     pub session: Session,
     pub unix_seconds: u64,
     pub body: LogBody,
+    /// Redactions applied by the logging client under the team's `LogRedactionRules`.
+    #[serde(default, skip_serializing_if="Vec::is_empty")]
+    pub redactions: Vec<Redaction>,
+    /// The last main chain block the logging client had read when the log was written.
+    #[serde(default, with = "b64data::option", skip_serializing_if="Option::is_none")]
+    pub main_chain_block_hash: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Redaction {
+    CommitMessageDropped {
+        /// Git object id computed before the message was dropped, since it can no longer be recomputed.
+        object_id: Option<String>,
+    },
+    UsernameHashed,
+    SessionDataTruncated {
+        original_length: u64,
+    },
+    SudoCommandDropped,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -22,6 +42,10 @@ pub enum LogBody {
     Ssh(SSHSignature),
     GitTag(GitTagSignature),
     GitCommit(GitCommitSignature),
+    Sudo(SudoApproval),
+    PamLogin(PamLogin),
+    SshAgentForwarding(SSHAgentForwarding),
+    GitPush(GitPush),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -91,6 +115,67 @@ pub enum GitSignatureResult {
     Error(String),
 }
 
+/// Result of a request that the phone approves without producing a signature.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalResult {
+    Approved(E),
+    UserRejected(E),
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SudoApproval {
+    pub user: String,
+    pub target_user: String,
+    pub host: String,
+    pub command: Option<String>,
+    pub result: ApprovalResult,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PamLogin {
+    pub user: String,
+    pub host: String,
+    pub service: String,
+    pub result: ApprovalResult,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SSHAgentForwarding {
+    pub user: String,
+    pub host_authorization: Option<HostAuthorization>,
+    /// Host the forwarded agent is being used to reach, if known.
+    pub destination_host: Option<String>,
+    pub result: ApprovalResult,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GitPush {
+    pub remote: String,
+    pub pusher: String,
+    pub ref_updates: Vec<GitRefUpdate>,
+    pub result: GitPushResult,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GitRefUpdate {
+    pub name: String,
+    pub old_object: String,
+    pub new_object: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum GitPushResult {
+    UserRejected(E),
+    /// Signature over the push certificate.
+    Signature(
+        #[serde(with = "b64data")]
+        Vec<u8>),
+    Error(String),
+}
+
 pub trait SignatureResult {
     fn is_success(&self) -> bool;
 }
@@ -107,6 +192,18 @@ impl SignatureResult for LogBody {
             &LogBody::GitTag(ref tag_signature) => {
                 tag_signature.result.is_success()
             }
+            &LogBody::Sudo(ref sudo) => {
+                sudo.result.is_success()
+            }
+            &LogBody::PamLogin(ref pam_login) => {
+                pam_login.result.is_success()
+            }
+            &LogBody::SshAgentForwarding(ref agent_forwarding) => {
+                agent_forwarding.result.is_success()
+            }
+            &LogBody::GitPush(ref push) => {
+                push.result.is_success()
+            }
         };
     }
 }
@@ -128,3 +225,21 @@ impl SignatureResult for GitSignatureResult {
         };
     }
 }
+
+impl SignatureResult for ApprovalResult {
+    fn is_success(&self) -> bool {
+        return match self {
+            &ApprovalResult::Approved(_) => { true }
+            _ => { false }
+        };
+    }
+}
+
+impl SignatureResult for GitPushResult {
+    fn is_success(&self) -> bool {
+        return match self {
+            &GitPushResult::Signature(_) => { true }
+            _ => { false }
+        };
+    }
+}
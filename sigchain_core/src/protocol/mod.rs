@@ -34,7 +34,13 @@ pub const CLOCK_SKEW_THRESHOLD: i64 = 60*60;
 // NOTE: Do not use other features of semantic versioning (i.e. pre and build).
 // iOS code does not support it.
 lazy_static! {
-    pub static ref CURRENT_VERSION: Version = Version::new(1, 0, 0);
+    pub static ref CURRENT_VERSION: Version = Version::new(2, 0, 0);
+    // Messages that 1.x clients can parse are still written at this version, so that those clients
+    // keep reading teams with newer members. See `Body::protocol_version`.
+    pub static ref COMPATIBLE_VERSION: Version = Version::new(1, 1, 0);
+    // Blocks signed at this version or later must carry valid PGP public keys, canonical SSH public
+    // keys and encryption key proofs. Older blocks are replayed as they were accepted.
+    pub static ref KEY_VALIDATION_VERSION: Version = Version::new(1, 1, 0);
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -78,7 +84,13 @@ impl Header {
     pub fn new() -> Header {
         Header {
             utc_time: Utc::now().timestamp(),
-            protocol_version: CURRENT_VERSION.clone(),
+            protocol_version: COMPATIBLE_VERSION.clone(),
+        }
+    }
+    pub fn for_body(body: &Body) -> Header {
+        Header {
+            protocol_version: body.protocol_version().clone(),
+            ..Header::new()
         }
     }
 }
@@ -94,6 +106,18 @@ pub enum Body {
     ReadBillingInfo(billing::ReadBillingInfo),
 }
 
+impl Body {
+    /// The version to write on a message with this body. Operations that 1.x clients cannot parse
+    /// are written at the current major version, which those clients reject as incompatible
+    /// instead of failing to deserialize the block.
+    pub fn protocol_version(&self) -> &'static Version {
+        match self {
+            &Body::Main(MainChain::Append(ref block)) if block.operation.added_in_version_2() => &*CURRENT_VERSION,
+            _ => &*COMPATIBLE_VERSION,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Message {
     pub header: Header,
@@ -103,7 +127,7 @@ pub struct Message {
 impl Message {
     pub fn new(body: Body) -> Message {
         Message {
-            header: Header::new(),
+            header: Header::for_body(&body),
             body,
         }
     }
@@ -16,6 +16,106 @@ pub struct Identity {
     #[serde(with = "b64data")]
     pub pgp_public_key: Vec<u8>,
     pub email: String,
+    /// An XEdDSA signature by the `encryption_public_key` secret key over
+    /// `encryption_key_proof_data`, showing the member holds the key they claim.
+    #[serde(default, skip_serializing_if="Option::is_none", with = "b64data::option")]
+    pub encryption_key_proof: Option<Vec<u8>>,
+}
+
+impl Identity {
+    /// The statement `encryption_key_proof` signs, binding the encryption key to the identity's
+    /// signing key and to the team.
+    pub fn encryption_key_proof_data(&self, team_public_key: &[u8]) -> Vec<u8> {
+        let mut data = b"krypton-encryption-key-proof".to_vec();
+        extend_length_prefixed(&mut data, &[team_public_key, &self.public_key, &self.encryption_public_key]);
+        data
+    }
+
+    #[cfg(feature = "crypto")]
+    pub fn prove_encryption_key(&mut self, team_public_key: &[u8], box_secret_key: &[u8]) -> Result<()> {
+        use crypto::xeddsa;
+        let proof_data = self.encryption_key_proof_data(team_public_key);
+        self.encryption_key_proof = Some(xeddsa::sign(box_secret_key, &proof_data)?);
+        Ok(())
+    }
+
+    /// Whether `encryption_key_proof` is present and valid for `team_public_key`.
+    #[cfg(feature = "crypto")]
+    pub fn verify_encryption_key_proof(&self, team_public_key: &[u8]) -> Result<bool> {
+        use crypto::xeddsa;
+        match self.encryption_key_proof {
+            Some(ref proof) => xeddsa::verify(&self.encryption_public_key, &self.encryption_key_proof_data(team_public_key), proof),
+            None => Ok(false),
+        }
+    }
+}
+
+/// An additional device of a member, with its own keys. Blocks and logs signed by the device's
+/// `public_key` are attributed to the member that added it.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Device {
+    #[serde(with = "b64data")]
+    pub public_key: Vec<u8>,
+    #[serde(with = "b64data")]
+    pub encryption_public_key: Vec<u8>,
+    #[serde(with = "b64data")]
+    pub ssh_public_key: Vec<u8>,
+    pub name: String,
+    /// A signature by the device's `public_key` over `authorization_data`, showing the device
+    /// agreed to act for the member.
+    #[serde(with = "b64data")]
+    pub device_signature: Vec<u8>,
+    /// An XEdDSA signature by the `encryption_public_key` secret key over `authorization_data`.
+    #[serde(with = "b64data")]
+    pub encryption_key_proof: Vec<u8>,
+}
+
+impl Device {
+    /// The statement the device's keys sign, binding them to the member and to the team.
+    pub fn authorization_data(&self, team_public_key: &[u8], member_public_key: &[u8]) -> Vec<u8> {
+        let mut data = b"krypton-device-authorization".to_vec();
+        extend_length_prefixed(&mut data, &[
+            team_public_key,
+            member_public_key,
+            &self.public_key,
+            &self.encryption_public_key,
+            &self.ssh_public_key,
+            self.name.as_bytes(),
+        ]);
+        data
+    }
+
+    /// Sign the authorization for `member_public_key` with the device's secret keys.
+    #[cfg(feature = "crypto")]
+    pub fn authorize(&mut self, team_public_key: &[u8], member_public_key: &[u8], sign_key_pair: &::crypto::SignKeyPair, box_secret_key: &[u8]) -> Result<()> {
+        use crypto::{ed25519, xeddsa};
+        let data = self.authorization_data(team_public_key, member_public_key);
+        self.device_signature = ed25519::sign_detached(&data, &sign_key_pair.secret_key).0.to_vec();
+        self.encryption_key_proof = xeddsa::sign(box_secret_key, &data)?;
+        Ok(())
+    }
+
+    /// Whether both the device signature and the encryption key proof are valid for
+    /// `member_public_key` on `team_public_key`.
+    #[cfg(feature = "crypto")]
+    pub fn verify_authorization(&self, team_public_key: &[u8], member_public_key: &[u8]) -> Result<bool> {
+        use crypto::{ed25519, xeddsa};
+        let data = self.authorization_data(team_public_key, member_public_key);
+        let signature_valid = match (ed25519::Signature::from_slice(&self.device_signature), ed25519::PublicKey::from_slice(&self.public_key)) {
+            (Some(signature), Some(public_key)) => ed25519::verify_detached(&signature, &data, &public_key),
+            _ => false,
+        };
+        Ok(signature_valid && xeddsa::verify(&self.encryption_public_key, &data, &self.encryption_key_proof)?)
+    }
+}
+
+/// Append each of `parts` to `data` behind a 4 byte big endian length.
+fn extend_length_prefixed(data: &mut Vec<u8>, parts: &[&[u8]]) {
+    for part in parts {
+        let length = part.len();
+        data.extend_from_slice(&[(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8, length as u8]);
+        data.extend_from_slice(part);
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -137,6 +237,50 @@ pub enum Operation {
         Vec<u8>),
     AddLoggingEndpoint(LoggingEndpoint),
     RemoveLoggingEndpoint(LoggingEndpoint),
+    VerifyMember(MemberVerification),
+    UpdateKeys(KeyUpdate),
+    AddDevice(Device),
+    RemoveDevice(
+        #[serde(with = "b64data")]
+        Vec<u8>),
+    PublishLogChainHead(PublishedLogChainHead),
+}
+
+impl Operation {
+    /// Operations that 1.x clients cannot parse.
+    pub fn added_in_version_2(&self) -> bool {
+        use self::Operation::*;
+        match self {
+            &VerifyMember(_) | &UpdateKeys(_) | &AddDevice(_) | &RemoveDevice(_) | &PublishLogChainHead(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// New SSH and/or PGP public keys for the member signing the block. The signing and encryption
+/// keys cannot be changed. `None` keeps the current key.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeyUpdate {
+    #[serde(default, skip_serializing_if="Option::is_none", with = "b64data::option")]
+    pub ssh_public_key: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if="Option::is_none", with = "b64data::option")]
+    pub pgp_public_key: Option<Vec<u8>>,
+}
+
+/// The latest block of the signer's log chain. Published on the main chain, which every member
+/// reads and extends, so that admins can detect a server withholding the end of a log chain.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublishedLogChainHead {
+    #[serde(with = "b64data")]
+    pub last_block_hash: Vec<u8>,
+}
+
+/// An admin's attestation that they compared a member's safety number with the member in person.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MemberVerification {
+    #[serde(with = "b64data")]
+    pub public_key: Vec<u8>,
+    pub safety_number: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -146,7 +290,7 @@ pub enum Invitation {
     Indirect(IndirectInvitation),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct IndirectInvitationSecret {
     #[serde(with = "b64data")]
     pub initial_team_public_key: Vec<u8>,
@@ -157,6 +301,18 @@ pub struct IndirectInvitationSecret {
     pub restriction: IndirectInvitationRestriction,
 }
 
+/// Redacts `nonce_keypair_seed`, which lets anyone holding it join the team.
+impl ::std::fmt::Debug for IndirectInvitationSecret {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("IndirectInvitationSecret")
+            .field("initial_team_public_key", &self.initial_team_public_key)
+            .field("last_block_hash", &self.last_block_hash)
+            .field("nonce_keypair_seed", &"****")
+            .field("restriction", &self.restriction)
+            .finish()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DirectInvitation {
     #[serde(with = "b64data")]
@@ -184,14 +340,16 @@ pub enum IndirectInvitationRestriction {
 
 impl IndirectInvitation {
     #[cfg(feature = "crypto")]
-    pub fn create_link(nonce_public_key: Vec<u8>, invite: IndirectInvitationSecret) -> Result<(IndirectInvitation, String)> {
+    pub fn create_link(nonce_public_key: Vec<u8>, mut invite: IndirectInvitationSecret) -> Result<(IndirectInvitation, String)> {
         use base64;
         use serde_json;
         use crypto;
         use sha256;
 
-        let plaintext = serde_json::to_vec(&invite)?;
-        let ciphertext = crypto::secretbox::ephemeral_encrypt(plaintext)?;
+        // The serialized invite carries the nonce key pair seed, so it is wiped when dropped too.
+        let plaintext = ::secret::SecretBytes::new(serde_json::to_vec(&invite)?);
+        ::secret::zeroize(&mut invite.nonce_keypair_seed);
+        let ciphertext = crypto::secretbox::ephemeral_encrypt(&plaintext)?;
 
         let link = format!("krypton://join_team/{}",
                            base64::encode_config(&ciphertext.symmetric_key, base64::URL_SAFE));
@@ -210,6 +368,32 @@ impl IndirectInvitation {
 pub struct Policy {
     #[serde(skip_serializing_if="Option::is_none")]
     pub temporary_approval_seconds: Option<i64>,
+    /// Rules applied to audit logs before they are encrypted. `None` leaves the team's current
+    /// rules in place so that clients unaware of redaction do not reset them when setting a policy.
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub log_redaction: Option<LogRedactionRules>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LogRedactionRules {
+    /// Remove git commit and tag messages.
+    #[serde(default)]
+    pub drop_commit_messages: bool,
+    /// Replace SSH, sudo and login usernames with a hash keyed by a secret of the logging device.
+    #[serde(default)]
+    pub hash_usernames: bool,
+    /// Keep at most this many bytes of SSH session data.
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub truncate_session_data: Option<u64>,
+    /// Remove the command from sudo approvals.
+    #[serde(default)]
+    pub drop_sudo_commands: bool,
+}
+
+impl LogRedactionRules {
+    pub fn is_empty(&self) -> bool {
+        *self == LogRedactionRules::default()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
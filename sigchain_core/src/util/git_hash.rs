@@ -1,4 +1,4 @@
-use protocol::logs::{GitCommitSignature, GitSignatureResult};
+use protocol::logs::{GitCommitSignature, GitTagSignature, GitSignatureResult};
 use super::sha1;
 use super::pgp::*;
 
@@ -85,3 +85,66 @@ impl GitHash for GitCommitSignature {
         return Some(hash.digest().bytes());
     }
 }
+
+impl GitHash for GitTagSignature {
+    fn git_hash(&self) -> Option<[u8; 20]> {
+        let signature = match self.result {
+            GitSignatureResult::Signature(ref sig) => pgp_signature_ascii_armor_string(sig).ok()?,
+            _ => { return None; }
+        };
+
+        // the signature is appended to the tag message
+        let mut tag_data = self.signed_data();
+        tag_data.extend_from_slice(signature.as_bytes());
+        tag_data.extend_from_slice(b"\n");
+
+        let mut full_tag_data: Vec<u8> = Vec::new();
+        full_tag_data.extend_from_slice(format!("tag {}", tag_data.len()).as_bytes());
+        full_tag_data.push(0x00);
+        full_tag_data.extend(tag_data);
+
+        let mut hash = sha1::Sha1::new();
+        hash.update(&full_tag_data);
+        Some(hash.digest().bytes())
+    }
+}
+
+/// The bytes covered by the PGP signature of a git object: the object as git stores it, without
+/// the signature.
+pub trait GitSignedData {
+    fn signed_data(&self) -> Vec<u8>;
+}
+
+impl GitSignedData for GitCommitSignature {
+    fn signed_data(&self) -> Vec<u8> {
+        let mut commit_data: Vec<u8> = Vec::new();
+        commit_data.extend_from_slice(format!("tree {}\n", self.tree).as_bytes());
+        for parent in &self.parents {
+            commit_data.extend_from_slice(format!("parent {}\n", parent).as_bytes());
+        }
+        commit_data.extend_from_slice(format!("author {}\n", self.author).as_bytes());
+        commit_data.extend_from_slice(format!("committer {}\n", self.committer).as_bytes());
+        commit_data.extend(self.message.clone());
+        commit_data
+    }
+}
+
+impl GitSignedData for GitTagSignature {
+    fn signed_data(&self) -> Vec<u8> {
+        let mut tag_data: Vec<u8> = Vec::new();
+        tag_data.extend_from_slice(format!("object {}\n", self.object).as_bytes());
+        tag_data.extend_from_slice(format!("type {}\n", self.type_).as_bytes());
+        tag_data.extend_from_slice(format!("tag {}\n", self.tag).as_bytes());
+        tag_data.extend_from_slice(format!("tagger {}\n", self.tagger).as_bytes());
+        tag_data.extend(self.message.clone());
+        tag_data
+    }
+}
+
+/// The id git assigns to a raw object: SHA-1 over `<type> <length>\0<data>`, as a hex string.
+pub fn git_object_id(object_type: &str, data: &[u8]) -> String {
+    let mut hash = sha1::Sha1::new();
+    hash.update(format!("{} {}\0", object_type, data.len()).as_bytes());
+    hash.update(data);
+    hash.digest().to_string()
+}
@@ -0,0 +1,323 @@
+/// Render decrypted audit logs for a SIEM as JSON Lines, RFC 5424 syslog or CEF.
+///
+/// All three formats carry the same fields under stable names so that downstream parsers do not
+/// depend on the shape of `logs::LogBody`.
+
+use super::Result;
+use super::chrono::prelude::*;
+use protocol::logs::*;
+use git_hash::GitHash;
+use log_search::LogSearchFields;
+use ssh_session::SSHSessionData;
+use b64data;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    JsonLines,
+    Syslog,
+    Cef,
+}
+
+impl ExportFormat {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "jsonl" | "json_lines" => Ok(ExportFormat::JsonLines),
+            "syslog" | "rfc5424" => Ok(ExportFormat::Syslog),
+            "cef" => Ok(ExportFormat::Cef),
+            _ => bail!(format!("unknown export format {:?}, expected jsonl, syslog or cef", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportRecord {
+    /// Local id of the log, increasing in the order logs were received.
+    pub log_id: i64,
+    pub unix_seconds: i64,
+    pub member_email: String,
+    #[serde(with = "b64data")]
+    pub member_public_key: Vec<u8>,
+    pub device_name: String,
+    #[serde(with = "b64data")]
+    pub device_id: Vec<u8>,
+    pub event_type: String,
+    pub host: Option<String>,
+    pub result: String,
+    pub success: bool,
+    pub description: String,
+    pub git_object_ids: Vec<String>,
+    pub redacted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_session: Option<SSHSessionData>,
+}
+
+const APP_NAME: &str = "krypton";
+const VENDOR: &str = "krypt.co";
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// RFC 5424 structured data id. 32473 is the enterprise number reserved for documentation (RFC 5612).
+const SD_ID: &str = "sigchain@32473";
+/// `event_type` of records for logs that could not be parsed.
+pub const MALFORMED_EVENT_TYPE: &str = "malformed_log";
+
+impl ExportRecord {
+    pub fn new(log_id: i64, member_email: &str, member_public_key: &[u8], log: &Log) -> ExportRecord {
+        let fields = LogSearchFields::from_log(log);
+        ExportRecord {
+            log_id,
+            unix_seconds: log.unix_seconds as i64,
+            member_email: member_email.into(),
+            member_public_key: member_public_key.into(),
+            device_name: log.session.device_name.clone(),
+            device_id: log.session.workstation_public_key_double_hash.clone(),
+            event_type: fields.log_type,
+            host: fields.host,
+            result: fields.result,
+            success: log.body.is_success(),
+            description: log.body.log_description(),
+            git_object_ids: git_object_ids(log),
+            redacted: !log.redactions.is_empty(),
+            ssh_session: SSHSessionData::from_log(log),
+        }
+    }
+
+    /// A record for a stored log that could not be parsed, so that collectors see the gap instead
+    /// of the log being skipped silently.
+    pub fn malformed(log_id: i64, unix_seconds: i64, member_email: &str, member_public_key: &[u8], error: &str) -> ExportRecord {
+        ExportRecord {
+            log_id,
+            unix_seconds,
+            member_email: member_email.into(),
+            member_public_key: member_public_key.into(),
+            device_name: String::new(),
+            device_id: vec![],
+            event_type: MALFORMED_EVENT_TYPE.into(),
+            host: None,
+            result: "error".into(),
+            success: false,
+            description: format!("malformed log: {}", error),
+            git_object_ids: vec![],
+            redacted: false,
+            ssh_session: None,
+        }
+    }
+
+    pub fn format(&self, format: ExportFormat) -> Result<String> {
+        Ok(match format {
+            ExportFormat::JsonLines => ::serde_json::to_string(self)?,
+            ExportFormat::Syslog => self.to_syslog(),
+            ExportFormat::Cef => self.to_cef(),
+        })
+    }
+
+    /// `None` if the log's time, set by the member's device, is out of range.
+    fn timestamp(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_opt(self.unix_seconds, 0).single()
+    }
+
+    /// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID [SD] MSG` with facility authpriv.
+    pub fn to_syslog(&self) -> String {
+        const AUTHPRIV: u8 = 10;
+        let severity = if self.success { 5 } else { 4 };
+
+        let mut params = vec![
+            ("log_id", self.log_id.to_string()),
+            ("member_email", self.member_email.clone()),
+            ("device_name", self.device_name.clone()),
+            ("result", self.result.clone()),
+        ];
+        if let Some(ref host) = self.host {
+            params.push(("host", host.clone()));
+        }
+        for object_id in &self.git_object_ids {
+            params.push(("git_object_id", object_id.clone()));
+        }
+        if self.redacted {
+            params.push(("redacted", "true".into()));
+        }
+        if let Some(ref session) = self.ssh_session {
+            params.push(("ssh_session_id", session.session_id_hex()));
+            params.push(("ssh_user", session.user.clone()));
+            params.push(("ssh_service", session.service.clone()));
+            params.push(("ssh_algorithm", session.algorithm.clone()));
+        }
+        let structured_data = params.iter()
+            .map(|&(k, ref v)| format!(" {}=\"{}\"", k, escape_sd_param(v)))
+            .collect::<String>();
+
+        // "-" is the NILVALUE for an unknown timestamp.
+        let timestamp = self.timestamp()
+            .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+            .unwrap_or_else(|| "-".into());
+
+        format!("<{}>1 {} - {} - {} [{}{}] {}",
+                AUTHPRIV * 8 + severity,
+                timestamp,
+                APP_NAME,
+                self.event_type,
+                SD_ID,
+                structured_data,
+                self.description.replace('\n', " "),
+        )
+    }
+
+    /// `CEF:Version|Device Vendor|Device Product|Device Version|Signature ID|Name|Severity|Extension`
+    pub fn to_cef(&self) -> String {
+        let severity = if self.success { 3 } else { 6 };
+
+        let mut extension = vec![];
+        if let Some(rt) = self.unix_seconds.checked_mul(1000) {
+            extension.push(("rt", rt.to_string()));
+        }
+        extension.extend(vec![
+            ("externalId", self.log_id.to_string()),
+            ("suser", self.member_email.clone()),
+            ("shost", self.device_name.clone()),
+            ("outcome", self.result.clone()),
+            ("msg", self.description.clone()),
+            ("cs1Label", "memberPublicKey".into()),
+            ("cs1", ::base64::encode(&self.member_public_key)),
+        ]);
+        if let Some(ref host) = self.host {
+            extension.push(("dhost", host.clone()));
+        }
+        if !self.git_object_ids.is_empty() {
+            extension.push(("cs2Label", "gitObjectIds".into()));
+            extension.push(("cs2", self.git_object_ids.join(",")));
+        }
+        if self.redacted {
+            extension.push(("cs3Label", "redacted".into()));
+            extension.push(("cs3", "true".into()));
+        }
+        if let Some(ref session) = self.ssh_session {
+            extension.push(("duser", session.user.clone()));
+            extension.push(("app", session.service.clone()));
+            extension.push(("cs4Label", "sshSessionId".into()));
+            extension.push(("cs4", session.session_id_hex()));
+            extension.push(("cs5Label", "sshAlgorithm".into()));
+            extension.push(("cs5", session.algorithm.clone()));
+        }
+        let extension = extension.iter()
+            .map(|&(k, ref v)| format!("{}={}", k, escape_cef_extension(v)))
+            .collect::<Vec<_>>().join(" ");
+
+        format!("CEF:0|{}|{}|{}|{}|{}|{}|{}",
+                escape_cef_header(VENDOR),
+                escape_cef_header(APP_NAME),
+                escape_cef_header(VERSION),
+                escape_cef_header(&self.event_type),
+                escape_cef_header(&format!("{} {}", self.event_type, self.result)),
+                severity,
+                extension,
+        )
+    }
+}
+
+fn git_object_ids(log: &Log) -> Vec<String> {
+    match log.body {
+        LogBody::GitCommit(ref commit) => {
+            let redacted_object_id = log.redactions.iter().filter_map(|r| match r {
+                &Redaction::CommitMessageDropped { ref object_id } => object_id.clone(),
+                _ => None,
+            }).next();
+            redacted_object_id.or(commit.git_hash_hex_string()).into_iter().collect()
+        }
+        LogBody::GitTag(ref tag) => vec![tag.object.clone()],
+        LogBody::GitPush(ref push) => push.ref_updates.iter().map(|r| r.new_object.clone()).collect(),
+        _ => vec![],
+    }
+}
+
+fn escape_sd_param(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]")
+}
+
+fn escape_cef_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+fn escape_cef_extension(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('=', "\\=").replace('\r', "\\r").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> ExportRecord {
+        ExportRecord {
+            log_id: 7,
+            unix_seconds: 1525000000,
+            member_email: "alice@example.com".into(),
+            member_public_key: vec![1, 2, 3],
+            device_name: "alice's \"laptop\" [work]".into(),
+            device_id: vec![4, 5, 6],
+            event_type: "sudo".into(),
+            host: Some("db1".into()),
+            result: "approved".into(),
+            success: true,
+            description: "sudo to root on db1".into(),
+            git_object_ids: vec![],
+            redacted: false,
+            ssh_session: None,
+        }
+    }
+
+    #[test]
+    fn escape_syslog_params() {
+        assert_eq!(escape_sd_param(r#"a"b]c\d"#), r#"a\"b\]c\\d"#);
+        assert_eq!(escape_sd_param("plain"), "plain");
+    }
+
+    #[test]
+    fn escape_cef_fields() {
+        assert_eq!(escape_cef_header(r"a|b\c=d"), r"a\|b\\c=d");
+        assert_eq!(escape_cef_extension("a=b\\c|d\r\ne"), r"a\=b\\c|d\r\ne");
+    }
+
+    #[test]
+    fn syslog_record() {
+        let line = record().to_syslog();
+        assert!(line.starts_with("<85>1 2018-04-29T11:06:40Z - krypton - sudo [sigchain@32473 log_id=\"7\""));
+        assert!(line.contains(r#" device_name="alice's \"laptop\" [work\]""#));
+        assert!(line.ends_with("] sudo to root on db1"));
+
+        let line = ExportRecord {
+            unix_seconds: i64::max_value(),
+            success: false,
+            description: "first\nsecond".into(),
+            ..record()
+        }.to_syslog();
+        assert!(line.starts_with("<84>1 - - krypton - sudo ["));
+        assert!(line.ends_with("] first second"));
+    }
+
+    #[test]
+    fn cef_record() {
+        let line = ExportRecord {
+            event_type: "git|push".into(),
+            description: "a=b\nc".into(),
+            ..record()
+        }.to_cef();
+        let prefix = format!("CEF:0|krypt.co|krypton|{}|git\\|push|git\\|push approved|3|rt=1525000000000 externalId=7 ", VERSION);
+        assert!(line.starts_with(&prefix), "{}", line);
+        assert!(line.contains(r" msg=a\=b\nc "));
+        assert!(line.contains(" cs1=AQID "));
+        assert!(line.ends_with(" dhost=db1"));
+
+        // Times whose milliseconds overflow are left out.
+        let line = ExportRecord { unix_seconds: i64::max_value(), ..record() }.to_cef();
+        assert!(!line.contains("rt="));
+    }
+
+    #[test]
+    fn malformed_record() {
+        let record = ExportRecord::malformed(9, 1525000000, "alice@example.com", &[1, 2, 3], "expected value");
+        let json: ::serde_json::Value = ::serde_json::from_str(&record.format(ExportFormat::JsonLines).unwrap()).unwrap();
+        assert_eq!(json["log_id"], 9);
+        assert_eq!(json["event_type"], MALFORMED_EVENT_TYPE);
+        assert_eq!(json["success"], false);
+        assert_eq!(json["description"], "malformed log: expected value");
+        assert!(record.to_syslog().starts_with("<84>1 2018-04-29T11:06:40Z - krypton - malformed_log ["));
+    }
+}
@@ -0,0 +1,347 @@
+/// Query language and indexed fields for searching decrypted audit logs.
+///
+/// A query is a list of space separated terms, all of which must match:
+///
+///   member:alice host:db1 result:rejected type:git since:7d "drop table"
+///
+/// `member`, `host`, and free text match substrings case-insensitively, `type` matches a prefix of
+/// the log type (so `type:git` matches commits, tags and pushes), and `since` takes a number
+/// followed by `s`, `m`, `h`, `d` or `w`. Repeating a key matches any of its values.
+///
+/// The indexed fields other than the time and type are encrypted, so `member`, `host`, `result`
+/// and free text terms decrypt every entry left by the other terms. To keep those searches from
+/// scanning the whole history, they only consider the newest `MAX_DECRYPTED_SEARCH_ENTRIES`
+/// entries; narrow them with `since` or `type` to reach older logs.
+
+use super::Result;
+use protocol::logs::*;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LogQuery {
+    pub members: Vec<String>,
+    pub hosts: Vec<String>,
+    pub results: Vec<String>,
+    pub types: Vec<String>,
+    pub since_unix_seconds: Option<i64>,
+    pub text: Vec<String>,
+}
+
+impl LogQuery {
+    pub fn parse(query: &str, now_unix_seconds: i64) -> Result<LogQuery> {
+        let mut parsed = LogQuery::default();
+        for term in tokenize(query)? {
+            let (key, value) = match term {
+                Term::Text(text) => {
+                    parsed.text.push(text.to_lowercase());
+                    continue;
+                }
+                Term::Field(key, value) => (key, value),
+            };
+            if value.is_empty() {
+                bail!(format!("missing value for {}:", key));
+            }
+            match key.as_str() {
+                "member" => parsed.members.push(value.to_lowercase()),
+                "host" => parsed.hosts.push(value.to_lowercase()),
+                "result" => {
+                    let value = value.to_lowercase();
+                    if !RESULTS.contains(&value.as_str()) {
+                        bail!(format!("unknown result {:?}, expected one of {}", value, RESULTS.join(", ")));
+                    }
+                    parsed.results.push(value);
+                }
+                "type" => {
+                    let value = value.to_lowercase();
+                    if !TYPES.iter().any(|t| t.starts_with(&value)) {
+                        bail!(format!("unknown type {:?}, expected one of {}", value, TYPES.join(", ")));
+                    }
+                    parsed.types.push(value);
+                }
+                "since" => {
+                    let since = match now_unix_seconds.checked_sub(parse_duration_seconds(&value)?) {
+                        Some(since) => since,
+                        None => bail!(format!("duration {:?} is too long", value)),
+                    };
+                    parsed.since_unix_seconds = Some(parsed.since_unix_seconds.map_or(since, |s| s.max(since)));
+                }
+                _ => bail!(format!("unknown search key {:?}", key)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == LogQuery::default()
+    }
+
+    /// Match a single log in memory, with the same semantics as the indexed search.
+    pub fn matches(&self, fields: &LogSearchFields, member_email: &str, unix_seconds: i64) -> bool {
+        fn any_or_empty<F: Fn(&String) -> bool>(values: &[String], f: F) -> bool {
+            values.is_empty() || values.iter().any(f)
+        }
+        self.since_unix_seconds.map_or(true, |since| unix_seconds >= since)
+            && any_or_empty(&self.results, |r| *r == fields.result)
+            && any_or_empty(&self.types, |t| fields.log_type.starts_with(t.as_str()))
+            && self.matches_substrings(&member_email.to_lowercase(), fields.host.as_ref().map(String::as_str), &fields.text)
+    }
+
+    /// Match the `member`, `host` and free text terms, which are substring matches done in memory
+    /// rather than with `LIKE`, so that `%` and `_` in a term are matched literally.
+    pub fn matches_substrings(&self, member_email: &str, host: Option<&str>, text: &str) -> bool {
+        (self.members.is_empty() || self.members.iter().any(|m| member_email.contains(m.as_str())))
+            && (self.hosts.is_empty() || host.map_or(false, |host| self.hosts.iter().any(|h| host.contains(h.as_str()))))
+            && self.text.iter().all(|t| text.contains(t.as_str()))
+    }
+
+    /// The log types matched by the `type` terms.
+    pub fn log_types(&self) -> Vec<&'static str> {
+        TYPES.iter().cloned()
+            .filter(|log_type| self.types.iter().any(|t| log_type.starts_with(t.as_str())))
+            .collect()
+    }
+}
+
+/// The most index entries decrypted by a single search.
+pub const MAX_DECRYPTED_SEARCH_ENTRIES: i64 = 20_000;
+
+pub const RESULTS: &[&str] = &["approved", "rejected", "host_mismatch", "error"];
+pub const TYPES: &[&str] = &["ssh", "ssh_agent_forwarding", "git_commit", "git_tag", "git_push", "sudo", "login"];
+
+/// Fields of a log that are stored in the search index.
+#[derive(Debug, Clone)]
+pub struct LogSearchFields {
+    pub log_type: String,
+    pub host: Option<String>,
+    pub result: String,
+    /// Lowercased text that free text terms are matched against.
+    pub text: String,
+}
+
+impl LogSearchFields {
+    pub fn from_log(log: &Log) -> LogSearchFields {
+        let (log_type, host, result, mut text) = match log.body {
+            LogBody::Ssh(ref ssh) => {
+                let result = match ssh.result {
+                    SSHSignatureResult::Signature(_) => "approved",
+                    SSHSignatureResult::UserRejected(_) => "rejected",
+                    SSHSignatureResult::HostMismatch(_) => "host_mismatch",
+                    SSHSignatureResult::Error(_) => "error",
+                };
+                let host = ssh.host_authorization.as_ref().map(|h| h.host.clone());
+                ("ssh", host, result, vec![ssh.user.clone()])
+            }
+            LogBody::GitCommit(ref commit) => {
+                let mut text = vec![commit.author.clone(), commit.committer.clone()];
+                text.extend(commit.message_string.clone());
+                ("git_commit", None, git_result(&commit.result), text)
+            }
+            LogBody::GitTag(ref tag) => {
+                let mut text = vec![tag.tag.clone(), tag.tagger.clone()];
+                text.extend(tag.message_string.clone());
+                ("git_tag", None, git_result(&tag.result), text)
+            }
+            LogBody::GitPush(ref push) => {
+                let result = match push.result {
+                    GitPushResult::Signature(_) => "approved",
+                    GitPushResult::UserRejected(_) => "rejected",
+                    GitPushResult::Error(_) => "error",
+                };
+                let mut text = vec![push.pusher.clone()];
+                text.extend(push.ref_updates.iter().map(|r| r.name.clone()));
+                ("git_push", Some(push.remote.clone()), result, text)
+            }
+            LogBody::Sudo(ref sudo) => {
+                let mut text = vec![sudo.user.clone(), sudo.target_user.clone()];
+                text.extend(sudo.command.clone());
+                ("sudo", Some(sudo.host.clone()), approval_result(&sudo.result), text)
+            }
+            LogBody::PamLogin(ref pam_login) => {
+                let text = vec![pam_login.user.clone(), pam_login.service.clone()];
+                ("login", Some(pam_login.host.clone()), approval_result(&pam_login.result), text)
+            }
+            LogBody::SshAgentForwarding(ref agent_forwarding) => {
+                let mut text = vec![agent_forwarding.user.clone()];
+                text.extend(agent_forwarding.destination_host.clone());
+                let host = agent_forwarding.host_authorization.as_ref().map(|h| h.host.clone());
+                ("ssh_agent_forwarding", host, approval_result(&agent_forwarding.result), text)
+            }
+        };
+        text.push(log.session.device_name.clone());
+        text.extend(host.clone());
+
+        LogSearchFields {
+            log_type: log_type.into(),
+            host: host.map(|h| h.to_lowercase()),
+            result: result.into(),
+            text: text.join("\n").to_lowercase(),
+        }
+    }
+}
+
+fn git_result(result: &GitSignatureResult) -> &'static str {
+    match result {
+        &GitSignatureResult::Signature(_) => "approved",
+        &GitSignatureResult::UserRejected(_) => "rejected",
+        &GitSignatureResult::Error(_) => "error",
+    }
+}
+
+fn approval_result(result: &ApprovalResult) -> &'static str {
+    match result {
+        &ApprovalResult::Approved(_) => "approved",
+        &ApprovalResult::UserRejected(_) => "rejected",
+        &ApprovalResult::Error(_) => "error",
+    }
+}
+
+fn parse_duration_seconds(duration: &str) -> Result<i64> {
+    let unit_index = duration.char_indices().last().map_or(0, |(i, _)| i);
+    let (count, unit) = duration.split_at(unit_index);
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => bail!(format!("invalid duration {:?}, expected e.g. 30m, 12h or 7d", duration)),
+    };
+    match count.parse::<i64>() {
+        Ok(count) if count >= 0 => match count.checked_mul(unit_seconds) {
+            Some(seconds) => Ok(seconds),
+            None => bail!(format!("duration {:?} is too long", duration)),
+        },
+        _ => bail!(format!("invalid duration {:?}, expected e.g. 30m, 12h or 7d", duration)),
+    }
+}
+
+enum Term {
+    Text(String),
+    Field(String, String),
+}
+
+fn tokenize(query: &str) -> Result<Vec<Term>> {
+    let mut terms = vec![];
+    let mut chars = query.chars().peekable();
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Ok(terms);
+        }
+
+        let mut key = None;
+        let mut value = String::new();
+        let mut quoted = false;
+        while let Some(&c) = chars.peek() {
+            if c == '"' {
+                chars.next();
+                quoted = !quoted;
+            } else if c.is_whitespace() && !quoted {
+                break;
+            } else if c == ':' && !quoted && key.is_none() && !value.is_empty() {
+                chars.next();
+                key = Some(value.to_lowercase());
+                value = String::new();
+            } else {
+                chars.next();
+                value.push(c);
+            }
+        }
+        if quoted {
+            bail!("unterminated quote in search query");
+        }
+
+        terms.push(match key {
+            Some(key) => Term::Field(key, value),
+            None => Term::Text(value),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(query: &str) -> Vec<(String, String)> {
+        tokenize(query).unwrap().into_iter().map(|term| match term {
+            Term::Text(text) => (String::new(), text),
+            Term::Field(key, value) => (key, value),
+        }).collect()
+    }
+
+    fn field(key: &str, value: &str) -> (String, String) {
+        (key.into(), value.into())
+    }
+
+    #[test]
+    fn tokenize_fields_and_text() {
+        assert_eq!(fields("  member:alice   deploy\tHOST:db1 "), vec![
+            field("member", "alice"),
+            field("", "deploy"),
+            field("host", "db1"),
+        ]);
+        assert!(fields("").is_empty());
+    }
+
+    #[test]
+    fn tokenize_quotes() {
+        assert_eq!(fields(r#""drop table" text:"a b:c" "host:db1""#), vec![
+            field("", "drop table"),
+            field("text", "a b:c"),
+            field("", "host:db1"),
+        ]);
+        assert!(tokenize(r#"member:"alice"#).is_err());
+    }
+
+    #[test]
+    fn tokenize_colons() {
+        //  Only the first colon after a non-empty key splits a field
+        assert_eq!(fields(":leading host:db1:22"), vec![
+            field("", ":leading"),
+            field("host", "db1:22"),
+        ]);
+        assert_eq!(fields("member:"), vec![field("member", "")]);
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration_seconds("0s").unwrap(), 0);
+        assert_eq!(parse_duration_seconds("45s").unwrap(), 45);
+        assert_eq!(parse_duration_seconds("30m").unwrap(), 30 * 60);
+        assert_eq!(parse_duration_seconds("12h").unwrap(), 12 * 60 * 60);
+        assert_eq!(parse_duration_seconds("7d").unwrap(), 7 * 24 * 60 * 60);
+        assert_eq!(parse_duration_seconds("2w").unwrap(), 2 * 7 * 24 * 60 * 60);
+    }
+
+    #[test]
+    fn reject_invalid_durations() {
+        for duration in &["", "d", "7", "7y", "-1d", "1.5h", "7dd", "7 d", "9223372036854775807w", "1é"] {
+            assert!(parse_duration_seconds(duration).is_err(), "{:?}", duration);
+        }
+    }
+
+    #[test]
+    fn parse_query() {
+        let now = 1525000000;
+        let query = LogQuery::parse(r#"member:Alice host:db1 result:Rejected type:git since:1d since:2h "DROP table""#, now).unwrap();
+        assert_eq!(query, LogQuery {
+            members: vec!["alice".into()],
+            hosts: vec!["db1".into()],
+            results: vec!["rejected".into()],
+            types: vec!["git".into()],
+            since_unix_seconds: Some(now - 2 * 60 * 60),
+            text: vec!["drop table".into()],
+        });
+        assert_eq!(query.log_types(), vec!["git_commit", "git_tag", "git_push"]);
+        assert!(LogQuery::parse("", now).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reject_invalid_queries() {
+        let now = 1525000000;
+        for query in &["member:", "result:maybe", "type:http", "since:1y", "since:9223372036854775807w", "owner:alice"] {
+            assert!(LogQuery::parse(query, now).is_err(), "{:?}", query);
+        }
+    }
+}
@@ -11,6 +11,11 @@ pub use self::timing::*;
 
 pub mod time_util;
 pub mod git_hash;
+pub mod log_search;
+pub mod log_export;
+pub mod ssh_session;
+pub mod ssh_public_key;
+pub mod secret;
 
 extern crate chrono;
 extern crate time;
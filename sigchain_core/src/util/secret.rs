@@ -0,0 +1,77 @@
+use std::fmt;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use base64;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+/// Overwrite `bytes` with zeros in a way the optimizer cannot remove.
+pub fn zeroize(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Secret key material that is zeroized when dropped and redacted from `Debug` output.
+///
+/// `SecretBytes` is intentionally not `Clone`: a copy made with `to_vec` is an ordinary `Vec<u8>`
+/// that the caller is responsible for wiping.
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> SecretBytes {
+        SecretBytes(bytes)
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> SecretBytes {
+        SecretBytes(bytes)
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for SecretBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretBytes(****)")
+    }
+}
+
+/// Serialized as a base64 string, like `b64data`, wiping the intermediate encoding.
+impl Serialize for SecretBytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let encoded = SecretBytes(base64::encode(&self.0).into_bytes());
+        serializer.serialize_str(::std::str::from_utf8(&encoded).map_err(::serde::ser::Error::custom)?)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretBytes {
+    fn deserialize<D>(deserializer: D) -> Result<SecretBytes, D::Error>
+        where D: Deserializer<'de>
+    {
+        let encoded = SecretBytes(String::deserialize(deserializer)?.into_bytes());
+        Ok(SecretBytes(base64::decode(&encoded[..]).map_err(::serde::de::Error::custom)?))
+    }
+}
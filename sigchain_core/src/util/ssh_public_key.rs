@@ -0,0 +1,140 @@
+/// Parse SSH wire format public keys (RFC 4253 section 6.6) as found in `Identity.ssh_public_key`
+/// and `SSHHostKey.public_key`.
+
+use super::Result;
+use base64;
+
+pub const KEY_TYPE_ED25519: &str = "ssh-ed25519";
+pub const KEY_TYPE_RSA: &str = "ssh-rsa";
+pub const KEY_TYPE_ECDSA_NISTP256: &str = "ecdsa-sha2-nistp256";
+pub const KEY_TYPE_ECDSA_NISTP384: &str = "ecdsa-sha2-nistp384";
+pub const KEY_TYPE_ECDSA_NISTP521: &str = "ecdsa-sha2-nistp521";
+
+/// RSA keys shorter than this are rejected.
+const MIN_RSA_MODULUS_BITS: usize = 2048;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SSHPublicKey {
+    pub key_type: String,
+    /// The key re-encoded without trailing data or superfluous leading zeros.
+    pub canonical_wire: Vec<u8>,
+}
+
+#[cfg(feature = "ssh-wire")]
+#[derive(Deserialize)]
+struct Ed25519PublicKey {
+    _key_type: String,
+    key: Vec<u8>,
+}
+
+#[cfg(feature = "ssh-wire")]
+#[derive(Deserialize)]
+struct RSAPublicKey {
+    _key_type: String,
+    e: Vec<u8>,
+    n: Vec<u8>,
+}
+
+#[cfg(feature = "ssh-wire")]
+#[derive(Deserialize)]
+struct ECDSAPublicKey {
+    _key_type: String,
+    curve: String,
+    point: Vec<u8>,
+}
+
+impl SSHPublicKey {
+    /// Errors if the key type is unknown or the key is malformed.
+    #[cfg(feature = "ssh-wire")]
+    pub fn parse(public_key_wire: &[u8]) -> Result<SSHPublicKey> {
+        use sshwire::ssh::PublicKeyHeader;
+        use sshwire::serde_de::from_slice;
+
+        let key_type = from_slice::<PublicKeyHeader>(public_key_wire)?._type;
+        let mut canonical_wire = vec![];
+        write_string(&mut canonical_wire, key_type.as_bytes());
+        match key_type.as_str() {
+            KEY_TYPE_ED25519 => {
+                let key = from_slice::<Ed25519PublicKey>(public_key_wire)?;
+                if key.key.len() != 32 {
+                    bail!("ed25519 public key must be 32 bytes");
+                }
+                write_string(&mut canonical_wire, &key.key);
+            }
+            KEY_TYPE_RSA => {
+                let key = from_slice::<RSAPublicKey>(public_key_wire)?;
+                let n = strip_leading_zeros(&key.n);
+                let e = strip_leading_zeros(&key.e);
+                if e.is_empty() || e[e.len() - 1] & 1 == 0 {
+                    bail!("invalid rsa public exponent");
+                }
+                let n_bits = n.first().map_or(0, |b| n.len() * 8 - b.leading_zeros() as usize);
+                if n_bits < MIN_RSA_MODULUS_BITS {
+                    bail!(format!("rsa modulus of {} bits is too short", n_bits));
+                }
+                write_mpint(&mut canonical_wire, e);
+                write_mpint(&mut canonical_wire, n);
+            }
+            KEY_TYPE_ECDSA_NISTP256 | KEY_TYPE_ECDSA_NISTP384 | KEY_TYPE_ECDSA_NISTP521 => {
+                let key = from_slice::<ECDSAPublicKey>(public_key_wire)?;
+                let (curve, point_length) = match key_type.as_str() {
+                    KEY_TYPE_ECDSA_NISTP256 => ("nistp256", 65),
+                    KEY_TYPE_ECDSA_NISTP384 => ("nistp384", 97),
+                    _ => ("nistp521", 133),
+                };
+                if key.curve != curve {
+                    bail!(format!("{} key names curve {}", key_type, key.curve));
+                }
+                if key.point.len() != point_length || key.point[0] != 0x04 {
+                    bail!("ecdsa public key must be an uncompressed curve point");
+                }
+                write_string(&mut canonical_wire, curve.as_bytes());
+                write_string(&mut canonical_wire, &key.point);
+            }
+            _ => bail!(format!("unsupported ssh key type {}", key_type)),
+        }
+        Ok(SSHPublicKey { key_type, canonical_wire })
+    }
+
+    #[cfg(not(feature = "ssh-wire"))]
+    pub fn parse(_: &[u8]) -> Result<SSHPublicKey> {
+        bail!("ssh public key decoding is not supported in this build")
+    }
+
+    /// The `authorized_keys` form: key type and base64 blob.
+    pub fn wire_string(&self) -> String {
+        format!("{} {}", self.key_type, base64::encode(&self.canonical_wire))
+    }
+
+    /// `SHA256:` and the unpadded base64 digest of the key, as printed by `ssh-keygen -l`.
+    #[cfg(feature = "crypto")]
+    pub fn fingerprint(&self) -> String {
+        use sha256;
+        let digest = sha256::hash(&self.canonical_wire);
+        format!("SHA256:{}", base64::encode(&digest.0).trim_right_matches('='))
+    }
+}
+
+#[cfg(feature = "ssh-wire")]
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    &bytes[bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len())..]
+}
+
+#[cfg(feature = "ssh-wire")]
+fn write_string(out: &mut Vec<u8>, string: &[u8]) {
+    let length = string.len();
+    out.extend_from_slice(&[(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8, length as u8]);
+    out.extend_from_slice(string);
+}
+
+#[cfg(feature = "ssh-wire")]
+/// Write a non-negative mpint, adding a leading zero byte if the high bit is set.
+fn write_mpint(out: &mut Vec<u8>, magnitude: &[u8]) {
+    if magnitude.first().map_or(false, |b| b & 0x80 != 0) {
+        let mut padded = vec![0];
+        padded.extend_from_slice(magnitude);
+        write_string(out, &padded);
+    } else {
+        write_string(out, magnitude);
+    }
+}
@@ -0,0 +1,123 @@
+/// Decode `SSHSignature.session_data`, the SSH userauth request (RFC 4252 section 7) that the
+/// member's key signed.
+
+use super::Result;
+use protocol::logs::*;
+use b64data;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SSHSessionData {
+    /// Exchange hash of the SSH connection, unique per session.
+    #[serde(with = "b64data")]
+    pub session_id: Vec<u8>,
+    /// Username requested on the host.
+    pub user: String,
+    pub service: String,
+    /// Public key algorithm named in the request, e.g. `ssh-ed25519` or `rsa-sha2-256`.
+    pub algorithm: String,
+    #[serde(with = "b64data")]
+    pub public_key: Vec<u8>,
+}
+
+#[cfg(feature = "ssh-wire")]
+#[derive(Deserialize)]
+struct UserAuthRequest {
+    session_id: Vec<u8>,
+    message_type: u8,
+    user: String,
+    service: String,
+    method: String,
+    has_signature: bool,
+    algorithm: String,
+    public_key: Vec<u8>,
+}
+
+impl SSHSessionData {
+    #[cfg(feature = "ssh-wire")]
+    pub fn parse(session_data: &[u8]) -> Result<SSHSessionData> {
+        const SSH_MSG_USERAUTH_REQUEST: u8 = 50;
+
+        let request = ::sshwire::serde_de::from_slice::<UserAuthRequest>(session_data)?;
+        if request.message_type != SSH_MSG_USERAUTH_REQUEST {
+            bail!(format!("unexpected ssh message type {}", request.message_type));
+        }
+        if request.method != "publickey" || !request.has_signature {
+            bail!(format!("unexpected ssh userauth method {}", request.method));
+        }
+        Ok(SSHSessionData {
+            session_id: request.session_id,
+            user: request.user,
+            service: request.service,
+            algorithm: request.algorithm,
+            public_key: request.public_key,
+        })
+    }
+
+    #[cfg(not(feature = "ssh-wire"))]
+    pub fn parse(_: &[u8]) -> Result<SSHSessionData> {
+        bail!("ssh session data decoding is not supported in this build")
+    }
+
+    /// The decoded session data of an SSH log, if it is well formed. If the log's username was
+    /// hashed, the user is taken from the log body so that session data redacted by older clients
+    /// does not reveal it.
+    pub fn from_log(log: &Log) -> Option<SSHSessionData> {
+        match log.body {
+            LogBody::Ssh(ref ssh) => {
+                let mut session = SSHSessionData::parse(&ssh.session_data).ok()?;
+                if log.redactions.contains(&Redaction::UsernameHashed) {
+                    session.user = ssh.user.clone();
+                }
+                Some(session)
+            }
+            _ => None,
+        }
+    }
+
+    /// Re-encode `session_data` with the requested username replaced by `user`, keeping the
+    /// session id and the rest of the request as they are.
+    pub fn replace_user(session_data: &[u8], user: &str) -> Result<Vec<u8>> {
+        let mut data = session_data;
+        let session_id = read_string(&mut data)?;
+        let (message_type, mut data) = match data.split_first() {
+            Some((message_type, data)) => (*message_type, data),
+            None => bail!("truncated ssh session data"),
+        };
+        read_string(&mut data)?;
+
+        let mut replaced = vec![];
+        write_string(&mut replaced, session_id);
+        replaced.push(message_type);
+        write_string(&mut replaced, user.as_bytes());
+        replaced.extend_from_slice(data);
+        Ok(replaced)
+    }
+
+    pub fn session_id_hex(&self) -> String {
+        self.session_id.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// The first 8 hex characters of the session id, enough to tell sessions apart at a glance.
+    pub fn short_session_id(&self) -> String {
+        self.session_id_hex().chars().take(8).collect()
+    }
+}
+
+fn read_string<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+    if data.len() < 4 {
+        bail!("truncated ssh session data");
+    }
+    let length = data[..4].iter().fold(0usize, |n, b| (n << 8) | *b as usize);
+    if data.len() - 4 < length {
+        bail!("truncated ssh session data");
+    }
+    let string = &data[4..4 + length];
+    *data = &data[4 + length..];
+    Ok(string)
+}
+
+fn write_string(out: &mut Vec<u8>, string: &[u8]) {
+    let length = string.len() as u32;
+    out.extend_from_slice(&[(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8, length as u8]);
+    out.extend_from_slice(string);
+}